@@ -38,10 +38,10 @@ fn main() -> Result<(), Box<dyn Error>> {
     );
 
     println!("Getting period of available data of site {site_id}");
-    let data_period = data_period(api_key, site_id)?;
+    let (start_date, end_date) = data_period(api_key, site_id)?.bounds()?;
     println!(
         "Data available from {} until {}",
-        data_period.start_date, data_period.end_date
+        start_date, end_date
     );
 
     println!("Getting overview of site {site_id}");
@@ -59,13 +59,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     );
 
     println!("Getting energy generation of past day");
-    let now = Local::now().naive_local();
-    let period: DataPeriod = DataPeriod {
-        start_date: now.date(),
-        end_date: now.date(),
-    };
-    let energy = energy(api_key, site_id, period, TimeUnit::Hour)?;
-    for e in energy.values() {
+    let energy = energy(api_key, site_id, DataPeriod::today(), TimeUnit::Hour)?;
+    for e in energy.values()? {
         println!(
             "\t{} - {}",
             e.date,
@@ -81,7 +76,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("Getting power generation of past hour");
     let now = Local::now().naive_local();
     let power = power(api_key, site_id, now - Duration::hours(1), now)?;
-    for e in power.values() {
+    for e in power.values()? {
         println!(
             "\t{} - {}",
             e.date,
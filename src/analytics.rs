@@ -0,0 +1,1186 @@
+//! Metrics to judge the production of a site, i.e. to compare sites of
+//! different sizes
+
+#[cfg(test)]
+use crate::units::{watt_hours, watts};
+use crate::{
+    equipment::{Equipment, InverterData},
+    meters::{EnergyDetails, MeterType, PowerDetails},
+    series::{Aggregation, TimeSeries, TimeSeriesValue},
+    site::{Site, TimeUnit},
+    storage::{Battery, BatteryTelemetry},
+    units::{as_watt_hours, Energy, EnergyUnit, Power, PowerUnit},
+    SolarApiError,
+};
+use chrono::{Datelike, NaiveDateTime};
+use std::collections::BTreeMap;
+
+// the peak power in kilowatt. Returns [`SolarApiError::InvalidPeakPower`] when
+// it is not positive, as it is used to divide by
+pub(crate) fn peak_power_kw(peak_power: Power) -> Result<f64, SolarApiError> {
+    let kw = PowerUnit::Kilowatt.value_of(peak_power);
+    if kw > 0.0 {
+        Ok(kw)
+    } else {
+        Err(SolarApiError::InvalidPeakPower(kw))
+    }
+}
+
+/// The specific yield, the energy in kWh produced per kWp of `peak_power`, per
+/// bucket of `time_unit`, i.e. per day, month or year. The energy is summed
+/// using [`TimeSeries::resample`]. The unit of the result is `kWh/kWp`
+pub fn specific_yield(
+    energy: &TimeSeries<Energy>,
+    peak_power: Power,
+    time_unit: TimeUnit,
+) -> Result<TimeSeries<f64>, SolarApiError> {
+    let kwp = peak_power_kw(peak_power)?;
+    let values = energy
+        .resample(time_unit, Aggregation::Sum)
+        .into_iter()
+        .map(|v| TimeSeriesValue {
+            date: v.date,
+            value: v
+                .value
+                .map(|energy| EnergyUnit::KilowattHour.value_of(energy) / kwp),
+        })
+        .collect();
+    Ok(TimeSeries::new(time_unit, "kWh/kWp", values))
+}
+
+/// The specific yield in kWh/kWp of all `energy`, i.e. of a whole year
+pub fn total_specific_yield(
+    energy: &TimeSeries<Energy>,
+    peak_power: Power,
+) -> Result<f64, SolarApiError> {
+    let kwp = peak_power_kw(peak_power)?;
+    Ok(EnergyUnit::KilowattHour.value_of(energy.total()) / kwp)
+}
+
+/// The capacity factor per bucket of `time_unit`: the energy divided by the
+/// energy the site would produce at `peak_power` during the whole bucket. The
+/// length of a bucket is calendar aware, so February has fewer hours than
+/// March. Buckets are not clipped to the first and last value of `energy`
+pub fn capacity_factor(
+    energy: &TimeSeries<Energy>,
+    peak_power: Power,
+    time_unit: TimeUnit,
+) -> Result<TimeSeries<f64>, SolarApiError> {
+    let kwp = peak_power_kw(peak_power)?;
+    let values = energy
+        .resample(time_unit, Aggregation::Sum)
+        .into_iter()
+        .map(|v| {
+            let hours = (time_unit.bucket_end(v.date) - v.date).num_seconds() as f64 / 3600.0;
+            TimeSeriesValue {
+                date: v.date,
+                value: v
+                    .value
+                    .map(|energy| EnergyUnit::KilowattHour.value_of(energy) / (kwp * hours)),
+            }
+        })
+        .collect();
+    Ok(TimeSeries::new(time_unit, "", values))
+}
+
+/// The day with the most energy, or None when `energy` has no values. The
+/// energy is summed per day first, so any time unit up to a day can be used
+pub fn best_day(energy: &TimeSeries<Energy>) -> Option<TimeSeriesValue<Energy>> {
+    energy.resample(TimeUnit::Day, Aggregation::Sum).max_value()
+}
+
+/// The `n` days with the least energy, least first. Days without a value are
+/// skipped, so use [`TimeSeries::gaps`] to find days without data
+pub fn worst_days(energy: &TimeSeries<Energy>, n: usize) -> Vec<TimeSeriesValue<Energy>> {
+    energy.resample(TimeUnit::Day, Aggregation::Sum).smallest(n)
+}
+
+/// The `n` days with the most energy, most first
+pub fn best_days(energy: &TimeSeries<Energy>, n: usize) -> Vec<TimeSeriesValue<Energy>> {
+    energy.resample(TimeUnit::Day, Aggregation::Sum).largest(n)
+}
+
+/// The highest power sample, or None when `power` has no values
+pub fn peak_power(power: &TimeSeries<Power>) -> Option<TimeSeriesValue<Power>> {
+    power.max_value()
+}
+
+/// The energy exported to and imported from the grid in a period, see
+/// [`grid_balance`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridBalance {
+    /// the energy exported to the grid, measured by the `FeedIn` meter
+    pub exported: Energy,
+    /// the energy imported from the grid, measured by the `Purchased` meter
+    pub imported: Energy,
+    /// exported minus imported energy. Positive when the site exported more
+    /// than it imported
+    pub net: Energy,
+    /// the highest export power, when the power of the meters is known
+    pub peak_export: Option<TimeSeriesValue<Power>>,
+    /// the highest import power, when the power of the meters is known
+    pub peak_import: Option<TimeSeriesValue<Power>>,
+}
+
+impl GridBalance {
+    /// Creates the balance from the energy of the `FeedIn` and `Purchased`
+    /// meters and optionally the power of these meters
+    pub fn from_series(
+        feed_in: &TimeSeries<Energy>,
+        purchased: &TimeSeries<Energy>,
+        power: Option<(&TimeSeries<Power>, &TimeSeries<Power>)>,
+    ) -> Self {
+        let exported = feed_in.total();
+        let imported = purchased.total();
+        GridBalance {
+            exported,
+            imported,
+            net: exported - imported,
+            peak_export: power.and_then(|(feed_in, _)| feed_in.max_value()),
+            peak_import: power.and_then(|(_, purchased)| purchased.max_value()),
+        }
+    }
+}
+
+/// Summarizes the energy exported to and imported from the grid using the
+/// `FeedIn` and `Purchased` meters of `energy`, i.e. to check net-metering
+/// bills. The peak power is determined when `power` is given. Returns
+/// [`SolarApiError::MissingMeter`] when a reply has no values for these meters
+pub fn grid_balance(
+    energy: &EnergyDetails,
+    power: Option<&PowerDetails>,
+) -> Result<GridBalance, SolarApiError> {
+    let meter = |meter| {
+        energy
+            .series(meter)?
+            .ok_or(SolarApiError::MissingMeter(meter))
+    };
+    let (feed_in, purchased) = (meter(MeterType::FeedIn)?, meter(MeterType::Purchased)?);
+    let power = match power {
+        Some(power) => {
+            let meter = |meter| {
+                power
+                    .series(meter)?
+                    .ok_or(SolarApiError::MissingMeter(meter))
+            };
+            Some((meter(MeterType::FeedIn)?, meter(MeterType::Purchased)?))
+        }
+        None => None,
+    };
+    Ok(GridBalance::from_series(
+        &feed_in,
+        &purchased,
+        power
+            .as_ref()
+            .map(|(feed_in, purchased)| (feed_in, purchased)),
+    ))
+}
+
+/// The typical energy per day for every calendar month, used to detect days on
+/// which a site produced less than expected, see [`underperforming_days`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpectedProduction {
+    daily: BTreeMap<u32, Energy>,
+}
+
+impl ExpectedProduction {
+    /// Create a model without expectations
+    pub fn new() -> Self {
+        ExpectedProduction::default()
+    }
+
+    /// Set the expected energy per day in `month`, i.e. from a clear-sky model
+    /// or a service like PVGIS
+    pub fn with_month(mut self, month: u32, daily_energy: Energy) -> Self {
+        self.daily.insert(month, daily_energy);
+        self
+    }
+
+    /// Create a model from historical energy of the site. The expected energy
+    /// per day of a month is the median energy per day of that calendar month
+    /// over all years, so a few bad days do not lower the expectation
+    pub fn from_history(energy: &TimeSeries<Energy>) -> Self {
+        let mut per_month: BTreeMap<u32, Vec<Energy>> = BTreeMap::new();
+        for v in energy.resample(TimeUnit::Day, Aggregation::Sum) {
+            // months with only days without value, i.e. the future, are left
+            // out
+            if let Some(value) = v.value {
+                per_month.entry(v.date.month()).or_default().push(value);
+            }
+        }
+        let daily = per_month
+            .into_iter()
+            .map(|(month, mut values)| {
+                values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+                (month, values[values.len() / 2])
+            })
+            .collect();
+        ExpectedProduction { daily }
+    }
+
+    /// The expected energy on a day in `month`, or None when it is not known
+    pub fn expected(&self, month: u32) -> Option<Energy> {
+        self.daily.get(&month).copied()
+    }
+}
+
+/// A day on which a site produced less than expected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Underperformance {
+    /// the start of the day
+    pub date: NaiveDateTime,
+    /// the energy produced on the day
+    pub actual: Energy,
+    /// the energy that was expected on the day
+    pub expected: Energy,
+    /// `actual` divided by `expected`
+    pub ratio: f64,
+}
+
+/// The days on which the energy was less than `threshold` times the expected
+/// energy, i.e. a `threshold` of 0.5 flags days with less than half of the
+/// expected energy. Days without a value or expectation are skipped; use
+/// [`TimeSeries::gaps`] to find days without data
+pub fn underperforming_days(
+    energy: &TimeSeries<Energy>,
+    expected: &ExpectedProduction,
+    threshold: f64,
+) -> Vec<Underperformance> {
+    energy
+        .resample(TimeUnit::Day, Aggregation::Sum)
+        .into_iter()
+        .filter_map(|v| {
+            let actual = v.value?;
+            let expected = expected.expected(v.date.month())?;
+            let expected_wh = as_watt_hours(expected);
+            if expected_wh <= 0.0 {
+                return None;
+            }
+            let ratio = as_watt_hours(actual) / expected_wh;
+            (ratio < threshold).then_some(Underperformance {
+                date: v.date,
+                actual,
+                expected,
+                ratio,
+            })
+        })
+        .collect()
+}
+
+/// The energy charged into and discharged from a battery in a period, see
+/// [`battery_efficiency`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatteryEfficiency {
+    /// the timestamp of the first telemetry of the period
+    pub start: NaiveDateTime,
+    /// the timestamp of the last telemetry of the period
+    pub end: NaiveDateTime,
+    pub charged: Energy,
+    pub discharged: Energy,
+    /// `discharged` divided by `charged`, or None when nothing was charged
+    pub round_trip_efficiency: Option<f64>,
+    /// `discharged` divided by the nameplate capacity, i.e. the number of full
+    /// cycles, or None when the nameplate capacity is not known
+    pub cycles: Option<f64>,
+    /// the highest minus the lowest state of charge in percent, or None when
+    /// the state of charge is not known
+    pub depth_of_cycle: Option<f64>,
+}
+
+impl BatteryEfficiency {
+    // the efficiency between the first and last of the sorted `telemetries`.
+    // Uses the lifetime counters, so missing telemetry in between does not
+    // lose energy
+    fn from_telemetries(battery: &Battery, telemetries: &[&BatteryTelemetry]) -> Option<Self> {
+        let (first, last) = match telemetries {
+            [first, .., last] => (first, last),
+            _ => return None,
+        };
+        // a replaced battery resets its counters
+        let delta = |from: Energy, to: Energy| (as_watt_hours(to) - as_watt_hours(from)).max(0.0);
+        let charged = delta(first.lifetime_energy_charged, last.lifetime_energy_charged);
+        let discharged = delta(
+            first.lifetime_energy_discharged,
+            last.lifetime_energy_discharged,
+        );
+        let nameplate = as_watt_hours(battery.nameplate);
+        let soc = telemetries
+            .iter()
+            .filter_map(|telemetry| telemetry.battery_percentage_state);
+        let depth_of_cycle = soc
+            .clone()
+            .reduce(f64::max)
+            .zip(soc.reduce(f64::min))
+            .map(|(max, min)| max - min);
+        Some(BatteryEfficiency {
+            start: first.timestamp,
+            end: last.timestamp,
+            charged: EnergyUnit::WattHour.energy(charged),
+            discharged: EnergyUnit::WattHour.energy(discharged),
+            round_trip_efficiency: (charged > 0.0).then(|| discharged / charged),
+            cycles: (nameplate > 0.0).then(|| discharged / nameplate),
+            depth_of_cycle,
+        })
+    }
+}
+
+/// The charged and discharged energy and round-trip efficiency of `battery`
+/// over all its telemetry. Returns None when there are less than two
+/// telemetries. The efficiency of a short period is distorted by the energy
+/// stored at its start and end, so use periods of days or more
+pub fn battery_efficiency(battery: &Battery) -> Option<BatteryEfficiency> {
+    BatteryEfficiency::from_telemetries(battery, &battery.sorted_telemetries())
+}
+
+/// The [`battery_efficiency`] per day, i.e. to follow the efficiency and
+/// cycling depth over the seasons. A day starts at the last telemetry of the
+/// previous day, so no energy is lost between days
+pub fn daily_battery_efficiency(battery: &Battery) -> Vec<BatteryEfficiency> {
+    battery_efficiency_per(battery, TimeUnit::Day)
+}
+
+// the battery efficiency per bucket of `time_unit`. A bucket starts at the last
+// telemetry of the previous bucket
+fn battery_efficiency_per(battery: &Battery, time_unit: TimeUnit) -> Vec<BatteryEfficiency> {
+    let mut buckets: BTreeMap<NaiveDateTime, Vec<&BatteryTelemetry>> = BTreeMap::new();
+    for telemetry in battery.sorted_telemetries() {
+        buckets
+            .entry(time_unit.bucket_start(telemetry.timestamp))
+            .or_default()
+            .push(telemetry);
+    }
+    let mut previous: Option<&BatteryTelemetry> = None;
+    let mut result = Vec::with_capacity(buckets.len());
+    for telemetries in buckets.values() {
+        let window: Vec<_> = previous
+            .into_iter()
+            .chain(telemetries.iter().copied())
+            .collect();
+        result.extend(BatteryEfficiency::from_telemetries(battery, &window));
+        previous = telemetries.last().copied();
+    }
+    result
+}
+
+// the change of the state of charge in percent points that ends a charge or
+// discharge, so small fluctuations are not counted as cycles
+const SOC_HYSTERESIS: f64 = 5.0;
+// the longest time between telemetries that counts towards the time at a
+// state of charge, so gaps in the telemetry are not counted
+const MAX_TELEMETRY_INTERVAL_IN_M: i64 = 15;
+
+/// The cycling of a battery in a month, see [`battery_cycles`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatteryMonth {
+    /// the first day of the month
+    pub month: chrono::NaiveDate,
+    /// the discharged energy divided by the nameplate capacity, or None when
+    /// the nameplate capacity is not known
+    pub equivalent_full_cycles: Option<f64>,
+    /// the average drop of the state of charge in percent points of the
+    /// discharges that ended in this month, or None when there were none
+    pub average_depth_of_discharge: Option<f64>,
+    /// the number of discharges that ended in this month
+    pub discharges: usize,
+    /// the time the state of charge was at or above the high threshold
+    pub time_at_high: chrono::Duration,
+    /// the time the state of charge was at or below the low threshold
+    pub time_at_low: chrono::Duration,
+}
+
+// the month starting at `month` in `months`, added when it is not there yet
+fn battery_month(
+    months: &mut BTreeMap<chrono::NaiveDate, BatteryMonth>,
+    month: chrono::NaiveDate,
+) -> &mut BatteryMonth {
+    months.entry(month).or_insert_with(|| BatteryMonth {
+        month,
+        equivalent_full_cycles: None,
+        average_depth_of_discharge: None,
+        discharges: 0,
+        time_at_high: chrono::Duration::zero(),
+        time_at_low: chrono::Duration::zero(),
+    })
+}
+
+/// The equivalent full cycles, the average depth of discharge and the time
+/// spent at a state of charge at or above `high` and at or below `low` percent
+/// per month, i.e. to check the cycles against the warranty of the battery. A
+/// discharge ends when the state of charge rises 5 percent points above its
+/// lowest value
+pub fn battery_cycles(battery: &Battery, low: f64, high: f64) -> Vec<BatteryMonth> {
+    let month_of = |datetime: NaiveDateTime| TimeUnit::Month.bucket_start(datetime).date();
+    let mut months: BTreeMap<chrono::NaiveDate, BatteryMonth> = BTreeMap::new();
+    let soc: Vec<_> = battery
+        .sorted_telemetries()
+        .into_iter()
+        .filter_map(|t| t.battery_percentage_state.map(|soc| (t.timestamp, soc)))
+        .collect();
+    let max_interval = chrono::Duration::minutes(MAX_TELEMETRY_INTERVAL_IN_M);
+    let mut depths: BTreeMap<chrono::NaiveDate, Vec<f64>> = BTreeMap::new();
+    // the highest state of charge before the current discharge and the lowest
+    // during it
+    let mut peak: Option<f64> = None;
+    let mut trough: Option<(NaiveDateTime, f64)> = None;
+    for (i, (timestamp, value)) in soc.iter().enumerate() {
+        let current = battery_month(&mut months, month_of(*timestamp));
+        if let Some((next, _)) = soc.get(i + 1) {
+            let interval = (*next - *timestamp).min(max_interval);
+            if *value >= high {
+                current.time_at_high += interval;
+            }
+            if *value <= low {
+                current.time_at_low += interval;
+            }
+        }
+
+        match (peak, trough) {
+            (Some(p), Some((at, t))) if *value >= t + SOC_HYSTERESIS => {
+                depths.entry(month_of(at)).or_default().push(p - t);
+                peak = Some(*value);
+                trough = None;
+            }
+            (Some(_), Some((_, t))) if *value < t => trough = Some((*timestamp, *value)),
+            (Some(p), None) if *value <= p - SOC_HYSTERESIS => {
+                trough = Some((*timestamp, *value));
+            }
+            (Some(p), None) if *value > p => peak = Some(*value),
+            (None, _) => peak = Some(*value),
+            _ => {}
+        }
+    }
+    if let (Some(p), Some((at, t))) = (peak, trough) {
+        depths.entry(month_of(at)).or_default().push(p - t);
+    }
+
+    for efficiency in battery_efficiency_per(battery, TimeUnit::Month) {
+        battery_month(&mut months, month_of(efficiency.end)).equivalent_full_cycles =
+            efficiency.cycles;
+    }
+    for (date, depths) in depths {
+        let current = battery_month(&mut months, date);
+        current.discharges = depths.len();
+        current.average_depth_of_discharge = Some(depths.iter().sum::<f64>() / depths.len() as f64);
+    }
+    months.into_values().collect()
+}
+
+/// The energy of an inverter compared with the other inverters of its site,
+/// see [`compare_inverters`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct InverterComparison {
+    pub inverter: Equipment,
+    /// the energy in the period, or None when the inverter has no telemetry
+    pub energy: Option<Energy>,
+    /// the deviation from the mean energy of the inverters as fraction, i.e.
+    /// -0.25 for an inverter that produced 25% less than the mean
+    pub deviation: Option<f64>,
+    /// true when the deviation is more than the threshold or the energy is
+    /// not known
+    pub flagged: bool,
+}
+
+/// Compares the energy of the `inverters` of a site with their mean and flags
+/// the inverters that deviate more than `threshold` from it, i.e. a
+/// `threshold` of 0.1 flags inverters that produced 10% more or less than the
+/// mean. Only works for sites with inverters of the same size and orientation
+pub fn compare_inverters(
+    inverters: &[(Equipment, InverterData)],
+    threshold: f64,
+) -> Vec<InverterComparison> {
+    let energies: Vec<_> = inverters
+        .iter()
+        .map(|(_, data)| data.energy().map(as_watt_hours))
+        .collect();
+    let known: Vec<f64> = energies.iter().flatten().copied().collect();
+    let mean = (!known.is_empty()).then(|| known.iter().sum::<f64>() / known.len() as f64);
+    inverters
+        .iter()
+        .zip(energies)
+        .map(|((inverter, _), energy)| {
+            let deviation = energy
+                .zip(mean.filter(|mean| *mean > 0.0))
+                .map(|(energy, mean)| (energy - mean) / mean);
+            InverterComparison {
+                inverter: inverter.clone(),
+                energy: energy.map(|wh| EnergyUnit::WattHour.energy(wh)),
+                deviation,
+                flagged: energy.is_none()
+                    || deviation.is_some_and(|deviation| deviation.abs() > threshold),
+            }
+        })
+        .collect()
+}
+
+/// The energy of the same calendar bucket in several years, see [`year_over_year`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct YearOverYearBucket {
+    /// the month of the bucket or None when comparing whole years
+    pub month: Option<u32>,
+    /// the day of the month of the bucket or None when comparing months or years
+    pub day: Option<u32>,
+    /// the energy per year, in the order of the compared years
+    pub values: Vec<Option<Energy>>,
+    /// the difference of the energy per year with the first year
+    pub deltas: Vec<Option<Energy>>,
+    /// the difference of the energy per year with the first year in percent of
+    /// the first year. None when the first year had no energy
+    pub percentages: Vec<Option<f64>>,
+}
+
+/// Compares the same calendar window of `years`, i.e. the energy of June 2022
+/// with June 2023, aligned by month and day. The first year is the base for the
+/// deltas. With [`TimeUnit::Month`] or [`TimeUnit::Year`] months or whole years
+/// are compared, other time units are compared per day. February 29 is a bucket
+/// of its own that has no value in years that are not leap years
+pub fn year_over_year(
+    energy: &TimeSeries<Energy>,
+    years: &[i32],
+    time_unit: TimeUnit,
+) -> Vec<YearOverYearBucket> {
+    let time_unit = match time_unit {
+        TimeUnit::Month | TimeUnit::Year => time_unit,
+        _ => TimeUnit::Day,
+    };
+    let mut buckets = BTreeMap::new();
+    for v in energy.resample(time_unit, Aggregation::Sum) {
+        let Some(index) = years.iter().position(|year| *year == v.date.year()) else {
+            continue;
+        };
+        let key = match time_unit {
+            TimeUnit::Year => (None, None),
+            TimeUnit::Month => (Some(v.date.month()), None),
+            _ => (Some(v.date.month()), Some(v.date.day())),
+        };
+        buckets
+            .entry(key)
+            .or_insert_with(|| vec![None; years.len()])[index] = v.value;
+    }
+    buckets
+        .into_iter()
+        .map(|((month, day), values)| {
+            let base = values.first().copied().flatten();
+            let deltas: Vec<Option<Energy>> = values
+                .iter()
+                .map(|value| Some(value.as_ref().copied()? - base?))
+                .collect();
+            let percentages = deltas
+                .iter()
+                .map(|delta| {
+                    let base = as_watt_hours(base?);
+                    let delta = as_watt_hours((*delta)?);
+                    (base != 0.0).then(|| delta / base * 100.0)
+                })
+                .collect();
+            YearOverYearBucket {
+                month,
+                day,
+                values,
+                deltas,
+                percentages,
+            }
+        })
+        .collect()
+}
+
+/// The estimated degradation of a site, see [`degradation_trend`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DegradationEstimate {
+    /// the estimated decrease of the production in percent per year. A
+    /// negative value means the production increased
+    pub percent_per_year: f64,
+    /// the lower bound of the 95% confidence interval of `percent_per_year`
+    pub lower_bound: f64,
+    /// the upper bound of the 95% confidence interval of `percent_per_year`
+    pub upper_bound: f64,
+    /// the number of months used for the estimate
+    pub months: usize,
+}
+
+/// Estimates the degradation of a site from its monthly specific yield over
+/// multiple years, see [`specific_yield`]. The seasonal pattern is removed by
+/// dividing every month by the average of that calendar month over all years.
+/// A linear trend is fitted to the result using least squares. The confidence
+/// bounds use the normal approximation of the standard error of the trend.
+/// Returns None when there are less than 24 months with a value, because the
+/// seasonal pattern cannot be separated from the trend then
+pub fn degradation_trend(monthly_yield: &TimeSeries<f64>) -> Option<DegradationEstimate> {
+    let values: Vec<(NaiveDateTime, f64)> = monthly_yield
+        .iter()
+        .filter_map(|v| Some((v.date, v.value?)))
+        .collect();
+    if values.len() < 24 {
+        return None;
+    }
+
+    let mut seasonal: BTreeMap<u32, (f64, usize)> = BTreeMap::new();
+    for (date, value) in &values {
+        let (sum, count) = seasonal.entry(date.month()).or_default();
+        *sum += value;
+        *count += 1;
+    }
+    let first = values[0].0;
+    let points: Vec<(f64, f64)> = values
+        .iter()
+        .filter_map(|(date, value)| {
+            let (sum, count) = seasonal[&date.month()];
+            let mean = sum / count as f64;
+            let years = (*date - first).num_days() as f64 / 365.25;
+            (mean > 0.0).then(|| (years, value / mean))
+        })
+        .collect();
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    if points.len() < 3 || sxx == 0.0 || mean_y == 0.0 {
+        return None;
+    }
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let residuals: f64 = points
+        .iter()
+        .map(|(x, y)| (y - intercept - slope * x).powi(2))
+        .sum();
+    let standard_error = (residuals / (n - 2.0) / sxx).sqrt();
+
+    let to_percent = |slope: f64| -slope / mean_y * 100.0;
+    Some(DegradationEstimate {
+        percent_per_year: to_percent(slope),
+        lower_bound: to_percent(slope + 1.96 * standard_error),
+        upper_bound: to_percent(slope - 1.96 * standard_error),
+        months: points.len(),
+    })
+}
+
+/// A source of the irradiation in the plane of the modules, used by
+/// [`performance_ratio`]. Implement this to use irradiation from a weather
+/// service or sensor
+pub trait IrradiationSource {
+    /// the irradiation in kWh/m² from `start` until `end`, or None when it is
+    /// not known for this period
+    fn irradiation(
+        &self,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Option<f64>, SolarApiError>;
+}
+
+/// A series of irradiation values in kWh/m² per time unit of the series. The
+/// irradiation of a period is the sum of the values with a timestamp in the
+/// period
+impl IrradiationSource for TimeSeries<f64> {
+    fn irradiation(
+        &self,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Option<f64>, SolarApiError> {
+        Ok(self
+            .iter()
+            .filter(|v| v.date >= start && v.date < end)
+            .filter_map(|v| v.value)
+            .reduce(|sum, v| sum + v))
+    }
+}
+
+/// The performance ratio per bucket of `time_unit`: the [`specific_yield`]
+/// divided by the reference yield, which is the irradiation in kWh/m² divided
+/// by the standard irradiance of 1 kW/m². The value of a bucket is None when
+/// there was no energy or irradiation, or the irradiation was zero
+pub fn performance_ratio(
+    energy: &TimeSeries<Energy>,
+    peak_power: Power,
+    irradiation: &impl IrradiationSource,
+    time_unit: TimeUnit,
+) -> Result<TimeSeries<f64>, SolarApiError> {
+    let mut values = Vec::new();
+    for v in specific_yield(energy, peak_power, time_unit)? {
+        let reference_yield = irradiation
+            .irradiation(v.date, time_unit.bucket_end(v.date))?
+            .filter(|irradiation| *irradiation > 0.0);
+        values.push(TimeSeriesValue {
+            date: v.date,
+            value: v.value.zip(reference_yield).map(|(y, r)| y / r),
+        });
+    }
+    Ok(TimeSeries::new(time_unit, "", values))
+}
+
+impl Site {
+    /// The specific yield of this site using its `peak_power`, see [`specific_yield`]
+    pub fn specific_yield(
+        &self,
+        energy: &TimeSeries<Energy>,
+        time_unit: TimeUnit,
+    ) -> Result<TimeSeries<f64>, SolarApiError> {
+        specific_yield(energy, self.peak_power, time_unit)
+    }
+
+    /// The capacity factor of this site using its `peak_power`, see
+    /// [`capacity_factor`]
+    pub fn capacity_factor(
+        &self,
+        energy: &TimeSeries<Energy>,
+        time_unit: TimeUnit,
+    ) -> Result<TimeSeries<f64>, SolarApiError> {
+        capacity_factor(energy, self.peak_power, time_unit)
+    }
+
+    /// The performance ratio of this site using its `peak_power`, see
+    /// [`performance_ratio`]
+    pub fn performance_ratio(
+        &self,
+        energy: &TimeSeries<Energy>,
+        irradiation: &impl IrradiationSource,
+        time_unit: TimeUnit,
+    ) -> Result<TimeSeries<f64>, SolarApiError> {
+        performance_ratio(energy, self.peak_power, irradiation, time_unit)
+    }
+}
+
+#[test]
+fn test_specific_yield() {
+    let date = |s: &str| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let energy = TimeSeries::new(
+        TimeUnit::Day,
+        "Wh",
+        vec![
+            TimeSeriesValue {
+                date: date("2023-06-01 00:00:00"),
+                value: Some(watt_hours(20_000.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-06-02 00:00:00"),
+                value: Some(watt_hours(30_000.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-07-01 00:00:00"),
+                value: None,
+            },
+        ],
+    );
+    let peak_power = watts(5000.0);
+
+    let daily = specific_yield(&energy, peak_power, TimeUnit::Day).unwrap();
+    assert_eq!("kWh/kWp", daily.unit);
+    assert_eq!(Some(4.0), daily.values[0].value);
+    assert_eq!(Some(6.0), daily.values[1].value);
+
+    let monthly = specific_yield(&energy, peak_power, TimeUnit::Month).unwrap();
+    assert_eq!(2, monthly.len());
+    assert_eq!(Some(10.0), monthly.values[0].value);
+    assert_eq!(None, monthly.values[1].value);
+
+    assert_eq!(10.0, total_specific_yield(&energy, peak_power).unwrap());
+    assert!(matches!(
+        total_specific_yield(&energy, watts(0.0)),
+        Err(SolarApiError::InvalidPeakPower(_))
+    ));
+}
+
+#[test]
+fn test_performance_ratio() {
+    // values per day starting at 2023-06-01
+    fn daily<Q>(values: Vec<Option<Q>>) -> Vec<TimeSeriesValue<Q>> {
+        let start = chrono::NaiveDate::from_ymd_opt(2023, 6, 1).unwrap();
+        (0..)
+            .zip(values)
+            .map(|(day, value)| TimeSeriesValue {
+                date: (start + chrono::Days::new(day)).into(),
+                value,
+            })
+            .collect()
+    }
+    let energy = TimeSeries::new(
+        TimeUnit::Day,
+        "Wh",
+        daily(vec![
+            Some(watt_hours(20_000.0)),
+            Some(watt_hours(30_000.0)),
+            Some(watt_hours(10_000.0)),
+        ]),
+    );
+    let irradiation = TimeSeries::new(
+        TimeUnit::Day,
+        "kWh/m²",
+        daily(vec![Some(5.0), Some(7.5), None]),
+    );
+
+    let pr = performance_ratio(&energy, watts(5000.0), &irradiation, TimeUnit::Day).unwrap();
+    assert_eq!(Some(0.8), pr.values[0].value);
+    assert_eq!(Some(0.8), pr.values[1].value);
+    assert_eq!(None, pr.values[2].value);
+
+    let monthly = performance_ratio(&energy, watts(5000.0), &irradiation, TimeUnit::Month).unwrap();
+    assert_eq!(1, monthly.len());
+    assert!((monthly.values[0].value.unwrap() - 12.0 / 12.5).abs() < 1e-9);
+}
+
+#[test]
+fn test_capacity_factor() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let energy = TimeSeries::new(
+        TimeUnit::Month,
+        "kWh",
+        vec![
+            // 28 days * 24 hours * 1 kW * 0.25
+            TimeSeriesValue {
+                date: date("2023-02-01 00:00:00"),
+                value: Some(watt_hours(168_000.0)),
+            },
+            // 31 days * 24 hours * 1 kW * 0.25
+            TimeSeriesValue {
+                date: date("2023-03-01 00:00:00"),
+                value: Some(watt_hours(186_000.0)),
+            },
+        ],
+    );
+
+    let factor = capacity_factor(&energy, watts(1000.0), TimeUnit::Month).unwrap();
+    assert_eq!(Some(0.25), factor.values[0].value);
+    assert_eq!(Some(0.25), factor.values[1].value);
+
+    let yearly = capacity_factor(&energy, watts(1000.0), TimeUnit::Year).unwrap();
+    assert_eq!(Some(354.0 / 8760.0), yearly.values[0].value);
+}
+
+#[test]
+fn test_year_over_year() {
+    let value = |date: &str, wh: f64| TimeSeriesValue {
+        date: chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .unwrap()
+            .into(),
+        value: Some(watt_hours(wh)),
+    };
+    let energy = TimeSeries::new(
+        TimeUnit::Day,
+        "Wh",
+        vec![
+            value("2023-02-28", 1000.0),
+            value("2023-03-01", 2000.0),
+            value("2024-02-28", 1500.0),
+            value("2024-02-29", 1200.0),
+            value("2024-03-01", 1000.0),
+            value("2025-03-01", 3000.0),
+        ],
+    );
+
+    let daily = year_over_year(&energy, &[2023, 2024], TimeUnit::Day);
+    assert_eq!(3, daily.len());
+    assert_eq!((Some(2), Some(28)), (daily[0].month, daily[0].day));
+    assert_eq!(Some(watt_hours(500.0)), daily[0].deltas[1]);
+    assert_eq!(Some(50.0), daily[0].percentages[1]);
+    // leap day has no value in 2023
+    assert_eq!((Some(2), Some(29)), (daily[1].month, daily[1].day));
+    assert_eq!(vec![None, Some(watt_hours(1200.0))], daily[1].values);
+    assert_eq!(None, daily[1].deltas[1]);
+    assert_eq!(Some(-50.0), daily[2].percentages[1]);
+
+    let monthly = year_over_year(&energy, &[2023, 2024, 2025], TimeUnit::Month);
+    assert_eq!(2, monthly.len());
+    assert_eq!(Some(watt_hours(2700.0)), monthly[0].values[1]);
+    assert_eq!(None, monthly[0].values[2]);
+    assert_eq!(Some(50.0), monthly[1].percentages[2]);
+}
+
+#[test]
+fn test_degradation_trend() {
+    let start = chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+    let season = [
+        30.0, 50.0, 90.0, 120.0, 150.0, 160.0, 155.0, 140.0, 100.0, 70.0, 35.0, 25.0,
+    ];
+    let values: Vec<TimeSeriesValue<f64>> = (0..48)
+        .map(|month| {
+            let date = start + chrono::Months::new(month);
+            let years = (date - start).num_days() as f64 / 365.25;
+            TimeSeriesValue {
+                date: date.into(),
+                // 0.8% degradation per year with some noise
+                value: Some(
+                    season[date.month0() as usize]
+                        * (1.0 - 0.008 * years)
+                        * (1.0 + 0.002 * (month as f64).sin()),
+                ),
+            }
+        })
+        .collect();
+
+    let estimate =
+        degradation_trend(&TimeSeries::new(TimeUnit::Month, "kWh/kWp", values.clone())).unwrap();
+    assert_eq!(48, estimate.months);
+    assert!((estimate.percent_per_year - 0.8).abs() < 0.1);
+    assert!(estimate.lower_bound < estimate.percent_per_year);
+    assert!(estimate.upper_bound > estimate.percent_per_year);
+
+    let one_year = TimeSeries::new(TimeUnit::Month, "kWh/kWp", values[..12].to_vec());
+    assert_eq!(None, degradation_trend(&one_year));
+}
+
+#[test]
+fn test_best_and_worst_days() {
+    let value = |date: &str, wh: Option<f64>| TimeSeriesValue {
+        date: NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap(),
+        value: wh.map(watt_hours),
+    };
+    let energy = TimeSeries::new(
+        TimeUnit::Hour,
+        "Wh",
+        vec![
+            value("2023-06-01 12:00:00", Some(3000.0)),
+            value("2023-06-01 13:00:00", Some(3000.0)),
+            value("2023-06-02 12:00:00", Some(5000.0)),
+            value("2023-06-03 12:00:00", Some(1000.0)),
+            value("2023-06-04 12:00:00", None),
+        ],
+    );
+
+    let best = best_day(&energy).unwrap();
+    assert_eq!(value("2023-06-01 00:00:00", Some(6000.0)), best);
+    assert_eq!(
+        vec![
+            value("2023-06-03 00:00:00", Some(1000.0)),
+            value("2023-06-02 00:00:00", Some(5000.0)),
+        ],
+        worst_days(&energy, 2)
+    );
+    assert_eq!(3, best_days(&energy, 5).len());
+
+    let power = TimeSeries::new(
+        TimeUnit::QuarterOfAnHour,
+        "W",
+        vec![
+            TimeSeriesValue {
+                date: best.date,
+                value: Some(watts(4000.0)),
+            },
+            TimeSeriesValue {
+                date: best.date + chrono::Duration::minutes(15),
+                value: Some(watts(4200.0)),
+            },
+        ],
+    );
+    assert_eq!(Some(watts(4200.0)), peak_power(&power).unwrap().value);
+}
+
+#[test]
+fn test_grid_balance() {
+    let energy: EnergyDetails = serde_json::from_value(serde_json::json!({
+        "timeUnit": "DAY",
+        "unit": "Wh",
+        "meters": [
+            {"type": "FeedIn", "values": [
+                {"date": "2023-06-01 00:00:00", "value": 8000.0},
+                {"date": "2023-06-02 00:00:00", "value": 6000.0}]},
+            {"type": "Purchased", "values": [
+                {"date": "2023-06-01 00:00:00", "value": 3000.0},
+                {"date": "2023-06-02 00:00:00", "value": null}]}
+        ]
+    }))
+    .unwrap();
+    let power: PowerDetails = serde_json::from_value(serde_json::json!({
+        "timeUnit": "QUARTER_OF_AN_HOUR",
+        "unit": "W",
+        "meters": [
+            {"type": "FeedIn", "values": [
+                {"date": "2023-06-01 12:00:00", "value": 3500.0},
+                {"date": "2023-06-01 12:15:00", "value": 3700.0}]},
+            {"type": "Purchased", "values": [
+                {"date": "2023-06-01 19:00:00", "value": 2500.0}]}
+        ]
+    }))
+    .unwrap();
+
+    let balance = grid_balance(&energy, Some(&power)).unwrap();
+    assert_eq!(watt_hours(14000.0), balance.exported);
+    assert_eq!(watt_hours(3000.0), balance.imported);
+    assert_eq!(watt_hours(11000.0), balance.net);
+    assert_eq!(Some(watts(3700.0)), balance.peak_export.unwrap().value);
+    assert_eq!(Some(watts(2500.0)), balance.peak_import.unwrap().value);
+
+    assert!(grid_balance(&energy, None).unwrap().peak_import.is_none());
+    let production_only: EnergyDetails = serde_json::from_value(serde_json::json!({
+        "timeUnit": "DAY", "unit": "Wh", "meters": [{"type": "Production", "values": []}]
+    }))
+    .unwrap();
+    assert!(matches!(
+        grid_balance(&production_only, None),
+        Err(SolarApiError::MissingMeter(MeterType::FeedIn))
+    ));
+}
+
+#[test]
+fn test_underperforming_days() {
+    let value = |date: &str, wh: Option<f64>| TimeSeriesValue {
+        date: NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap(),
+        value: wh.map(watt_hours),
+    };
+    let history = TimeSeries::new(
+        TimeUnit::Day,
+        "Wh",
+        vec![
+            value("2022-06-01 00:00:00", Some(20_000.0)),
+            value("2022-06-02 00:00:00", Some(2_000.0)),
+            value("2022-06-03 00:00:00", Some(22_000.0)),
+        ],
+    );
+    let expected = ExpectedProduction::from_history(&history);
+    assert_eq!(Some(watt_hours(20_000.0)), expected.expected(6));
+    assert_eq!(None, expected.expected(7));
+
+    let future = TimeSeries::new(
+        TimeUnit::Day,
+        "Wh",
+        vec![
+            value("2023-06-30 00:00:00", Some(20_000.0)),
+            value("2023-07-01 00:00:00", None),
+            value("2023-07-02 00:00:00", None),
+        ],
+    );
+    let expected_future = ExpectedProduction::from_history(&future);
+    assert_eq!(Some(watt_hours(20_000.0)), expected_future.expected(6));
+    assert_eq!(None, expected_future.expected(7));
+
+    let recent = TimeSeries::new(
+        TimeUnit::Day,
+        "Wh",
+        vec![
+            value("2023-06-01 00:00:00", Some(18_000.0)),
+            value("2023-06-02 00:00:00", Some(0.0)),
+            value("2023-06-03 00:00:00", None),
+            value("2023-07-01 00:00:00", Some(1_000.0)),
+        ],
+    );
+    let flagged = underperforming_days(&recent, &expected, 0.5);
+    assert_eq!(1, flagged.len());
+    assert_eq!(recent.values[1].date, flagged[0].date);
+    assert_eq!(0.0, flagged[0].ratio);
+
+    let manual = ExpectedProduction::new().with_month(7, watt_hours(10_000.0));
+    assert_eq!(1, underperforming_days(&recent, &manual, 0.5).len());
+}
+
+#[test]
+fn test_battery_efficiency() {
+    let telemetry = |date: &str, charged: f64, discharged: f64, soc: f64| BatteryTelemetry {
+        timestamp: NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap(),
+        power: None,
+        battery_state: None,
+        lifetime_energy_discharged: watt_hours(discharged),
+        lifetime_energy_charged: watt_hours(charged),
+        full_pack_energy_available: None,
+        internal_temp: None,
+        ac_grid_charging: None,
+        battery_percentage_state: Some(soc),
+    };
+    let battery = Battery {
+        nameplate: watt_hours(10_000.0),
+        serial_number: "BAT-1".to_string(),
+        model_number: String::new(),
+        telemetry_count: 4,
+        telemetries: vec![
+            telemetry("2023-06-02 20:00:00", 10_000.0, 9_000.0, 10.0),
+            telemetry("2023-06-01 08:00:00", 0.0, 0.0, 10.0),
+            telemetry("2023-06-01 16:00:00", 8_000.0, 0.0, 90.0),
+            telemetry("2023-06-01 23:00:00", 8_000.0, 7_000.0, 20.0),
+        ],
+    };
+
+    let total = battery_efficiency(&battery).unwrap();
+    assert_eq!(10_000.0, as_watt_hours(total.charged));
+    assert_eq!(Some(0.9), total.round_trip_efficiency);
+    assert_eq!(Some(0.9), total.cycles);
+    assert_eq!(Some(80.0), total.depth_of_cycle);
+
+    let days = daily_battery_efficiency(&battery);
+    assert_eq!(2, days.len());
+    assert_eq!(Some(0.875), days[0].round_trip_efficiency);
+    assert_eq!(2_000.0, as_watt_hours(days[1].discharged));
+    assert_eq!(Some(10.0), days[1].depth_of_cycle);
+}
+
+#[test]
+fn test_compare_inverters() {
+    let inverter = |serial: &str, energy: &[f64]| {
+        let telemetries = energy
+            .iter()
+            .enumerate()
+            .map(|(i, wh)| {
+                serde_json::from_value(serde_json::json!({
+                    "date": format!("2023-06-01 1{i}:00:00"),
+                    "totalEnergy": wh,
+                }))
+                .unwrap()
+            })
+            .collect();
+        let equipment = Equipment {
+            name: serial.to_string(),
+            manufacturer: String::new(),
+            model: String::new(),
+            serial_number: serial.to_string(),
+        };
+        let data = InverterData {
+            count: energy.len() as u32,
+            telemetries,
+        };
+        (equipment, data)
+    };
+    let inverters = vec![
+        inverter("1", &[0.0, 10_500.0]),
+        inverter("2", &[0.0, 11_000.0]),
+        inverter("3", &[0.0, 8_500.0]),
+        inverter("4", &[]),
+    ];
+
+    let comparison = compare_inverters(&inverters, 0.12);
+    assert_eq!(Some(0.1), comparison[1].deviation);
+    assert!(!comparison[0].flagged);
+    assert!(!comparison[1].flagged);
+    assert!(comparison[2].flagged);
+    assert_eq!(None, comparison[3].energy);
+    assert!(comparison[3].flagged);
+}
+
+#[test]
+fn test_battery_cycles() {
+    let telemetry = |date: &str, discharged: f64, soc: f64| BatteryTelemetry {
+        timestamp: NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap(),
+        power: None,
+        battery_state: None,
+        lifetime_energy_discharged: watt_hours(discharged),
+        lifetime_energy_charged: watt_hours(discharged),
+        full_pack_energy_available: None,
+        internal_temp: None,
+        ac_grid_charging: None,
+        battery_percentage_state: Some(soc),
+    };
+    let battery = Battery {
+        nameplate: watt_hours(10_000.0),
+        serial_number: "BAT-1".to_string(),
+        model_number: String::new(),
+        telemetry_count: 6,
+        telemetries: vec![
+            telemetry("2023-05-31 20:00:00", 0.0, 100.0),
+            telemetry("2023-05-31 20:15:00", 2_000.0, 80.0),
+            telemetry("2023-06-01 06:00:00", 8_000.0, 20.0),
+            telemetry("2023-06-01 06:10:00", 8_000.0, 18.0),
+            telemetry("2023-06-01 14:00:00", 8_000.0, 90.0),
+            telemetry("2023-06-01 22:00:00", 12_000.0, 50.0),
+        ],
+    };
+
+    let months = battery_cycles(&battery, 20.0, 95.0);
+    assert_eq!(2, months.len());
+    assert_eq!(chrono::Duration::minutes(15), months[0].time_at_high);
+    assert_eq!(Some(0.2), months[0].equivalent_full_cycles);
+    assert_eq!(0, months[0].discharges);
+    // 100 -> 18 and 90 -> 50
+    assert_eq!(2, months[1].discharges);
+    assert_eq!(Some(61.0), months[1].average_depth_of_discharge);
+    assert_eq!(Some(1.0), months[1].equivalent_full_cycles);
+    assert_eq!(chrono::Duration::minutes(10 + 15), months[1].time_at_low);
+}
@@ -0,0 +1,654 @@
+//! Detects anomalies in the production of a site, like an inverter that
+//! tripped and stopped producing during the day, as typed events that can be
+//! used to alert the owner of the site
+
+use crate::{
+    equipment::{InverterData, InverterTelemetry, PhaseData},
+    series::{Aggregation, TimeSeries},
+    site::TimeUnit,
+    units::{Power, PowerUnit},
+};
+#[cfg(test)]
+use crate::{series::TimeSeriesValue, units::watts};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use std::collections::BTreeMap;
+
+/// The period of a day in which a site is expected to produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DaylightWindow {
+    /// the start of the window, i.e. sunrise
+    pub start: NaiveDateTime,
+    /// the end of the window (exclusive), i.e. sunset
+    pub end: NaiveDateTime,
+}
+
+impl DaylightWindow {
+    /// true when `datetime` is in this window
+    pub fn contains(&self, datetime: NaiveDateTime) -> bool {
+        datetime >= self.start && datetime < self.end
+    }
+
+    /// This window with `margin` removed from both ends, i.e. to skip the low
+    /// production just after sunrise and before sunset. Returns None when
+    /// nothing is left
+    pub fn shrink(&self, margin: Duration) -> Option<DaylightWindow> {
+        let window = DaylightWindow {
+            start: self.start + margin,
+            end: self.end - margin,
+        };
+        (window.start < window.end).then_some(window)
+    }
+}
+
+/// A source of the daylight window of a day in the local time of the site,
+/// used by [`ZeroProductionDetector`]. A pair of times is a fixed window for
+/// every day, i.e. 09:00 until 17:00
+pub trait DaylightSource {
+    /// the daylight window of `date`, or None when there is no daylight or it
+    /// is not known
+    fn daylight_window(&self, date: NaiveDate) -> Option<DaylightWindow>;
+}
+
+impl DaylightSource for (NaiveTime, NaiveTime) {
+    fn daylight_window(&self, date: NaiveDate) -> Option<DaylightWindow> {
+        let window = DaylightWindow {
+            start: date.and_time(self.0),
+            end: date.and_time(self.1),
+        };
+        (window.start < window.end).then_some(window)
+    }
+}
+
+/// The kind of an [`Anomaly`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnomalyKind {
+    /// the site reported no production during daylight, the signature of a
+    /// tripped inverter
+    ZeroProduction,
+    /// the site reported no values during daylight, i.e. a communication
+    /// problem
+    MissingData,
+}
+
+/// An interval of a series in which the production of a site was anomalous
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    /// the timestamp of the first anomalous value
+    pub start: NaiveDateTime,
+    /// the end of the interval (exclusive), which is the end of the interval
+    /// of the last anomalous value
+    pub end: NaiveDateTime,
+}
+
+impl Anomaly {
+    /// the length of the interval
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// Detects intervals during daylight in which a power series is zero or has
+/// no values.
+///
+/// ```
+/// # use solar_api::anomalies::ZeroProductionDetector;
+/// # use chrono::Duration;
+/// let detector = ZeroProductionDetector::new()
+///     .with_min_duration(Duration::hours(1))
+///     .with_margin(Duration::minutes(45));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZeroProductionDetector {
+    min_duration: Duration,
+    margin: Duration,
+    threshold: Power,
+}
+
+impl Default for ZeroProductionDetector {
+    fn default() -> Self {
+        ZeroProductionDetector {
+            min_duration: Duration::minutes(30),
+            margin: Duration::minutes(30),
+            threshold: PowerUnit::Watt.power(0.0),
+        }
+    }
+}
+
+impl ZeroProductionDetector {
+    /// Create a detector that reports intervals of at least 30 minutes, more
+    /// than 30 minutes after sunrise and before sunset
+    pub fn new() -> Self {
+        ZeroProductionDetector::default()
+    }
+
+    /// Set the minimum length of a reported interval
+    pub fn with_min_duration(mut self, min_duration: Duration) -> Self {
+        self.min_duration = min_duration;
+        self
+    }
+
+    /// Set the time after the start and before the end of the daylight window
+    /// in which zero production is expected and not reported
+    pub fn with_margin(mut self, margin: Duration) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Set the power at or below which production counts as zero, i.e. to
+    /// ignore the standby consumption of an inverter
+    pub fn with_threshold(mut self, threshold: Power) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// The anomalies in `power` during the daylight windows of `daylight`.
+    /// Consecutive anomalous values of the same kind are combined into one
+    /// anomaly, which ends at the end of the daylight window at the latest
+    pub fn detect(
+        &self,
+        power: &TimeSeries<Power>,
+        daylight: &impl DaylightSource,
+    ) -> Vec<Anomaly> {
+        let step = power.time_unit.duration();
+        let mut values: Vec<_> = power.iter().collect();
+        values.sort_by_key(|v| v.date);
+
+        let mut anomalies = Vec::new();
+        let mut current: Option<Anomaly> = None;
+        for v in values {
+            let window = daylight
+                .daylight_window(v.date.date())
+                .and_then(|window| window.shrink(self.margin))
+                .filter(|window| window.contains(v.date));
+            let kind = match (window, v.value) {
+                (Some(_), None) => Some(AnomalyKind::MissingData),
+                (Some(_), Some(value)) if value <= self.threshold => {
+                    Some(AnomalyKind::ZeroProduction)
+                }
+                _ => None,
+            };
+            match (&mut current, kind) {
+                (Some(anomaly), Some(kind)) if anomaly.kind == kind && anomaly.end == v.date => {
+                    anomaly.end = v.date + step;
+                }
+                _ => {
+                    anomalies.extend(current.take());
+                    current = kind.map(|kind| Anomaly {
+                        kind,
+                        start: v.date,
+                        end: v.date + step,
+                    });
+                }
+            }
+            if let (Some(anomaly), Some(window)) = (&mut current, window) {
+                anomaly.end = anomaly.end.min(window.end);
+            }
+        }
+        anomalies.extend(current);
+        anomalies.retain(|anomaly| anomaly.duration() >= self.min_duration);
+        anomalies
+    }
+}
+
+/// A cause of lost production found by [`SoilingDetector`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LossFinding {
+    /// the yield declined gradually compared with the baseline, i.e. because
+    /// dust or pollen collected on the modules
+    Soiling {
+        start: NaiveDate,
+        end: NaiveDate,
+        /// the decline of the yield relative to the baseline over the period
+        /// as fraction, i.e. 0.08 for 8%
+        decline: f64,
+    },
+    /// the yield dropped to near zero for several days in winter, i.e.
+    /// because snow covered the modules
+    Snow { start: NaiveDate, end: NaiveDate },
+}
+
+/// Distinguishes soiling from snow by comparing the daily specific yield of a
+/// site with the yield around the same day of the year in earlier years.
+///
+/// ```
+/// # use solar_api::anomalies::SoilingDetector;
+/// // southern hemisphere
+/// let detector = SoilingDetector::new()
+///     .with_snow_months(&[6, 7, 8])
+///     .with_soiling_decline(0.1);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoilingDetector {
+    baseline_days: i64,
+    near_zero: f64,
+    snow_days: usize,
+    snow_months: Vec<u32>,
+    soiling_days: i64,
+    soiling_decline: f64,
+}
+
+impl Default for SoilingDetector {
+    fn default() -> Self {
+        SoilingDetector {
+            baseline_days: 15,
+            near_zero: 0.1,
+            snow_days: 2,
+            snow_months: vec![11, 12, 1, 2, 3],
+            soiling_days: 30,
+            soiling_decline: 0.05,
+        }
+    }
+}
+
+impl SoilingDetector {
+    /// Create a detector for the northern hemisphere that reports snow of at
+    /// least 2 days in november until march and soiling of at least 5% in the
+    /// last 30 days
+    pub fn new() -> Self {
+        SoilingDetector::default()
+    }
+
+    /// Set the number of days before and after the same day of the year in
+    /// earlier years that make up the baseline of a day
+    pub fn with_baseline_days(mut self, days: i64) -> Self {
+        self.baseline_days = days;
+        self
+    }
+
+    /// Set the fraction of the baseline below which the yield of a day counts
+    /// as near zero
+    pub fn with_near_zero(mut self, fraction: f64) -> Self {
+        self.near_zero = fraction;
+        self
+    }
+
+    /// Set the minimum number of consecutive near zero days reported as snow
+    pub fn with_snow_days(mut self, days: usize) -> Self {
+        self.snow_days = days;
+        self
+    }
+
+    /// Set the months in which snow can occur
+    pub fn with_snow_months(mut self, months: &[u32]) -> Self {
+        self.snow_months = months.to_vec();
+        self
+    }
+
+    /// Set the number of most recent days that are checked for soiling
+    pub fn with_soiling_days(mut self, days: i64) -> Self {
+        self.soiling_days = days;
+        self
+    }
+
+    /// Set the minimum decline as fraction of the baseline reported as soiling
+    pub fn with_soiling_decline(mut self, fraction: f64) -> Self {
+        self.soiling_decline = fraction;
+        self
+    }
+
+    /// The soiling and snow in `specific_yield`, the daily specific yield of a
+    /// site as returned by [`specific_yield`](crate::analytics::specific_yield).
+    /// Needs at least a year of history, as days without a baseline are
+    /// skipped. Snow is reported for every period of near zero days, soiling
+    /// only for the most recent days
+    pub fn detect(&self, specific_yield: &TimeSeries<f64>) -> Vec<LossFinding> {
+        let daily: BTreeMap<NaiveDate, f64> = specific_yield
+            .resample(TimeUnit::Day, Aggregation::Sum)
+            .into_iter()
+            .filter_map(|v| v.value.map(|value| (v.date.date(), value)))
+            .collect();
+        let ratios: Vec<(NaiveDate, f64)> = daily
+            .keys()
+            .filter_map(|date| {
+                let baseline = self.baseline(&daily, *date)?;
+                Some((*date, daily[date] / baseline))
+            })
+            .collect();
+
+        let mut findings = Vec::new();
+        let mut snow: Vec<NaiveDate> = Vec::new();
+        let mut snow_dates = Vec::new();
+        for (date, ratio) in &ratios {
+            let is_snow = *ratio < self.near_zero && self.snow_months.contains(&date.month());
+            let follows = snow
+                .last()
+                .is_none_or(|last| *date - *last == Duration::days(1));
+            if !(is_snow && follows) {
+                self.push_snow(&mut findings, &snow);
+                snow_dates.append(&mut snow);
+            }
+            if is_snow {
+                snow.push(*date);
+            }
+        }
+        self.push_snow(&mut findings, &snow);
+        snow_dates.append(&mut snow);
+
+        if let Some((last, _)) = ratios.last() {
+            let start = *last - Duration::days(self.soiling_days - 1);
+            let points: Vec<(f64, f64)> = ratios
+                .iter()
+                .filter(|(date, _)| *date >= start && !snow_dates.contains(date))
+                .map(|(date, ratio)| ((*date - start).num_days() as f64, *ratio))
+                .collect();
+            if points.len() as i64 >= self.soiling_days / 2 {
+                let n = points.len() as f64;
+                let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+                let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+                let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+                let sxy: f64 = points
+                    .iter()
+                    .map(|(x, y)| (x - mean_x) * (y - mean_y))
+                    .sum();
+                let decline = -sxy / sxx * (self.soiling_days - 1) as f64;
+                if decline >= self.soiling_decline {
+                    findings.push(LossFinding::Soiling {
+                        start,
+                        end: *last,
+                        decline,
+                    });
+                }
+            }
+        }
+        findings
+    }
+
+    // the median yield around the same day of the year as `date` in earlier
+    // years, or None when there is none
+    fn baseline(&self, daily: &BTreeMap<NaiveDate, f64>, date: NaiveDate) -> Option<f64> {
+        let mut values: Vec<f64> = daily
+            .iter()
+            .filter(|(other, _)| other.year() < date.year())
+            .filter(|(other, _)| {
+                let diff = (other.ordinal0() as i64 - date.ordinal0() as i64).abs();
+                diff.min(365 - diff) <= self.baseline_days
+            })
+            .map(|(_, value)| *value)
+            .collect();
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        values.get(values.len() / 2).copied().filter(|v| *v > 0.0)
+    }
+
+    // adds the consecutive near zero days in `snow` as finding when there are enough
+    fn push_snow(&self, findings: &mut Vec<LossFinding>, snow: &[NaiveDate]) {
+        if let (Some(start), Some(end)) = (snow.first(), snow.last()) {
+            if snow.len() >= self.snow_days {
+                findings.push(LossFinding::Snow {
+                    start: *start,
+                    end: *end,
+                });
+            }
+        }
+    }
+}
+
+/// The measurement of the phases that is imbalanced, see
+/// [`PhaseImbalanceDetector`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhaseQuantity {
+    Voltage,
+    Current,
+}
+
+/// An interval in which the voltage or current of the phases of an inverter
+/// was imbalanced, see [`PhaseImbalanceDetector`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhaseImbalance {
+    pub quantity: PhaseQuantity,
+    /// the timestamp of the first imbalanced telemetry
+    pub start: NaiveDateTime,
+    /// the timestamp of the last imbalanced telemetry
+    pub end: NaiveDateTime,
+    /// the largest imbalance in the interval as fraction of the mean of the
+    /// phases
+    pub max_imbalance: f64,
+}
+
+/// Detects intervals in which the voltage or current of L1, L2 and L3 of a
+/// three-phase inverter is imbalanced, i.e. because a phase is failing. The
+/// imbalance is the largest deviation of a phase from the mean of the phases
+/// divided by that mean.
+///
+/// ```
+/// # use solar_api::anomalies::PhaseImbalanceDetector;
+/// let detector = PhaseImbalanceDetector::new()
+///     .with_voltage_threshold(0.03)
+///     .with_min_current(2.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhaseImbalanceDetector {
+    voltage_threshold: f64,
+    current_threshold: f64,
+    min_current: f64,
+}
+
+impl Default for PhaseImbalanceDetector {
+    fn default() -> Self {
+        PhaseImbalanceDetector {
+            voltage_threshold: 0.02,
+            current_threshold: 0.1,
+            min_current: 1.0,
+        }
+    }
+}
+
+impl PhaseImbalanceDetector {
+    /// Create a detector that reports a voltage imbalance above 2% and a
+    /// current imbalance above 10% when the mean current is at least 1 A
+    pub fn new() -> Self {
+        PhaseImbalanceDetector::default()
+    }
+
+    /// Set the voltage imbalance above which a telemetry is imbalanced
+    pub fn with_voltage_threshold(mut self, fraction: f64) -> Self {
+        self.voltage_threshold = fraction;
+        self
+    }
+
+    /// Set the current imbalance above which a telemetry is imbalanced
+    pub fn with_current_threshold(mut self, fraction: f64) -> Self {
+        self.current_threshold = fraction;
+        self
+    }
+
+    /// Set the mean current in A below which the current imbalance is not
+    /// checked, i.e. at dawn when small currents differ a lot relatively
+    pub fn with_min_current(mut self, current: f64) -> Self {
+        self.min_current = current;
+        self
+    }
+
+    /// The intervals in which the telemetry of `data` is imbalanced.
+    /// Consecutive imbalanced telemetries of the same quantity are combined
+    /// into one interval. Telemetries without data of all three phases are
+    /// skipped
+    pub fn detect(&self, data: &InverterData) -> Vec<PhaseImbalance> {
+        let mut imbalances = Vec::new();
+        for quantity in [PhaseQuantity::Voltage, PhaseQuantity::Current] {
+            let mut current: Option<PhaseImbalance> = None;
+            for telemetry in data.sorted_telemetries() {
+                let Some(imbalance) = self.imbalance(telemetry, quantity) else {
+                    continue;
+                };
+                let threshold = match quantity {
+                    PhaseQuantity::Voltage => self.voltage_threshold,
+                    PhaseQuantity::Current => self.current_threshold,
+                };
+                match (&mut current, imbalance > threshold) {
+                    (Some(interval), true) => {
+                        interval.end = telemetry.date;
+                        interval.max_imbalance = interval.max_imbalance.max(imbalance);
+                    }
+                    (None, true) => {
+                        current = Some(PhaseImbalance {
+                            quantity,
+                            start: telemetry.date,
+                            end: telemetry.date,
+                            max_imbalance: imbalance,
+                        })
+                    }
+                    (_, false) => imbalances.extend(current.take()),
+                }
+            }
+            imbalances.extend(current);
+        }
+        imbalances.sort_by_key(|imbalance| imbalance.start);
+        imbalances
+    }
+
+    // the imbalance of `quantity` of `telemetry`, or None when it is not known
+    // or the current is too small to check
+    fn imbalance(&self, telemetry: &InverterTelemetry, quantity: PhaseQuantity) -> Option<f64> {
+        let value = |phase: Option<PhaseData>| {
+            phase.and_then(|phase| match quantity {
+                PhaseQuantity::Voltage => phase.ac_voltage,
+                PhaseQuantity::Current => phase.ac_current,
+            })
+        };
+        let phases = [
+            value(telemetry.l1_data)?,
+            value(telemetry.l2_data)?,
+            value(telemetry.l3_data)?,
+        ];
+        let mean = phases.iter().sum::<f64>() / 3.0;
+        if mean <= 0.0 || (quantity == PhaseQuantity::Current && mean < self.min_current) {
+            return None;
+        }
+        let deviation = phases
+            .iter()
+            .map(|phase| (phase - mean).abs())
+            .fold(0.0, f64::max);
+        Some(deviation / mean)
+    }
+}
+
+#[test]
+fn test_zero_production() {
+    let date = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap();
+    let at = |h: u32, m: u32| date.and_hms_opt(h, m, 0).unwrap();
+    // produces from 06:00 until 20:00, trips at 12:00 until 14:00 and misses
+    // data from 16:00 until 16:15
+    let values = (0..96)
+        .map(|i| {
+            let datetime = date.and_time(NaiveTime::MIN) + Duration::minutes(15 * i);
+            let value = match datetime.time() {
+                t if t >= at(16, 0).time() && t < at(16, 15).time() => None,
+                t if t >= at(12, 0).time() && t < at(14, 0).time() => Some(0.0),
+                t if t >= at(6, 0).time() && t < at(20, 0).time() => Some(1000.0),
+                _ => Some(0.0),
+            };
+            TimeSeriesValue {
+                date: datetime,
+                value: value.map(watts),
+            }
+        })
+        .collect();
+    let power = TimeSeries::new(TimeUnit::QuarterOfAnHour, "W", values);
+    let daylight = (at(5, 30).time(), at(20, 30).time());
+
+    let anomalies = ZeroProductionDetector::new()
+        .with_min_duration(Duration::minutes(15))
+        .detect(&power, &daylight);
+    assert_eq!(
+        vec![
+            Anomaly {
+                kind: AnomalyKind::ZeroProduction,
+                start: at(12, 0),
+                end: at(14, 0),
+            },
+            Anomaly {
+                kind: AnomalyKind::MissingData,
+                start: at(16, 0),
+                end: at(16, 15),
+            },
+        ],
+        anomalies
+    );
+
+    let long_only = ZeroProductionDetector::new()
+        .with_min_duration(Duration::hours(1))
+        .detect(&power, &daylight);
+    assert_eq!(1, long_only.len());
+}
+
+#[test]
+fn test_soiling_and_snow() {
+    let date = |y: i32, m: u32, d: u32| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    // a constant yield in 2022, snow in january 2023 and a decline of 15% in
+    // june 2023
+    let values = date(2022, 1, 1)
+        .iter_days()
+        .take_while(|day| *day <= date(2023, 6, 30))
+        .map(|day| {
+            let value = if day >= date(2023, 1, 10) && day <= date(2023, 1, 13) {
+                0.1
+            } else if day >= date(2023, 6, 1) {
+                4.0 * (1.0 - 0.005 * (day - date(2023, 6, 1)).num_days() as f64)
+            } else {
+                4.0
+            };
+            TimeSeriesValue {
+                date: day.into(),
+                value: Some(value),
+            }
+        })
+        .collect();
+    let specific_yield = TimeSeries::new(TimeUnit::Day, "kWh/kWp", values);
+
+    let findings = SoilingDetector::new().detect(&specific_yield);
+    assert_eq!(2, findings.len());
+    assert_eq!(
+        LossFinding::Snow {
+            start: date(2023, 1, 10),
+            end: date(2023, 1, 13)
+        },
+        findings[0]
+    );
+    let LossFinding::Soiling { start, decline, .. } = findings[1] else {
+        panic!("expected soiling");
+    };
+    assert_eq!(date(2023, 6, 1), start);
+    assert!((decline - 0.145).abs() < 1e-6);
+
+    let strict = SoilingDetector::new()
+        .with_snow_months(&[6, 7, 8])
+        .with_soiling_decline(0.2);
+    assert!(strict.detect(&specific_yield).is_empty());
+}
+
+#[test]
+fn test_phase_imbalance() {
+    let telemetry = |minute: u32, voltages: [f64; 3], currents: [f64; 3]| {
+        let phase =
+            |i: usize| serde_json::json!({"acVoltage": voltages[i], "acCurrent": currents[i]});
+        serde_json::json!({
+            "date": format!("2023-06-01 12:{minute:02}:00"),
+            "L1Data": phase(0), "L2Data": phase(1), "L3Data": phase(2)
+        })
+    };
+    let data: InverterData = serde_json::from_value(serde_json::json!({
+        "count": 5,
+        "telemetries": [
+            telemetry(0, [230.0, 230.0, 230.0], [10.0, 10.0, 10.0]),
+            telemetry(5, [230.0, 230.0, 215.0], [10.0, 10.0, 10.0]),
+            telemetry(10, [230.0, 231.0, 210.0], [10.0, 10.0, 6.0]),
+            telemetry(15, [230.0, 230.0, 230.0], [10.0, 10.0, 10.0]),
+            // too little current to check
+            telemetry(20, [230.0, 230.0, 230.0], [0.5, 0.5, 0.0]),
+        ]
+    }))
+    .unwrap();
+
+    let imbalances = PhaseImbalanceDetector::new().detect(&data);
+    assert_eq!(2, imbalances.len());
+    let voltage = imbalances[0];
+    assert_eq!(PhaseQuantity::Voltage, voltage.quantity);
+    assert_eq!("12:05", voltage.start.format("%H:%M").to_string());
+    assert_eq!("12:10", voltage.end.format("%H:%M").to_string());
+    assert!((voltage.max_imbalance - 41.0 / 671.0).abs() < 1e-9);
+    let current = imbalances[1];
+    assert_eq!(PhaseQuantity::Current, current.quantity);
+    assert_eq!(current.start, current.end);
+    assert!((current.max_imbalance - 2.6666 / 8.6666).abs() < 1e-3);
+}
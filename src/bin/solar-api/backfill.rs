@@ -0,0 +1,155 @@
+//! The `backfill` subcommand, which downloads the history of a site with
+//! [`Client::backfill`] and writes it to Parquet or CSV files
+
+use crate::{parse_date, SiteArg};
+use chrono::NaiveDate;
+#[cfg(feature = "arrow")]
+use solar_api::export::parquet::ParquetWriter;
+use solar_api::{
+    export::csv::{CsvOptions, CsvTable},
+    sync::{backfill_periods, SyncState},
+    Client, Energy, SolarApiError, TimeSeries, TimeUnit,
+};
+use std::path::{Path, PathBuf};
+
+/// The format of the files of a backfill
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// A CSV file per request at `site_id=1234/time_unit=DAY/2023-01-01.csv`
+    Csv,
+    /// Parquet files per site and month, see `export::parquet`
+    #[cfg(feature = "arrow")]
+    Parquet,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct BackfillArgs {
+    #[command(flatten)]
+    pub site: SiteArg,
+    /// The first day, the start of the data period of the site by default
+    #[arg(long, value_parser = parse_date)]
+    pub from: Option<NaiveDate>,
+    /// The last day, the end of the data period of the site by default
+    #[arg(long, value_parser = parse_date)]
+    pub to: Option<NaiveDate>,
+    /// The time units to download, i.e. `quarter,day`
+    #[arg(long, value_delimiter = ',', default_value = "DAY")]
+    pub resolution: Vec<TimeUnit>,
+    /// The directory to write the files to
+    #[arg(long)]
+    pub out: PathBuf,
+    /// The format of the files
+    #[arg(long, value_enum, default_value = "csv")]
+    pub format: Format,
+    /// The file that keeps the progress, to resume the next day when the
+    /// request budget is used. `sync.json` in the output directory by default
+    #[arg(long)]
+    pub state: Option<PathBuf>,
+}
+
+pub fn backfill(client: &Client, site_id: u32, args: &BackfillArgs) -> Result<(), SolarApiError> {
+    std::fs::create_dir_all(&args.out).map_err(SolarApiError::ExportIo)?;
+    let state_path = args
+        .state
+        .clone()
+        .unwrap_or_else(|| args.out.join("sync.json"));
+    let mut state = SyncState::load(&state_path)?;
+    let (from, to) = match (args.from, args.to) {
+        (Some(from), Some(to)) => (from, to),
+        (from, to) => {
+            let period = client.data_period(site_id)?;
+            state.record_requests(chrono::Local::now().date_naive(), 1);
+            match (from.or(period.start_date), to.or(period.end_date)) {
+                (Some(from), Some(to)) => (from, to),
+                _ => {
+                    eprintln!("Site {site_id} has no data to backfill yet");
+                    return state.save(&state_path);
+                }
+            }
+        }
+    };
+    let total: usize = args
+        .resolution
+        .iter()
+        .map(|time_unit| {
+            let last_seen = state.last_seen(site_id, &SyncState::energy_endpoint(*time_unit));
+            backfill_periods(from, to, last_seen, *time_unit).len()
+        })
+        .sum();
+    eprintln!("Backfilling site {site_id} from {from} until {to} in {total} requests");
+
+    let mut done = 0;
+    #[cfg(feature = "arrow")]
+    let mut parquet = ParquetWriter::new(&args.out).with_merge();
+    let result = client.backfill(
+        &mut state,
+        site_id,
+        Some(from),
+        Some(to),
+        &args.resolution,
+        &mut |site_id, time_unit: TimeUnit, energy: &TimeSeries<Energy>| {
+            done += 1;
+            eprintln!(
+                "[{done}/{total}] {} {}: {} values",
+                time_unit.to_param(),
+                energy
+                    .values
+                    .last()
+                    .map_or("-".to_string(), |last| last.date.date().to_string()),
+                energy.values.len()
+            );
+            match args.format {
+                Format::Csv => write_csv(&args.out, site_id, time_unit, energy),
+                #[cfg(feature = "arrow")]
+                Format::Parquet => {
+                    parquet.add_energy(
+                        site_id,
+                        &format!("energy.{}", time_unit.to_param()),
+                        energy,
+                    );
+                    Ok(())
+                }
+            }
+        },
+    );
+    // keep what was downloaded, also when the backfill failed
+    #[cfg(feature = "arrow")]
+    let written = parquet.finish();
+    state.save(&state_path)?;
+    let report = result?;
+    #[cfg(feature = "arrow")]
+    written?;
+
+    eprintln!(
+        "Wrote {} values in {} requests",
+        report.values, report.requests
+    );
+    if !report.complete {
+        eprintln!(
+            "The request budget of today is used, run this command again tomorrow to continue"
+        );
+    }
+    Ok(())
+}
+
+// writes `energy` to a CSV file named after the date of its first value
+fn write_csv(
+    out: &Path,
+    site_id: u32,
+    time_unit: TimeUnit,
+    energy: &TimeSeries<Energy>,
+) -> Result<(), SolarApiError> {
+    let Some(first) = energy.values.first() else {
+        return Ok(());
+    };
+    let dir = out
+        .join(format!("site_id={site_id}"))
+        .join(format!("time_unit={}", time_unit.to_param()));
+    std::fs::create_dir_all(&dir).map_err(SolarApiError::ExportIo)?;
+    let file = std::fs::File::create(dir.join(format!("{}.csv", first.date.date())))
+        .map_err(SolarApiError::ExportIo)?;
+    CsvTable::new(CsvOptions::new())
+        .with_energy("energy", energy)
+        .write_to(file)
+        .map_err(SolarApiError::ExportIo)
+}
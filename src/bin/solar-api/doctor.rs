@@ -0,0 +1,244 @@
+//! The `doctor` subcommand, which checks the config, the API key and the site
+//! and reports what the API returns, to find out why an integration does not
+//! work
+
+use crate::{Cli, SiteArg};
+use chrono::{Duration, Local};
+use reqwest::StatusCode;
+use solar_api::{
+    config::Config,
+    display::{format_power, Table},
+    sync::DAILY_REQUEST_LIMIT,
+    Client, Location, SolarApiError,
+};
+use std::error::Error;
+
+// the outcome of a check
+#[derive(Debug)]
+enum Outcome {
+    Passed(String),
+    Failed(SolarApiError),
+    Warning(String),
+    Skipped(&'static str),
+}
+
+impl From<Result<String, SolarApiError>> for Outcome {
+    fn from(result: Result<String, SolarApiError>) -> Self {
+        match result {
+            Ok(details) => Outcome::Passed(details),
+            Err(e) => Outcome::Failed(e),
+        }
+    }
+}
+
+/// Run every check that the config allows and print a table with the
+/// outcomes. Returns the error of the first failed check
+pub fn doctor(cli: &Cli, site: &SiteArg) -> Result<(), SolarApiError> {
+    let mut checks: Vec<(&str, Outcome)> = Vec::new();
+    let config = match &cli.config {
+        Some(path) => Config::load_from(path),
+        None => Config::load(),
+    };
+    let config = match config {
+        Ok(mut config) => {
+            let path = cli.config.clone().or_else(Config::default_path);
+            let details = match path {
+                Some(path) if path.exists() => path.display().to_string(),
+                _ => "no config file, using the environment".to_string(),
+            };
+            checks.push(("Config", Outcome::Passed(details)));
+            if let Some(api_key) = &cli.api_key {
+                config.api_key = Some(api_key.clone());
+            }
+            config
+        }
+        Err(e) => {
+            checks.push(("Config", Outcome::Failed(e)));
+            return finish(checks, None);
+        }
+    };
+    let client = match config.api_key() {
+        Ok(api_key) => {
+            checks.push(("API key", Outcome::Passed(masked(api_key))));
+            Client::from_config(&config)?
+        }
+        Err(e) => {
+            checks.push(("API key", Outcome::Failed(e)));
+            return finish(checks, None);
+        }
+    };
+    let site_id = site.site_id.or(config.site_id);
+
+    let mut requests = 1;
+    let sites = client.list().map(|sites| {
+        let ids: Vec<String> = sites.iter().map(|site| site.id.to_string()).collect();
+        let mut details = format!(
+            "{} site(s) on this API key: {}",
+            sites.len(),
+            ids.join(", ")
+        );
+        if let Some(site_id) = site_id.filter(|id| !sites.iter().any(|site| site.id == *id)) {
+            details.push_str(&format!(", site {site_id} is not one of them"));
+        }
+        details
+    });
+    checks.push(("Sites", sites.into()));
+
+    match site_id {
+        None => {
+            let skipped = "no site id, pass one or set site_id in the config";
+            for check in ["Site", "Time zone", "Data period", "Overview", "Batteries"] {
+                checks.push((check, Outcome::Skipped(skipped)));
+            }
+        }
+        Some(site_id) => {
+            requests += 4;
+            match client.details(site_id) {
+                Ok(site) => {
+                    checks.push((
+                        "Site",
+                        Outcome::Passed(format!(
+                            "{} {}: {}, {}, {}",
+                            site.id,
+                            site.name,
+                            site.status,
+                            site.site_type,
+                            format_power(site.peak_power)
+                        )),
+                    ));
+                    checks.push(("Time zone", time_zone(&site.location).into()));
+                }
+                Err(e) => {
+                    checks.push(("Site", Outcome::Failed(e)));
+                    checks.push(("Time zone", Outcome::Skipped("no site details")));
+                }
+            }
+            let period = client
+                .data_period(site_id)
+                .map(|period| match period.bounds() {
+                    Ok((start_date, end_date)) => format!("{start_date} until {end_date}"),
+                    Err(_) => "no data yet".to_string(),
+                });
+            checks.push(("Data period", period.into()));
+            let overview = client.overview(site_id).map(|overview| {
+                let Some(last_updated) = overview.last_updated_time else {
+                    return "the site has not communicated yet".to_string();
+                };
+                let age = Local::now().naive_local() - last_updated;
+                format!(
+                    "last update {} ({} minutes ago), power {}",
+                    last_updated,
+                    age.num_minutes(),
+                    format_power(overview.current_power.power)
+                )
+            });
+            checks.push(("Overview", overview.into()));
+            let now = Local::now().naive_local();
+            let storage = client
+                .storage_data(site_id, now - Duration::hours(1), now, &[])
+                .map(|storage| format!("{} battery(ies)", storage.batteries.len()));
+            checks.push(("Batteries", storage.into()));
+        }
+    }
+
+    let rate_limited = checks.iter().any(|(_, outcome)| match outcome {
+        Outcome::Failed(
+            SolarApiError::ApiError(e)
+            | SolarApiError::ForbiddenError(e)
+            | SolarApiError::NetworkError(e),
+        ) => e.status() == Some(StatusCode::TOO_MANY_REQUESTS),
+        _ => false,
+    });
+    let budget = if rate_limited {
+        Outcome::Warning(format!(
+            "the {DAILY_REQUEST_LIMIT} requests of today are used, try again tomorrow"
+        ))
+    } else {
+        Outcome::Passed(format!(
+            "at most {} of {DAILY_REQUEST_LIMIT} requests left today, this check used \
+             {requests}. Other integrations with this API key or site count as well",
+            DAILY_REQUEST_LIMIT.saturating_sub(requests)
+        ))
+    };
+    checks.push(("Request budget", budget));
+    finish(checks, config.api_key.as_deref())
+}
+
+// prints the table of `checks` and returns the error of the first failed check.
+// `api_key` is redacted from the URLs in the errors
+fn finish(checks: Vec<(&str, Outcome)>, api_key: Option<&str>) -> Result<(), SolarApiError> {
+    let mut table = Table::new(&["Check", "Status", "Details"]);
+    for (check, outcome) in &checks {
+        let (status, details) = match outcome {
+            Outcome::Passed(details) => ("ok", details.clone()),
+            Outcome::Failed(e) => {
+                let description = describe(e);
+                match api_key {
+                    Some(api_key) if !api_key.is_empty() => {
+                        ("FAILED", description.replace(api_key, "<redacted>"))
+                    }
+                    _ => ("FAILED", description),
+                }
+            }
+            Outcome::Warning(details) => ("warning", details.clone()),
+            Outcome::Skipped(reason) => ("skipped", reason.to_string()),
+        };
+        table.add_row(vec![check.to_string(), status.to_string(), details]);
+    }
+    print!("{table}");
+    let failed = checks.into_iter().find_map(|(_, outcome)| match outcome {
+        Outcome::Failed(e) => Some(e),
+        _ => None,
+    });
+    failed.map_or(Ok(()), Err)
+}
+
+// the time zone of the site with its current offset, when it is known
+fn time_zone(location: &Location) -> Result<String, SolarApiError> {
+    #[cfg(feature = "chrono-tz")]
+    {
+        let tz = location.tz()?;
+        let offset = chrono::Utc::now().with_timezone(&tz).format("%:z");
+        Ok(format!("{} (UTC{offset})", location.time_zone))
+    }
+    #[cfg(not(feature = "chrono-tz"))]
+    Ok(location.time_zone.clone())
+}
+
+// `e` with its sources, like `API returned an Error: HTTP status client error`
+fn describe(e: &SolarApiError) -> String {
+    let mut description = e.to_string();
+    // the errors of reqwest are no source of these variants
+    let mut source: Option<&dyn Error> = match e {
+        SolarApiError::NetworkError(e)
+        | SolarApiError::ApiError(e)
+        | SolarApiError::ForbiddenError(e) => Some(e),
+        e => e.source(),
+    };
+    while let Some(e) = source {
+        description.push_str(&format!(": {e}"));
+        source = e.source();
+    }
+    description
+}
+
+// the first and last 4 characters of `api_key`, so the key can be recognized
+// without showing it
+fn masked(api_key: &str) -> String {
+    let chars: Vec<char> = api_key.chars().collect();
+    if chars.len() <= 8 {
+        return "*".repeat(chars.len());
+    }
+    let start: String = chars[..4].iter().collect();
+    let end: String = chars[chars.len() - 4..].iter().collect();
+    format!("{start}...{end} ({} characters)", chars.len())
+}
+
+#[test]
+fn test_masked() {
+    assert_eq!(
+        "L4QL...6B1O (32 characters)",
+        masked("L4QLVQ1LOKCQX2193VSEICXW61NP6B1O")
+    );
+    assert_eq!("*****", masked("short"));
+}
@@ -0,0 +1,180 @@
+//! The `export` subcommand, which fetches a period of a site and pushes it to
+//! a sink with the exporters of the library
+
+use crate::{SiteArg, TimeRange};
+use reqwest::Method;
+#[cfg(feature = "prometheus")]
+use solar_api::prometheus::Metrics;
+#[cfg(feature = "sqlite")]
+use solar_api::sqlite::SqliteSink;
+use solar_api::{
+    export::{
+        csv::{CsvOptions, CsvTable},
+        influx::LineProtocolWriter,
+    },
+    Client, DataPeriod, SolarApiError, TimeUnit,
+};
+use std::io::Write;
+
+/// The sink of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Sink {
+    /// The energy and power as InfluxDB line protocol, POSTed to the write API
+    /// when the target is a URL. Set `INFLUX_TOKEN` to authenticate
+    Influx,
+    /// The overview and the last values of the batteries as Prometheus
+    /// gauges, PUT to a Pushgateway when the target is a URL or written to a
+    /// file for the textfile collector
+    #[cfg(feature = "prometheus")]
+    Prometheus,
+    /// Rows of the normalized schema in the SQLite database at the target
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+    /// CSV with a column per series
+    Csv,
+}
+
+/// The data of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Data {
+    /// The energy per time unit
+    Energy,
+    /// The power per quarter of an hour
+    Power,
+    /// The power and state of charge of the batteries
+    Storage,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    pub site: SiteArg,
+    /// The sink to export to
+    #[arg(long)]
+    pub to: Sink,
+    /// The data to export. Prometheus exports the overview as well
+    #[arg(long, value_delimiter = ',', default_value = "energy,power")]
+    pub data: Vec<Data>,
+    #[command(flatten)]
+    pub range: TimeRange,
+    /// The time unit of the energy
+    #[arg(long, default_value = "QUARTER_OF_AN_HOUR")]
+    pub time_unit: TimeUnit,
+    /// A file, or the URL of InfluxDB or a Pushgateway. stdout by default,
+    /// `solar-api.db` for SQLite
+    #[arg(long)]
+    pub target: Option<String>,
+}
+
+pub fn export(client: &Client, site_id: u32, args: &ExportArgs) -> Result<(), SolarApiError> {
+    let (start, end) = args.range.resolve(chrono::Local::now().naive_local());
+    let has = |data: Data| args.data.contains(&data);
+    let energy = if has(Data::Energy) {
+        let period = DataPeriod::between(start.date(), end.date())?;
+        Some(
+            client
+                .energy_chunked(site_id, period, args.time_unit)?
+                .to_series()?,
+        )
+    } else {
+        None
+    };
+    let power = if has(Data::Power) {
+        Some(client.power_chunked(site_id, start, end)?.to_series()?)
+    } else {
+        None
+    };
+    let storage = if has(Data::Storage) {
+        Some(client.storage_data(site_id, start, end, &[])?)
+    } else {
+        None
+    };
+
+    match args.to {
+        Sink::Influx => {
+            let mut writer = LineProtocolWriter::new(Vec::new());
+            if let Some(energy) = &energy {
+                writer.write_energy(site_id, energy)?;
+            }
+            if let Some(power) = &power {
+                writer.write_power(site_id, power)?;
+            }
+            let token = std::env::var("INFLUX_TOKEN").ok();
+            push(
+                Method::POST,
+                token,
+                args.target.as_deref(),
+                writer.into_inner(),
+            )
+        }
+        #[cfg(feature = "prometheus")]
+        Sink::Prometheus => {
+            let mut metrics = Metrics::new();
+            metrics.add_overview(site_id, &client.overview(site_id)?);
+            if let Some(storage) = &storage {
+                metrics.add_storage(site_id, storage);
+            }
+            let metrics = metrics.to_string().into_bytes();
+            push(Method::PUT, None, args.target.as_deref(), metrics)
+        }
+        #[cfg(feature = "sqlite")]
+        Sink::Sqlite => {
+            let path = args.target.as_deref().unwrap_or("solar-api.db");
+            let db =
+                solar_api::rusqlite::Connection::open(path).map_err(SolarApiError::SqliteError)?;
+            let sink = SqliteSink::new(&db);
+            sink.create_table()?;
+            let mut rows = 0;
+            if let Some(energy) = &energy {
+                rows += sink.write_energy(site_id, "energy", energy)?;
+            }
+            if let Some(power) = &power {
+                rows += sink.write_power(site_id, "power", power)?;
+            }
+            if let Some(storage) = &storage {
+                rows += sink.write_storage(site_id, storage)?;
+            }
+            eprintln!("Wrote {rows} rows to {path}");
+            Ok(())
+        }
+        Sink::Csv => {
+            let mut table = CsvTable::new(CsvOptions::new());
+            if let Some(energy) = &energy {
+                table = table.with_energy("energy", energy);
+            }
+            if let Some(power) = &power {
+                table = table.with_power("power", power);
+            }
+            if let Some(storage) = &storage {
+                table = table.with_storage(storage);
+            }
+            let csv = table.to_string().into_bytes();
+            push(Method::POST, None, args.target.as_deref(), csv)
+        }
+    }
+}
+
+// sends `body` to the URL `target` with `method` and the optional `token`, or
+// writes it to the file `target` or stdout
+fn push(
+    method: Method,
+    token: Option<String>,
+    target: Option<&str>,
+    body: Vec<u8>,
+) -> Result<(), SolarApiError> {
+    match target {
+        Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+            let request = reqwest::blocking::Client::new().request(method, url);
+            let request = match token {
+                Some(token) => request.header("Authorization", format!("Token {token}")),
+                None => request,
+            };
+            request.body(body).send()?.error_for_status()?;
+            Ok(())
+        }
+        Some(path) => std::fs::write(path, body).map_err(SolarApiError::ExportIo),
+        None => std::io::stdout()
+            .write_all(&body)
+            .map_err(SolarApiError::ExportIo),
+    }
+}
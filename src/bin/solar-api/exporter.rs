@@ -0,0 +1,104 @@
+//! The `prometheus-exporter` subcommand, which polls sites and serves their
+//! latest readings to Prometheus. Scrapes never call the API, so Prometheus
+//! can scrape as often as it likes
+
+use chrono::{Duration, Utc};
+use solar_api::{
+    prometheus::{Metrics, MetricsExporter},
+    scheduler::TokenBucket,
+    Client, SolarApiError,
+};
+use std::sync::{Arc, Mutex, PoisonError};
+
+#[derive(Debug, clap::Args)]
+pub struct ExporterArgs {
+    /// The ids of the sites, `site_id` of the config by default
+    pub site_ids: Vec<u32>,
+    /// The address to serve `/metrics` on
+    #[arg(long, default_value = "0.0.0.0:9917")]
+    pub listen: String,
+    /// The number of overview requests per site per day. Sites with batteries
+    /// use one more request per update for the state of charge
+    #[arg(long, default_value_t = 150, value_parser = clap::value_parser!(u32).range(1..))]
+    pub daily_budget: u32,
+}
+
+/// Poll every site in a thread and serve the latest readings until the
+/// listener fails. Exits the process when the API refuses the API key
+pub fn prometheus_exporter(
+    client: &Client,
+    site_ids: &[u32],
+    args: &ExporterArgs,
+) -> Result<(), SolarApiError> {
+    let exporter = MetricsExporter::bind(&args.listen)?;
+    eprintln!(
+        "Serving the metrics of {} sites on http://{}/metrics",
+        site_ids.len(),
+        exporter.local_addr()?
+    );
+    let metrics = Arc::new(Mutex::new(Metrics::new()));
+    for &site_id in site_ids {
+        let client = client.clone();
+        let metrics = Arc::clone(&metrics);
+        let daily_budget = args.daily_budget;
+        std::thread::spawn(move || {
+            if let Err(e) = poll(&client, site_id, daily_budget, &metrics) {
+                eprintln!("Error: site {site_id}: {e}");
+                std::process::exit(1);
+            }
+        });
+    }
+    exporter.serve(|| {
+        Ok(metrics
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone())
+    })
+}
+
+// adds every update of the site to `metrics`, with the power and state of
+// charge of its batteries, until the API key is refused
+fn poll(
+    client: &Client,
+    site_id: u32,
+    daily_budget: u32,
+    metrics: &Mutex<Metrics>,
+) -> Result<(), SolarApiError> {
+    let refill = Duration::days(1) / daily_budget as i32;
+    let poller = client
+        .poller(site_id)
+        .with_rate_limit(TokenBucket::new(2, refill, Utc::now()));
+    // the batteries are only requested while the site has them
+    let mut has_batteries = true;
+    for overview in poller {
+        let overview = match overview {
+            Ok(overview) => overview,
+            Err(e @ SolarApiError::ForbiddenError(_)) => return Err(e),
+            Err(e) => {
+                eprintln!("Error: site {site_id}: {e}");
+                continue;
+            }
+        };
+        let storage = if let (true, Some(end)) = (has_batteries, overview.last_updated_time) {
+            match client.storage_data(site_id, end - Duration::minutes(15), end, &[]) {
+                Ok(storage) => Some(storage),
+                Err(e) => {
+                    eprintln!("Error: site {site_id}: {e}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        has_batteries = storage
+            .as_ref()
+            .map_or(has_batteries, |storage| !storage.batteries.is_empty());
+
+        let mut metrics = metrics.lock().unwrap_or_else(PoisonError::into_inner);
+        metrics.add_overview(site_id, &overview);
+        if let Some(storage) = &storage {
+            metrics.add_storage(site_id, storage);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,160 @@
+//! The `fleet` subcommand, which compares the sites of an account with the
+//! bulk APIs, see `solar_api::portfolio`
+
+use crate::{output::Printer, parse_date};
+use chrono::{Datelike, NaiveDate};
+use solar_api::{
+    units::{Energy, Power},
+    Client, DataPeriod, Site, SolarApiError, TimeUnit,
+};
+
+#[derive(Debug, clap::Subcommand)]
+pub enum FleetAction {
+    /// Rank the sites by their specific yield in a period, worst first
+    Summary(SummaryArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SummaryArgs {
+    /// The ids of the sites, i.e. `1234,5678`, or globs like `12*`. All sites
+    /// of the account for `all` or by default
+    #[arg(value_delimiter = ',')]
+    pub sites: Vec<String>,
+    /// The first day, the first day of this month by default
+    #[arg(long, value_parser = parse_date)]
+    pub start: Option<NaiveDate>,
+    /// The last day, today by default
+    #[arg(long, value_parser = parse_date)]
+    pub end: Option<NaiveDate>,
+}
+
+/// A site in the summary of a fleet
+#[derive(Debug)]
+pub struct FleetRow<'a> {
+    pub site: &'a Site,
+    /// the position in the ranking, 1 for the worst site. None for sites
+    /// without peak power or values
+    pub rank: Option<usize>,
+    /// the energy in the period
+    pub energy: Option<Energy>,
+    /// the specific yield in kWh/kWp at the timestamps all ranked sites have
+    /// values for
+    pub specific_yield: Option<f64>,
+    pub ratio_to_median: Option<f64>,
+    pub current_power: Option<Power>,
+}
+
+pub fn fleet(
+    client: &Client,
+    printer: &Printer,
+    action: &FleetAction,
+    today: NaiveDate,
+) -> Result<(), SolarApiError> {
+    match action {
+        FleetAction::Summary(args) => summary(client, printer, args, today),
+    }
+}
+
+fn summary(
+    client: &Client,
+    printer: &Printer,
+    args: &SummaryArgs,
+    today: NaiveDate,
+) -> Result<(), SolarApiError> {
+    let sites: Vec<Site> = client
+        .list()?
+        .into_iter()
+        .filter(|site| {
+            let id = site.id.to_string();
+            args.sites.is_empty()
+                || args
+                    .sites
+                    .iter()
+                    .any(|glob| glob == "all" || matches_glob(glob, &id))
+        })
+        .collect();
+    if sites.is_empty() {
+        eprintln!("No site of the account matches {}", args.sites.join(","));
+        return Ok(());
+    }
+    let site_ids: Vec<u32> = sites.iter().map(|site| site.id).collect();
+    let period = DataPeriod::between(
+        args.start
+            .unwrap_or_else(|| today.with_day(1).unwrap_or(today)),
+        args.end.unwrap_or(today),
+    )?;
+
+    let portfolio = client.portfolio(&site_ids);
+    let energy = portfolio.energy(period, TimeUnit::Day)?;
+    let overviews = portfolio.overviews()?;
+    let totals = energy.totals();
+    let ranking = energy.ranking(&sites);
+
+    // the ranked sites in the order of the ranking, followed by the others
+    let is_ranked = |site: &&Site| ranking.iter().any(|ranked| ranked.site_id == site.id);
+    let mut ordered: Vec<&Site> = ranking
+        .iter()
+        .filter_map(|ranked| sites.iter().find(|site| site.id == ranked.site_id))
+        .collect();
+    ordered.extend(sites.iter().filter(|site| !is_ranked(site)));
+    let rows: Vec<FleetRow> = ordered
+        .into_iter()
+        .map(|site| {
+            let ranked = ranking.iter().find(|ranked| ranked.site_id == site.id);
+            FleetRow {
+                site,
+                rank: ranked.map(|ranked| ranked.rank),
+                energy: totals.get(&site.id).copied(),
+                specific_yield: ranked.map(|ranked| ranked.specific_yield),
+                ratio_to_median: ranked.and_then(|ranked| ranked.ratio_to_median),
+                current_power: overviews
+                    .get(&site.id)
+                    .map(|overview| overview.current_power.power),
+            }
+        })
+        .collect();
+    printer.fleet(&rows)
+}
+
+// true when `text` matches `glob`, in which `*` matches any characters and `?`
+// one character
+fn matches_glob(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.trim().chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // the position in text and glob after the last `*`, to backtrack to
+    let mut star: Option<(usize, usize)> = None;
+    let (mut g, mut t) = (0, 0);
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g + 1, t));
+                g += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_g, star_t)) => {
+                    g = star_g;
+                    t = star_t + 1;
+                    star = Some((star_g, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+#[test]
+fn test_matches_glob() {
+    assert!(matches_glob("1234", "1234"));
+    assert!(!matches_glob("1234", "12345"));
+    assert!(matches_glob("12*", "12345"));
+    assert!(matches_glob("*", "1"));
+    assert!(matches_glob("1?3*", "1234"));
+    assert!(matches_glob("*4*5", "124345"));
+    assert!(!matches_glob("*4*5", "12434"));
+    assert!(!matches_glob("5*", "1234"));
+}
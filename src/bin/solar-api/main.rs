@@ -0,0 +1,518 @@
+//! Command line interface to the SolarEdge Monitoring API. Requires the `cli`
+//! feature: `cargo install solar-api --features cli`
+
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use clap::{Parser, Subcommand};
+use output::{Output, Printer};
+use solar_api::{
+    config::Config, scheduler::TokenBucket, Client, DataPeriod, Overview, SolarApiError, TimeUnit,
+};
+use std::{path::PathBuf, process::ExitCode};
+
+mod backfill;
+mod doctor;
+mod export;
+#[cfg(feature = "prometheus")]
+mod exporter;
+mod fleet;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod output;
+
+/// Query the SolarEdge Monitoring API
+#[derive(Debug, Parser)]
+#[command(name = "solar-api", version, about)]
+struct Cli {
+    /// The API key of the account or site. Prefer `SOLAREDGE_API_KEY`, the
+    /// config file or the keyring, which keep the key out of the shell history
+    #[arg(long, global = true)]
+    api_key: Option<String>,
+    /// The config file, `solar-api/config.toml` in the config directory of the
+    /// user by default
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// The format of the output
+    #[arg(long, short, global = true, value_enum, default_value_t)]
+    output: Output,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List the sites of the account
+    List,
+    /// Show the details of a site
+    Details {
+        #[command(flatten)]
+        site: SiteArg,
+    },
+    /// Show the current power and the energy of a site
+    Overview {
+        #[command(flatten)]
+        site: SiteArg,
+    },
+    /// Show the energy of a site per time unit
+    Energy {
+        #[command(flatten)]
+        site: SiteArg,
+        /// The first day, i.e. 2023-06-01
+        #[arg(long, value_parser = parse_date)]
+        start: NaiveDate,
+        /// The last day, today by default
+        #[arg(long, value_parser = parse_date)]
+        end: Option<NaiveDate>,
+        /// QUARTER_OF_AN_HOUR, HOUR, DAY, WEEK, MONTH or YEAR
+        #[arg(long, default_value = "DAY")]
+        time_unit: TimeUnit,
+    },
+    /// Show the power of a site per quarter of an hour
+    Power {
+        #[command(flatten)]
+        site: SiteArg,
+        #[command(flatten)]
+        range: TimeRange,
+    },
+    /// Show the power and state of charge of the batteries of a site
+    Storage {
+        #[command(flatten)]
+        site: SiteArg,
+        #[command(flatten)]
+        range: TimeRange,
+    },
+    /// Print the current power, the energy of today and the state of charge of
+    /// the batteries on every update of a site
+    Watch {
+        #[command(flatten)]
+        site: SiteArg,
+        /// Redraw a table on every update instead of printing a line
+        #[arg(long)]
+        clear: bool,
+        /// The number of overview requests per day. Sites with batteries use
+        /// one more request per update for the state of charge
+        #[arg(long, default_value_t = 150, value_parser = clap::value_parser!(u32).range(1..))]
+        daily_budget: u32,
+    },
+    /// Download the energy of a site per time unit to Parquet or CSV files.
+    /// Continues where the previous run stopped
+    Backfill(backfill::BackfillArgs),
+    /// Fetch a period of a site and push it to InfluxDB, a Prometheus
+    /// Pushgateway, SQLite or CSV
+    Export(export::ExportArgs),
+    /// Check the config, the API key and the site, to find out why requests
+    /// fail
+    Doctor {
+        #[command(flatten)]
+        site: SiteArg,
+    },
+    /// Compare the sites of the account with the bulk APIs
+    Fleet {
+        #[command(subcommand)]
+        action: fleet::FleetAction,
+    },
+    /// Poll sites and publish their readings and the Home Assistant discovery
+    /// config to an MQTT broker
+    #[cfg(feature = "mqtt")]
+    Mqtt(mqtt::MqttArgs),
+    /// Poll sites and serve their latest readings on `/metrics` for Prometheus
+    #[cfg(feature = "prometheus")]
+    PrometheusExporter(exporter::ExporterArgs),
+    /// Store the API key in the keyring of the OS or remove it
+    #[cfg(feature = "keyring")]
+    Keyring {
+        #[command(subcommand)]
+        action: KeyringAction,
+    },
+}
+
+#[cfg(feature = "keyring")]
+#[derive(Debug, Subcommand)]
+enum KeyringAction {
+    /// Read the API key from stdin and store it
+    Set,
+    /// Remove the API key
+    Delete,
+}
+
+#[derive(Debug, clap::Args)]
+struct SiteArg {
+    /// The id of the site, `site_id` of the config by default
+    site_id: Option<u32>,
+}
+
+#[derive(Debug, clap::Args)]
+struct TimeRange {
+    /// The start, i.e. `2023-06-01 12:00:00` or `2023-06-01`, the start of today
+    /// by default
+    #[arg(long, value_parser = parse_date_time)]
+    start: Option<NaiveDateTime>,
+    /// The end, now by default
+    #[arg(long, value_parser = parse_date_time)]
+    end: Option<NaiveDateTime>,
+}
+
+impl TimeRange {
+    // the start and end, with the defaults for missing values
+    fn resolve(&self, now: NaiveDateTime) -> (NaiveDateTime, NaiveDateTime) {
+        let start = self
+            .start
+            .unwrap_or_else(|| now.date().and_time(NaiveTime::MIN));
+        (start, self.end.unwrap_or(now))
+    }
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+    let cli = Cli::parse();
+    match run(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<(), SolarApiError> {
+    // the keyring does not need an API key and the doctor reports a missing
+    // one
+    #[cfg(feature = "keyring")]
+    if let Command::Keyring { action } = &cli.command {
+        return keyring(action);
+    }
+    if let Command::Doctor { site } = &cli.command {
+        return doctor::doctor(cli, site);
+    }
+    let mut config = match &cli.config {
+        Some(path) => Config::load_from(path)?,
+        None => Config::load()?,
+    };
+    if let Some(api_key) = &cli.api_key {
+        config.api_key = Some(api_key.clone());
+    }
+    let site_id_of = |arg: &SiteArg| arg.site_id.map_or_else(|| config.site_id(), Ok);
+    let client = Client::from_config(&config)?;
+    let now = Local::now().naive_local();
+    let mut printer = Printer::new(cli.output);
+    match &cli.command {
+        Command::List => printer.sites(&client.list()?),
+        Command::Details { site } => printer.sites(&[client.details(site_id_of(site)?)?]),
+        Command::Overview { site } => {
+            let site_id = site_id_of(site)?;
+            printer.overview(site_id, &client.overview(site_id)?)
+        }
+        Command::Energy {
+            site,
+            start,
+            end,
+            time_unit,
+        } => {
+            let site_id = site_id_of(site)?;
+            let period = DataPeriod::between(*start, end.unwrap_or(now.date()))?;
+            let energy = client.energy_chunked(site_id, period, *time_unit)?;
+            printer.energy(site_id, &energy.to_series()?)
+        }
+        Command::Power { site, range } => {
+            let site_id = site_id_of(site)?;
+            let (start, end) = range.resolve(now);
+            printer.power(site_id, &client.power(site_id, start, end)?.to_series()?)
+        }
+        Command::Storage { site, range } => {
+            let site_id = site_id_of(site)?;
+            let (start, end) = range.resolve(now);
+            printer.storage(site_id, &client.storage_data(site_id, start, end, &[])?)
+        }
+        Command::Watch {
+            site,
+            clear,
+            daily_budget,
+        } => watch(
+            &client,
+            &mut printer,
+            site_id_of(site)?,
+            *clear,
+            *daily_budget,
+        ),
+        Command::Backfill(args) => backfill::backfill(&client, site_id_of(&args.site)?, args),
+        Command::Export(args) => export::export(&client, site_id_of(&args.site)?, args),
+        Command::Fleet { action } => fleet::fleet(&client, &printer, action, now.date()),
+        #[cfg(feature = "mqtt")]
+        Command::Mqtt(args) => {
+            let site_ids = match args.site.as_slice() {
+                [] => vec![config.site_id()?],
+                site_ids => site_ids.to_vec(),
+            };
+            mqtt::mqtt(&client, &site_ids, args)
+        }
+        #[cfg(feature = "prometheus")]
+        Command::PrometheusExporter(args) => {
+            let site_ids = match args.site_ids.as_slice() {
+                [] => vec![config.site_id()?],
+                site_ids => site_ids.to_vec(),
+            };
+            exporter::prometheus_exporter(&client, &site_ids, args)
+        }
+        Command::Doctor { .. } => unreachable!("handled without config"),
+        #[cfg(feature = "keyring")]
+        Command::Keyring { .. } => unreachable!("handled without config"),
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn keyring(action: &KeyringAction) -> Result<(), SolarApiError> {
+    match action {
+        KeyringAction::Set => {
+            eprint!("API key: ");
+            let mut api_key = String::new();
+            std::io::stdin()
+                .read_line(&mut api_key)
+                .map_err(|source| SolarApiError::ConfigIo {
+                    path: "<stdin>".to_string(),
+                    source,
+                })?;
+            let api_key = api_key.trim();
+            if api_key.is_empty() {
+                return Err(SolarApiError::MissingConfig("API key on stdin".to_string()));
+            }
+            solar_api::config::store_api_key(api_key)
+        }
+        KeyringAction::Delete => solar_api::config::delete_api_key(),
+    }
+}
+
+fn watch(
+    client: &Client,
+    printer: &mut Printer,
+    site_id: u32,
+    clear: bool,
+    daily_budget: u32,
+) -> Result<(), SolarApiError> {
+    let refill = Duration::days(1) / daily_budget as i32;
+    let poller = client
+        .poller(site_id)
+        .with_rate_limit(TokenBucket::new(2, refill, Utc::now()));
+    // the batteries are only requested while the site has them, at most once
+    // per update of the poller
+    let mut has_batteries = true;
+    for overview in poller {
+        let overview = match overview {
+            Ok(overview) => overview,
+            Err(e @ SolarApiError::ForbiddenError(_)) => return Err(e),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                continue;
+            }
+        };
+        let batteries = if has_batteries {
+            state_of_charge(client, site_id, &overview).unwrap_or_else(|e| {
+                eprintln!("Error: {e}");
+                Vec::new()
+            })
+        } else {
+            Vec::new()
+        };
+        printer.update(site_id, &overview, &batteries, clear)?;
+        has_batteries = has_batteries && !batteries.is_empty();
+    }
+    Ok(())
+}
+
+// the serial number and the last state of charge of every battery of the site
+// in the 15 minutes before the update of `overview`
+fn state_of_charge(
+    client: &Client,
+    site_id: u32,
+    overview: &Overview,
+) -> Result<Vec<(String, Option<f64>)>, SolarApiError> {
+    let Some(end) = overview.last_updated_time else {
+        return Ok(Vec::new());
+    };
+    let storage = client.storage_data(site_id, end - Duration::minutes(15), end, &[])?;
+    Ok(storage
+        .batteries
+        .iter()
+        .map(|battery| {
+            let soc = battery
+                .sorted_telemetries()
+                .iter()
+                .rev()
+                .find_map(|telemetry| telemetry.battery_percentage_state);
+            (battery.serial_number.clone(), soc)
+        })
+        .collect())
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|e| format!("{e}, expected 2023-06-01"))
+}
+
+fn parse_date_time(s: &str) -> Result<NaiveDateTime, String> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| parse_date(s).map(|date| date.and_time(NaiveTime::MIN)))
+        .map_err(|_| format!("invalid date and time {s}, expected 2023-06-01 12:00:00"))
+}
+
+#[test]
+fn test_cli() {
+    use clap::CommandFactory;
+    Cli::command().debug_assert();
+
+    let cli = Cli::try_parse_from([
+        "solar-api",
+        "--api-key",
+        "KEY",
+        "energy",
+        "1234",
+        "--start",
+        "2023-06-01",
+        "--time-unit",
+        "month",
+        "--output",
+        "csv",
+    ])
+    .unwrap();
+    assert!(matches!(
+        cli.command,
+        Command::Energy {
+            site: SiteArg {
+                site_id: Some(1234)
+            },
+            end: None,
+            time_unit: TimeUnit::Month,
+            ..
+        }
+    ));
+    assert_eq!(Output::Csv, cli.output);
+
+    let cli = Cli::try_parse_from([
+        "solar-api",
+        "--api-key",
+        "KEY",
+        "power",
+        "1234",
+        "--start",
+        "2023-06-01",
+    ])
+    .unwrap();
+    let Command::Power { range, .. } = cli.command else {
+        panic!("expected power command");
+    };
+    let now = parse_date_time("2023-06-02 12:00:00").unwrap();
+    assert_eq!(
+        (parse_date_time("2023-06-01 00:00:00").unwrap(), now),
+        range.resolve(now)
+    );
+    assert!(Cli::try_parse_from(["solar-api", "--api-key", "KEY", "details", "abc"]).is_err());
+    // the site and the API key come from the config
+    assert!(matches!(
+        Cli::try_parse_from(["solar-api", "overview"]).unwrap(),
+        Cli {
+            api_key: None,
+            command: Command::Overview {
+                site: SiteArg { site_id: None }
+            },
+            ..
+        }
+    ));
+    assert!(matches!(
+        Cli::try_parse_from(["solar-api", "--api-key", "KEY", "watch", "1234", "--clear"])
+            .unwrap()
+            .command,
+        Command::Watch {
+            site: SiteArg {
+                site_id: Some(1234)
+            },
+            clear: true,
+            daily_budget: 150
+        }
+    ));
+    let cli = Cli::try_parse_from([
+        "solar-api",
+        "backfill",
+        "--from",
+        "2021-01-01",
+        "--resolution",
+        "quarter,day",
+        "--out",
+        "data",
+    ])
+    .unwrap();
+    let Command::Backfill(args) = cli.command else {
+        panic!("expected backfill command");
+    };
+    assert_eq!(
+        vec![TimeUnit::QuarterOfAnHour, TimeUnit::Day],
+        args.resolution
+    );
+    assert_eq!(backfill::Format::Csv, args.format);
+    let cli = Cli::try_parse_from([
+        "solar-api",
+        "export",
+        "1234",
+        "--to",
+        "influx",
+        "--target",
+        "http://localhost:8086/api/v2/write?org=home&bucket=solar",
+    ])
+    .unwrap();
+    let Command::Export(args) = cli.command else {
+        panic!("expected export command");
+    };
+    assert_eq!(
+        (
+            export::Sink::Influx,
+            vec![export::Data::Energy, export::Data::Power]
+        ),
+        (args.to, args.data)
+    );
+    assert!(Cli::try_parse_from([
+        "solar-api",
+        "--api-key",
+        "KEY",
+        "watch",
+        "1234",
+        "--daily-budget",
+        "0"
+    ])
+    .is_err());
+    let cli = Cli::try_parse_from(["solar-api", "fleet", "summary", "1234,56*", "7890"]).unwrap();
+    let Command::Fleet {
+        action: fleet::FleetAction::Summary(args),
+    } = cli.command
+    else {
+        panic!("expected fleet summary command");
+    };
+    assert_eq!(vec!["1234", "56*", "7890"], args.sites);
+    #[cfg(feature = "mqtt")]
+    {
+        let cli = Cli::try_parse_from([
+            "solar-api",
+            "mqtt",
+            "--broker",
+            "mqtt://broker.local:1884",
+            "--site",
+            "1234,5678",
+        ])
+        .unwrap();
+        let Command::Mqtt(args) = cli.command else {
+            panic!("expected mqtt command");
+        };
+        assert_eq!(
+            (("broker.local".to_string(), 1884), vec![1234, 5678]),
+            (args.broker, args.site)
+        );
+        assert!(Cli::try_parse_from(["solar-api", "mqtt", "--broker", "localhost:abc"]).is_err());
+    }
+    #[cfg(feature = "prometheus")]
+    {
+        let cli =
+            Cli::try_parse_from(["solar-api", "prometheus-exporter", "1234", "5678"]).unwrap();
+        let Command::PrometheusExporter(args) = cli.command else {
+            panic!("expected prometheus-exporter command");
+        };
+        assert_eq!(
+            (vec![1234, 5678], "0.0.0.0:9917"),
+            (args.site_ids, args.listen.as_str())
+        );
+    }
+}
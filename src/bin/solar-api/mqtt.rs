@@ -0,0 +1,203 @@
+//! The `mqtt` subcommand, which polls sites and publishes their readings and
+//! the Home Assistant discovery config to an MQTT broker as a long-lived
+//! service
+
+use chrono::{Duration, Utc};
+use solar_api::{
+    mqtt::MqttPublisher,
+    rumqttc::{self, Event, MqttOptions, Packet},
+    scheduler::TokenBucket,
+    Client, Site, SolarApiError,
+};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+// the number of messages that can be queued before publishing blocks
+const QUEUE_CAPACITY: usize = 64;
+// the time to wait before reconnecting to the broker or retrying the details
+// of a site
+const RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+const MAX_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(3600);
+
+#[derive(Debug, clap::Args)]
+pub struct MqttArgs {
+    /// The broker, i.e. `localhost` or `localhost:1883`
+    #[arg(long, value_parser = parse_broker)]
+    pub broker: (String, u16),
+    /// The ids of the sites, i.e. `1234,5678`, `site_id` of the config by
+    /// default
+    #[arg(long, value_delimiter = ',')]
+    pub site: Vec<u32>,
+    /// The user name at the broker
+    #[arg(long)]
+    pub username: Option<String>,
+    /// The password at the broker
+    #[arg(long, env = "MQTT_PASSWORD", hide_env_values = true)]
+    pub password: Option<String>,
+    /// The client id at the broker
+    #[arg(long, default_value = "solar-api")]
+    pub client_id: String,
+    /// The prefix of the topics of the readings
+    #[arg(long, default_value = "solaredge")]
+    pub topic_prefix: String,
+    /// The prefix of the discovery topics of Home Assistant
+    #[arg(long, default_value = "homeassistant")]
+    pub discovery_prefix: String,
+    /// Do not publish the discovery config of Home Assistant
+    #[arg(long)]
+    pub no_discovery: bool,
+    /// The number of overview requests per site per day. Sites with batteries
+    /// use one more request per update for the state of charge
+    #[arg(long, default_value_t = 150, value_parser = clap::value_parser!(u32).range(1..))]
+    pub daily_budget: u32,
+}
+
+/// Poll every site in a thread and publish its updates, while this thread
+/// keeps the connection to the broker. The discovery config is published
+/// again after every reconnect, as a broker without persistence forgets the
+/// retained messages. Exits the process when the API refuses the API key
+pub fn mqtt(client: &Client, site_ids: &[u32], args: &MqttArgs) -> Result<(), SolarApiError> {
+    let (host, port) = &args.broker;
+    let mut options = MqttOptions::new(&args.client_id, host, *port);
+    options.set_keep_alive(std::time::Duration::from_secs(30));
+    if let Some(username) = &args.username {
+        options.set_credentials(username, args.password.as_deref().unwrap_or_default());
+    }
+    let (mqtt_client, mut connection) = rumqttc::Client::new(options, QUEUE_CAPACITY);
+    let publisher = MqttPublisher::from_client(mqtt_client)
+        .with_topic_prefix(&args.topic_prefix)
+        .with_discovery_prefix(&args.discovery_prefix);
+
+    // the number of connections to the broker, to republish the discovery
+    // config after a reconnect
+    let connections = Arc::new(AtomicU64::new(0));
+    for &site_id in site_ids {
+        let client = client.clone();
+        let publisher = publisher.clone();
+        let connections = Arc::clone(&connections);
+        let discovery = !args.no_discovery;
+        let daily_budget = args.daily_budget;
+        std::thread::spawn(move || {
+            let connections = discovery.then_some(&*connections);
+            if let Err(e) = publish_site(&client, &publisher, site_id, daily_budget, connections) {
+                eprintln!("Error: site {site_id}: {e}");
+                std::process::exit(1);
+            }
+        });
+    }
+
+    for event in connection.iter() {
+        match event {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                eprintln!("Connected to {host}:{port}");
+                connections.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Error: connection to {host}:{port}: {e}");
+                std::thread::sleep(RETRY_DELAY);
+            }
+        }
+    }
+    Ok(())
+}
+
+// publishes every update of the site, with the power and state of charge of
+// its batteries, until the API key is refused or the connection thread is
+// gone. With `connections`, the discovery config is published once per
+// connection
+fn publish_site(
+    client: &Client,
+    publisher: &MqttPublisher,
+    site_id: u32,
+    daily_budget: u32,
+    connections: Option<&AtomicU64>,
+) -> Result<(), SolarApiError> {
+    let site = details(client, site_id)?;
+    let refill = Duration::days(1) / daily_budget as i32;
+    let poller = client
+        .poller(site_id)
+        .with_rate_limit(TokenBucket::new(2, refill, Utc::now()));
+    // the connection the discovery config of the site and of its batteries
+    // was published for
+    let (mut site_discovered, mut batteries_discovered) = (None, None);
+    // the batteries are only requested while the site has them
+    let mut has_batteries = true;
+    for overview in poller {
+        let overview = match overview {
+            Ok(overview) => overview,
+            Err(e @ SolarApiError::ForbiddenError(_)) => return Err(e),
+            Err(e) => {
+                eprintln!("Error: site {site_id}: {e}");
+                continue;
+            }
+        };
+        let storage = if let (true, Some(end)) = (has_batteries, overview.last_updated_time) {
+            match client.storage_data(site_id, end - Duration::minutes(15), end, &[]) {
+                Ok(storage) => Some(storage),
+                Err(e) => {
+                    eprintln!("Error: site {site_id}: {e}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        has_batteries = storage
+            .as_ref()
+            .map_or(has_batteries, |storage| !storage.batteries.is_empty());
+
+        // publishing only fails when the connection thread is gone
+        if let Some(connection) = connections.map(|c| c.load(Ordering::SeqCst)) {
+            if site_discovered != Some(connection) {
+                publisher.publish_discovery(&site)?;
+                site_discovered = Some(connection);
+            }
+            match &storage {
+                Some(storage)
+                    if !storage.batteries.is_empty()
+                        && batteries_discovered != Some(connection) =>
+                {
+                    publisher.publish_battery_discovery(&site, storage)?;
+                    batteries_discovered = Some(connection);
+                }
+                _ => {}
+            }
+        }
+        publisher.publish_overview(site_id, &overview)?;
+        if let Some(storage) = &storage {
+            publisher.publish_storage(site_id, storage)?;
+        }
+    }
+    Ok(())
+}
+
+// the details of the site, retried with a doubling delay of at most an hour
+// until the API answers or refuses the API key
+fn details(client: &Client, site_id: u32) -> Result<Site, SolarApiError> {
+    let mut delay = RETRY_DELAY;
+    loop {
+        match client.details(site_id) {
+            Ok(site) => return Ok(site),
+            Err(e @ SolarApiError::ForbiddenError(_)) => return Err(e),
+            Err(e) => {
+                eprintln!("Error: site {site_id}: {e}, retrying in {delay:?}");
+                std::thread::sleep(delay);
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+        }
+    }
+}
+
+fn parse_broker(s: &str) -> Result<(String, u16), String> {
+    let s = s.strip_prefix("mqtt://").unwrap_or(s);
+    match s.rsplit_once(':') {
+        Some((host, port)) => port
+            .parse()
+            .map(|port| (host.to_string(), port))
+            .map_err(|_| format!("invalid port {port}, expected localhost:1883")),
+        None => Ok((s.to_string(), 1883)),
+    }
+}
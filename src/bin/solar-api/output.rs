@@ -0,0 +1,344 @@
+//! Prints the replies of the API as a table, as newline delimited JSON or as
+//! CSV
+
+use crate::fleet::FleetRow;
+use solar_api::{
+    display::{format_energy, format_power, Table, ToTable},
+    export::{
+        csv::{CsvOptions, CsvTable},
+        ndjson::NdjsonWriter,
+    },
+    units::{as_watt_hours, as_watts, Energy, Power},
+    Overview, Site, SolarApiError, StorageData, TimeSeries,
+};
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+/// The format of the output of a subcommand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Output {
+    /// Aligned tables with readable units
+    #[default]
+    Table,
+    /// A JSON object per line, see `export::ndjson`
+    Json,
+    /// CSV with a header row, energy in Wh and power in W
+    Csv,
+}
+
+/// Prints the output of the subcommands in one format to stdout
+#[derive(Debug)]
+pub struct Printer {
+    output: Output,
+    // true when the header of the updates of watch is printed
+    header_printed: bool,
+}
+
+impl Printer {
+    pub fn new(output: Output) -> Self {
+        Printer {
+            output,
+            header_printed: false,
+        }
+    }
+
+    pub fn sites(&self, sites: &[Site]) -> Result<(), SolarApiError> {
+        match self.output {
+            Output::Table => print(sites.to_table()),
+            Output::Json => {
+                let mut writer = NdjsonWriter::new(io::stdout().lock());
+                sites.iter().try_for_each(|site| writer.write_site(site))
+            }
+            Output::Csv => {
+                let mut csv = csv_row(&[
+                    "id",
+                    "name",
+                    "status",
+                    "type",
+                    "peak_power_w",
+                    "installation_date",
+                    "last_update_time",
+                ]);
+                for site in sites {
+                    let date = |date: Option<chrono::NaiveDate>| {
+                        date.map_or(String::new(), |date| date.to_string())
+                    };
+                    csv.push_str(&csv_row(&[
+                        &site.id.to_string(),
+                        &site.name,
+                        &site.status,
+                        &site.site_type,
+                        &as_watts(site.peak_power).to_string(),
+                        &date(site.installation_date),
+                        &date(site.last_update_time),
+                    ]));
+                }
+                print(csv)
+            }
+        }
+    }
+
+    pub fn overview(&self, site_id: u32, overview: &Overview) -> Result<(), SolarApiError> {
+        match self.output {
+            Output::Table => print(overview.to_table()),
+            Output::Json => print_json(&serde_json::json!({
+                "site_id": site_id,
+                "overview": overview,
+            })),
+            Output::Csv => print(format!(
+                "{}{}",
+                csv_row(&OVERVIEW_COLUMNS),
+                csv_row(&overview_row(site_id, overview))
+            )),
+        }
+    }
+
+    pub fn energy(&self, site_id: u32, energy: &TimeSeries<Energy>) -> Result<(), SolarApiError> {
+        match self.output {
+            Output::Table => print(energy.to_table()),
+            Output::Json => {
+                NdjsonWriter::new(io::stdout().lock()).write_energy(site_id, "energy", energy)
+            }
+            Output::Csv => print(CsvTable::new(CsvOptions::new()).with_energy("energy", energy)),
+        }
+    }
+
+    pub fn power(&self, site_id: u32, power: &TimeSeries<Power>) -> Result<(), SolarApiError> {
+        match self.output {
+            Output::Table => print(power.to_table()),
+            Output::Json => {
+                NdjsonWriter::new(io::stdout().lock()).write_power(site_id, "power", power)
+            }
+            Output::Csv => print(CsvTable::new(CsvOptions::new()).with_power("power", power)),
+        }
+    }
+
+    pub fn storage(&self, site_id: u32, storage: &StorageData) -> Result<(), SolarApiError> {
+        match self.output {
+            Output::Table => {
+                let mut table = Table::new(&["Timestamp", "Battery", "Power", "State of charge"]);
+                for battery in &storage.batteries {
+                    for telemetry in battery.sorted_telemetries() {
+                        table.add_row(vec![
+                            telemetry.timestamp.format("%Y-%m-%d %H:%M").to_string(),
+                            battery.serial_number.clone(),
+                            telemetry.power.map_or("-".to_string(), format_power),
+                            telemetry
+                                .battery_percentage_state
+                                .map_or("-".to_string(), |soc| format!("{soc:.0} %")),
+                        ]);
+                    }
+                }
+                print(table)
+            }
+            Output::Json => NdjsonWriter::new(io::stdout().lock()).write_storage(site_id, storage),
+            Output::Csv => print(CsvTable::new(CsvOptions::new()).with_storage(storage)),
+        }
+    }
+
+    pub fn fleet(&self, rows: &[FleetRow]) -> Result<(), SolarApiError> {
+        let number = |value: Option<f64>, decimals: usize| {
+            value.map_or("-".to_string(), |value| format!("{value:.decimals$}"))
+        };
+        match self.output {
+            Output::Table => {
+                let mut table = Table::new(&[
+                    "Rank",
+                    "Site",
+                    "Name",
+                    "Peak power",
+                    "Energy",
+                    "kWh/kWp",
+                    "Median",
+                    "Power",
+                ]);
+                for row in rows {
+                    table.add_row(vec![
+                        row.rank.map_or("-".to_string(), |rank| rank.to_string()),
+                        row.site.id.to_string(),
+                        row.site.name.clone(),
+                        format_power(row.site.peak_power),
+                        row.energy.map_or("-".to_string(), format_energy),
+                        number(row.specific_yield, 2),
+                        row.ratio_to_median
+                            .map_or("-".to_string(), |ratio| format!("{:.0} %", ratio * 100.0)),
+                        row.current_power.map_or("-".to_string(), format_power),
+                    ]);
+                }
+                print(table)
+            }
+            Output::Json => rows.iter().try_for_each(|row| {
+                print_json(&serde_json::json!({
+                    "site_id": row.site.id,
+                    "name": row.site.name,
+                    "rank": row.rank,
+                    "peak_power_w": as_watts(row.site.peak_power),
+                    "energy_wh": row.energy.map(as_watt_hours),
+                    "specific_yield": row.specific_yield,
+                    "ratio_to_median": row.ratio_to_median,
+                    "current_power_w": row.current_power.map(as_watts),
+                }))
+            }),
+            Output::Csv => {
+                let mut csv = csv_row(&[
+                    "rank",
+                    "site_id",
+                    "name",
+                    "peak_power_w",
+                    "energy_wh",
+                    "specific_yield",
+                    "ratio_to_median",
+                    "current_power_w",
+                ]);
+                let cell = |value: Option<f64>| value.map_or(String::new(), |v| v.to_string());
+                for row in rows {
+                    csv.push_str(&csv_row(&[
+                        row.rank.map_or(String::new(), |rank| rank.to_string()),
+                        row.site.id.to_string(),
+                        row.site.name.clone(),
+                        as_watts(row.site.peak_power).to_string(),
+                        cell(row.energy.map(as_watt_hours)),
+                        cell(row.specific_yield),
+                        cell(row.ratio_to_median),
+                        cell(row.current_power.map(as_watts)),
+                    ]));
+                }
+                print(csv)
+            }
+        }
+    }
+
+    /// Print an update of `watch` with the state of charge per battery. With
+    /// `clear`, tables redraw the terminal instead of printing a line
+    pub fn update(
+        &mut self,
+        site_id: u32,
+        overview: &Overview,
+        batteries: &[(String, Option<f64>)],
+        clear: bool,
+    ) -> Result<(), SolarApiError> {
+        let soc = |soc: &Option<f64>| soc.map_or("-".to_string(), |soc| format!("{soc:.0} %"));
+        match self.output {
+            Output::Table if clear => {
+                let mut table = overview.to_table();
+                for (serial_number, state_of_charge) in batteries {
+                    table.add_row(vec![
+                        format!("Battery {serial_number}"),
+                        soc(state_of_charge),
+                    ]);
+                }
+                // clear the terminal and move the cursor home
+                print(format!("\x1b[2J\x1b[H{table}"))
+            }
+            Output::Table => {
+                let mut line = format!(
+                    "{}  power {}  today {}",
+                    overview
+                        .last_updated_time
+                        .map_or("never updated".to_string(), |time| time.to_string()),
+                    format_power(overview.current_power.power),
+                    format_energy(overview.last_day_data.energy)
+                );
+                for (serial_number, state_of_charge) in batteries {
+                    line.push_str(&format!("  {serial_number} {}", soc(state_of_charge)));
+                }
+                print(format!("{line}\n"))
+            }
+            Output::Json => print_json(&serde_json::json!({
+                "site_id": site_id,
+                "overview": overview,
+                "state_of_charge": batteries
+                    .iter()
+                    .map(|(serial_number, soc)| (serial_number, soc))
+                    .collect::<BTreeMap<_, _>>(),
+            })),
+            Output::Csv => {
+                // the columns of the batteries are the ones of the first update
+                let mut csv = String::new();
+                if !self.header_printed {
+                    let mut columns: Vec<String> =
+                        OVERVIEW_COLUMNS.iter().map(|c| c.to_string()).collect();
+                    columns.extend(
+                        batteries
+                            .iter()
+                            .map(|(serial_number, _)| format!("{serial_number} state of charge")),
+                    );
+                    csv = csv_row(&columns);
+                    self.header_printed = true;
+                }
+                let mut row = overview_row(site_id, overview);
+                row.extend(
+                    batteries
+                        .iter()
+                        .map(|(_, soc)| soc.map_or(String::new(), |soc| soc.to_string())),
+                );
+                csv.push_str(&csv_row(&row));
+                print(csv)
+            }
+        }
+    }
+}
+
+const OVERVIEW_COLUMNS: [&str; 7] = [
+    "site_id",
+    "last_update_time",
+    "current_power_w",
+    "today_wh",
+    "this_month_wh",
+    "this_year_wh",
+    "lifetime_wh",
+];
+
+// the cells of `overview` for the OVERVIEW_COLUMNS
+fn overview_row(site_id: u32, overview: &Overview) -> Vec<String> {
+    vec![
+        site_id.to_string(),
+        overview
+            .last_updated_time
+            .map_or(String::new(), |time| time.to_string()),
+        as_watts(overview.current_power.power).to_string(),
+        as_watt_hours(overview.last_day_data.energy).to_string(),
+        as_watt_hours(overview.last_month_data.energy).to_string(),
+        as_watt_hours(overview.last_year_data.energy).to_string(),
+        as_watt_hours(overview.life_time_data.energy).to_string(),
+    ]
+}
+
+// a line of CSV, quoting the cells with a delimiter, quote or line break
+fn csv_row(cells: &[impl AsRef<str>]) -> String {
+    let cells: Vec<String> = cells
+        .iter()
+        .map(|cell| {
+            let cell = cell.as_ref();
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_string()
+            }
+        })
+        .collect();
+    format!("{}\n", cells.join(","))
+}
+
+fn print_json(value: &serde_json::Value) -> Result<(), SolarApiError> {
+    print(format!("{value}\n"))
+}
+
+// writes `output` to stdout and flushes, so updates show up immediately
+fn print(output: impl std::fmt::Display) -> Result<(), SolarApiError> {
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "{output}")
+        .and_then(|_| stdout.flush())
+        .map_err(SolarApiError::ExportIo)
+}
+
+#[test]
+fn test_csv_row() {
+    assert_eq!("a,b\n", csv_row(&["a", "b"]));
+    assert_eq!(
+        "1234,\"Home, roof\",\"say \"\"hi\"\"\"\n",
+        csv_row(&["1234", "Home, roof", "say \"hi\""])
+    );
+}
@@ -0,0 +1,212 @@
+//! Stores replies of the API on disk that cannot change anymore, like the
+//! energy of days that passed, so running an analysis again does not request
+//! the same data again. See [`Client::with_cache`](crate::Client::with_cache)
+//!
+//! ```no_run
+//! # use solar_api::{cache::ResponseCache, Client, DataPeriod, TimeUnit};
+//! # use solar_api::chrono::NaiveDate;
+//! let client = Client::new("API_KEY").with_cache(ResponseCache::new("cache"));
+//! let period = DataPeriod::between(
+//!     NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+//!     NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(),
+//! )?;
+//! // the second call reads the reply from the cache directory
+//! let energy = client.energy(1234, period, TimeUnit::Day)?;
+//! let energy = client.energy(1234, period, TimeUnit::Day)?;
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use crate::{
+    site::{parse_date_time, serialize_date_time},
+    SolarApiError,
+};
+use chrono::{Duration, NaiveDate, NaiveDateTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Data returned by [`Client::or_stale`](crate::Client::or_stale), which may
+/// come from the cache when the API failed
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaybeStale<T> {
+    pub data: T,
+    /// the age of the oldest cached reply that was used, or None when all data
+    /// came from the API or from settled replies in the cache
+    pub age: Option<Duration>,
+}
+
+impl<T> MaybeStale<T> {
+    /// Returns true if the API failed and cached data was returned instead
+    pub fn is_stale(&self) -> bool {
+        self.age.is_some()
+    }
+}
+
+/// A reply of the API read from the cache
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CachedReply {
+    // the request, to detect collisions of file names
+    key: String,
+    /// the time in UTC at which the reply was fetched from the API
+    #[serde(
+        deserialize_with = "parse_date_time",
+        serialize_with = "serialize_date_time"
+    )]
+    pub fetched: NaiveDateTime,
+    /// the reply text of the API
+    pub reply: String,
+}
+
+/// A directory with a JSON file per cached reply. Only replies with data that
+/// ended at least `settle_days` before today are cached, because the API may
+/// still add data of recent days, i.e. when a site was offline for a while.
+/// The directory is created on the first write
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    settle_days: u32,
+}
+
+impl ResponseCache {
+    /// Create a cache in `dir` that caches data that ended before yesterday in
+    /// UTC, so data of all time zones is complete
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ResponseCache {
+            dir: dir.into(),
+            settle_days: 1,
+        }
+    }
+
+    /// Set the number of full days after the end of the data before it is
+    /// cached, 1 by default
+    pub fn with_settle_days(mut self, settle_days: u32) -> Self {
+        self.settle_days = settle_days;
+        self
+    }
+
+    /// The directory of the cache
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns true if data that ends on `end` cannot change anymore on
+    /// `today`. `end` is a date in the time zone of the site, which the
+    /// [`Client`](crate::Client) does not know, so it uses the date in UTC as
+    /// `today`. Sites are at most 12 hours behind UTC, so with the default of
+    /// 1 settle day the last day of the data has ended in every time zone
+    pub fn is_settled(&self, end: NaiveDate, today: NaiveDate) -> bool {
+        end + Duration::days(self.settle_days as i64) < today
+    }
+
+    /// The cached reply of the request `key`, or None when it was not cached
+    pub fn get(&self, key: &str) -> Result<Option<CachedReply>, SolarApiError> {
+        let path = self.path(key);
+        let json = match std::fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(cache_io(&path, source)),
+        };
+        let cached: CachedReply =
+            serde_json::from_str(&json).map_err(|source| SolarApiError::InvalidCache {
+                path: path.display().to_string(),
+                source,
+            })?;
+        Ok((cached.key == key).then_some(cached))
+    }
+
+    /// Store `reply` as the reply of the request `key`, fetched now
+    pub fn put(&self, key: &str, reply: &str) -> Result<(), SolarApiError> {
+        std::fs::create_dir_all(&self.dir).map_err(|source| cache_io(&self.dir, source))?;
+        let path = self.path(key);
+        let cached = CachedReply {
+            key: key.to_string(),
+            fetched: Utc::now()
+                .naive_utc()
+                .with_nanosecond(0)
+                .unwrap_or_default(),
+            reply: reply.to_string(),
+        };
+        let json =
+            serde_json::to_string(&cached).map_err(|source| SolarApiError::InvalidCache {
+                path: path.display().to_string(),
+                source,
+            })?;
+        // write to a temporary file first, so a reply is never half written
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, json).map_err(|source| cache_io(&temporary, source))?;
+        std::fs::rename(&temporary, &path).map_err(|source| cache_io(&path, source))
+    }
+
+    /// Remove all cached replies
+    pub fn clear(&self) -> Result<(), SolarApiError> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                Err(cache_io(&self.dir, error))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // the file of the request `key`
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", fnv1a(key)))
+    }
+}
+
+// the key of a request: the path with the parameters sorted by name, without
+// the api key, so the key does not depend on the order of the parameters
+pub(crate) fn request_key(path: &str, params: &HashMap<String, String>) -> String {
+    let mut params: Vec<String> = params
+        .iter()
+        .filter(|(name, _)| *name != "api_key")
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect();
+    params.sort();
+    format!("{path}?{}", params.join("&"))
+}
+
+// the 64 bit FNV-1a hash, which unlike the hasher of std is stable between
+// versions of Rust, so file names stay the same
+pub(crate) fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn cache_io(path: &Path, source: std::io::Error) -> SolarApiError {
+    SolarApiError::CacheIo {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+#[test]
+fn test_response_cache() {
+    let dir = std::env::temp_dir().join(format!("solar-api-cache-{}", std::process::id()));
+    let cache = ResponseCache::new(&dir);
+    let params = HashMap::from([
+        ("api_key".to_string(), "SECRET".to_string()),
+        ("timeUnit".to_string(), "DAY".to_string()),
+        ("startDate".to_string(), "2023-01-01".to_string()),
+    ]);
+    let key = request_key("/site/1/energy", &params);
+    assert_eq!("/site/1/energy?startDate=2023-01-01&timeUnit=DAY", key);
+
+    assert_eq!(None, cache.get(&key).unwrap());
+    cache.put(&key, r#"{"energy":{}}"#).unwrap();
+    let cached = cache.get(&key).unwrap().unwrap();
+    assert_eq!(r#"{"energy":{}}"#, cached.reply);
+    assert!(Utc::now().naive_utc() - cached.fetched < Duration::minutes(1));
+    assert_eq!(None, cache.get("/site/2/energy?").unwrap());
+
+    cache.clear().unwrap();
+    assert_eq!(None, cache.get(&key).unwrap());
+    cache.clear().unwrap();
+
+    let date = |d: u32| NaiveDate::from_ymd_opt(2023, 6, d).unwrap();
+    assert!(cache.is_settled(date(1), date(3)));
+    assert!(!cache.is_settled(date(2), date(3)));
+    assert!(!cache.with_settle_days(3).is_settled(date(1), date(3)));
+}
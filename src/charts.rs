@@ -0,0 +1,349 @@
+//! Renders series as PNG or SVG charts with [plotters](https://docs.rs/plotters),
+//! i.e. for reports and chat bots, see [`Chart`]. Requires the `charts`
+//! feature. The labels of PNG charts are drawn with the fonts of the system.
+//!
+//! ```no_run
+//! # use solar_api::charts::Chart;
+//! # use solar_api::Client;
+//! # use solar_api::chrono::{NaiveDate, NaiveTime};
+//! let client = Client::new("API_KEY");
+//! let day = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap();
+//! let power = client
+//!     .power(1234, day.and_time(NaiveTime::MIN), day.and_hms_opt(23, 59, 59).unwrap())?
+//!     .to_series()?;
+//! Chart::power_curve(&power)
+//!     .with_title("Power on June 1st")
+//!     .save("power.png")?;
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+#[cfg(test)]
+use crate::{
+    series::TimeSeriesValue,
+    units::{watt_hours, watts},
+};
+use crate::{
+    series::{Aggregation, Heatmap, TimeSeries},
+    site::TimeUnit,
+    units::{as_watt_hours, as_watts, Energy, Power},
+    SolarApiError,
+};
+use chrono::{Duration, NaiveDateTime};
+use plotters::{
+    coord::Shift,
+    prelude::{
+        BitMapBackend, ChartBuilder, DrawingArea, DrawingAreaErrorKind, DrawingBackend,
+        IntoDrawingArea, LineSeries, Rectangle, SVGBackend,
+    },
+    style::{Color, RGBColor, BLUE, WHITE},
+};
+use std::path::Path;
+
+// the color of heatmap cells without a value
+const NO_VALUE: RGBColor = RGBColor(230, 230, 230);
+// the colors of the lowest and the highest value of a heatmap
+const LOW: RGBColor = RGBColor(255, 255, 204);
+const HIGH: RGBColor = RGBColor(189, 0, 38);
+
+// the data of a chart
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    // lines of hours since `start` and values, split where values are missing
+    Curve {
+        start: NaiveDateTime,
+        segments: Vec<Vec<(f64, f64)>>,
+        unit: &'static str,
+    },
+    // a bar per label
+    Bars {
+        bars: Vec<(String, f64)>,
+        unit: &'static str,
+    },
+    Heatmap(Heatmap),
+}
+
+/// A chart of a series that is rendered to an SVG string with
+/// [`Chart::to_svg`] or to a PNG or SVG file with [`Chart::save`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chart {
+    kind: Kind,
+    title: Option<String>,
+    size: (u32, u32),
+}
+
+impl Chart {
+    /// A line chart of `power` in W, i.e. of the power of a day. The line is
+    /// interrupted where the series has no values
+    pub fn power_curve(power: &TimeSeries<Power>) -> Self {
+        let start = power.iter().next().map(|v| v.date).unwrap_or_default();
+        let mut segments = vec![vec![]];
+        for v in power.iter() {
+            match v.value {
+                Some(value) => {
+                    let hours = (v.date - start).num_seconds() as f64 / 3600.0;
+                    segments
+                        .last_mut()
+                        .expect("segments are not empty")
+                        .push((hours, as_watts(value)));
+                }
+                None => segments.push(vec![]),
+            }
+        }
+        segments.retain(|segment| !segment.is_empty());
+        Self::new(Kind::Curve {
+            start,
+            segments,
+            unit: "W",
+        })
+    }
+
+    /// A bar chart of the energy per month of `energy` in kWh
+    pub fn monthly_energy(energy: &TimeSeries<Energy>) -> Self {
+        let bars = energy
+            .resample(TimeUnit::Month, Aggregation::Sum)
+            .iter()
+            .map(|v| {
+                let label = v.date.format("%b %Y").to_string();
+                (label, v.value.map_or(0.0, as_watt_hours) / 1000.0)
+            })
+            .collect();
+        Self::new(Kind::Bars { bars, unit: "kWh" })
+    }
+
+    /// A heatmap with a column per day and a row per hour of the day, see
+    /// [`TimeSeries::heatmap`]. The color ranges from light yellow for the
+    /// lowest value to red for the highest value
+    pub fn heatmap(heatmap: Heatmap) -> Self {
+        Self::new(Kind::Heatmap(heatmap))
+    }
+
+    fn new(kind: Kind) -> Self {
+        Chart {
+            kind,
+            title: None,
+            size: (800, 480),
+        }
+    }
+
+    /// Set the title above the chart
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    /// Set the width and height in pixels, 800 by 480 by default
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    /// Render the chart as SVG
+    pub fn to_svg(&self) -> Result<String, SolarApiError> {
+        let mut svg = String::new();
+        self.draw(SVGBackend::with_string(&mut svg, self.size).into_drawing_area())
+            .map_err(|e| SolarApiError::ChartError(e.to_string()))?;
+        Ok(svg)
+    }
+
+    /// Render the chart to `path`, as SVG when the extension is `svg` and as
+    /// PNG otherwise
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SolarApiError> {
+        let path = path.as_ref();
+        let svg = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+        let result = if svg {
+            self.draw(SVGBackend::new(path, self.size).into_drawing_area())
+                .map_err(|e| e.to_string())
+        } else {
+            self.draw(BitMapBackend::new(path, self.size).into_drawing_area())
+                .map_err(|e| e.to_string())
+        };
+        result.map_err(SolarApiError::ChartError)
+    }
+
+    // draws the chart on `root`
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        root.fill(&WHITE)?;
+        let mut builder = ChartBuilder::on(&root);
+        builder
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(60);
+        if let Some(title) = &self.title {
+            builder.caption(title, ("sans-serif", 20));
+        }
+        match &self.kind {
+            Kind::Curve {
+                start,
+                segments,
+                unit,
+            } => {
+                let points = segments.iter().flatten();
+                let x_max = points.clone().map(|(x, _)| *x).fold(1.0, f64::max);
+                let y_max = points.map(|(_, y)| *y).fold(1.0, f64::max);
+                let mut chart = builder.build_cartesian_2d(0.0..x_max, 0.0..y_max * 1.05)?;
+                let time_label = |hours: &f64| {
+                    let time = *start + Duration::seconds((hours * 3600.0) as i64);
+                    time.format("%H:%M").to_string()
+                };
+                chart
+                    .configure_mesh()
+                    .x_label_formatter(&time_label)
+                    .y_desc(*unit)
+                    .draw()?;
+                for segment in segments {
+                    chart.draw_series(LineSeries::new(segment.iter().copied(), &BLUE))?;
+                }
+            }
+            Kind::Bars { bars, unit } => {
+                let y_max = bars.iter().map(|(_, y)| *y).fold(1.0, f64::max);
+                let mut chart =
+                    builder.build_cartesian_2d(-0.5..bars.len() as f64 - 0.5, 0.0..y_max * 1.05)?;
+                let bar_label = |x: &f64| label(bars.iter().map(|(label, _)| label), *x);
+                chart
+                    .configure_mesh()
+                    .disable_x_mesh()
+                    .x_labels(bars.len())
+                    .x_label_formatter(&bar_label)
+                    .y_desc(*unit)
+                    .draw()?;
+                chart.draw_series(bars.iter().enumerate().map(|(i, (_, y))| {
+                    let x = i as f64;
+                    Rectangle::new([(x - 0.4, 0.0), (x + 0.4, *y)], BLUE.filled())
+                }))?;
+            }
+            Kind::Heatmap(heatmap) => {
+                let values = heatmap.values.iter().flatten().flatten().copied();
+                let min = values.clone().fold(f64::INFINITY, f64::min);
+                let max = values.fold(f64::NEG_INFINITY, f64::max);
+                let days = heatmap.days.len().max(1);
+                let mut chart = builder.build_cartesian_2d(0.0..days as f64, 0.0..24.0)?;
+                let day_labels = heatmap.day_labels();
+                let day_label = |x: &f64| label(day_labels.iter(), x.floor());
+                chart
+                    .configure_mesh()
+                    .disable_mesh()
+                    .x_labels(days.min(10))
+                    .x_label_formatter(&day_label)
+                    .y_labels(13)
+                    .y_label_formatter(&|y| format!("{:02}:00", *y as u32))
+                    .draw()?;
+                chart.draw_series(heatmap.values.iter().enumerate().flat_map(|(hour, row)| {
+                    row.iter().enumerate().map(move |(day, value)| {
+                        let (x, y) = (day as f64, hour as f64);
+                        let color = value.map_or(NO_VALUE, |value| gradient(value, min, max));
+                        Rectangle::new([(x, y), (x + 1.0, y + 1.0)], color.filled())
+                    })
+                }))?;
+            }
+        }
+        root.present()
+    }
+}
+
+// the label at index `x`, or nothing when `x` is not an index of `labels`
+fn label<'a>(mut labels: impl Iterator<Item = &'a String>, x: f64) -> String {
+    if x < 0.0 || x.fract() != 0.0 {
+        return String::new();
+    }
+    labels.nth(x as usize).cloned().unwrap_or_default()
+}
+
+// the color of `value` between the colors of `min` and `max`
+fn gradient(value: f64, min: f64, max: f64) -> RGBColor {
+    let t = if max > min {
+        (value - min) / (max - min)
+    } else {
+        1.0
+    };
+    let channel = |low: u8, high: u8| (low as f64 + (high as f64 - low as f64) * t).round() as u8;
+    RGBColor(
+        channel(LOW.0, HIGH.0),
+        channel(LOW.1, HIGH.1),
+        channel(LOW.2, HIGH.2),
+    )
+}
+
+#[test]
+fn test_charts() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let power = TimeSeries::new(
+        TimeUnit::QuarterOfAnHour,
+        "W",
+        vec![
+            TimeSeriesValue {
+                date: date("2023-06-01 12:00:00"),
+                value: Some(watts(1000.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-06-01 12:15:00"),
+                value: Some(watts(1500.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-06-01 12:30:00"),
+                value: None,
+            },
+            TimeSeriesValue {
+                date: date("2023-06-01 12:45:00"),
+                value: Some(watts(500.0)),
+            },
+        ],
+    );
+    let chart = Chart::power_curve(&power);
+    assert_eq!(
+        Kind::Curve {
+            start: date("2023-06-01 12:00:00"),
+            segments: vec![vec![(0.0, 1000.0), (0.25, 1500.0)], vec![(0.75, 500.0)]],
+            unit: "W",
+        },
+        chart.kind
+    );
+    let svg = chart.with_title("Power").to_svg().unwrap();
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains("Power"));
+
+    let energy = TimeSeries::new(
+        TimeUnit::Day,
+        "Wh",
+        vec![
+            TimeSeriesValue {
+                date: date("2023-06-01 00:00:00"),
+                value: Some(watt_hours(1500.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-06-02 00:00:00"),
+                value: Some(watt_hours(500.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-07-01 00:00:00"),
+                value: Some(watt_hours(3000.0)),
+            },
+        ],
+    );
+    let chart = Chart::monthly_energy(&energy);
+    assert_eq!(
+        Kind::Bars {
+            bars: vec![("Jun 2023".to_string(), 2.0), ("Jul 2023".to_string(), 3.0)],
+            unit: "kWh",
+        },
+        chart.kind
+    );
+    assert!(chart.to_svg().unwrap().contains("Jul 2023"));
+
+    let dir = std::env::temp_dir().join(format!("solar-api-charts-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let heatmap = power.heatmap(Aggregation::Mean, as_watts);
+    Chart::heatmap(heatmap)
+        .save(dir.join("heatmap.png"))
+        .unwrap();
+    let png = std::fs::read(dir.join("heatmap.png")).unwrap();
+    assert_eq!(b"\x89PNG", &png[..4]);
+    std::fs::remove_dir_all(dir).unwrap();
+
+    assert_eq!(LOW, gradient(1.0, 1.0, 3.0));
+    assert_eq!(RGBColor(222, 128, 121), gradient(2.0, 1.0, 3.0));
+}
@@ -0,0 +1,805 @@
+#[cfg(feature = "test-util")]
+use crate::fixtures::record::{FixtureRecorder, Sanitizer};
+use crate::{
+    analytics::{self, InverterComparison},
+    cache::{request_key, MaybeStale, ResponseCache},
+    call_url, default_map,
+    equipment::{self, Equipment},
+    meters::{self, MeterType},
+    parse,
+    portfolio::{self, BULK_MAX_SITES},
+    power_flow,
+    scheduler::TokenBucket,
+    series::TimeSeries,
+    site::{self, DataPeriod, TimeUnit},
+    storage, to_url,
+    units::Energy,
+    SolarApiError, BASE_URL,
+};
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use log::{debug, trace, warn};
+use reqwest::StatusCode;
+#[cfg(feature = "test-util")]
+use std::path::PathBuf;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+// the site ids as comma separated list for the path of the bulk APIs
+fn site_ids_param(site_ids: &[u32]) -> String {
+    site_ids
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Determines how replies of the API are parsed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Fields in the reply that this lib does not know are ignored. This is
+    /// the default and the most robust choice for production use
+    #[default]
+    Lenient,
+    /// Fields in the reply that this lib does not know result in a
+    /// [`SolarApiError::UnknownFields`] error. Useful in CI to detect that
+    /// the API added fields that are silently dropped
+    Strict,
+}
+
+/// Client for the SolarEdge Monitoring API. The free functions of this lib,
+/// like [`crate::overview`], use a [`Client`] with default settings.
+///
+/// ```no_run
+/// # use solar_api::{Client, ParseMode};
+/// let client = Client::new("API_KEY").with_parse_mode(ParseMode::Strict);
+/// let overview = client.overview(1234)?;
+/// # Ok::<(), solar_api::SolarApiError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Client {
+    api_key: String,
+    base_url: String,
+    parse_mode: ParseMode,
+    cache: Option<ResponseCache>,
+    // the oldest fetch time of the cached replies used within
+    // [`Client::or_stale`]
+    stale: Option<Arc<Mutex<Option<NaiveDateTime>>>>,
+    // shared by the clones of the client, so they take from the same budget
+    rate_limit: Option<Arc<Mutex<TokenBucket>>>,
+    #[cfg(feature = "test-util")]
+    recorder: Option<FixtureRecorder>,
+}
+
+impl Client {
+    /// Create a client using the given `api_key` and lenient parsing
+    pub fn new(api_key: &str) -> Self {
+        Client {
+            api_key: api_key.to_string(),
+            base_url: BASE_URL.to_string(),
+            parse_mode: ParseMode::default(),
+            cache: None,
+            stale: None,
+            rate_limit: None,
+            #[cfg(feature = "test-util")]
+            recorder: None,
+        }
+    }
+
+    /// Set the [`ParseMode`] used to parse replies of the API
+    pub fn with_parse_mode(mut self, parse_mode: ParseMode) -> Self {
+        self.parse_mode = parse_mode;
+        self
+    }
+
+    /// Call the API at `base_url` instead of [`BASE_URL`], i.e.
+    /// `http://127.0.0.1:8080` for a fake API in tests
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// The [`ParseMode`] used to parse replies of the API
+    pub fn parse_mode(&self) -> ParseMode {
+        self.parse_mode
+    }
+
+    /// Store the replies of energy, power and telemetry requests for periods
+    /// that passed in `cache` and read them from there instead of calling the
+    /// API again
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Take a token of `bucket` before every request of
+    /// [`Client::energy_chunked`] and [`Client::power_chunked`], waiting until the bucket has one. Clones of
+    /// the client share the bucket
+    ///
+    /// ```no_run
+    /// # use solar_api::{scheduler::TokenBucket, Client};
+    /// let client = Client::new("API_KEY").with_rate_limit(TokenBucket::daily(10));
+    /// ```
+    pub fn with_rate_limit(mut self, bucket: TokenBucket) -> Self {
+        self.rate_limit = Some(Arc::new(Mutex::new(bucket)));
+        self
+    }
+
+    // waits until the bucket of `with_rate_limit` has a token and takes it
+    pub(crate) fn take_token(&self) {
+        let Some(bucket) = &self.rate_limit else {
+            return;
+        };
+        let mut bucket = bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            let now = Utc::now();
+            if bucket.try_take(now) {
+                return;
+            }
+            if let Ok(wait) = (bucket.available_at(now) - now).to_std() {
+                debug!("Waiting {wait:?} for the rate limit");
+                std::thread::sleep(wait);
+            }
+        }
+    }
+
+    /// Write every reply to a JSON file in `dir`, with the API key, site ids,
+    /// account ids, addresses and serial numbers replaced by pseudonyms, see
+    /// [`crate::fixtures::record`]
+    #[cfg(feature = "test-util")]
+    pub fn with_recorder(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.recorder = Some(FixtureRecorder::new(dir, Sanitizer::new(&self.api_key)));
+        self
+    }
+
+    /// Run `request` and fall back to replies in the cache when the API cannot
+    /// be reached or fails with a server error, i.e. during maintenance of the
+    /// API. Replies of `request` are stored in the cache, so the next call has
+    /// a reply to fall back to. The result tells whether data of the cache was
+    /// used and how old it is. Without a cache, this is the same as calling
+    /// `request`
+    ///
+    /// ```no_run
+    /// # use solar_api::{cache::ResponseCache, Client};
+    /// let client = Client::new("API_KEY").with_cache(ResponseCache::new("cache"));
+    /// let overview = client.or_stale(|client| client.overview(1234))?;
+    /// if let Some(age) = overview.age {
+    ///     println!("The API is down, showing data of {} minutes ago", age.num_minutes());
+    /// }
+    /// # Ok::<(), solar_api::SolarApiError>(())
+    /// ```
+    pub fn or_stale<T>(
+        &self,
+        request: impl FnOnce(&Client) -> Result<T, SolarApiError>,
+    ) -> Result<MaybeStale<T>, SolarApiError> {
+        let stale = Arc::new(Mutex::new(None));
+        let client = Client {
+            stale: Some(stale.clone()),
+            ..self.clone()
+        };
+        let data = request(&client)?;
+        let fetched: Option<NaiveDateTime> = *stale.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(MaybeStale {
+            data,
+            age: fetched.map(|fetched| Utc::now().naive_utc() - fetched),
+        })
+    }
+
+    // calls the API at `path` with [`Client::fetch`] and records the reply
+    // when recording fixtures
+    fn call(
+        &self,
+        path: &str,
+        params: &HashMap<String, String>,
+        end: Option<NaiveDate>,
+    ) -> Result<String, SolarApiError> {
+        let reply_text = self.fetch(path, params, end)?;
+        #[cfg(feature = "test-util")]
+        if let Some(recorder) = &self.recorder {
+            let file = recorder.record(path, params, &reply_text)?;
+            debug!("Recorded reply as {}", file.display());
+        }
+        Ok(reply_text)
+    }
+
+    // fetches the reply of `path`. When the data of the request ends on `end`
+    // and cannot change anymore, the reply is read from or stored in the
+    // cache. Within [`Client::or_stale`], replies are stored as well and read
+    // when the API fails. Errors of the cache are logged, as the API can still
+    // be used
+    fn fetch(
+        &self,
+        path: &str,
+        params: &HashMap<String, String>,
+        end: Option<NaiveDate>,
+    ) -> Result<String, SolarApiError> {
+        let url = to_url(&self.base_url, path, params);
+        let Some(cache) = &self.cache else {
+            return Ok(call_url(&url)?);
+        };
+        let key = request_key(path, params);
+        let settled = end.is_some_and(|end| cache.is_settled(end, Utc::now().date_naive()));
+        if settled {
+            match cache.get(&key) {
+                Ok(Some(cached)) => {
+                    debug!("Using cached reply of {key}");
+                    return Ok(cached.reply);
+                }
+                Ok(None) => {}
+                Err(error) => warn!("Could not read cached reply of {key}: {error}"),
+            }
+        }
+        match call_url(&url) {
+            Ok(reply_text) => {
+                if settled || self.stale.is_some() {
+                    if let Err(error) = cache.put(&key, &reply_text) {
+                        warn!("Could not cache reply of {key}: {error}");
+                    }
+                }
+                Ok(reply_text)
+            }
+            Err(error) => {
+                let outage = error.status().is_none_or(|status| {
+                    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+                });
+                if let (Some(stale), true) = (&self.stale, outage) {
+                    if let Ok(Some(cached)) = cache.get(&key) {
+                        warn!(
+                            "Using reply of {key} fetched at {} as the API failed: {error}",
+                            cached.fetched
+                        );
+                        let mut fetched = stale.lock().unwrap_or_else(PoisonError::into_inner);
+                        let oldest = fetched.map_or(cached.fetched, |f| f.min(cached.fetched));
+                        *fetched = Some(oldest);
+                        return Ok(cached.reply);
+                    }
+                }
+                Err(error.into())
+            }
+        }
+    }
+
+    /// List all sites of customer. Each [`site::Site`] has an id that can be
+    /// used to retrieve detailled information using for example [`Client::energy`]
+    pub fn list(&self) -> Result<Vec<site::Site>, SolarApiError> {
+        debug!("Calling list of sites");
+        let params = default_map(&self.api_key);
+        let reply_text = self.call("/sites/list", &params, None)?;
+
+        trace!("Parsing");
+        let reply: site::SitesReply = parse(&reply_text, self.parse_mode, &self.api_key)?;
+
+        Ok((*reply.sites()).clone())
+    }
+
+    /// Displays the site details, such as name, location, status, etc.
+    pub fn details(&self, site_id: u32) -> Result<site::Site, SolarApiError> {
+        debug!("Getting details of {site_id}");
+        let params = default_map(&self.api_key);
+        let path = format!("/site/{site_id}/details");
+        let reply_text = self.call(&path, &params, None)?;
+
+        trace!("Parsing json");
+        let site: site::SiteDetails = parse(&reply_text, self.parse_mode, &self.api_key)?;
+
+        Ok(site.details)
+    }
+
+    /// Return the energy production start and end dates of the site
+    pub fn data_period(&self, site_id: u32) -> Result<site::DataPeriod, SolarApiError> {
+        debug!("Getting data_period of {site_id}");
+        let params = default_map(&self.api_key);
+        let path = format!("/site/{site_id}/dataPeriod");
+        let reply_text = self.call(&path, &params, None)?;
+
+        trace!("Parsing json");
+        let period: site::DataPeriodReply = parse(&reply_text, self.parse_mode, &self.api_key)?;
+
+        Ok(period.data_period)
+    }
+
+    /// Display the site overview data.
+    pub fn overview(&self, site_id: u32) -> Result<site::Overview, SolarApiError> {
+        debug!("Getting overview of {}", site_id);
+        let params = default_map(&self.api_key);
+        let path = format!("/site/{}/overview", site_id);
+        let reply_text = self.call(&path, &params, None)?;
+
+        trace!("Parsing json");
+        let overview: site::OverviewReply = parse(&reply_text, self.parse_mode, &self.api_key)?;
+
+        Ok(overview.overview)
+    }
+
+    /// Return the current power flow between the PV, the batteries, the grid
+    /// and the consumption of the site
+    pub fn current_power_flow(&self, site_id: u32) -> Result<power_flow::PowerFlow, SolarApiError> {
+        debug!("Getting current power flow of {site_id}");
+        let params = default_map(&self.api_key);
+        let path = format!("/site/{site_id}/currentPowerFlow");
+        let reply_text = self.call(&path, &params, None)?;
+
+        trace!("Parsing json");
+        let flow: power_flow::PowerFlowReply = parse(&reply_text, self.parse_mode, &self.api_key)?;
+
+        Ok(flow.site_current_power_flow)
+    }
+
+    /// Return the overview of every site in `site_ids` using the bulk API.
+    /// The sites are requested in calls of at most [`BULK_MAX_SITES`] sites
+    pub fn overviews(
+        &self,
+        site_ids: &[u32],
+    ) -> Result<BTreeMap<u32, site::Overview>, SolarApiError> {
+        let mut overviews = BTreeMap::new();
+        for chunk in site_ids.chunks(BULK_MAX_SITES) {
+            debug!("Getting overviews of {} sites", chunk.len());
+            let params = default_map(&self.api_key);
+            let path = format!("/sites/{}/overview", site_ids_param(chunk));
+            let reply_text = self.call(&path, &params, None)?;
+
+            trace!("Parsing json");
+            let reply: portfolio::OverviewsReply =
+                parse(&reply_text, self.parse_mode, &self.api_key)?;
+            overviews.extend(reply.overviews());
+        }
+        Ok(overviews)
+    }
+
+    /// Return the energy of every site in `site_ids` using the bulk API. See
+    /// [`crate::energy`] for the usage limitations of this API. The sites are
+    /// requested in calls of at most [`BULK_MAX_SITES`] sites
+    pub fn sites_energy(
+        &self,
+        site_ids: &[u32],
+        period: DataPeriod,
+        time_unit: TimeUnit,
+    ) -> Result<BTreeMap<u32, TimeSeries<Energy>>, SolarApiError> {
+        period.validate_for(time_unit)?;
+        let (start_date, end_date) = period.bounds()?;
+
+        let mut energy = BTreeMap::new();
+        for chunk in site_ids.chunks(BULK_MAX_SITES) {
+            debug!(
+                "Getting energy of {} sites for {}-{} with unit {}",
+                chunk.len(),
+                start_date,
+                end_date,
+                time_unit.to_param()
+            );
+            let mut params = default_map(&self.api_key);
+            params.insert(
+                "startDate".into(),
+                start_date.format("%Y-%m-%d").to_string(),
+            );
+            params.insert("endDate".into(), end_date.format("%Y-%m-%d").to_string());
+            params.insert("timeUnit".into(), time_unit.to_param().into());
+            let path = format!("/sites/{}/energy", site_ids_param(chunk));
+            let reply_text = self.call(&path, &params, Some(end_date))?;
+
+            trace!("Parsing json");
+            let reply: portfolio::SitesEnergyReply =
+                parse(&reply_text, self.parse_mode, &self.api_key)?;
+            energy.extend(reply.energy()?.1);
+        }
+        Ok(energy)
+    }
+
+    /// Return the site energy measurements. See [`crate::energy`] for the
+    /// usage limitations of this API
+    pub fn energy(
+        &self,
+        site_id: u32,
+        period: DataPeriod,
+        time_unit: TimeUnit,
+    ) -> Result<site::GeneratedEnergy, SolarApiError> {
+        period.validate_for(time_unit)?;
+        let (start_date, end_date) = period.bounds()?;
+        debug!(
+            "Getting energy for {}-{} with unit {}",
+            start_date,
+            end_date,
+            time_unit.to_param()
+        );
+
+        let mut params = default_map(&self.api_key);
+        params.insert(
+            "startDate".into(),
+            start_date.format("%Y-%m-%d").to_string(),
+        );
+        params.insert("endDate".into(), end_date.format("%Y-%m-%d").to_string());
+        params.insert("timeUnit".into(), time_unit.to_param().into());
+        let path = format!("/site/{site_id}/energy");
+        let reply_text = self.call(&path, &params, Some(end_date))?;
+
+        trace!("Parsing json");
+        let energy: site::GeneratedEnergyReply =
+            parse(&reply_text, self.parse_mode, &self.api_key)?;
+
+        Ok(energy.energy)
+    }
+
+    /// Return the site energy measurements for a period that may exceed the
+    /// limits of [`Client::energy`]. The period is split into periods that
+    /// satisfy the limits using [`DataPeriod::split_for`]. These are requested
+    /// one after the other, within the limit of [`Client::with_rate_limit`],
+    /// and merged into one series
+    pub fn energy_chunked(
+        &self,
+        site_id: u32,
+        period: DataPeriod,
+        time_unit: TimeUnit,
+    ) -> Result<site::GeneratedEnergy, SolarApiError> {
+        let mut periods = period.split_for(time_unit).into_iter();
+        let first = periods.next().unwrap_or(period);
+        self.take_token();
+        let mut energy = self.energy(site_id, first, time_unit)?;
+        for period in periods {
+            self.take_token();
+            energy.append(self.energy(site_id, period, time_unit)?)?;
+        }
+        Ok(energy)
+    }
+
+    /// Return the site power measurements in 15 minutes resolution. See
+    /// [`crate::power`] for the usage limitations of this API
+    pub fn power(
+        &self,
+        site_id: u32,
+        start_datetime: NaiveDateTime,
+        end_datetime: NaiveDateTime,
+    ) -> Result<site::GeneratedPowerPerTimeUnit, SolarApiError> {
+        debug!("Getting power for {}-{}", start_datetime, end_datetime,);
+
+        let mut params = default_map(&self.api_key);
+        params.insert(
+            "startTime".into(),
+            format!("{}", start_datetime.format("%Y-%m-%d %H:%M:%S")),
+        );
+        params.insert(
+            "endTime".into(),
+            format!("{}", end_datetime.format("%Y-%m-%d %H:%M:%S")),
+        );
+        let path = format!("/site/{site_id}/power");
+        let reply_text = self.call(&path, &params, Some(end_datetime.date()))?;
+
+        trace!("Parsing json");
+        let power: site::GeneratedPowerReply = parse(&reply_text, self.parse_mode, &self.api_key)?;
+
+        Ok(power.power)
+    }
+
+    /// Return the energy per meter of the site. See [`crate::energy_details`]
+    /// for the usage limitations of this API
+    pub fn energy_details(
+        &self,
+        site_id: u32,
+        start_datetime: NaiveDateTime,
+        end_datetime: NaiveDateTime,
+        time_unit: TimeUnit,
+        meters: &[MeterType],
+    ) -> Result<meters::EnergyDetails, SolarApiError> {
+        debug!(
+            "Getting energy details for {}-{} with unit {}",
+            start_datetime,
+            end_datetime,
+            time_unit.to_param()
+        );
+        DataPeriod::between(start_datetime.date(), end_datetime.date())?.validate_for(time_unit)?;
+
+        let mut params = default_map(&self.api_key);
+        params.insert(
+            "startTime".into(),
+            format!("{}", start_datetime.format("%Y-%m-%d %H:%M:%S")),
+        );
+        params.insert(
+            "endTime".into(),
+            format!("{}", end_datetime.format("%Y-%m-%d %H:%M:%S")),
+        );
+        params.insert("timeUnit".into(), time_unit.to_param().into());
+        if !meters.is_empty() {
+            params.insert("meters".into(), MeterType::to_params(meters));
+        }
+        let path = format!("/site/{site_id}/energyDetails");
+        let reply_text = self.call(&path, &params, Some(end_datetime.date()))?;
+
+        trace!("Parsing json");
+        let details: meters::EnergyDetailsReply =
+            parse(&reply_text, self.parse_mode, &self.api_key)?;
+
+        Ok(details.energy_details)
+    }
+
+    /// Return the power per meter of the site in 15 minutes resolution. See
+    /// [`crate::power_details`] for the usage limitations of this API
+    pub fn power_details(
+        &self,
+        site_id: u32,
+        start_datetime: NaiveDateTime,
+        end_datetime: NaiveDateTime,
+        meters: &[MeterType],
+    ) -> Result<meters::PowerDetails, SolarApiError> {
+        debug!(
+            "Getting power details for {}-{}",
+            start_datetime, end_datetime
+        );
+
+        let mut params = default_map(&self.api_key);
+        params.insert(
+            "startTime".into(),
+            format!("{}", start_datetime.format("%Y-%m-%d %H:%M:%S")),
+        );
+        params.insert(
+            "endTime".into(),
+            format!("{}", end_datetime.format("%Y-%m-%d %H:%M:%S")),
+        );
+        if !meters.is_empty() {
+            params.insert("meters".into(), MeterType::to_params(meters));
+        }
+        let path = format!("/site/{site_id}/powerDetails");
+        let reply_text = self.call(&path, &params, Some(end_datetime.date()))?;
+
+        trace!("Parsing json");
+        let details: meters::PowerDetailsReply =
+            parse(&reply_text, self.parse_mode, &self.api_key)?;
+
+        Ok(details.power_details)
+    }
+
+    /// Return the telemetry of the batteries of the site. See
+    /// [`crate::storage_data`] for the usage limitations of this API
+    pub fn storage_data(
+        &self,
+        site_id: u32,
+        start_datetime: NaiveDateTime,
+        end_datetime: NaiveDateTime,
+        serials: &[&str],
+    ) -> Result<storage::StorageData, SolarApiError> {
+        debug!(
+            "Getting storage data for {}-{}",
+            start_datetime, end_datetime
+        );
+
+        let mut params = default_map(&self.api_key);
+        params.insert(
+            "startTime".into(),
+            format!("{}", start_datetime.format("%Y-%m-%d %H:%M:%S")),
+        );
+        params.insert(
+            "endTime".into(),
+            format!("{}", end_datetime.format("%Y-%m-%d %H:%M:%S")),
+        );
+        if !serials.is_empty() {
+            params.insert("serials".into(), serials.join(","));
+        }
+        let path = format!("/site/{site_id}/storageData");
+        let reply_text = self.call(&path, &params, Some(end_datetime.date()))?;
+
+        trace!("Parsing json");
+        let data: storage::StorageDataReply = parse(&reply_text, self.parse_mode, &self.api_key)?;
+
+        Ok(data.storage_data)
+    }
+
+    /// Return the inverters of the site
+    pub fn equipment_list(&self, site_id: u32) -> Result<Vec<Equipment>, SolarApiError> {
+        debug!("Getting equipment list");
+
+        let params = default_map(&self.api_key);
+        let path = format!("/equipment/{site_id}/list");
+        let reply_text = self.call(&path, &params, None)?;
+
+        trace!("Parsing json");
+        let list: equipment::EquipmentListReply =
+            parse(&reply_text, self.parse_mode, &self.api_key)?;
+
+        Ok(list.equipment())
+    }
+
+    /// Return the technical data of an inverter. See [`crate::inverter_data`]
+    /// for the usage limitations of this API
+    pub fn inverter_data(
+        &self,
+        site_id: u32,
+        serial_number: &str,
+        start_datetime: NaiveDateTime,
+        end_datetime: NaiveDateTime,
+    ) -> Result<equipment::InverterData, SolarApiError> {
+        debug!(
+            "Getting inverter data of {} for {}-{}",
+            serial_number, start_datetime, end_datetime
+        );
+
+        let mut params = default_map(&self.api_key);
+        params.insert(
+            "startTime".into(),
+            format!("{}", start_datetime.format("%Y-%m-%d %H:%M:%S")),
+        );
+        params.insert(
+            "endTime".into(),
+            format!("{}", end_datetime.format("%Y-%m-%d %H:%M:%S")),
+        );
+        let path = format!("/equipment/{site_id}/{serial_number}/data");
+        let reply_text = self.call(&path, &params, Some(end_datetime.date()))?;
+
+        trace!("Parsing json");
+        let data: equipment::InverterDataReply =
+            parse(&reply_text, self.parse_mode, &self.api_key)?;
+
+        Ok(data.data)
+    }
+
+    /// Return the energy of every inverter of the site in the period and flag
+    /// the inverters that deviate more than `threshold` from the mean of the
+    /// site, see [`analytics::compare_inverters`]. Calls the API once for the
+    /// equipment list and once per inverter
+    pub fn inverter_comparison(
+        &self,
+        site_id: u32,
+        start_datetime: NaiveDateTime,
+        end_datetime: NaiveDateTime,
+        threshold: f64,
+    ) -> Result<Vec<InverterComparison>, SolarApiError> {
+        let mut inverters = Vec::new();
+        for inverter in self.equipment_list(site_id)? {
+            let data = self.inverter_data(
+                site_id,
+                &inverter.serial_number,
+                start_datetime,
+                end_datetime,
+            )?;
+            inverters.push((inverter, data));
+        }
+        Ok(analytics::compare_inverters(&inverters, threshold))
+    }
+
+    /// Return the site power measurements in 15 minutes resolution for a
+    /// period that may exceed the one month limit of [`Client::power`]. The
+    /// period is split using [`site::power_windows`]. The windows are requested
+    /// one after the other, within the limit of [`Client::with_rate_limit`], and
+    /// merged into one series without duplicate timestamps
+    pub fn power_chunked(
+        &self,
+        site_id: u32,
+        start_datetime: NaiveDateTime,
+        end_datetime: NaiveDateTime,
+    ) -> Result<site::GeneratedPowerPerTimeUnit, SolarApiError> {
+        let mut windows = site::power_windows(start_datetime, end_datetime).into_iter();
+        let (start, end) = windows.next().unwrap_or((start_datetime, end_datetime));
+        self.take_token();
+        let mut power = self.power(site_id, start, end)?;
+        for (start, end) in windows {
+            self.take_token();
+            power.append(self.power(site_id, start, end)?)?;
+        }
+        Ok(power)
+    }
+}
+
+/// The endpoints of the API that describe the production of a single site, so
+/// applications can use a [`Client`] or a
+/// [`SimulatedClient`](crate::simulation::SimulatedClient) without an API key.
+/// The bulk and details endpoints are only available on [`Client`], as are the
+/// helpers that take a [`Client`], like [`Poller`](crate::poller::Poller) and
+/// [`Portfolio`](crate::portfolio::Portfolio)
+pub trait MonitoringApi {
+    /// The sites of the account, see [`Client::list`]
+    fn list(&self) -> Result<Vec<site::Site>, SolarApiError>;
+
+    /// The details of a site, see [`Client::details`]
+    fn details(&self, site_id: u32) -> Result<site::Site, SolarApiError>;
+
+    /// The first and last day with data of a site, see [`Client::data_period`]
+    fn data_period(&self, site_id: u32) -> Result<site::DataPeriod, SolarApiError>;
+
+    /// The current power and energy of a site, see [`Client::overview`]
+    fn overview(&self, site_id: u32) -> Result<site::Overview, SolarApiError>;
+
+    /// The energy of a site per time unit, see [`Client::energy`]
+    fn energy(
+        &self,
+        site_id: u32,
+        period: DataPeriod,
+        time_unit: TimeUnit,
+    ) -> Result<site::GeneratedEnergy, SolarApiError>;
+
+    /// The power of a site per quarter of an hour, see [`Client::power`]
+    fn power(
+        &self,
+        site_id: u32,
+        start_datetime: NaiveDateTime,
+        end_datetime: NaiveDateTime,
+    ) -> Result<site::GeneratedPowerPerTimeUnit, SolarApiError>;
+}
+
+impl MonitoringApi for Client {
+    fn list(&self) -> Result<Vec<site::Site>, SolarApiError> {
+        Client::list(self)
+    }
+
+    fn details(&self, site_id: u32) -> Result<site::Site, SolarApiError> {
+        Client::details(self, site_id)
+    }
+
+    fn data_period(&self, site_id: u32) -> Result<site::DataPeriod, SolarApiError> {
+        Client::data_period(self, site_id)
+    }
+
+    fn overview(&self, site_id: u32) -> Result<site::Overview, SolarApiError> {
+        Client::overview(self, site_id)
+    }
+
+    fn energy(
+        &self,
+        site_id: u32,
+        period: DataPeriod,
+        time_unit: TimeUnit,
+    ) -> Result<site::GeneratedEnergy, SolarApiError> {
+        Client::energy(self, site_id, period, time_unit)
+    }
+
+    fn power(
+        &self,
+        site_id: u32,
+        start_datetime: NaiveDateTime,
+        end_datetime: NaiveDateTime,
+    ) -> Result<site::GeneratedPowerPerTimeUnit, SolarApiError> {
+        Client::power(self, site_id, start_datetime, end_datetime)
+    }
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_energy_chunked_rate_limit() {
+    use crate::fake_server::{FakeServer, FakeSite, Scenario};
+    use chrono::Duration;
+
+    let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let server = FakeServer::start(
+        Scenario::new("KEY")
+            .with_site(FakeSite::new(1234, 5.0))
+            .with_now(date("2023-06-01").and_hms_opt(12, 0, 0).unwrap()),
+    )
+    .unwrap();
+    let bucket = TokenBucket::new(1, Duration::milliseconds(200), Utc::now());
+    let client = server.client().with_rate_limit(bucket);
+
+    // the hours of January until half March are three requests, the last two
+    // wait for a token
+    let period = DataPeriod::between(date("2023-01-01"), date("2023-03-15")).unwrap();
+    let started = std::time::Instant::now();
+    let energy = client.energy_chunked(1234, period, TimeUnit::Hour).unwrap();
+    assert!(started.elapsed() >= std::time::Duration::from_millis(400));
+    assert_eq!(3, server.requests().len());
+    assert_eq!(74 * 24, energy.to_series().unwrap().len());
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_power_chunked_rate_limit() {
+    use crate::fake_server::{FakeServer, FakeSite, Scenario};
+    use chrono::Duration;
+
+    let time = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let server = FakeServer::start(
+        Scenario::new("KEY")
+            .with_site(FakeSite::new(1234, 5.0))
+            .with_now(time("2023-06-01 12:00:00")),
+    )
+    .unwrap();
+    let bucket = TokenBucket::new(1, Duration::milliseconds(200), Utc::now());
+    let client = server.client().with_rate_limit(bucket);
+
+    // two months are two windows, the second waits for a token
+    let started = std::time::Instant::now();
+    client
+        .power_chunked(
+            1234,
+            time("2023-01-01 00:00:00"),
+            time("2023-03-01 00:00:00"),
+        )
+        .unwrap();
+    assert!(started.elapsed() >= std::time::Duration::from_millis(200));
+    assert_eq!(2, server.requests().len());
+}
@@ -0,0 +1,245 @@
+//! Loads the API key and the default site from a TOML file, the environment
+//! and, with the `keyring` feature, the keyring of the OS, see [`Config`].
+//! Requires the `config` feature.
+//!
+//! The file has the keys `api_key` and `site_id`, both optional:
+//!
+//! ```toml
+//! api_key = "L4QLVQ1LOKCQX2193VSEICXW61NP6B1O"
+//! site_id = 1234
+//! ```
+//!
+//! The environment variables `SOLAREDGE_API_KEY` and `SOLAREDGE_SITE_ID`
+//! override the file. When neither has an API key, it is read from the keyring
+//! where `store_api_key` stored it, so the key is never passed on the command
+//! line and does not end up in the shell history.
+//!
+//! ```no_run
+//! # use solar_api::Client;
+//! let client = Client::from_default_config()?;
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use crate::{client::Client, SolarApiError};
+use serde::Deserialize;
+use std::{
+    env, fmt,
+    path::{Path, PathBuf},
+};
+
+/// The environment variable with the API key
+pub const API_KEY_ENV: &str = "SOLAREDGE_API_KEY";
+/// The environment variable with the id of the default site
+pub const SITE_ID_ENV: &str = "SOLAREDGE_SITE_ID";
+
+// the service and user of the API key in the keyring of the OS
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "solar-api";
+#[cfg(feature = "keyring")]
+const KEYRING_USER: &str = "api_key";
+
+/// The API key and the default site. The API key is not shown by [`Debug`]
+#[derive(Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub api_key: Option<String>,
+    pub site_id: Option<u32>,
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("site_id", &self.site_id)
+            .finish()
+    }
+}
+
+impl Config {
+    /// Create a config without API key and site
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the API key
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    /// Set the id of the default site
+    pub fn with_site_id(mut self, site_id: u32) -> Self {
+        self.site_id = Some(site_id);
+        self
+    }
+
+    /// Parse a config from TOML
+    pub fn from_toml(toml: &str) -> Result<Self, SolarApiError> {
+        toml::from_str(toml).map_err(|source| SolarApiError::InvalidConfig {
+            path: "<toml>".to_string(),
+            source,
+        })
+    }
+
+    /// Read the config from the TOML file at `path`
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SolarApiError> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path).map_err(|source| SolarApiError::ConfigIo {
+            path: path.display().to_string(),
+            source,
+        })?;
+        toml::from_str(&toml).map_err(|source| SolarApiError::InvalidConfig {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+
+    /// The path of the config file: `solar-api/config.toml` in
+    /// `$XDG_CONFIG_HOME`, `~/.config` or `%APPDATA%` on Windows. None when
+    /// none of these is set
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(dir.join("solar-api").join("config.toml"))
+    }
+
+    /// Load the config from the file at [`Config::default_path`] when it
+    /// exists, overridden by the environment, and read the API key from the
+    /// keyring when it is still missing
+    pub fn load() -> Result<Self, SolarApiError> {
+        let config = match Config::default_path() {
+            Some(path) if path.exists() => Config::from_file(path)?,
+            _ => Config::default(),
+        };
+        config.with_env()?.with_keyring()
+    }
+
+    /// Load the config from the file at `path`, overridden by the environment,
+    /// and read the API key from the keyring when it is still missing
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self, SolarApiError> {
+        Config::from_file(path)?.with_env()?.with_keyring()
+    }
+
+    /// Override the API key and site with [`API_KEY_ENV`] and [`SITE_ID_ENV`]
+    /// when they are set
+    pub fn with_env(self) -> Result<Self, SolarApiError> {
+        self.with_vars(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+    }
+
+    /// Read the API key from the keyring when it is missing
+    #[cfg(feature = "keyring")]
+    pub fn with_keyring(mut self) -> Result<Self, SolarApiError> {
+        if self.api_key.is_none() {
+            self.api_key = match keyring_entry()?.get_password() {
+                Ok(api_key) => Some(api_key),
+                Err(keyring::Error::NoEntry) => None,
+                Err(e) => return Err(SolarApiError::KeyringError(e)),
+            };
+        }
+        Ok(self)
+    }
+
+    // without the keyring feature, the API key is not read from the keyring
+    #[cfg(not(feature = "keyring"))]
+    fn with_keyring(self) -> Result<Self, SolarApiError> {
+        Ok(self)
+    }
+
+    /// The API key. Returns [`SolarApiError::MissingConfig`] when there is none
+    pub fn api_key(&self) -> Result<&str, SolarApiError> {
+        self.api_key
+            .as_deref()
+            .ok_or_else(|| SolarApiError::MissingConfig(format!("api_key or {API_KEY_ENV}")))
+    }
+
+    /// The id of the default site. Returns [`SolarApiError::MissingConfig`]
+    /// when there is none
+    pub fn site_id(&self) -> Result<u32, SolarApiError> {
+        self.site_id
+            .ok_or_else(|| SolarApiError::MissingConfig(format!("site_id or {SITE_ID_ENV}")))
+    }
+
+    // overrides the fields with the values of the variables that `var` returns
+    fn with_vars(mut self, var: impl Fn(&str) -> Option<String>) -> Result<Self, SolarApiError> {
+        if let Some(api_key) = var(API_KEY_ENV) {
+            self.api_key = Some(api_key);
+        }
+        if let Some(site_id) = var(SITE_ID_ENV) {
+            let invalid = || SolarApiError::InvalidConfigValue {
+                name: SITE_ID_ENV.to_string(),
+                value: site_id.clone(),
+            };
+            self.site_id = Some(site_id.trim().parse().map_err(|_| invalid())?);
+        }
+        Ok(self)
+    }
+}
+
+/// Store `api_key` in the keyring of the OS, for [`Config::load`]
+#[cfg(feature = "keyring")]
+pub fn store_api_key(api_key: &str) -> Result<(), SolarApiError> {
+    keyring_entry()?
+        .set_password(api_key)
+        .map_err(SolarApiError::KeyringError)
+}
+
+/// Remove the API key from the keyring of the OS. Succeeds when there is none
+#[cfg(feature = "keyring")]
+pub fn delete_api_key() -> Result<(), SolarApiError> {
+    match keyring_entry()?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(SolarApiError::KeyringError(e)),
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_entry() -> Result<keyring::Entry, SolarApiError> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).map_err(SolarApiError::KeyringError)
+}
+
+impl Client {
+    /// Create a client with the API key of `config`
+    pub fn from_config(config: &Config) -> Result<Self, SolarApiError> {
+        Ok(Client::new(config.api_key()?))
+    }
+
+    /// Create a client with the API key of [`Config::load`]
+    pub fn from_default_config() -> Result<Self, SolarApiError> {
+        Client::from_config(&Config::load()?)
+    }
+}
+
+#[test]
+fn test_config() {
+    let config = Config::from_toml("api_key = \"KEY\"\nsite_id = 1234\n").unwrap();
+    assert_eq!(Config::new().with_api_key("KEY").with_site_id(1234), config);
+    assert_eq!(
+        "Config { api_key: Some(\"<redacted>\"), site_id: Some(1234) }",
+        format!("{config:?}")
+    );
+    assert!(matches!(
+        Config::from_toml("api_token = \"KEY\""),
+        Err(SolarApiError::InvalidConfig { .. })
+    ));
+
+    let vars = |name: &str| match name {
+        SITE_ID_ENV => Some("5678".to_string()),
+        _ => None,
+    };
+    let config = config.with_vars(vars).unwrap();
+    assert_eq!(
+        ("KEY", 5678),
+        (config.api_key().unwrap(), config.site_id().unwrap())
+    );
+    assert!(matches!(
+        Config::new().with_vars(|_| Some("abc".to_string())),
+        Err(SolarApiError::InvalidConfigValue { .. })
+    ));
+    assert!(matches!(
+        Config::new().api_key(),
+        Err(SolarApiError::MissingConfig(_))
+    ));
+}
@@ -0,0 +1,678 @@
+//! Runs requests for sites on cron-like schedules, so one process can fetch
+//! data of a site at several cadences, i.e. energy details every hour, the
+//! details of the site every day and the inventory every week. See
+//! [`CronTable`] for the format of a schedule and [`Client::cron_poller`]
+//!
+//! ```
+//! # use solar_api::cron::{CronJob, CronTable};
+//! # use solar_api::TimeUnit;
+//! let table: CronTable = "
+//! 5 * * * *  energy_details QUARTER_OF_AN_HOUR
+//! @daily     details
+//! 0 3 * * mon inventory"
+//!     .parse()?;
+//! assert_eq!(3, table.entries().len());
+//! assert_eq!(
+//!     CronJob::EnergyDetails(TimeUnit::QuarterOfAnHour),
+//!     table.entries()[0].job
+//! );
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use crate::{
+    client::Client,
+    site::{DataPeriod, GeneratedEnergy, Overview, Site, TimeUnit},
+    timezone::localize,
+    EnergyDetails, Equipment, SolarApiError,
+};
+use chrono::{
+    Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc,
+};
+use log::debug;
+use std::{collections::VecDeque, fmt, str::FromStr};
+
+// the number of years to look ahead for the next time of an expression, so
+// expressions like `0 0 29 2 *` are found and `0 0 30 2 *` end
+const MAX_YEARS_AHEAD: i32 = 8;
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A cron expression with the fields minute, hour, day of month, month and
+/// day of week. Fields are `*`, a value, a range like `1-5` or a list like
+/// `1,15`, optionally with a step like `*/15`. Months and days of week can be
+/// names like `jan` and `mon`, and Sunday is both 0 and 7. As in cron, a day
+/// matches either field when both day of month and day of week are
+/// restricted. The macros `@hourly`, `@daily`, `@weekly`, `@monthly` and
+/// `@yearly` are supported as well
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpression {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronExpression {
+    /// Returns true if the minute of `datetime` matches the expression
+    pub fn matches(&self, datetime: NaiveDateTime) -> bool {
+        self.matches_date(datetime.date())
+            && bit(self.hours, datetime.hour())
+            && bit(self.minutes, datetime.minute())
+    }
+
+    /// The first minute after `datetime` that matches the expression, or None
+    /// if no date matches, like the 30th of February
+    pub fn next_after(&self, datetime: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut next = datetime.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let last_year = datetime.year() + MAX_YEARS_AHEAD;
+        while next.year() <= last_year {
+            if !bit(self.months, next.month()) {
+                let (year, month) = match next.month() {
+                    12 => (next.year() + 1, 1),
+                    month => (next.year(), month + 1),
+                };
+                next = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_date(next.date()) {
+                next = next.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !bit(self.hours, next.hour()) {
+                next = next.with_minute(0)? + Duration::hours(1);
+            } else if !bit(self.minutes, next.minute()) {
+                next += Duration::minutes(1);
+            } else {
+                return Some(next);
+            }
+        }
+        None
+    }
+
+    // matches the month, day of month and day of week
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        let day = bit(self.days, date.day());
+        let weekday = bit(self.weekdays, date.weekday().num_days_from_sunday());
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+        bit(self.months, date.month()) && day_matches
+    }
+}
+
+// returns true if `value` is set in the bitmask of a field
+fn bit(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+// parses a field of a cron expression into a bitmask of the values from `min`
+// to `max`. `names` are the names of the values starting from `min`
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |text: &str| -> Result<u32, String> {
+        let lowercase = text.to_lowercase();
+        let value = match names.iter().position(|name| *name == lowercase) {
+            Some(index) => index as u32 + min,
+            None => text
+                .parse()
+                .map_err(|_| format!("invalid value {text} in {field}"))?,
+        };
+        if value < min || value > max {
+            return Err(format!("value {value} in {field} not in {min}-{max}"));
+        }
+        Ok(value)
+    };
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("invalid step {step} in {field}")),
+            },
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (value(start)?, value(end)?),
+            None if step > 1 => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+        if start > end {
+            return Err(format!("invalid range {range} in {field}"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+impl FromStr for CronExpression {
+    type Err = SolarApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |message: String| SolarApiError::InvalidCronSchedule(message);
+        let expanded = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expression => expression,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(invalid(format!("expected 5 fields in {s}")));
+        };
+        // Sunday is both 0 and 7
+        let weekday_mask = parse_field(weekdays, 0, 7, &WEEKDAYS).map_err(invalid)?;
+        Ok(CronExpression {
+            expression: s.trim().to_string(),
+            minutes: parse_field(minutes, 0, 59, &[]).map_err(invalid)?,
+            hours: parse_field(hours, 0, 23, &[]).map_err(invalid)?,
+            days: parse_field(days, 1, 31, &[]).map_err(invalid)?,
+            months: parse_field(months, 1, 12, &MONTHS).map_err(invalid)?,
+            weekdays: (weekday_mask | (weekday_mask >> 7)) & 0x7f,
+            any_day: days.starts_with('*'),
+            any_weekday: weekdays.starts_with('*'),
+        })
+    }
+}
+
+impl fmt::Display for CronExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+/// The request that a [`CronEntry`] runs for every site
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CronJob {
+    /// [`Client::overview`], written as `overview`
+    Overview,
+    /// [`Client::details`], written as `details`
+    Details,
+    /// [`Client::equipment_list`], written as `inventory`
+    Inventory,
+    /// [`Client::energy_chunked`] of the days since the previous run, written
+    /// as `energy DAY`
+    Energy(TimeUnit),
+    /// [`Client::energy_details`] since the previous run, split into periods
+    /// that the API allows for the time unit, written as
+    /// `energy_details QUARTER_OF_AN_HOUR`
+    EnergyDetails(TimeUnit),
+}
+
+impl FromStr for CronJob {
+    type Err = SolarApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let (job, time_unit) = (words.next().unwrap_or_default(), words.next());
+        let time_unit = || match time_unit {
+            Some(time_unit) => time_unit.parse(),
+            None => Err(SolarApiError::InvalidCronSchedule(format!(
+                "{job} needs a time unit"
+            ))),
+        };
+        let parsed = match job {
+            "overview" => CronJob::Overview,
+            "details" => CronJob::Details,
+            "inventory" => CronJob::Inventory,
+            "energy" => CronJob::Energy(time_unit()?),
+            "energy_details" => CronJob::EnergyDetails(time_unit()?),
+            _ => {
+                return Err(SolarApiError::InvalidCronSchedule(format!(
+                    "unknown job {s}"
+                )))
+            }
+        };
+        if words.next().is_some() {
+            return Err(SolarApiError::InvalidCronSchedule(format!(
+                "unexpected arguments in {s}"
+            )));
+        }
+        Ok(parsed)
+    }
+}
+
+impl fmt::Display for CronJob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CronJob::Overview => f.write_str("overview"),
+            CronJob::Details => f.write_str("details"),
+            CronJob::Inventory => f.write_str("inventory"),
+            CronJob::Energy(time_unit) => write!(f, "energy {time_unit}"),
+            CronJob::EnergyDetails(time_unit) => write!(f, "energy_details {time_unit}"),
+        }
+    }
+}
+
+/// A job with the schedule to run it on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronEntry {
+    pub schedule: CronExpression,
+    pub job: CronJob,
+}
+
+/// The jobs of a [`CronPoller`] with their schedules. A table is built in code
+/// with [`CronTable::with_entry`] or parsed from text with a line per entry:
+/// a [`CronExpression`] followed by a [`CronJob`]. Empty lines and lines
+/// starting with `#` are ignored
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CronTable {
+    entries: Vec<CronEntry>,
+}
+
+impl CronTable {
+    /// Create an empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entry that runs `job` on `schedule`
+    pub fn with_entry(mut self, schedule: CronExpression, job: CronJob) -> Self {
+        self.entries.push(CronEntry { schedule, job });
+        self
+    }
+
+    /// The entries of the table
+    pub fn entries(&self) -> &[CronEntry] {
+        &self.entries
+    }
+}
+
+impl FromStr for CronTable {
+    type Err = SolarApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut table = CronTable::new();
+        for (line, text) in s.lines().enumerate() {
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let fields = if text.starts_with('@') { 1 } else { 5 };
+            let words: Vec<&str> = text.split_whitespace().collect();
+            let on_line = |error: SolarApiError| match error {
+                SolarApiError::InvalidCronSchedule(message) => {
+                    SolarApiError::InvalidCronSchedule(format!("line {}: {message}", line + 1))
+                }
+                error => error,
+            };
+            let schedule = words
+                .get(..fields)
+                .unwrap_or_default()
+                .join(" ")
+                .parse()
+                .map_err(on_line)?;
+            let job = words
+                .get(fields..)
+                .unwrap_or_default()
+                .join(" ")
+                .parse()
+                .map_err(on_line)?;
+            table = table.with_entry(schedule, job);
+        }
+        Ok(table)
+    }
+}
+
+/// The data returned by a [`CronJob`]
+#[derive(Debug, Clone)]
+pub enum JobData {
+    Overview(Overview),
+    Details(Box<Site>),
+    Inventory(Vec<Equipment>),
+    Energy(GeneratedEnergy),
+    EnergyDetails(EnergyDetails),
+}
+
+// the schedule of an entry
+#[derive(Debug, Clone)]
+struct ScheduledEntry {
+    entry: CronEntry,
+    previous: Option<NaiveDateTime>,
+    next: Option<NaiveDateTime>,
+}
+
+/// Runs the jobs of a [`CronTable`] for a list of sites. The schedules are in
+/// the time zone of the sites, which is the time zone of this machine until
+/// [`CronPoller::with_timezone`] is used. Iterating blocks until the next job
+/// is due and yields the data of every site with its site id. Errors are
+/// yielded as well, after which the poller continues with the next job.
+///
+/// ```no_run
+/// # use solar_api::Client;
+/// # use solar_api::cron::{CronTable, JobData};
+/// let client = Client::new("API_KEY");
+/// let table: CronTable = "@hourly energy_details HOUR\n@daily details".parse()?;
+/// for result in client.cron_poller(&[1234, 5678], table) {
+///     match result? {
+///         (site_id, JobData::EnergyDetails(details)) => println!("{site_id}: {details:?}"),
+///         (site_id, data) => println!("{site_id}: {data:?}"),
+///     }
+/// }
+/// # Ok::<(), solar_api::SolarApiError>(())
+/// ```
+#[derive(Debug)]
+pub struct CronPoller<'a, Tz: TimeZone = Local> {
+    client: &'a Client,
+    site_ids: Vec<u32>,
+    tz: Tz,
+    entries: Vec<ScheduledEntry>,
+    results: VecDeque<Result<(u32, JobData), SolarApiError>>,
+}
+
+impl<'a> CronPoller<'a, Local> {
+    /// Create a poller that runs the jobs of `table` for `site_ids`, starting
+    /// with the first scheduled time from now
+    pub fn new(client: &'a Client, site_ids: &[u32], table: CronTable) -> Self {
+        let now = Local::now().naive_local();
+        CronPoller {
+            client,
+            site_ids: site_ids.to_vec(),
+            tz: Local,
+            entries: table
+                .entries
+                .into_iter()
+                .map(|entry| ScheduledEntry {
+                    previous: None,
+                    next: entry.schedule.next_after(now),
+                    entry,
+                })
+                .collect(),
+            results: VecDeque::new(),
+        }
+    }
+}
+
+impl<'a, Tz: TimeZone> CronPoller<'a, Tz> {
+    /// Use `tz` as the time zone of the sites, i.e. from
+    /// [`Location::tz`](crate::Location). The schedules start from now in `tz`
+    pub fn with_timezone<Tz2: TimeZone>(self, tz: Tz2) -> CronPoller<'a, Tz2> {
+        let now = Utc::now().with_timezone(&tz).naive_local();
+        CronPoller {
+            client: self.client,
+            site_ids: self.site_ids,
+            entries: self
+                .entries
+                .into_iter()
+                .map(|scheduled| ScheduledEntry {
+                    next: scheduled.entry.schedule.next_after(now),
+                    ..scheduled
+                })
+                .collect(),
+            tz,
+            results: self.results,
+        }
+    }
+
+    /// The local time at which the next job is due, or None when no schedule
+    /// matches any time
+    pub fn next_run(&self) -> Option<NaiveDateTime> {
+        self.entries
+            .iter()
+            .filter_map(|scheduled| scheduled.next)
+            .min()
+    }
+
+    // runs `job` for `site_id` at the local time `at`, which was `previous`
+    // the last time
+    fn run(
+        &self,
+        site_id: u32,
+        job: CronJob,
+        previous: Option<NaiveDateTime>,
+        at: NaiveDateTime,
+    ) -> Result<JobData, SolarApiError> {
+        let start = previous.unwrap_or(at.date().and_hms_opt(0, 0, 0).unwrap_or(at));
+        Ok(match job {
+            CronJob::Overview => JobData::Overview(self.client.overview(site_id)?),
+            CronJob::Details => JobData::Details(Box::new(self.client.details(site_id)?)),
+            CronJob::Inventory => JobData::Inventory(self.client.equipment_list(site_id)?),
+            CronJob::Energy(time_unit) => JobData::Energy(self.client.energy_chunked(
+                site_id,
+                DataPeriod::between(start.date(), at.date())?,
+                time_unit,
+            )?),
+            CronJob::EnergyDetails(time_unit) => {
+                let mut windows = detail_windows(start, at, time_unit)?.into_iter();
+                let (first_start, first_end) = windows.next().unwrap_or((start, at));
+                let mut details =
+                    self.client
+                        .energy_details(site_id, first_start, first_end, time_unit, &[])?;
+                for (start, end) in windows {
+                    details.append(self.client.energy_details(
+                        site_id,
+                        start,
+                        end,
+                        time_unit,
+                        &[],
+                    )?)?;
+                }
+                JobData::EnergyDetails(details)
+            }
+        })
+    }
+}
+
+impl<Tz: TimeZone> Iterator for CronPoller<'_, Tz> {
+    type Item = Result<(u32, JobData), SolarApiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.results.pop_front() {
+                return Some(result);
+            }
+            let at = self.next_run()?;
+            // a time that does not exist in the time zone, when the clock is
+            // turned forward, is skipped
+            if let Ok(localized) = localize(at, &self.tz) {
+                let wait = localized.with_timezone(&Utc) - Utc::now();
+                if let Ok(wait) = wait.to_std() {
+                    debug!("Waiting {wait:?} until {at}");
+                    std::thread::sleep(wait);
+                }
+            }
+            for index in 0..self.entries.len() {
+                let ScheduledEntry {
+                    entry,
+                    previous,
+                    next,
+                } = self.entries[index].clone();
+                if next != Some(at) {
+                    continue;
+                }
+                if localize(at, &self.tz).is_ok() {
+                    for site_id in &self.site_ids {
+                        debug!("Running {} for site {site_id}", entry.job);
+                        let result = self.run(*site_id, entry.job, previous, at);
+                        self.results.push_back(result.map(|data| (*site_id, data)));
+                    }
+                }
+                self.entries[index].previous = Some(at);
+                self.entries[index].next = entry.schedule.next_after(at);
+            }
+        }
+    }
+}
+
+// splits `start` until `end` into windows of the periods of
+// [`DataPeriod::split_for`], which end at the last second of their last day
+fn detail_windows(
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    time_unit: TimeUnit,
+) -> Result<Vec<(NaiveDateTime, NaiveDateTime)>, SolarApiError> {
+    let periods = DataPeriod::between(start.date(), end.date())?.split_for(time_unit);
+    let mut windows = Vec::new();
+    for period in periods {
+        let (first_day, last_day) = period.bounds()?;
+        let window_start = first_day.and_time(NaiveTime::MIN).max(start);
+        let window_end = last_day
+            .and_hms_opt(23, 59, 59)
+            .map_or(end, |last_second| last_second.min(end));
+        windows.push((window_start, window_end));
+    }
+    Ok(windows)
+}
+
+impl Client {
+    /// Create a [`CronPoller`] that runs the jobs of `table` for `site_ids`
+    pub fn cron_poller(&self, site_ids: &[u32], table: CronTable) -> CronPoller<'_> {
+        CronPoller::new(self, site_ids, table)
+    }
+}
+
+#[test]
+fn test_cron_expression() {
+    let datetime = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+    let next = |expression: &str, after: &str| {
+        expression
+            .parse::<CronExpression>()
+            .unwrap()
+            .next_after(datetime(after))
+            .map(|next| next.format("%Y-%m-%d %H:%M").to_string())
+    };
+
+    assert_eq!(
+        Some("2023-06-01 12:15".to_string()),
+        next("*/15 * * * *", "2023-06-01 12:00")
+    );
+    assert_eq!(
+        Some("2023-06-01 13:05".to_string()),
+        next("5 * * * *", "2023-06-01 12:05")
+    );
+    // 2023-06-05 is a Monday
+    assert_eq!(
+        Some("2023-06-05 03:00".to_string()),
+        next("0 3 * * mon", "2023-06-01 12:00")
+    );
+    assert_eq!(
+        Some("2023-06-04 00:00".to_string()),
+        next("@weekly", "2023-06-01 12:00")
+    );
+    assert_eq!(
+        Some("2023-06-04 00:00".to_string()),
+        next("0 0 * * 7", "2023-06-01 12:00")
+    );
+    // day of month or day of week when both are restricted
+    assert_eq!(
+        Some("2023-06-02 00:00".to_string()),
+        next("0 0 15 * fri", "2023-06-01 12:00")
+    );
+    assert_eq!(
+        Some("2024-02-29 06:30".to_string()),
+        next("30 6 29 feb *", "2023-03-01 00:00")
+    );
+    assert_eq!(
+        Some("2023-07-01 08:00".to_string()),
+        next("0 8-17/3 1 jul-sep *", "2023-06-01 12:00")
+    );
+    assert_eq!(None, next("0 0 30 2 *", "2023-06-01 12:00"));
+
+    assert!("0 0 * *".parse::<CronExpression>().is_err());
+    assert!("60 * * * *".parse::<CronExpression>().is_err());
+    assert!("*/0 * * * *".parse::<CronExpression>().is_err());
+    assert!("5-1 * * * *".parse::<CronExpression>().is_err());
+}
+
+#[test]
+fn test_cron_table() {
+    let table: CronTable = "# comment
+
+*/15 6-22 * * * overview
+@daily energy day
+0 3 * * sun inventory"
+        .parse()
+        .unwrap();
+    let jobs: Vec<String> = table
+        .entries()
+        .iter()
+        .map(|entry| format!("{} {}", entry.schedule, entry.job))
+        .collect();
+    assert_eq!(
+        vec![
+            "*/15 6-22 * * * overview",
+            "@daily energy DAY",
+            "0 3 * * sun inventory"
+        ],
+        jobs
+    );
+
+    let error = "@daily overview\n@hourly energy".parse::<CronTable>();
+    assert!(
+        matches!(error, Err(SolarApiError::InvalidCronSchedule(message)) if message == "line 2: energy needs a time unit")
+    );
+    assert!("* * * * * power".parse::<CronTable>().is_err());
+
+    let client = Client::new("API_KEY");
+    let poller = client
+        .cron_poller(&[1234], table)
+        .with_timezone(chrono::FixedOffset::east_opt(3600).unwrap());
+    assert!(poller.next_run().is_some());
+}
+
+#[test]
+fn test_detail_windows() {
+    let time = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let windows = detail_windows(
+        time("2023-01-15 10:05:00"),
+        time("2023-03-01 11:05:00"),
+        TimeUnit::Hour,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![
+            (time("2023-01-15 10:05:00"), time("2023-02-14 23:59:59")),
+            (time("2023-02-15 00:00:00"), time("2023-03-01 11:05:00")),
+        ],
+        windows
+    );
+    for (start, end) in windows {
+        let period = DataPeriod::between(start.date(), end.date()).unwrap();
+        assert!(period.validate_for(TimeUnit::Hour).is_ok());
+    }
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_cron_energy_after_long_gap() {
+    use crate::fake_server::{FakeServer, FakeSite, Scenario};
+
+    let time = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let server = FakeServer::start(
+        Scenario::new("KEY")
+            .with_site(FakeSite::new(1234, 5.0))
+            .with_now(time("2023-06-01 12:00:00")),
+    )
+    .unwrap();
+    let client = server.client();
+    let poller = client.cron_poller(&[1234], "@hourly energy HOUR".parse().unwrap());
+
+    // the previous run was more than a month ago, which is too long for one
+    // request of hours
+    let data = poller
+        .run(
+            1234,
+            CronJob::Energy(TimeUnit::Hour),
+            Some(time("2023-03-20 10:00:00")),
+            time("2023-05-01 11:00:00"),
+        )
+        .unwrap();
+    let JobData::Energy(energy) = data else {
+        panic!("expected energy, got {data:?}");
+    };
+    assert_eq!(2, server.requests().len());
+    let series = energy.to_series().unwrap();
+    assert_eq!(time("2023-03-20 00:00:00"), series.values[0].date);
+    assert_eq!(
+        time("2023-05-01 23:00:00"),
+        series.values[series.len() - 1].date
+    );
+    assert_eq!(43 * 24, series.len());
+}
@@ -0,0 +1,181 @@
+//! Converts series and portfolio results to [polars](https://docs.rs/polars)
+//! data frames with [`ToDataFrame`]. Requires the `polars` feature. Timestamps
+//! are datetime columns in milliseconds without time zone, in the local time of
+//! the site, and values are float columns in the base unit of
+//! [`Quantity`]: Wh for energy and W for power
+//!
+//! ```
+//! # use solar_api::dataframe::ToDataFrame;
+//! # use solar_api::{TimeSeries, TimeSeriesValue, TimeUnit};
+//! # use solar_api::units::watt_hours;
+//! # use solar_api::chrono::NaiveDate;
+//! let date = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+//! let energy = TimeSeries::new(TimeUnit::Day, "Wh", vec![TimeSeriesValue {
+//!     date,
+//!     value: Some(watt_hours(12500.0)),
+//! }]);
+//! let df = energy.to_dataframe()?;
+//! assert_eq!((1, 2), df.shape());
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use crate::{
+    portfolio::{PortfolioEnergy, SiteRanking},
+    series::TimeSeries,
+    units::Quantity,
+    SolarApiError,
+};
+#[cfg(test)]
+use crate::{
+    series::TimeSeriesValue,
+    site::TimeUnit,
+    units::{watt_hours, Energy},
+};
+use chrono::NaiveDateTime;
+use polars::prelude::{
+    Column, DataFrame, DataType, IntoColumn, IntoSeries, NamedFrom, Series, TimeUnit as PlTimeUnit,
+};
+
+/// Conversion to a polars [`DataFrame`]
+pub trait ToDataFrame {
+    /// A data frame with a row per value
+    fn to_dataframe(&self) -> Result<DataFrame, SolarApiError>;
+}
+
+/// The columns `timestamp` and `value`
+impl<Q: Quantity> ToDataFrame for TimeSeries<Q> {
+    fn to_dataframe(&self) -> Result<DataFrame, SolarApiError> {
+        let (timestamps, values): (Vec<_>, Vec<_>) = self
+            .iter()
+            .map(|v| (v.date, v.value.map(Quantity::base_value)))
+            .unzip();
+        DataFrame::new(vec![
+            timestamp_column(&timestamps)?,
+            Series::new("value".into(), values).into_column(),
+        ])
+        .map_err(SolarApiError::DataFrameError)
+    }
+}
+
+/// The columns `site_id`, `timestamp` and `value`, ordered by site id and
+/// timestamp
+impl ToDataFrame for PortfolioEnergy {
+    fn to_dataframe(&self) -> Result<DataFrame, SolarApiError> {
+        let mut site_ids = Vec::new();
+        let mut timestamps = Vec::new();
+        let mut values = Vec::new();
+        for (site_id, series) in &self.sites {
+            for v in series.iter() {
+                site_ids.push(*site_id);
+                timestamps.push(v.date);
+                values.push(v.value.map(Quantity::base_value));
+            }
+        }
+        DataFrame::new(vec![
+            Series::new("site_id".into(), site_ids).into_column(),
+            timestamp_column(&timestamps)?,
+            Series::new("value".into(), values).into_column(),
+        ])
+        .map_err(SolarApiError::DataFrameError)
+    }
+}
+
+/// The columns `site_id`, `rank`, `specific_yield`, `fleet_median` and
+/// `ratio_to_median`
+impl ToDataFrame for [SiteRanking] {
+    fn to_dataframe(&self) -> Result<DataFrame, SolarApiError> {
+        let column = |name: &str, value: fn(&SiteRanking) -> f64| {
+            Series::new(name.into(), self.iter().map(value).collect::<Vec<_>>()).into_column()
+        };
+        DataFrame::new(vec![
+            Series::new(
+                "site_id".into(),
+                self.iter().map(|r| r.site_id).collect::<Vec<_>>(),
+            )
+            .into_column(),
+            Series::new(
+                "rank".into(),
+                self.iter().map(|r| r.rank as u32).collect::<Vec<_>>(),
+            )
+            .into_column(),
+            column("specific_yield", |r| r.specific_yield),
+            column("fleet_median", |r| r.fleet_median),
+            Series::new(
+                "ratio_to_median".into(),
+                self.iter().map(|r| r.ratio_to_median).collect::<Vec<_>>(),
+            )
+            .into_column(),
+        ])
+        .map_err(SolarApiError::DataFrameError)
+    }
+}
+
+// a datetime column in milliseconds without time zone
+fn timestamp_column(timestamps: &[NaiveDateTime]) -> Result<Column, SolarApiError> {
+    let millis: Vec<i64> = timestamps
+        .iter()
+        .map(|date| date.and_utc().timestamp_millis())
+        .collect();
+    Series::new("timestamp".into(), millis)
+        .cast(&DataType::Datetime(PlTimeUnit::Milliseconds, None))
+        .map(IntoSeries::into_series)
+        .map(IntoColumn::into_column)
+        .map_err(SolarApiError::DataFrameError)
+}
+
+#[test]
+fn test_to_dataframe() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let series = |values: Vec<Option<Energy>>| {
+        TimeSeries::new(
+            TimeUnit::Day,
+            "Wh",
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(day, value)| TimeSeriesValue {
+                    date: date(&format!("2023-06-0{} 00:00:00", day + 1)),
+                    value,
+                })
+                .collect(),
+        )
+    };
+    let energy = PortfolioEnergy {
+        time_unit: TimeUnit::Day,
+        sites: [
+            (1, series(vec![Some(watt_hours(1000.0)), None])),
+            (2, series(vec![Some(watt_hours(2000.0))])),
+        ]
+        .into(),
+    };
+
+    let df = energy.sites[&1].to_dataframe().unwrap();
+    assert_eq!(2, df.height());
+    assert_eq!(
+        &DataType::Datetime(PlTimeUnit::Milliseconds, None),
+        df.column("timestamp").unwrap().dtype()
+    );
+    let values = df.column("value").unwrap().f64().unwrap();
+    assert_eq!(
+        vec![Some(1000.0), None],
+        values.into_iter().collect::<Vec<_>>()
+    );
+
+    let df = energy.to_dataframe().unwrap();
+    assert_eq!(3, df.height());
+    let site_ids = df.column("site_id").unwrap().u32().unwrap();
+    assert_eq!(
+        vec![Some(1), Some(1), Some(2)],
+        site_ids.into_iter().collect::<Vec<_>>()
+    );
+
+    let ranking = [SiteRanking {
+        site_id: 1,
+        rank: 1,
+        specific_yield: 2.0,
+        fleet_median: 4.0,
+        ratio_to_median: Some(0.5),
+    }];
+    let df = ranking.to_dataframe().unwrap();
+    assert_eq!((1, 5), df.shape());
+}
@@ -0,0 +1,321 @@
+//! Renders sites, overviews and series as aligned tables for the terminal with
+//! [`ToTable`], with energy and power in readable units like `12.50 kWh`.
+//! Requires the `display` feature.
+//!
+//! ```
+//! # use solar_api::display::ToTable;
+//! # use solar_api::{TimeSeries, TimeSeriesValue, TimeUnit};
+//! # use solar_api::units::watt_hours;
+//! # use solar_api::chrono::NaiveDate;
+//! let date = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+//! let energy = TimeSeries::new(TimeUnit::Day, "Wh", vec![TimeSeriesValue {
+//!     date,
+//!     value: Some(watt_hours(12500.0)),
+//! }]);
+//! assert_eq!(
+//!     "Timestamp            Energy\n\
+//!      ----------------  ---------\n\
+//!      2023-06-01 00:00  12.50 kWh\n",
+//!     energy.to_table().to_string()
+//! );
+//! ```
+
+use crate::{
+    series::TimeSeries,
+    site::{Overview, Site},
+    units::{as_watt_hours, as_watts, Energy, EnergyUnit, Power, PowerUnit, Quantity},
+};
+#[cfg(test)]
+use crate::{
+    series::TimeSeriesValue,
+    site::TimeUnit,
+    units::{watt_hours, watts},
+};
+#[cfg(test)]
+use chrono::NaiveDateTime;
+use std::{collections::BTreeMap, fmt};
+
+// the text of a missing value
+const MISSING: &str = "-";
+
+/// A table with a header row. Columns with only numbers are aligned to the
+/// right, the other columns to the left. [`Table`] is displayed as text
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Create a table with the columns `headers` without rows
+    pub fn new(headers: &[&str]) -> Self {
+        Table {
+            headers: headers.iter().map(|header| header.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a row with a cell per column. Missing cells are empty and extra
+    /// cells are ignored
+    pub fn with_row(mut self, row: Vec<String>) -> Self {
+        self.add_row(row);
+        self
+    }
+
+    /// Add a row with a cell per column
+    pub fn add_row(&mut self, mut row: Vec<String>) {
+        row.resize(self.headers.len(), String::new());
+        self.rows.push(row);
+    }
+
+    /// The number of rows without the header row
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// true when the table has no rows
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let columns: Vec<(usize, bool)> = (0..self.headers.len())
+            .map(|column| {
+                let cells = self.rows.iter().map(|row| &row[column]);
+                let width = cells
+                    .clone()
+                    .chain([&self.headers[column]])
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or(0);
+                let numeric = self.rows.iter().any(|row| is_number(&row[column]))
+                    && cells.clone().all(|cell| cell == MISSING || is_number(cell));
+                (width, numeric)
+            })
+            .collect();
+        let separator: Vec<String> = columns
+            .iter()
+            .map(|(width, _)| "-".repeat(*width))
+            .collect();
+        for row in [&self.headers, &separator].into_iter().chain(&self.rows) {
+            let mut line = String::new();
+            for (cell, (width, numeric)) in row.iter().zip(&columns) {
+                if !line.is_empty() {
+                    line.push_str("  ");
+                }
+                if *numeric {
+                    line.push_str(&format!("{cell:>width$}"));
+                } else {
+                    line.push_str(&format!("{cell:<width$}"));
+                }
+            }
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Conversion to a [`Table`]
+pub trait ToTable {
+    /// The table of `self`
+    fn to_table(&self) -> Table;
+}
+
+/// A row per site with its id, name, status, type, peak power, installation
+/// date and date of the last update
+impl ToTable for [Site] {
+    fn to_table(&self) -> Table {
+        let mut table = Table::new(&[
+            "Id",
+            "Name",
+            "Status",
+            "Type",
+            "Peak power",
+            "Installed",
+            "Last update",
+        ]);
+        for site in self {
+            let date = |date: Option<chrono::NaiveDate>| {
+                date.map_or(MISSING.to_string(), |date| date.to_string())
+            };
+            table.add_row(vec![
+                site.id.to_string(),
+                site.name.clone(),
+                site.status.clone(),
+                site.site_type.clone(),
+                format_power(site.peak_power),
+                date(site.installation_date),
+                date(site.last_update_time),
+            ]);
+        }
+        table
+    }
+}
+
+/// A row per field of the overview
+impl ToTable for Overview {
+    fn to_table(&self) -> Table {
+        let fields = [
+            (
+                "Last update",
+                self.last_updated_time
+                    .map_or(MISSING.to_string(), |time| time.to_string()),
+            ),
+            ("Current power", format_power(self.current_power.power)),
+            ("Today", format_energy(self.last_day_data.energy)),
+            ("This month", format_energy(self.last_month_data.energy)),
+            ("This year", format_energy(self.last_year_data.energy)),
+            ("Lifetime", format_energy(self.life_time_data.energy)),
+        ];
+        let mut table = Table::new(&["Field", "Value"]);
+        for (field, value) in fields {
+            table.add_row(vec![field.to_string(), value]);
+        }
+        table
+    }
+}
+
+/// A row per site with the fields of its overview, i.e. of
+/// [`Client::overviews`](crate::Client::overviews)
+impl ToTable for BTreeMap<u32, Overview> {
+    fn to_table(&self) -> Table {
+        let mut table = Table::new(&[
+            "Site",
+            "Last update",
+            "Power",
+            "Today",
+            "This month",
+            "This year",
+            "Lifetime",
+        ]);
+        for (site_id, overview) in self {
+            table.add_row(vec![
+                site_id.to_string(),
+                overview
+                    .last_updated_time
+                    .map_or(MISSING.to_string(), |time| time.to_string()),
+                format_power(overview.current_power.power),
+                format_energy(overview.last_day_data.energy),
+                format_energy(overview.last_month_data.energy),
+                format_energy(overview.last_year_data.energy),
+                format_energy(overview.life_time_data.energy),
+            ]);
+        }
+        table
+    }
+}
+
+/// A row per value with the timestamp and the value. Series of energy and
+/// power are shown in readable units, other series with their unit
+impl<Q: Quantity> ToTable for TimeSeries<Q> {
+    fn to_table(&self) -> Table {
+        let (header, format): (&str, fn(f64, &str) -> String) =
+            if self.unit.parse::<EnergyUnit>().is_ok() {
+                ("Energy", |value, _| scaled(value, "Wh"))
+            } else if self.unit.parse::<PowerUnit>().is_ok() {
+                ("Power", |value, _| scaled(value, "W"))
+            } else {
+                ("Value", |value, unit| format!("{value:.2} {unit}"))
+            };
+        let mut table = Table::new(&["Timestamp", header]);
+        for value in self.iter() {
+            table.add_row(vec![
+                value.date.format("%Y-%m-%d %H:%M").to_string(),
+                value.value.map_or(MISSING.to_string(), |value| {
+                    format(value.base_value(), &self.unit)
+                }),
+            ]);
+        }
+        table
+    }
+}
+
+/// `energy` in Wh, kWh, MWh or GWh with 2 decimals, i.e. `12.50 kWh`
+pub fn format_energy(energy: Energy) -> String {
+    scaled(as_watt_hours(energy), "Wh")
+}
+
+/// `power` in W, kW, MW or GW with 2 decimals, i.e. `750.00 W`
+pub fn format_power(power: Power) -> String {
+    scaled(as_watts(power), "W")
+}
+
+// `value` in `symbol` with the largest prefix that keeps it at least 1
+fn scaled(value: f64, symbol: &str) -> String {
+    let (value, prefix) = [(1e9, "G"), (1e6, "M"), (1e3, "k")]
+        .into_iter()
+        .find(|(factor, _)| value.abs() >= *factor)
+        .map_or((value, ""), |(factor, prefix)| (value / factor, prefix));
+    format!("{value:.2} {prefix}{symbol}")
+}
+
+// true when `cell` is a number, optionally followed by a unit like `12.50 kWh`
+fn is_number(cell: &str) -> bool {
+    cell.split(' ')
+        .next()
+        .is_some_and(|number| number.parse::<f64>().is_ok())
+}
+
+#[test]
+fn test_format_units() {
+    assert_eq!("750.00 W", format_power(watts(750.0)));
+    assert_eq!("-1.20 kW", format_power(watts(-1200.0)));
+    assert_eq!("12.50 kWh", format_energy(watt_hours(12500.0)));
+    assert_eq!("1.00 GWh", format_energy(watt_hours(1e9)));
+}
+
+#[test]
+fn test_to_table() {
+    let overview: Overview = serde_json::from_str(
+        r#"{"lastUpdateTime":"2023-06-01 12:20:00",
+            "lifeTimeData":{"energy":1000000.0},"lastYearData":{"energy":500000.0},
+            "lastMonthData":{"energy":20000.0},"lastDayData":{"energy":2000.0},
+            "currentPower":{"power":750.0},"measuredBy":"INVERTER"}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        "Field          Value\n\
+         -------------  -------------------\n\
+         Last update    2023-06-01 12:20:00\n\
+         Current power  750.00 W\n\
+         Today          2.00 kWh\n\
+         This month     20.00 kWh\n\
+         This year      500.00 kWh\n\
+         Lifetime       1.00 MWh\n",
+        overview.to_table().to_string()
+    );
+
+    let overviews = BTreeMap::from([(1234, overview.clone()), (56, overview)]);
+    assert_eq!(
+        "Site  Last update             Power     Today  This month   This year  Lifetime\n\
+         ----  -------------------  --------  --------  ----------  ----------  --------\n\
+         \x20 56  2023-06-01 12:20:00  750.00 W  2.00 kWh   20.00 kWh  500.00 kWh  1.00 MWh\n\
+         1234  2023-06-01 12:20:00  750.00 W  2.00 kWh   20.00 kWh  500.00 kWh  1.00 MWh\n",
+        overviews.to_table().to_string()
+    );
+
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let power = TimeSeries::new(
+        TimeUnit::QuarterOfAnHour,
+        "W",
+        vec![
+            TimeSeriesValue {
+                date: date("2023-06-01 12:00:00"),
+                value: Some(watts(1500.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-06-01 12:15:00"),
+                value: None,
+            },
+        ],
+    );
+    assert_eq!(
+        "Timestamp           Power\n\
+         ----------------  -------\n\
+         2023-06-01 12:00  1.50 kW\n\
+         2023-06-01 12:15        -\n",
+        power.to_table().to_string()
+    );
+}
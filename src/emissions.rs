@@ -0,0 +1,138 @@
+//! Calculates the CO2 emissions avoided by the production of a site using
+//! grid emission factors that you configure, instead of the fixed assumptions
+//! of the API, so reports can state which factors were used
+
+use crate::{
+    series::{TimeSeries, TimeSeriesValue},
+    site::Site,
+    units::{Energy, EnergyUnit},
+    SolarApiError,
+};
+#[cfg(test)]
+use crate::{site::TimeUnit, units::watt_hours};
+use chrono::Datelike;
+use std::collections::HashMap;
+
+/// Grid emission factors in kg CO2 per kWh per country and optionally per
+/// year. Countries are identified by the country code of the site, i.e. `NL`
+///
+/// ```
+/// # use solar_api::emissions::EmissionFactors;
+/// let factors = EmissionFactors::new()
+///     .with_factor("NL", None, 0.33)
+///     .with_factor("NL", Some(2023), 0.27);
+/// assert_eq!(Some(0.27), factors.factor("NL", 2023));
+/// assert_eq!(Some(0.33), factors.factor("NL", 2022));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EmissionFactors {
+    default: Option<f64>,
+    factors: HashMap<(String, Option<i32>), f64>,
+}
+
+impl EmissionFactors {
+    /// Create an empty set of factors
+    pub fn new() -> Self {
+        EmissionFactors::default()
+    }
+
+    /// Set the factor used for countries and years without a factor
+    pub fn with_default(mut self, kg_per_kwh: f64) -> Self {
+        self.default = Some(kg_per_kwh);
+        self
+    }
+
+    /// Set the factor of `country` in `year`, or of all years of `country`
+    /// without a factor of their own when `year` is None
+    pub fn with_factor(mut self, country: &str, year: Option<i32>, kg_per_kwh: f64) -> Self {
+        self.factors
+            .insert((country.to_uppercase(), year), kg_per_kwh);
+        self
+    }
+
+    /// The factor of `country` in `year`. Falls back to the factor of all years
+    /// of `country` and then to the default
+    pub fn factor(&self, country: &str, year: i32) -> Option<f64> {
+        let country = country.to_uppercase();
+        self.factors
+            .get(&(country.clone(), Some(year)))
+            .or_else(|| self.factors.get(&(country, None)))
+            .copied()
+            .or(self.default)
+    }
+}
+
+/// The CO2 emissions in kg avoided by every value of `energy` produced in
+/// `country`, using the factor of the year of the value. Returns
+/// [`SolarApiError::MissingEmissionFactor`] when `factors` has no factor for
+/// a year with energy
+pub fn co2_avoided(
+    energy: &TimeSeries<Energy>,
+    country: &str,
+    factors: &EmissionFactors,
+) -> Result<TimeSeries<f64>, SolarApiError> {
+    let mut values = Vec::with_capacity(energy.len());
+    for v in energy {
+        let value = match v.value {
+            Some(energy) => {
+                let year = v.date.year();
+                let factor = factors.factor(country, year).ok_or_else(|| {
+                    SolarApiError::MissingEmissionFactor {
+                        country: country.to_string(),
+                        year,
+                    }
+                })?;
+                Some(EnergyUnit::KilowattHour.value_of(energy) * factor)
+            }
+            None => None,
+        };
+        values.push(TimeSeriesValue {
+            date: v.date,
+            value,
+        });
+    }
+    Ok(TimeSeries::new(energy.time_unit, "kg", values))
+}
+
+impl Site {
+    /// The CO2 emissions avoided by `energy` of this site, using the country
+    /// code of its location. See [`co2_avoided`]
+    pub fn co2_avoided(
+        &self,
+        energy: &TimeSeries<Energy>,
+        factors: &EmissionFactors,
+    ) -> Result<TimeSeries<f64>, SolarApiError> {
+        co2_avoided(energy, &self.location.country_code, factors)
+    }
+}
+
+#[test]
+fn test_co2_avoided() {
+    let value = |year: i32, wh: f64| TimeSeriesValue {
+        date: chrono::NaiveDate::from_ymd_opt(year, 1, 1).unwrap().into(),
+        value: Some(watt_hours(wh)),
+    };
+    let energy = TimeSeries::new(
+        TimeUnit::Year,
+        "Wh",
+        vec![value(2022, 4_000_000.0), value(2023, 5_000_000.0)],
+    );
+    let factors = EmissionFactors::new()
+        .with_factor("nl", None, 0.5)
+        .with_factor("NL", Some(2023), 0.25);
+
+    let avoided = co2_avoided(&energy, "NL", &factors).unwrap();
+    assert_eq!("kg", avoided.unit);
+    assert_eq!(Some(2000.0), avoided.values[0].value);
+    assert_eq!(Some(1250.0), avoided.values[1].value);
+
+    assert!(matches!(
+        co2_avoided(&energy, "BE", &factors),
+        Err(SolarApiError::MissingEmissionFactor { year: 2022, .. })
+    ));
+    let with_default = factors.with_default(0.1);
+    assert_eq!(
+        900.0,
+        co2_avoided(&energy, "BE", &with_default).unwrap().total()
+    );
+}
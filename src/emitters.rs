@@ -0,0 +1,300 @@
+//! Pushes the current power and energy of sites as gauges to Graphite with the
+//! plaintext protocol, see [`GraphiteEmitter`], or to StatsD over UDP, see
+//! [`StatsdEmitter`]. Requires the `emitters` feature.
+//!
+//! The gauges are named `{prefix}.{site_id}.{gauge}`, with `solaredge` as
+//! default prefix:
+//! * `current_power_watts`: the current power of the overview
+//! * `energy_today_watt_hours`: the energy of the overview of today
+//! * `energy_month_watt_hours`: the energy of the overview of this month
+//! * `energy_year_watt_hours`: the energy of the overview of this year
+//! * `energy_lifetime_watt_hours`: the lifetime energy of the overview
+//!
+//! ```no_run
+//! # use solar_api::emitters::{GaugeEmitter, GraphiteEmitter};
+//! # use solar_api::Client;
+//! let client = Client::new("API_KEY");
+//! let mut emitter = GraphiteEmitter::connect("localhost:2003")?.with_prefix("home.solar");
+//! // emits every new overview until the API key is rejected
+//! emitter.emit_updates(1234, client.poller(1234))?;
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use crate::{
+    site::Overview,
+    units::{as_watt_hours, as_watts},
+    SolarApiError,
+};
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use std::{
+    io::{self, Write},
+    net::{TcpStream, ToSocketAddrs, UdpSocket},
+};
+
+// the maximum size of a StatsD packet that is not fragmented on most networks
+const MAX_PACKET_SIZE: usize = 512;
+
+/// Emits gauges to a metrics stack. The provided methods emit the gauges of
+/// overviews
+pub trait GaugeEmitter {
+    /// Emit `gauges` as name and value at `timestamp`. The names are relative
+    /// to the prefix of the emitter
+    fn emit(
+        &mut self,
+        gauges: &[(String, f64)],
+        timestamp: DateTime<Utc>,
+    ) -> Result<(), SolarApiError>;
+
+    /// Emit the current power and energy of `overview` of `site_id`
+    fn emit_overview(&mut self, site_id: u32, overview: &Overview) -> Result<(), SolarApiError> {
+        self.emit(&overview_gauges(site_id, overview), Utc::now())
+    }
+
+    /// Emit every overview of `updates`, i.e. of a
+    /// [`Poller`](crate::poller::Poller), until the updates end. Errors of the
+    /// updates are logged and skipped, except
+    /// [`SolarApiError::ForbiddenError`], which is returned as the API will keep
+    /// rejecting the requests
+    fn emit_updates(
+        &mut self,
+        site_id: u32,
+        updates: impl IntoIterator<Item = Result<Overview, SolarApiError>>,
+    ) -> Result<(), SolarApiError>
+    where
+        Self: Sized,
+    {
+        for update in updates {
+            match update {
+                Ok(overview) => self.emit_overview(site_id, &overview)?,
+                Err(e @ SolarApiError::ForbiddenError(_)) => return Err(e),
+                Err(e) => warn!("Could not get overview of site {site_id}: {e}"),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes gauges as lines of the Graphite plaintext protocol, i.e.
+/// `solaredge.1234.current_power_watts 750 1685622000`, to a writer, which is
+/// a connection to Carbon by default
+#[derive(Debug)]
+pub struct GraphiteEmitter<W: Write = TcpStream> {
+    writer: W,
+    prefix: String,
+}
+
+impl GraphiteEmitter {
+    /// Connect to the plaintext listener of Carbon at `addr`, i.e.
+    /// `localhost:2003`
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self, SolarApiError> {
+        let stream = TcpStream::connect(addr).map_err(SolarApiError::EmitterError)?;
+        Ok(GraphiteEmitter::new(stream))
+    }
+}
+
+impl<W: Write> GraphiteEmitter<W> {
+    /// Create an emitter that writes to `writer`
+    pub fn new(writer: W) -> Self {
+        GraphiteEmitter {
+            writer,
+            prefix: "solaredge".to_string(),
+        }
+    }
+
+    /// Set the prefix of the metric paths, `solaredge` by default
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.trim_end_matches('.').to_string();
+        self
+    }
+
+    /// The writer of the emitter
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> GaugeEmitter for GraphiteEmitter<W> {
+    fn emit(
+        &mut self,
+        gauges: &[(String, f64)],
+        timestamp: DateTime<Utc>,
+    ) -> Result<(), SolarApiError> {
+        let lines: String = gauges
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "{} {value} {}\n",
+                    metric_name(&self.prefix, name),
+                    timestamp.timestamp()
+                )
+            })
+            .collect();
+        debug!("Emit {lines:?} to Graphite");
+        self.writer
+            .write_all(lines.as_bytes())
+            .and_then(|()| self.writer.flush())
+            .map_err(SolarApiError::EmitterError)
+    }
+}
+
+/// Sends gauges to StatsD over UDP, i.e. `solaredge.1234.current_power_watts:750|g`,
+/// batched in packets of at most 512 bytes. StatsD sets the time of the gauges
+/// when it receives them
+#[derive(Debug)]
+pub struct StatsdEmitter {
+    socket: UdpSocket,
+    prefix: String,
+}
+
+impl StatsdEmitter {
+    /// Create an emitter that sends to StatsD at `addr`, i.e. `localhost:8125`
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self, SolarApiError> {
+        let connect = || {
+            let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "StatsD address did not resolve")
+            })?;
+            let local = if addr.is_ipv4() {
+                "0.0.0.0:0"
+            } else {
+                "[::]:0"
+            };
+            let socket = UdpSocket::bind(local)?;
+            socket.connect(addr)?;
+            Ok(socket)
+        };
+        let socket = connect().map_err(SolarApiError::EmitterError)?;
+        Ok(StatsdEmitter {
+            socket,
+            prefix: "solaredge".to_string(),
+        })
+    }
+
+    /// Set the prefix of the metric names, `solaredge` by default
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.trim_end_matches('.').to_string();
+        self
+    }
+}
+
+impl GaugeEmitter for StatsdEmitter {
+    fn emit(
+        &mut self,
+        gauges: &[(String, f64)],
+        _timestamp: DateTime<Utc>,
+    ) -> Result<(), SolarApiError> {
+        for packet in statsd_packets(&self.prefix, gauges) {
+            debug!("Emit {packet:?} to StatsD");
+            self.socket
+                .send(packet.as_bytes())
+                .map_err(SolarApiError::EmitterError)?;
+        }
+        Ok(())
+    }
+}
+
+// the gauges of `overview` of `site_id`
+fn overview_gauges(site_id: u32, overview: &Overview) -> Vec<(String, f64)> {
+    vec![
+        (
+            format!("{site_id}.current_power_watts"),
+            as_watts(overview.current_power.power),
+        ),
+        (
+            format!("{site_id}.energy_today_watt_hours"),
+            as_watt_hours(overview.last_day_data.energy),
+        ),
+        (
+            format!("{site_id}.energy_month_watt_hours"),
+            as_watt_hours(overview.last_month_data.energy),
+        ),
+        (
+            format!("{site_id}.energy_year_watt_hours"),
+            as_watt_hours(overview.last_year_data.energy),
+        ),
+        (
+            format!("{site_id}.energy_lifetime_watt_hours"),
+            as_watt_hours(overview.life_time_data.energy),
+        ),
+    ]
+}
+
+// the name of `name` below `prefix`, with characters that the protocols do
+// not allow replaced
+fn metric_name(prefix: &str, name: &str) -> String {
+    let name = if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{prefix}.{name}")
+    };
+    name.replace(|c: char| c.is_whitespace() || c == ':' || c == '|', "_")
+}
+
+// the packets of the lines of `gauges`. A negative gauge is set to zero first,
+// as StatsD reads a signed value as a change of the gauge
+fn statsd_packets(prefix: &str, gauges: &[(String, f64)]) -> Vec<String> {
+    let mut packets: Vec<String> = Vec::new();
+    for (name, value) in gauges {
+        let name = metric_name(prefix, name);
+        let lines = if *value < 0.0 {
+            format!("{name}:0|g\n{name}:{value}|g")
+        } else {
+            format!("{name}:{value}|g")
+        };
+        match packets.last_mut() {
+            Some(packet) if packet.len() + 1 + lines.len() <= MAX_PACKET_SIZE => {
+                packet.push('\n');
+                packet.push_str(&lines);
+            }
+            _ => packets.push(lines),
+        }
+    }
+    packets
+}
+
+#[test]
+fn test_graphite_emitter() {
+    let timestamp = DateTime::from_timestamp(1685622000, 0).unwrap();
+    let mut emitter = GraphiteEmitter::new(Vec::new()).with_prefix("home solar.");
+    emitter
+        .emit(
+            &[
+                ("1234.current_power_watts".to_string(), 750.0),
+                ("1234.energy_today_watt_hours".to_string(), 2000.5),
+            ],
+            timestamp,
+        )
+        .unwrap();
+    assert_eq!(
+        "home_solar.1234.current_power_watts 750 1685622000\n\
+         home_solar.1234.energy_today_watt_hours 2000.5 1685622000\n",
+        String::from_utf8(emitter.into_inner()).unwrap()
+    );
+}
+
+#[test]
+fn test_statsd_emitter() {
+    let gauges: Vec<_> = (0..20)
+        .map(|i| (format!("1234.gauge_{i:02}"), f64::from(i) * 10.0))
+        .collect();
+    let packets = statsd_packets("solaredge", &gauges);
+    assert_eq!(2, packets.len());
+    assert!(packets.iter().all(|packet| packet.len() <= MAX_PACKET_SIZE));
+    assert!(packets[0].starts_with("solaredge.1234.gauge_00:0|g\nsolaredge.1234.gauge_01:10|g\n"));
+    assert_eq!(
+        vec!["1234.battery:0|g\n1234.battery:-250|g".to_string()],
+        statsd_packets("", &[("1234.battery".to_string(), -250.0)])
+    );
+
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut emitter = StatsdEmitter::connect(receiver.local_addr().unwrap()).unwrap();
+    emitter
+        .emit(
+            &[("1234.current_power_watts".to_string(), 750.0)],
+            Utc::now(),
+        )
+        .unwrap();
+    let mut buf = [0; MAX_PACKET_SIZE];
+    let size = receiver.recv(&mut buf).unwrap();
+    assert_eq!(b"solaredge.1234.current_power_watts:750|g", &buf[..size]);
+}
@@ -0,0 +1,209 @@
+#[cfg(test)]
+use crate::units::watts;
+use crate::{
+    series::{TimeSeries, TimeSeriesValue},
+    site::{
+        parse_date_time, parse_optional_energy_wh, parse_optional_power_w, serialize_date_time,
+        serialize_optional_energy_wh, serialize_optional_power_w, TimeUnit,
+    },
+    units::{as_watt_hours, Energy, EnergyUnit, Power},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct EquipmentListReply {
+    reporters: Reporters,
+}
+
+impl EquipmentListReply {
+    pub(crate) fn equipment(self) -> Vec<Equipment> {
+        self.reporters.list
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Reporters {
+    #[serde(rename = "count")]
+    _count: u32,
+    list: Vec<Equipment>,
+}
+
+/// An inverter or other reporting component of a site, see
+/// [`crate::equipment_list`]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Equipment {
+    pub name: String,
+    #[serde(default)]
+    pub manufacturer: String,
+    #[serde(default)]
+    pub model: String,
+    pub serial_number: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct InverterDataReply {
+    pub(crate) data: InverterData,
+}
+
+/// The technical data of an inverter, see [`crate::inverter_data`]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct InverterData {
+    pub count: u32,
+    #[serde(default)]
+    pub telemetries: Vec<InverterTelemetry>,
+}
+
+impl InverterData {
+    /// The telemetry ordered by timestamp
+    pub fn sorted_telemetries(&self) -> Vec<&InverterTelemetry> {
+        let mut telemetries: Vec<_> = self.telemetries.iter().collect();
+        telemetries.sort_by_key(|telemetry| telemetry.date);
+        telemetries
+    }
+
+    /// The energy produced between the first and last telemetry, using the
+    /// lifetime energy of the inverter. Returns None when there are less than
+    /// two telemetries with a lifetime energy
+    pub fn energy(&self) -> Option<Energy> {
+        let total_energy: Vec<f64> = self
+            .sorted_telemetries()
+            .into_iter()
+            .filter_map(|telemetry| telemetry.total_energy.map(as_watt_hours))
+            .collect();
+        match total_energy.as_slice() {
+            [first, .., last] => Some(EnergyUnit::WattHour.energy((last - first).max(0.0))),
+            _ => None,
+        }
+    }
+
+    /// The total active power per timestamp
+    pub fn power(&self) -> TimeSeries<Power> {
+        let values = self
+            .sorted_telemetries()
+            .into_iter()
+            .map(|telemetry| TimeSeriesValue {
+                date: telemetry.date,
+                value: telemetry.total_active_power,
+            })
+            .collect();
+        TimeSeries::new(TimeUnit::QuarterOfAnHour, "W", values)
+    }
+
+    /// The JSON of this reply as returned by [`crate::inverter_data`]
+    pub fn to_api_json(&self) -> serde_json::Value {
+        serde_json::json!({ "data": self })
+    }
+}
+
+/// A measurement of an inverter
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InverterTelemetry {
+    #[serde(
+        deserialize_with = "parse_date_time",
+        serialize_with = "serialize_date_time"
+    )]
+    pub date: chrono::NaiveDateTime,
+    /// the AC power of all phases
+    #[serde(
+        default,
+        deserialize_with = "parse_optional_power_w",
+        serialize_with = "serialize_optional_power_w"
+    )]
+    pub total_active_power: Option<Power>,
+    #[serde(default)]
+    pub dc_voltage: Option<f64>,
+    /// in kOhm
+    #[serde(default)]
+    pub ground_fault_resistance: Option<f64>,
+    /// in percent
+    #[serde(default)]
+    pub power_limit: Option<f64>,
+    /// the energy produced during the lifetime of the inverter
+    #[serde(
+        default,
+        deserialize_with = "parse_optional_energy_wh",
+        serialize_with = "serialize_optional_energy_wh"
+    )]
+    pub total_energy: Option<Energy>,
+    /// in degrees Celsius
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    /// i.e. `MPPT` during normal production
+    #[serde(default)]
+    pub inverter_mode: Option<String>,
+    #[serde(default)]
+    pub operation_mode: Option<u32>,
+    #[serde(rename = "L1Data", default, skip_serializing_if = "Option::is_none")]
+    pub l1_data: Option<PhaseData>,
+    #[serde(rename = "L2Data", default, skip_serializing_if = "Option::is_none")]
+    pub l2_data: Option<PhaseData>,
+    #[serde(rename = "L3Data", default, skip_serializing_if = "Option::is_none")]
+    pub l3_data: Option<PhaseData>,
+}
+
+/// The AC measurements of one phase of an inverter
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseData {
+    #[serde(default)]
+    pub ac_current: Option<f64>,
+    #[serde(default)]
+    pub ac_voltage: Option<f64>,
+    #[serde(default)]
+    pub ac_frequency: Option<f64>,
+    #[serde(default)]
+    pub apparent_power: Option<f64>,
+    #[serde(default)]
+    pub active_power: Option<f64>,
+    #[serde(default)]
+    pub reactive_power: Option<f64>,
+    #[serde(default)]
+    pub cos_phi: Option<f64>,
+}
+
+#[test]
+fn test_equipment_list() {
+    let reply = r#"
+    {"reporters":{"count":2,"list":[
+        {"name":"Inverter 1","manufacturer":"SolarEdge","model":"SE16K","serialNumber":"1234-01"},
+        {"name":"Inverter 2","manufacturer":"SolarEdge","model":"SE16K","serialNumber":"1234-02"}
+    ]}}
+    "#;
+    let equipment = serde_json::from_str::<EquipmentListReply>(reply)
+        .unwrap()
+        .equipment();
+    assert_eq!(2, equipment.len());
+    assert_eq!("1234-02", equipment[1].serial_number);
+}
+
+#[test]
+fn test_inverter_data() {
+    let reply = r#"
+    {"data":{"count":2,"telemetries":[
+        {"date":"2023-06-01 12:05:00","totalActivePower":4000.0,"dcVoltage":750.0,
+         "groundFaultResistance":11000.0,"powerLimit":100.0,"totalEnergy":1001000.0,
+         "temperature":45.0,"inverterMode":"MPPT","operationMode":0,
+         "L1Data":{"acCurrent":6.0,"acVoltage":230.0,"acFrequency":50.0,
+                   "apparentPower":1380.0,"activePower":1350.0,"reactivePower":20.0,"cosPhi":1.0}},
+        {"date":"2023-06-01 12:00:00","totalActivePower":3500.0,"totalEnergy":1000000.0}
+    ]}}
+    "#;
+    let data = serde_json::from_str::<InverterDataReply>(reply)
+        .unwrap()
+        .data;
+
+    assert_eq!(Some(1000.0), data.energy().map(as_watt_hours));
+    assert_eq!(Some(watts(3500.0)), data.power().values[0].value);
+    assert_eq!(
+        Some(230.0),
+        data.telemetries[0].l1_data.and_then(|l1| l1.ac_voltage)
+    );
+
+    let json: serde_json::Value = serde_json::from_str(reply).unwrap();
+    assert_eq!(
+        json["data"]["telemetries"][0],
+        data.to_api_json()["data"]["telemetries"][0]
+    );
+}
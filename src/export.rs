@@ -0,0 +1,11 @@
+//! Writes the data of sites to file formats for other tools, see the
+//! submodules per format
+
+pub mod csv;
+pub mod influx;
+pub mod ndjson;
+#[cfg(feature = "arrow")]
+pub mod parquet;
+pub mod schema;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
@@ -0,0 +1,313 @@
+//! Writes series to CSV with a row per timestamp and a column per series, see
+//! [`CsvTable`]
+//!
+//! ```no_run
+//! # use solar_api::{Client, TimeUnit};
+//! # use solar_api::export::csv::{CsvOptions, CsvTable};
+//! # use solar_api::chrono::NaiveDate;
+//! # use solar_api::EnergyUnit;
+//! let client = Client::new("API_KEY");
+//! let start = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+//! let end = NaiveDate::from_ymd_opt(2023, 6, 30).unwrap().and_hms_opt(0, 0, 0).unwrap();
+//! let details = client.energy_details(1234, start, end, TimeUnit::Day, &[])?;
+//! let csv = CsvTable::new(CsvOptions::new().with_energy_unit(EnergyUnit::KilowattHour))
+//!     .with_energy_details(&details)?
+//!     .to_string();
+//! std::fs::write("june.csv", csv).unwrap();
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use crate::{
+    meters::{EnergyDetails, PowerDetails},
+    series::TimeSeries,
+    storage::StorageData,
+    units::{Energy, EnergyUnit, Power, PowerUnit},
+    SolarApiError,
+};
+#[cfg(test)]
+use crate::{
+    series::TimeSeriesValue,
+    site::TimeUnit,
+    units::{watt_hours, watts},
+};
+use chrono::NaiveDateTime;
+use std::{collections::BTreeMap, fmt, io};
+
+/// The format of the CSV written by [`CsvTable`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    timestamp_format: String,
+    energy_unit: EnergyUnit,
+    power_unit: PowerUnit,
+    delimiter: char,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            timestamp_format: "%Y-%m-%d %H:%M:%S".to_string(),
+            energy_unit: EnergyUnit::WattHour,
+            power_unit: PowerUnit::Watt,
+            delimiter: ',',
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Create options for timestamps like `2023-06-01 12:00:00`, energy in Wh,
+    /// power in W and `,` as delimiter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the format of the timestamps, see [`chrono::format::strftime`], i.e.
+    /// `%Y-%m-%dT%H:%M:%S` for ISO 8601
+    pub fn with_timestamp_format(mut self, timestamp_format: &str) -> Self {
+        self.timestamp_format = timestamp_format.to_string();
+        self
+    }
+
+    /// Set the unit of energy columns
+    pub fn with_energy_unit(mut self, energy_unit: EnergyUnit) -> Self {
+        self.energy_unit = energy_unit;
+        self
+    }
+
+    /// Set the unit of power columns
+    pub fn with_power_unit(mut self, power_unit: PowerUnit) -> Self {
+        self.power_unit = power_unit;
+        self
+    }
+
+    /// Set the delimiter between the columns, i.e. `;` for spreadsheets that
+    /// use `,` as decimal separator
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+}
+
+/// A table of series joined on their timestamps. The first column is the
+/// timestamp, followed by a column per added series with its unit in the
+/// header, i.e. `Production (kWh)`. Cells of series without a value at a
+/// timestamp are empty
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvTable {
+    options: CsvOptions,
+    headers: Vec<String>,
+    rows: BTreeMap<NaiveDateTime, Vec<Option<f64>>>,
+}
+
+impl CsvTable {
+    /// Create a table without columns
+    pub fn new(options: CsvOptions) -> Self {
+        CsvTable {
+            options,
+            headers: Vec::new(),
+            rows: BTreeMap::new(),
+        }
+    }
+
+    /// Add a column `name` of `series`, where `value` converts the values to
+    /// numbers in `unit`
+    pub fn with_column<Q: Copy>(
+        mut self,
+        name: &str,
+        unit: &str,
+        series: &TimeSeries<Q>,
+        value: impl Fn(Q) -> f64,
+    ) -> Self {
+        let column = self.headers.len();
+        self.headers.push(format!("{name} ({unit})"));
+        for v in series.iter() {
+            let row = self.rows.entry(v.date).or_default();
+            row.resize(column + 1, None);
+            row[column] = v.value.map(&value);
+        }
+        self
+    }
+
+    /// Add a column of energy in the energy unit of the options
+    pub fn with_energy(self, name: &str, series: &TimeSeries<Energy>) -> Self {
+        let unit = self.options.energy_unit;
+        self.with_column(name, unit.symbol(), series, |energy| unit.value_of(energy))
+    }
+
+    /// Add a column of power in the power unit of the options
+    pub fn with_power(self, name: &str, series: &TimeSeries<Power>) -> Self {
+        let unit = self.options.power_unit;
+        self.with_column(name, unit.symbol(), series, |power| unit.value_of(power))
+    }
+
+    /// Add a column of energy per meter of `details`
+    pub fn with_energy_details(mut self, details: &EnergyDetails) -> Result<Self, SolarApiError> {
+        for meter in details.meters() {
+            if let Some(series) = details.series(meter)? {
+                self = self.with_energy(meter.to_param(), &series);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Add a column of power per meter of `details`
+    pub fn with_power_details(mut self, details: &PowerDetails) -> Result<Self, SolarApiError> {
+        for meter in details.meters() {
+            if let Some(series) = details.series(meter)? {
+                self = self.with_power(meter.to_param(), &series);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Add the power and state of charge of every battery of `storage`, named
+    /// after the serial number of the battery
+    pub fn with_storage(mut self, storage: &StorageData) -> Self {
+        for battery in &storage.batteries {
+            self = self
+                .with_power(
+                    &format!("{} power", battery.serial_number),
+                    &battery.power(),
+                )
+                .with_column(
+                    &format!("{} state of charge", battery.serial_number),
+                    "%",
+                    &battery.state_of_charge(),
+                    |percentage| percentage,
+                );
+        }
+        self
+    }
+
+    /// Write the table as CSV to `writer`
+    pub fn write_to(&self, mut writer: impl io::Write) -> io::Result<()> {
+        write!(writer, "{self}")
+    }
+
+    // the headers of the columns after the timestamp
+    #[cfg(feature = "xlsx")]
+    pub(crate) fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    // the values per timestamp. A row is shorter than the headers when the
+    // last columns have no value at that timestamp
+    #[cfg(feature = "xlsx")]
+    pub(crate) fn rows(&self) -> &BTreeMap<NaiveDateTime, Vec<Option<f64>>> {
+        &self.rows
+    }
+
+    // quotes `cell` when it contains the delimiter, a quote or a line break
+    fn escape(&self, cell: &str) -> String {
+        if cell.contains([self.options.delimiter, '"', '\n', '\r']) {
+            format!("\"{}\"", cell.replace('"', "\"\""))
+        } else {
+            cell.to_string()
+        }
+    }
+}
+
+impl fmt::Display for CsvTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let delimiter = self.options.delimiter;
+        write!(f, "{}", self.escape("timestamp"))?;
+        for header in &self.headers {
+            write!(f, "{delimiter}{}", self.escape(header))?;
+        }
+        writeln!(f)?;
+        for (date, row) in &self.rows {
+            let timestamp = date.format(&self.options.timestamp_format).to_string();
+            write!(f, "{}", self.escape(&timestamp))?;
+            for column in 0..self.headers.len() {
+                match row.get(column).copied().flatten() {
+                    Some(value) => write!(f, "{delimiter}{value}")?,
+                    None => write!(f, "{delimiter}")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_csv_table() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let energy = TimeSeries::new(
+        TimeUnit::Hour,
+        "Wh",
+        vec![
+            TimeSeriesValue {
+                date: date("2023-06-01 12:00:00"),
+                value: Some(watt_hours(1500.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-06-01 13:00:00"),
+                value: None,
+            },
+        ],
+    );
+    let power = TimeSeries::new(
+        TimeUnit::Hour,
+        "W",
+        vec![TimeSeriesValue {
+            date: date("2023-06-01 14:00:00"),
+            value: Some(watts(250.0)),
+        }],
+    );
+
+    let table = CsvTable::new(
+        CsvOptions::new()
+            .with_energy_unit(EnergyUnit::KilowattHour)
+            .with_timestamp_format("%Y-%m-%dT%H:%M"),
+    )
+    .with_energy("Production", &energy)
+    .with_power("Power", &power);
+    assert_eq!(
+        "timestamp,Production (kWh),Power (W)
+2023-06-01T12:00,1.5,
+2023-06-01T13:00,,
+2023-06-01T14:00,,250
+",
+        table.to_string()
+    );
+
+    let table = CsvTable::new(CsvOptions::new().with_delimiter(';')).with_column(
+        "a;b",
+        "%",
+        &energy,
+        |_| 0.5,
+    );
+    let mut written = Vec::new();
+    table.write_to(&mut written).unwrap();
+    assert_eq!(
+        "timestamp;\"a;b (%)\"\n2023-06-01 12:00:00;0.5\n2023-06-01 13:00:00;\n",
+        String::from_utf8(written).unwrap()
+    );
+}
+
+#[test]
+fn test_csv_energy_details() {
+    let reply = r#"
+    {"energyDetails":{
+        "timeUnit":"DAY",
+        "unit":"Wh",
+        "meters":[
+            {"type":"FeedIn","values":[{"date":"2023-06-01 00:00:00","value":12.5}]},
+            {"type":"Purchased","values":[{"date":"2023-06-02 00:00:00","value":3.0}]}
+        ]}}
+    "#;
+    let details = serde_json::from_str::<crate::meters::EnergyDetailsReply>(reply)
+        .unwrap()
+        .energy_details;
+    let csv = CsvTable::new(CsvOptions::new())
+        .with_energy_details(&details)
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        "timestamp,FeedIn (Wh),Purchased (Wh)
+2023-06-01 00:00:00,12.5,
+2023-06-02 00:00:00,,3
+",
+        csv
+    );
+}
@@ -0,0 +1,280 @@
+//! Writes series and overviews as [InfluxDB line
+//! protocol](https://docs.influxdata.com/influxdb/v2/reference/syntax/line-protocol/),
+//! ready to POST to the write API of InfluxDB or VictoriaMetrics, see
+//! [`LineProtocolWriter`].
+//!
+//! Energy is written to the measurement `energy` with the field `wh` and power
+//! to the measurement `power` with the field `w`. Every line has the tag
+//! `site_id` and the lines of energy and power details also have the tag
+//! `meter`, i.e. `energy,site_id=1234,meter=Production wh=1500 1685613600000000000`.
+//! Overviews are written to the measurement `overview` with the fields
+//! `current_power_w`, `last_day_energy_wh`, `last_month_energy_wh`,
+//! `last_year_energy_wh` and `life_time_energy_wh`. Timestamps are in
+//! nanoseconds. Values the API did not return are left out, as line protocol
+//! has no null values.
+//!
+//! ```
+//! # use solar_api::export::influx::LineProtocolWriter;
+//! # use solar_api::{TimeSeries, TimeSeriesValue, TimeUnit};
+//! # use solar_api::units::watt_hours;
+//! # use solar_api::chrono::{NaiveDate, Utc};
+//! let date = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+//! let energy = TimeSeries::new(TimeUnit::Day, "Wh", vec![TimeSeriesValue {
+//!     date,
+//!     value: Some(watt_hours(12500.0)),
+//! }]);
+//! let mut writer = LineProtocolWriter::new(Vec::new()).with_timezone(Utc);
+//! writer.write_energy(1234, &energy)?;
+//! assert_eq!(
+//!     "energy,site_id=1234 wh=12500 1685577600000000000\n",
+//!     String::from_utf8(writer.into_inner()).unwrap()
+//! );
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use crate::{
+    meters::{EnergyDetails, PowerDetails},
+    series::TimeSeries,
+    site::Overview,
+    timezone::localize,
+    units::{as_watt_hours, as_watts, Energy, Power},
+    SolarApiError,
+};
+#[cfg(test)]
+use crate::{series::TimeSeriesValue, site::TimeUnit};
+use chrono::{Local, NaiveDateTime, TimeZone};
+use std::{fmt::Write as _, io};
+
+/// Writes lines of InfluxDB line protocol to a writer. The API returns
+/// timestamps in the local time of the site, so set the time zone of the site
+/// with [`LineProtocolWriter::with_timezone`] when it differs from the local
+/// time zone
+#[derive(Debug)]
+pub struct LineProtocolWriter<W: io::Write, Tz: TimeZone = Local> {
+    writer: W,
+    tz: Tz,
+}
+
+impl<W: io::Write> LineProtocolWriter<W> {
+    /// Create a writer that writes to `writer` and uses the local time zone
+    pub fn new(writer: W) -> Self {
+        LineProtocolWriter { writer, tz: Local }
+    }
+}
+
+impl<W: io::Write, Tz: TimeZone> LineProtocolWriter<W, Tz> {
+    /// Use `tz` as the time zone of the site, i.e. from
+    /// [`Location::tz`](crate::Location)
+    pub fn with_timezone<Tz2: TimeZone>(self, tz: Tz2) -> LineProtocolWriter<W, Tz2> {
+        LineProtocolWriter {
+            writer: self.writer,
+            tz,
+        }
+    }
+
+    /// Flush and return the underlying writer
+    pub fn into_inner(mut self) -> W {
+        let _ = self.writer.flush();
+        self.writer
+    }
+
+    /// Write a line to the measurement `energy` per value of `energy`
+    pub fn write_energy(
+        &mut self,
+        site_id: u32,
+        energy: &TimeSeries<Energy>,
+    ) -> Result<(), SolarApiError> {
+        self.write_series("energy", site_id, None, "wh", energy, as_watt_hours)
+    }
+
+    /// Write a line to the measurement `power` per value of `power`
+    pub fn write_power(
+        &mut self,
+        site_id: u32,
+        power: &TimeSeries<Power>,
+    ) -> Result<(), SolarApiError> {
+        self.write_series("power", site_id, None, "w", power, as_watts)
+    }
+
+    /// Write the series of every meter of `details` to the measurement
+    /// `energy`, tagged with the meter
+    pub fn write_energy_details(
+        &mut self,
+        site_id: u32,
+        details: &EnergyDetails,
+    ) -> Result<(), SolarApiError> {
+        for meter in details.meters() {
+            if let Some(energy) = details.series(meter)? {
+                let meter = meter.to_param();
+                self.write_series("energy", site_id, Some(meter), "wh", &energy, as_watt_hours)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the series of every meter of `details` to the measurement
+    /// `power`, tagged with the meter
+    pub fn write_power_details(
+        &mut self,
+        site_id: u32,
+        details: &PowerDetails,
+    ) -> Result<(), SolarApiError> {
+        for meter in details.meters() {
+            if let Some(power) = details.series(meter)? {
+                let meter = meter.to_param();
+                self.write_series("power", site_id, Some(meter), "w", &power, as_watts)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a line to the measurement `overview` with the current power and
+    /// the energy of `overview`, at the time the site last updated. Writes
+    /// nothing for a site that has not communicated yet
+    pub fn write_overview(
+        &mut self,
+        site_id: u32,
+        overview: &Overview,
+    ) -> Result<(), SolarApiError> {
+        let Some(last_updated) = overview.last_updated_time else {
+            return Ok(());
+        };
+        let fields = [
+            ("current_power_w", as_watts(overview.current_power.power)),
+            (
+                "last_day_energy_wh",
+                as_watt_hours(overview.last_day_data.energy),
+            ),
+            (
+                "last_month_energy_wh",
+                as_watt_hours(overview.last_month_data.energy),
+            ),
+            (
+                "last_year_energy_wh",
+                as_watt_hours(overview.last_year_data.energy),
+            ),
+            (
+                "life_time_energy_wh",
+                as_watt_hours(overview.life_time_data.energy),
+            ),
+        ];
+        self.write_line("overview", site_id, None, &fields, last_updated)
+    }
+
+    // writes a line per value of `values` with `value` as `field`
+    fn write_series<Q: Copy>(
+        &mut self,
+        measurement: &str,
+        site_id: u32,
+        meter: Option<&str>,
+        field: &str,
+        values: &TimeSeries<Q>,
+        value: impl Fn(Q) -> f64,
+    ) -> Result<(), SolarApiError> {
+        for v in values.iter() {
+            if let Some(quantity) = v.value {
+                self.write_line(
+                    measurement,
+                    site_id,
+                    meter,
+                    &[(field, value(quantity))],
+                    v.date,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    // writes a line with `fields` at `date` in the time zone of the site
+    fn write_line(
+        &mut self,
+        measurement: &str,
+        site_id: u32,
+        meter: Option<&str>,
+        fields: &[(&str, f64)],
+        date: NaiveDateTime,
+    ) -> Result<(), SolarApiError> {
+        let timestamp = localize(date, &self.tz)?
+            .timestamp_nanos_opt()
+            .ok_or(SolarApiError::InvalidLocalTime(date))?;
+        let mut line = format!("{},site_id={site_id}", escape(measurement));
+        if let Some(meter) = meter {
+            let _ = write!(line, ",meter={}", escape(meter));
+        }
+        for (i, (field, value)) in fields.iter().enumerate() {
+            let separator = if i == 0 { ' ' } else { ',' };
+            let _ = write!(line, "{separator}{}={value}", escape(field));
+        }
+        let _ = writeln!(line, " {timestamp}");
+        self.writer
+            .write_all(line.as_bytes())
+            .map_err(SolarApiError::ExportIo)
+    }
+}
+
+// escapes the characters with a meaning in measurements, tags and field keys
+fn escape(s: &str) -> String {
+    s.replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+#[test]
+fn test_line_protocol_writer() {
+    let reply = r#"
+    {"powerDetails":{
+        "timeUnit":"QUARTER_OF_AN_HOUR",
+        "unit":"W",
+        "meters":[
+            {"type":"Production","values":[
+                {"date":"2023-06-01 12:00:00","value":1500.5},
+                {"date":"2023-06-01 12:15:00"}
+            ]}
+        ]}}
+    "#;
+    let details = serde_json::from_str::<crate::meters::PowerDetailsReply>(reply)
+        .unwrap()
+        .power_details;
+    let reply = r#"
+    {"overview":{
+        "lastUpdateTime":"2023-06-01 12:20:00",
+        "lifeTimeData":{"energy":1000000.0,"revenue":200.0},
+        "lastYearData":{"energy":500000.0},
+        "lastMonthData":{"energy":20000.0},
+        "lastDayData":{"energy":2000.0},
+        "currentPower":{"power":750.0},
+        "measuredBy":"INVERTER"
+    }}
+    "#;
+    let overview = serde_json::from_str::<crate::site::OverviewReply>(reply)
+        .unwrap()
+        .overview;
+    let tz = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+
+    let mut writer = LineProtocolWriter::new(Vec::new()).with_timezone(tz);
+    writer.write_power_details(1234, &details).unwrap();
+    writer.write_overview(1234, &overview).unwrap();
+    assert_eq!(
+        "power,site_id=1234,meter=Production w=1500.5 1685613600000000000
+overview,site_id=1234 current_power_w=750,last_day_energy_wh=2000,last_month_energy_wh=20000,last_year_energy_wh=500000,life_time_energy_wh=1000000 1685614800000000000
+",
+        String::from_utf8(writer.into_inner()).unwrap()
+    );
+
+    let date = NaiveDateTime::parse_from_str("2023-06-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+    let energy = TimeSeries::new(
+        TimeUnit::Day,
+        "Wh",
+        vec![TimeSeriesValue {
+            date,
+            value: Some(crate::units::watt_hours(1.0)),
+        }],
+    );
+    let mut writer = LineProtocolWriter::new(Vec::new()).with_timezone(chrono::Utc);
+    writer.write_energy(1, &energy).unwrap();
+    assert_eq!(
+        "energy,site_id=1 wh=1 1685577600000000000\n",
+        String::from_utf8(writer.into_inner()).unwrap()
+    );
+    assert_eq!("a\\ b\\,c\\=d", escape("a b,c=d"));
+}
@@ -0,0 +1,217 @@
+//! Writes site metadata and series as newline delimited JSON, one record per
+//! line, for tools like `jq`, Logstash or BigQuery, see [`NdjsonWriter`]. The
+//! records have a stable schema with the type of record in the `record` field.
+//!
+//! A `site` record has the fields `site_id`, `name`, `status`,
+//! `peak_power_w`, `installation_date` (`2023-06-01` or null), `time_zone`,
+//! `country_code`, `latitude` and `longitude` (null when unknown).
+//!
+//! A `value` record has the fields of a [`Reading`] of the normalized
+//! [`schema`](super::schema), i.e. `schema_version`, `site_id`, `channel`,
+//! `timestamp`, `value`, `unit` and `quality`.
+//!
+//! ```
+//! # use solar_api::export::ndjson::NdjsonWriter;
+//! # use solar_api::{TimeSeries, TimeSeriesValue, TimeUnit};
+//! # use solar_api::units::watt_hours;
+//! # use solar_api::chrono::NaiveDate;
+//! let date = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+//! let energy = TimeSeries::new(TimeUnit::Day, "Wh", vec![TimeSeriesValue {
+//!     date,
+//!     value: Some(watt_hours(12500.0)),
+//! }]);
+//! let mut writer = NdjsonWriter::new(Vec::new());
+//! writer.write_energy(1234, "energy", &energy)?;
+//! assert_eq!(
+//!     r#"{"record":"value","schema_version":1,"site_id":1234,"channel":"energy","timestamp":"2023-06-01 00:00:00","value":12500.0,"unit":"Wh","quality":"measured"}
+//! "#,
+//!     String::from_utf8(writer.into_inner()).unwrap()
+//! );
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use super::schema::{self, Reading};
+use crate::{
+    meters::{EnergyDetails, PowerDetails},
+    series::TimeSeries,
+    site::Site,
+    storage::StorageData,
+    units::{as_watts, Energy, Power},
+    SolarApiError,
+};
+#[cfg(test)]
+use crate::{series::TimeSeriesValue, site::TimeUnit};
+use serde::Serialize;
+use std::io;
+
+// a line of the export
+#[derive(Debug, Serialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum Record<'a> {
+    Site {
+        site_id: u32,
+        name: &'a str,
+        status: &'a str,
+        peak_power_w: f64,
+        installation_date: Option<String>,
+        time_zone: &'a str,
+        country_code: &'a str,
+        latitude: Option<f64>,
+        longitude: Option<f64>,
+    },
+    Value(&'a Reading),
+}
+
+/// Writes records as newline delimited JSON to a writer. Every record is
+/// written at once, so the output can be streamed
+#[derive(Debug)]
+pub struct NdjsonWriter<W: io::Write> {
+    writer: W,
+}
+
+impl<W: io::Write> NdjsonWriter<W> {
+    /// Create a writer that writes to `writer`
+    pub fn new(writer: W) -> Self {
+        NdjsonWriter { writer }
+    }
+
+    /// Flush and return the underlying writer
+    pub fn into_inner(mut self) -> W {
+        let _ = self.writer.flush();
+        self.writer
+    }
+
+    /// Write a `site` record with the metadata of `site`
+    pub fn write_site(&mut self, site: &Site) -> Result<(), SolarApiError> {
+        self.write(&Record::Site {
+            site_id: site.id,
+            name: &site.name,
+            status: &site.status,
+            peak_power_w: as_watts(site.peak_power),
+            installation_date: site
+                .installation_date
+                .map(|date| date.format("%Y-%m-%d").to_string()),
+            time_zone: &site.location.time_zone,
+            country_code: &site.location.country_code,
+            latitude: site.location.latitude,
+            longitude: site.location.longitude,
+        })
+    }
+
+    /// Write a `value` record in Wh per value of `energy` of `site_id`
+    pub fn write_energy(
+        &mut self,
+        site_id: u32,
+        channel: &str,
+        energy: &TimeSeries<Energy>,
+    ) -> Result<(), SolarApiError> {
+        self.write_readings(&schema::energy_readings(site_id, channel, energy))
+    }
+
+    /// Write a `value` record in W per value of `power` of `site_id`
+    pub fn write_power(
+        &mut self,
+        site_id: u32,
+        channel: &str,
+        power: &TimeSeries<Power>,
+    ) -> Result<(), SolarApiError> {
+        self.write_readings(&schema::power_readings(site_id, channel, power))
+    }
+
+    /// Write the series of every meter of `details`
+    pub fn write_energy_details(
+        &mut self,
+        site_id: u32,
+        details: &EnergyDetails,
+    ) -> Result<(), SolarApiError> {
+        self.write_readings(&schema::energy_details_readings(site_id, details)?)
+    }
+
+    /// Write the series of every meter of `details`
+    pub fn write_power_details(
+        &mut self,
+        site_id: u32,
+        details: &PowerDetails,
+    ) -> Result<(), SolarApiError> {
+        self.write_readings(&schema::power_details_readings(site_id, details)?)
+    }
+
+    /// Write the power and state of charge of every battery of `storage`
+    pub fn write_storage(
+        &mut self,
+        site_id: u32,
+        storage: &StorageData,
+    ) -> Result<(), SolarApiError> {
+        self.write_readings(&schema::storage_readings(site_id, storage))
+    }
+
+    /// Write a `value` record per reading of `readings`
+    pub fn write_readings(&mut self, readings: &[Reading]) -> Result<(), SolarApiError> {
+        readings
+            .iter()
+            .try_for_each(|reading| self.write(&Record::Value(reading)))
+    }
+
+    // writes `record` on a line
+    fn write(&mut self, record: &Record) -> Result<(), SolarApiError> {
+        serde_json::to_writer(&mut self.writer, record)
+            .map_err(io::Error::from)
+            .and_then(|_| self.writer.write_all(b"\n"))
+            .map_err(SolarApiError::ExportIo)
+    }
+}
+
+#[test]
+fn test_ndjson_writer() {
+    let reply = r#"
+    {"storageData":{
+        "batteryCount":1,
+        "batteries":[{
+            "nameplate":10000.0,
+            "serialNumber":"BAT-1",
+            "telemetries":[
+                {"timeStamp":"2023-06-01 12:00:00","power":1000.0,
+                 "lifeTimeEnergyDischarged":1000.0,"lifeTimeEnergyCharged":3000.0,
+                 "batteryPercentageState":60.0}
+            ]}]}}
+    "#;
+    let storage = serde_json::from_str::<crate::storage::StorageDataReply>(reply)
+        .unwrap()
+        .storage_data;
+    let date =
+        chrono::NaiveDateTime::parse_from_str("2023-06-01 12:15:00", "%Y-%m-%d %H:%M:%S").unwrap();
+    let power = TimeSeries::new(
+        TimeUnit::QuarterOfAnHour,
+        "W",
+        vec![TimeSeriesValue {
+            date,
+            value: None::<Power>,
+        }],
+    );
+
+    let mut writer = NdjsonWriter::new(Vec::new());
+    writer.write_storage(1234, &storage).unwrap();
+    writer.write_power(1234, "power", &power).unwrap();
+    let output = String::from_utf8(writer.into_inner()).unwrap();
+    let lines: Vec<serde_json::Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(3, lines.len());
+    assert_eq!("storage.BAT-1.power", lines[0]["channel"]);
+    assert_eq!(1000.0, lines[0]["value"]);
+    assert_eq!("%", lines[1]["unit"]);
+    assert_eq!(
+        serde_json::json!({
+            "record": "value",
+            "schema_version": 1,
+            "site_id": 1234,
+            "channel": "power",
+            "timestamp": "2023-06-01 12:15:00",
+            "value": null,
+            "unit": "W",
+            "quality": "missing"
+        }),
+        lines[2]
+    );
+}
@@ -0,0 +1,311 @@
+//! Writes series to Parquet files partitioned by site and month, for loading
+//! years of data into data warehouses and data frame libraries, see
+//! [`ParquetWriter`]. Requires the `arrow` feature.
+//!
+//! The files have a row per value with the columns of a [`Reading`] of the
+//! normalized [`schema`](super::schema): `site_id` (uint32), `channel`
+//! (string), `timestamp` (timestamp in seconds without time zone, in the local
+//! time of the site), `value` (float64, null when the API had no value),
+//! `unit` (string) and `quality` (string). The version of the schema is
+//! `schema_version` in the metadata of the Arrow schema of the files.
+//!
+//! ```no_run
+//! # use solar_api::export::parquet::ParquetWriter;
+//! # use solar_api::{Client, DataPeriod, TimeUnit};
+//! # use solar_api::chrono::NaiveDate;
+//! let client = Client::new("API_KEY");
+//! let period = DataPeriod::between(
+//!     NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+//!     NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(),
+//! )?;
+//! let energy = client.energy(1234, period, TimeUnit::Day)?.to_series()?;
+//! let mut writer = ParquetWriter::new("data");
+//! writer.add_energy(1234, "energy", &energy);
+//! // writes data/site_id=1234/month=2023-01/data.parquet and so on
+//! let files = writer.finish()?;
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use super::schema::{self, Quality, Reading};
+use crate::{
+    meters::{EnergyDetails, PowerDetails},
+    record_batch::ToRecordBatch,
+    series::TimeSeries,
+    units::{Energy, Power},
+    SolarApiError,
+};
+#[cfg(test)]
+use crate::{series::TimeSeriesValue, site::TimeUnit, units::watt_hours};
+use arrow_array::{
+    Array, Float64Array, RecordBatch, StringArray, TimestampSecondArray, UInt32Array,
+};
+#[cfg(test)]
+use chrono::NaiveDateTime;
+use chrono::{DateTime, Datelike};
+use parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
+    errors::ParquetError,
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+/// Collects series of sites and writes them to Parquet files in a directory,
+/// with a file per site and month at `site_id=1234/month=2023-06/data.parquet`.
+/// This layout is understood as partitions by tools like DuckDB, Spark and
+/// polars. Files of a partition are replaced when data of that site and month
+/// is written again, so add all data of a month before calling
+/// [`ParquetWriter::finish`] or use [`ParquetWriter::with_merge`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParquetWriter {
+    dir: PathBuf,
+    merge: bool,
+    // the readings per site and month
+    partitions: BTreeMap<(u32, i32, u32), Vec<Reading>>,
+}
+
+impl ParquetWriter {
+    /// Create a writer that writes to `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ParquetWriter {
+            dir: dir.into(),
+            merge: false,
+            partitions: BTreeMap::new(),
+        }
+    }
+
+    /// Keep the readings in existing files of a partition that are not added
+    /// again, so the data of a month can be written in several runs, i.e. by a
+    /// backfill that resumes the next day. Readings with the same channel and
+    /// timestamp are replaced
+    pub fn with_merge(mut self) -> Self {
+        self.merge = true;
+        self
+    }
+
+    /// Add the values of `energy` of `site_id` in Wh
+    pub fn add_energy(&mut self, site_id: u32, channel: &str, energy: &TimeSeries<Energy>) {
+        self.add_readings(schema::energy_readings(site_id, channel, energy));
+    }
+
+    /// Add the values of `power` of `site_id` in W
+    pub fn add_power(&mut self, site_id: u32, channel: &str, power: &TimeSeries<Power>) {
+        self.add_readings(schema::power_readings(site_id, channel, power));
+    }
+
+    /// Add the series of every meter of `details`
+    pub fn add_energy_details(
+        &mut self,
+        site_id: u32,
+        details: &EnergyDetails,
+    ) -> Result<(), SolarApiError> {
+        self.add_readings(schema::energy_details_readings(site_id, details)?);
+        Ok(())
+    }
+
+    /// Add the series of every meter of `details`
+    pub fn add_power_details(
+        &mut self,
+        site_id: u32,
+        details: &PowerDetails,
+    ) -> Result<(), SolarApiError> {
+        self.add_readings(schema::power_details_readings(site_id, details)?);
+        Ok(())
+    }
+
+    /// Add `readings`, i.e. of several sites
+    pub fn add_readings(&mut self, readings: impl IntoIterator<Item = Reading>) {
+        for reading in readings {
+            let timestamp = reading.timestamp;
+            self.partitions
+                .entry((reading.site_id, timestamp.year(), timestamp.month()))
+                .or_default()
+                .push(reading);
+        }
+    }
+
+    /// Write a file per site and month and return the paths of the files
+    pub fn finish(self) -> Result<Vec<PathBuf>, SolarApiError> {
+        let mut files = Vec::new();
+        for ((site_id, year, month), mut readings) in self.partitions {
+            let dir = self
+                .dir
+                .join(format!("site_id={site_id}"))
+                .join(format!("month={year}-{month:02}"));
+            std::fs::create_dir_all(&dir).map_err(SolarApiError::ExportIo)?;
+            let path = dir.join("data.parquet");
+            let parquet_error = |source| SolarApiError::ParquetError {
+                path: path.display().to_string(),
+                source,
+            };
+            if self.merge && path.exists() {
+                let added: HashSet<_> = readings
+                    .iter()
+                    .map(|reading| (reading.channel.clone(), reading.timestamp))
+                    .collect();
+                let existing = read_file(&path).map_err(parquet_error)?;
+                readings.extend(existing.into_iter().filter(|reading| {
+                    !added.contains(&(reading.channel.clone(), reading.timestamp))
+                }));
+            }
+            readings.sort_by(|a, b| (a.timestamp, &a.channel).cmp(&(b.timestamp, &b.channel)));
+            write_file(&path, &readings.to_record_batch()).map_err(parquet_error)?;
+            files.push(path);
+        }
+        Ok(files)
+    }
+}
+
+fn write_file(path: &Path, batch: &RecordBatch) -> Result<(), ParquetError> {
+    let file = std::fs::File::create(path)?;
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), None)?;
+    writer.write(batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+// the readings of a file written by `write_file`
+fn read_file(path: &Path) -> Result<Vec<Reading>, ParquetError> {
+    let file = std::fs::File::open(path)?;
+    let mut readings = Vec::new();
+    for batch in ParquetRecordBatchReaderBuilder::try_new(file)?.build()? {
+        let batch = batch?;
+        let site_ids = column::<UInt32Array>(&batch, "site_id")?;
+        let channels = column::<StringArray>(&batch, "channel")?;
+        let timestamps = column::<TimestampSecondArray>(&batch, "timestamp")?;
+        let values = column::<Float64Array>(&batch, "value")?;
+        let units = column::<StringArray>(&batch, "unit")?;
+        let qualities = column::<StringArray>(&batch, "quality")?;
+        for row in 0..batch.num_rows() {
+            let invalid = |name: &str, value: &str| {
+                ParquetError::General(format!("invalid {name} {value} in row {row}"))
+            };
+            let timestamp = DateTime::from_timestamp(timestamps.value(row), 0)
+                .ok_or_else(|| invalid("timestamp", &timestamps.value(row).to_string()))?;
+            let unit = ["Wh", "W", "%"]
+                .into_iter()
+                .find(|unit| *unit == units.value(row))
+                .ok_or_else(|| invalid("unit", units.value(row)))?;
+            let quality = [Quality::Measured, Quality::Filled, Quality::Missing]
+                .into_iter()
+                .find(|quality| quality.as_str() == qualities.value(row))
+                .ok_or_else(|| invalid("quality", qualities.value(row)))?;
+            readings.push(Reading {
+                site_id: site_ids.value(row),
+                channel: channels.value(row).to_string(),
+                timestamp: timestamp.naive_utc(),
+                value: (!values.is_null(row)).then(|| values.value(row)),
+                unit,
+                quality,
+            });
+        }
+    }
+    Ok(readings)
+}
+
+// the column `name` of `batch` as array of type `T`
+fn column<'a, T: Array + 'static>(
+    batch: &'a RecordBatch,
+    name: &str,
+) -> Result<&'a T, ParquetError> {
+    batch
+        .column_by_name(name)
+        .and_then(|column| column.as_any().downcast_ref::<T>())
+        .ok_or_else(|| ParquetError::General(format!("column {name} is missing or invalid")))
+}
+
+#[test]
+fn test_parquet_writer() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let energy = TimeSeries::new(
+        TimeUnit::Day,
+        "Wh",
+        vec![
+            TimeSeriesValue {
+                date: date("2023-06-30 00:00:00"),
+                value: Some(watt_hours(1500.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-07-01 00:00:00"),
+                value: None,
+            },
+        ],
+    );
+    let dir = std::env::temp_dir().join(format!("solar-api-parquet-{}", std::process::id()));
+    let mut writer = ParquetWriter::new(&dir);
+    writer.add_energy(1234, "energy", &energy);
+    let files = writer.finish().unwrap();
+    assert_eq!(
+        vec![
+            dir.join("site_id=1234/month=2023-06/data.parquet"),
+            dir.join("site_id=1234/month=2023-07/data.parquet")
+        ],
+        files
+    );
+
+    let file = std::fs::File::open(&files[0]).unwrap();
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+    assert_eq!(
+        Some(&"1".to_string()),
+        reader.schema().metadata().get("schema_version")
+    );
+    let batch = reader.build().unwrap().next().unwrap().unwrap();
+    assert_eq!(1, batch.num_rows());
+    let values = batch
+        .column_by_name("value")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .unwrap();
+    assert_eq!(1500.0, values.value(0));
+    let timestamps = batch
+        .column_by_name("timestamp")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<TimestampSecondArray>()
+        .unwrap();
+    assert_eq!(
+        date("2023-06-30 00:00:00").and_utc().timestamp(),
+        timestamps.value(0)
+    );
+    let quality = batch
+        .column_by_name("quality")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    assert_eq!("measured", quality.value(0));
+
+    // merging keeps the readings of the file that are not added again
+    let mut writer = ParquetWriter::new(&dir).with_merge();
+    writer.add_energy(
+        1234,
+        "energy",
+        &TimeSeries::new(
+            TimeUnit::Day,
+            "Wh",
+            vec![TimeSeriesValue {
+                date: date("2023-06-29 00:00:00"),
+                value: Some(watt_hours(1000.0)),
+            }],
+        ),
+    );
+    let files = writer.finish().unwrap();
+    let readings = read_file(&files[0]).unwrap();
+    assert_eq!(
+        vec![
+            (date("2023-06-29 00:00:00"), Some(1000.0)),
+            (date("2023-06-30 00:00:00"), Some(1500.0))
+        ],
+        readings
+            .iter()
+            .map(|reading| (reading.timestamp, reading.value))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        schema::energy_readings(1234, "energy", &energy)[0],
+        readings[1]
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,267 @@
+//! The normalized schema of the long formats of this crate, see [`Reading`].
+//! The NDJSON export, the Parquet files, the Arrow record batches of readings
+//! and the PostgreSQL sink all write these fields, so pipelines that read one
+//! of them keep working when the models of the API in this crate change.
+//!
+//! Version 1 of the schema has a reading per value with the fields:
+//! * `schema_version`: the version of the schema, [`SCHEMA_VERSION`]
+//! * `site_id`: the id of the site
+//! * `channel`: what was measured, `energy` or `power` for the series of a
+//!   site, the meter for energy and power details like
+//!   `energy_details.Production`, and the battery for storage data like
+//!   `storage.BAT-1.power` or `storage.BAT-1.state_of_charge`
+//! * `timestamp`: the start of the period of the value, in the local time of
+//!   the site, i.e. `2023-06-01 12:00:00`
+//! * `value`: the value, null when the API had no value
+//! * `unit`: `Wh` for energy, `W` for power and `%` for state of charge
+//! * `quality`: `measured` for values of the API, `filled` for values added by
+//!   [`TimeSeries::fill_gaps`] or [`TimeSeries::interpolate_gaps`] and
+//!   `missing` for null values
+//!
+//! Parquet files and Arrow record batches keep the `schema_version` in the
+//! metadata of their schema and PostgreSQL in the comment of the table.
+//!
+//! New channels and quality flags can be added within a version. Renaming or
+//! removing a field, or changing the unit or the meaning of a field, increases
+//! the version.
+//!
+//! ```
+//! # use solar_api::export::schema::{energy_readings, Quality};
+//! # use solar_api::{TimeSeries, TimeSeriesValue, TimeUnit};
+//! # use solar_api::units::watt_hours;
+//! # use solar_api::chrono::NaiveDate;
+//! let date = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+//! let energy = TimeSeries::new(TimeUnit::Day, "kWh", vec![TimeSeriesValue {
+//!     date,
+//!     value: Some(watt_hours(12500.0)),
+//! }]);
+//! let readings = energy_readings(1234, "energy", &energy);
+//! assert_eq!(Some(12500.0), readings[0].value);
+//! assert_eq!("Wh", readings[0].unit);
+//! assert_eq!(Quality::Measured, readings[0].quality);
+//! ```
+
+use crate::{
+    meters::{EnergyDetails, PowerDetails},
+    series::{FillReport, TimeSeries},
+    storage::StorageData,
+    units::{as_watt_hours, as_watts, Energy, Power},
+    SolarApiError,
+};
+#[cfg(test)]
+use crate::{series::TimeSeriesValue, site::TimeUnit, units::watts};
+use chrono::NaiveDateTime;
+use serde::{Serialize, Serializer};
+
+/// The version of the schema of [`Reading`]
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Where the value of a [`Reading`] comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quality {
+    /// the value was returned by the API
+    Measured,
+    /// the value was missing and filled, i.e. by [`TimeSeries::fill_gaps`]
+    Filled,
+    /// the API had no value
+    Missing,
+}
+
+impl Quality {
+    /// The name of the flag in the schema, i.e. `measured`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Quality::Measured => "measured",
+            Quality::Filled => "filled",
+            Quality::Missing => "missing",
+        }
+    }
+}
+
+/// A value of a site in the normalized schema. Serializes to an object with
+/// the fields of the schema, including `schema_version`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub site_id: u32,
+    /// i.e. `energy_details.Production`
+    pub channel: String,
+    /// in the local time of the site
+    pub timestamp: NaiveDateTime,
+    /// in `unit`, None when the API had no value
+    pub value: Option<f64>,
+    /// `Wh`, `W` or `%`
+    pub unit: &'static str,
+    pub quality: Quality,
+}
+
+impl Serialize for Reading {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Fields<'a> {
+            schema_version: u32,
+            site_id: u32,
+            channel: &'a str,
+            timestamp: String,
+            value: Option<f64>,
+            unit: &'a str,
+            quality: Quality,
+        }
+        Fields {
+            schema_version: SCHEMA_VERSION,
+            site_id: self.site_id,
+            channel: &self.channel,
+            timestamp: self.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+            value: self.value,
+            unit: self.unit,
+            quality: self.quality,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// A reading in Wh per value of `energy` of `site_id`
+pub fn energy_readings(site_id: u32, channel: &str, energy: &TimeSeries<Energy>) -> Vec<Reading> {
+    readings(site_id, channel, "Wh", energy, as_watt_hours)
+}
+
+/// A reading in W per value of `power` of `site_id`
+pub fn power_readings(site_id: u32, channel: &str, power: &TimeSeries<Power>) -> Vec<Reading> {
+    readings(site_id, channel, "W", power, as_watts)
+}
+
+/// The readings of every meter of `details` of `site_id`
+pub fn energy_details_readings(
+    site_id: u32,
+    details: &EnergyDetails,
+) -> Result<Vec<Reading>, SolarApiError> {
+    let mut readings = Vec::new();
+    for meter in details.meters() {
+        if let Some(energy) = details.series(meter)? {
+            let channel = format!("energy_details.{meter}");
+            readings.extend(energy_readings(site_id, &channel, &energy));
+        }
+    }
+    Ok(readings)
+}
+
+/// The readings of every meter of `details` of `site_id`
+pub fn power_details_readings(
+    site_id: u32,
+    details: &PowerDetails,
+) -> Result<Vec<Reading>, SolarApiError> {
+    let mut readings = Vec::new();
+    for meter in details.meters() {
+        if let Some(power) = details.series(meter)? {
+            let channel = format!("power_details.{meter}");
+            readings.extend(power_readings(site_id, &channel, &power));
+        }
+    }
+    Ok(readings)
+}
+
+/// The readings of the power and state of charge of every battery of
+/// `storage` of `site_id`
+pub fn storage_readings(site_id: u32, storage: &StorageData) -> Vec<Reading> {
+    let mut result = Vec::new();
+    for battery in &storage.batteries {
+        let serial_number = &battery.serial_number;
+        result.extend(power_readings(
+            site_id,
+            &format!("storage.{serial_number}.power"),
+            &battery.power(),
+        ));
+        result.extend(readings(
+            site_id,
+            &format!("storage.{serial_number}.state_of_charge"),
+            "%",
+            &battery.state_of_charge(),
+            |percentage| percentage,
+        ));
+    }
+    result
+}
+
+/// Flag the readings at the timestamps that `report` filled as
+/// [`Quality::Filled`], for readings of a series returned by
+/// [`TimeSeries::fill_gaps`] or [`TimeSeries::interpolate_gaps`]
+pub fn mark_filled(readings: &mut [Reading], report: &FillReport) {
+    for reading in readings {
+        if reading.value.is_some() && report.filled.contains(&reading.timestamp) {
+            reading.quality = Quality::Filled;
+        }
+    }
+}
+
+// a reading per value of `values`
+fn readings<Q: Copy>(
+    site_id: u32,
+    channel: &str,
+    unit: &'static str,
+    values: &TimeSeries<Q>,
+    value: impl Fn(Q) -> f64,
+) -> Vec<Reading> {
+    values
+        .iter()
+        .map(|v| Reading {
+            site_id,
+            channel: channel.to_string(),
+            timestamp: v.date,
+            value: v.value.map(&value),
+            unit,
+            quality: if v.value.is_some() {
+                Quality::Measured
+            } else {
+                Quality::Missing
+            },
+        })
+        .collect()
+}
+
+#[test]
+fn test_readings() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let power = TimeSeries::new(
+        TimeUnit::QuarterOfAnHour,
+        "W",
+        vec![
+            TimeSeriesValue {
+                date: date("2023-06-01 12:00:00"),
+                value: Some(watts(1000.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-06-01 12:15:00"),
+                value: None,
+            },
+            TimeSeriesValue {
+                date: date("2023-06-01 12:30:00"),
+                value: Some(watts(2000.0)),
+            },
+        ],
+    );
+
+    let readings = power_readings(1234, "power", &power);
+    assert_eq!(
+        vec![Quality::Measured, Quality::Missing, Quality::Measured],
+        readings.iter().map(|r| r.quality).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        serde_json::json!({
+            "schema_version": 1,
+            "site_id": 1234,
+            "channel": "power",
+            "timestamp": "2023-06-01 12:15:00",
+            "value": null,
+            "unit": "W",
+            "quality": "missing"
+        }),
+        serde_json::to_value(&readings[1]).unwrap()
+    );
+
+    let (filled, report) = power.interpolate_gaps();
+    let mut readings = power_readings(1234, "power", &filled);
+    mark_filled(&mut readings, &report);
+    assert_eq!(Some(1500.0), readings[1].value);
+    assert_eq!(Quality::Filled, readings[1].quality);
+    assert_eq!(Quality::Measured, readings[2].quality);
+}
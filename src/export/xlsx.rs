@@ -0,0 +1,265 @@
+//! Writes summaries and series to an Excel workbook with a sheet per part, see
+//! [`XlsxWorkbook`]. Requires the `xlsx` feature.
+//!
+//! ```no_run
+//! # use solar_api::export::csv::{CsvOptions, CsvTable};
+//! # use solar_api::export::xlsx::XlsxWorkbook;
+//! # use solar_api::{Client, DataPeriod, TimeUnit};
+//! # use solar_api::chrono::NaiveDate;
+//! let client = Client::new("API_KEY");
+//! let period = DataPeriod::between(
+//!     NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+//!     NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(),
+//! )?;
+//! let energy = client.energy(1234, period, TimeUnit::Day)?.to_series()?;
+//! XlsxWorkbook::new()
+//!     .with_monthly_summary(&energy)
+//!     .with_annual_summary(&energy)
+//!     .with_table("Daily", &CsvTable::new(CsvOptions::new()).with_energy("Production", &energy))
+//!     .save("2023.xlsx")?;
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use super::csv::CsvTable;
+#[cfg(test)]
+use crate::{export::csv::CsvOptions, series::TimeSeriesValue, units::watt_hours};
+use crate::{
+    series::{Aggregation, TimeSeries},
+    site::TimeUnit,
+    units::{as_watt_hours, Energy},
+    SolarApiError,
+};
+use chrono::NaiveDateTime;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use std::path::Path;
+
+// the width of the columns in characters
+const COLUMN_WIDTH: f64 = 20.0;
+
+// a cell of a sheet
+#[derive(Debug, Clone, PartialEq)]
+enum Cell {
+    // a date and time shown in the number format
+    DateTime(NaiveDateTime, &'static str),
+    Number(f64),
+    Empty,
+}
+
+// a sheet with a header row
+#[derive(Debug, Clone, PartialEq)]
+struct Sheet {
+    name: String,
+    headers: Vec<String>,
+    rows: Vec<Vec<Cell>>,
+}
+
+/// A workbook with a sheet per added summary or table. Dates and timestamps
+/// are written as Excel dates, so they can be used in formulas and charts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct XlsxWorkbook {
+    sheets: Vec<Sheet>,
+}
+
+impl XlsxWorkbook {
+    /// Create a workbook without sheets
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the sheet `Monthly` with the energy and the average energy per day
+    /// with data of every month of `energy` in kWh
+    pub fn with_monthly_summary(self, energy: &TimeSeries<Energy>) -> Self {
+        self.with_summary("Monthly", "Month", "mmm yyyy", TimeUnit::Month, energy)
+    }
+
+    /// Add the sheet `Annual` with the energy and the average energy per day
+    /// with data of every year of `energy` in kWh
+    pub fn with_annual_summary(self, energy: &TimeSeries<Energy>) -> Self {
+        self.with_summary("Annual", "Year", "yyyy", TimeUnit::Year, energy)
+    }
+
+    /// Add the sheet `name` with the columns of `table`, i.e. the raw series
+    /// in the units of the [`CsvOptions`](super::csv::CsvOptions) of the table
+    pub fn with_table(mut self, name: &str, table: &CsvTable) -> Self {
+        let columns = table.headers().len();
+        let rows = table
+            .rows()
+            .iter()
+            .map(|(date, values)| {
+                let mut row = vec![Cell::DateTime(*date, "yyyy-mm-dd hh:mm")];
+                row.extend((0..columns).map(|column| {
+                    values
+                        .get(column)
+                        .copied()
+                        .flatten()
+                        .map_or(Cell::Empty, Cell::Number)
+                }));
+                row
+            })
+            .collect();
+        let mut headers = vec!["Timestamp".to_string()];
+        headers.extend(table.headers().iter().cloned());
+        self.sheets.push(Sheet {
+            name: name.to_string(),
+            headers,
+            rows,
+        });
+        self
+    }
+
+    /// Write the workbook to `path`
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SolarApiError> {
+        self.workbook()
+            .and_then(|mut workbook| workbook.save(path))
+            .map_err(SolarApiError::XlsxError)
+    }
+
+    /// The workbook as the bytes of an `.xlsx` file
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SolarApiError> {
+        self.workbook()
+            .and_then(|mut workbook| workbook.save_to_buffer())
+            .map_err(SolarApiError::XlsxError)
+    }
+
+    // adds a sheet with the energy per `time_unit` of `energy`
+    fn with_summary(
+        mut self,
+        name: &str,
+        period: &str,
+        date_format: &'static str,
+        time_unit: TimeUnit,
+        energy: &TimeSeries<Energy>,
+    ) -> Self {
+        let daily = energy.resample(TimeUnit::Day, Aggregation::Sum);
+        let rows = daily
+            .resample(time_unit, Aggregation::Sum)
+            .iter()
+            .map(|bucket| {
+                let days = daily
+                    .iter()
+                    .filter(|day| time_unit.bucket_start(day.date) == bucket.date)
+                    .filter(|day| day.value.is_some())
+                    .count();
+                let total = bucket.value.map(|total| as_watt_hours(total) / 1000.0);
+                vec![
+                    Cell::DateTime(bucket.date, date_format),
+                    total.map_or(Cell::Empty, Cell::Number),
+                    match (total, days) {
+                        (Some(total), days) if days > 0 => Cell::Number(total / days as f64),
+                        _ => Cell::Empty,
+                    },
+                ]
+            })
+            .collect();
+        self.sheets.push(Sheet {
+            name: name.to_string(),
+            headers: vec![
+                period.to_string(),
+                "Energy (kWh)".to_string(),
+                "Average per day (kWh)".to_string(),
+            ],
+            rows,
+        });
+        self
+    }
+
+    // the workbook with all sheets
+    fn workbook(&self) -> Result<Workbook, XlsxError> {
+        let mut workbook = Workbook::new();
+        let bold = Format::new().set_bold();
+        for sheet in &self.sheets {
+            let worksheet = workbook.add_worksheet();
+            worksheet.set_name(&sheet.name)?;
+            worksheet.set_freeze_panes(1, 0)?;
+            for (column, header) in sheet.headers.iter().enumerate() {
+                let column = column as u16;
+                worksheet.set_column_width(column, COLUMN_WIDTH)?;
+                worksheet.write_string_with_format(0, column, header, &bold)?;
+            }
+            for (row, cells) in sheet.rows.iter().enumerate() {
+                let row = row as u32 + 1;
+                for (column, cell) in cells.iter().enumerate() {
+                    let column = column as u16;
+                    match cell {
+                        Cell::DateTime(date, format) => {
+                            let format = Format::new().set_num_format(*format);
+                            worksheet.write_datetime_with_format(row, column, date, &format)?;
+                        }
+                        Cell::Number(value) => {
+                            worksheet.write_number(row, column, *value)?;
+                        }
+                        Cell::Empty => {}
+                    }
+                }
+            }
+        }
+        Ok(workbook)
+    }
+}
+
+#[test]
+fn test_xlsx_workbook() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let energy = TimeSeries::new(
+        TimeUnit::Day,
+        "Wh",
+        vec![
+            TimeSeriesValue {
+                date: date("2023-06-01 00:00:00"),
+                value: Some(watt_hours(1500.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-06-02 00:00:00"),
+                value: Some(watt_hours(2500.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-07-01 00:00:00"),
+                value: None,
+            },
+        ],
+    );
+    let workbook = XlsxWorkbook::new()
+        .with_monthly_summary(&energy)
+        .with_annual_summary(&energy)
+        .with_table(
+            "Daily",
+            &CsvTable::new(CsvOptions::new()).with_energy("Production", &energy),
+        );
+
+    assert_eq!(
+        vec![
+            vec![
+                Cell::DateTime(date("2023-06-01 00:00:00"), "mmm yyyy"),
+                Cell::Number(4.0),
+                Cell::Number(2.0)
+            ],
+            vec![
+                Cell::DateTime(date("2023-07-01 00:00:00"), "mmm yyyy"),
+                Cell::Empty,
+                Cell::Empty
+            ],
+        ],
+        workbook.sheets[0].rows
+    );
+    assert_eq!(
+        vec![
+            Cell::DateTime(date("2023-01-01 00:00:00"), "yyyy"),
+            Cell::Number(4.0),
+            Cell::Number(2.0)
+        ],
+        workbook.sheets[1].rows[0]
+    );
+    assert_eq!(
+        vec!["Timestamp".to_string(), "Production (Wh)".to_string()],
+        workbook.sheets[2].headers
+    );
+    assert_eq!(3, workbook.sheets[2].rows.len());
+    assert_eq!(Cell::Number(1500.0), workbook.sheets[2].rows[0][1]);
+
+    let bytes = workbook.to_bytes().unwrap();
+    assert_eq!(b"PK", &bytes[..2]);
+    assert!(XlsxWorkbook::new()
+        .with_table("a/b", &CsvTable::new(CsvOptions::new()))
+        .to_bytes()
+        .is_err());
+}
@@ -0,0 +1,796 @@
+//! A fake SolarEdge API on a local port, to integration test the retry and
+//! scheduling logic of applications against the failures of the real API, see
+//! [`FakeServer`]. Requires the `test-util` feature.
+//!
+//! A [`Scenario`] describes the sites of the API key and how the API behaves:
+//! * requests with another API key or for a site of another account are
+//!   answered with status 403
+//! * requests over the daily limit are answered with status 429
+//! * the data of a quarter of an hour is published after a delay, so the
+//!   overview is not updated at the time of its update schedule
+//! * [`FakeServer::fail_next`] answers the next requests with an error, i.e.
+//!   500 during maintenance of the API
+//!
+//! The server answers
+//! * `/sites/list` with `size` (at most 100) and `startIndex` for pagination
+//! * `/site/{site_id}/details`, `/site/{site_id}/dataPeriod` and
+//!   `/site/{site_id}/overview`, like [`fixtures::DATA_PERIOD_PENDING`] and
+//!   [`fixtures::OVERVIEW_PENDING`] for a site that is not active
+//! * `/site/{site_id}/energy` with `startDate`, `endDate` and `timeUnit`
+//! * `/site/{site_id}/power` with `startTime` and `endTime`
+//! * `/site/{site_id}/currentPowerFlow`, with the power of the overview
+//!   flowing from the PV to the consumption
+//! * `/sites/{site_ids}/overview` and `/sites/{site_ids}/energy` of the bulk
+//!   API
+//!
+//! The power of a site follows a clear sky curve from 6:00 until 18:00 with
+//! the peak power at noon, unless [`Scenario::with_power`] is used. The clock
+//! of the server is the local time of this machine, or a time set by the test.
+//!
+//! ```
+//! # use solar_api::fake_server::{FakeServer, FakeSite, Scenario};
+//! # use solar_api::chrono::NaiveDate;
+//! let now = NaiveDate::from_ymd_opt(2023, 6, 1)
+//!     .unwrap()
+//!     .and_hms_opt(12, 20, 0)
+//!     .unwrap();
+//! let server = FakeServer::start(
+//!     Scenario::new("API_KEY")
+//!         .with_site(FakeSite::new(1234, 5.0))
+//!         .with_now(now),
+//! )?;
+//! let client = server.client();
+//! let last_updated = client.overview(1234)?.last_updated_time.unwrap();
+//! assert_eq!("2023-06-01 12:15:00", last_updated.to_string());
+//! assert!(client.overview(5678).is_err());
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use crate::{
+    client::Client,
+    fixtures,
+    site::{DataPeriod, TimeUnit},
+    SolarApiError,
+};
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use log::{debug, warn};
+use reqwest::Url;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread::JoinHandle,
+};
+
+// the most sites per page of the site list
+const MAX_PAGE_SIZE: usize = 100;
+// the format of the times of the API
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// the power in W of a site at a time, None for a missing value
+type PowerFn = dyn Fn(u32, NaiveDateTime) -> Option<f64> + Send + Sync;
+
+/// A site of a [`Scenario`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeSite {
+    pub id: u32,
+    pub name: String,
+    pub status: String,
+    /// the peak power in kWp
+    pub peak_power_kw: f64,
+    /// the first day with data
+    pub installation_date: NaiveDate,
+    pub time_zone: String,
+}
+
+impl FakeSite {
+    /// Create an active site in Amsterdam that produces since 2020
+    pub fn new(id: u32, peak_power_kw: f64) -> Self {
+        FakeSite {
+            id,
+            name: format!("Site {id}"),
+            status: "Active".to_string(),
+            peak_power_kw,
+            installation_date: NaiveDate::from_ymd_opt(2020, 1, 1).expect("valid date"),
+            time_zone: "Europe/Amsterdam".to_string(),
+        }
+    }
+
+    /// Set the name
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Set the status, i.e. `Pending Communication` for a new site without
+    /// data
+    pub fn with_status(mut self, status: &str) -> Self {
+        self.status = status.to_string();
+        self
+    }
+
+    /// Set the first day with data
+    pub fn with_installation_date(mut self, installation_date: NaiveDate) -> Self {
+        self.installation_date = installation_date;
+        self
+    }
+
+    // the site as in the site list and the details
+    fn to_json(&self, last_update: NaiveDateTime) -> Value {
+        let active = self.status == "Active";
+        json!({
+            "id": self.id,
+            "name": self.name,
+            "accountId": 1,
+            "status": self.status,
+            "peakPower": self.peak_power_kw,
+            "lastUpdateTime": active.then(|| last_update.date().to_string()),
+            "installationDate": self.installation_date.to_string(),
+            "ptoDate": null,
+            "notes": "",
+            "type": "Optimizers & Inverters",
+            "location": {
+                "country": "Netherlands",
+                "city": "Amsterdam",
+                "address": "Dam 1",
+                "zip": "1012 JS",
+                "timeZone": self.time_zone,
+                "countryCode": "NL",
+            },
+            "primaryModule": {"manufacturerName": "", "modelName": ""},
+            "uris": {},
+            "publicSettings": {"isPublic": false},
+        })
+    }
+}
+
+/// The sites and the behavior of a [`FakeServer`]
+#[derive(Clone)]
+pub struct Scenario {
+    api_key: String,
+    sites: Vec<FakeSite>,
+    daily_limit: Option<u32>,
+    publication_delay: Duration,
+    now: Option<NaiveDateTime>,
+    power: Option<Arc<PowerFn>>,
+}
+
+impl fmt::Debug for Scenario {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scenario")
+            .field("sites", &self.sites)
+            .field("daily_limit", &self.daily_limit)
+            .field("publication_delay", &self.publication_delay)
+            .field("now", &self.now)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Scenario {
+    /// Create a scenario without sites in which only `api_key` is accepted,
+    /// without daily limit and publication delay
+    pub fn new(api_key: &str) -> Self {
+        Scenario {
+            api_key: api_key.to_string(),
+            sites: Vec::new(),
+            daily_limit: None,
+            publication_delay: Duration::zero(),
+            now: None,
+            power: None,
+        }
+    }
+
+    /// Add `site` to the sites of the API key
+    pub fn with_site(mut self, site: FakeSite) -> Self {
+        self.sites.push(site);
+        self
+    }
+
+    /// Answer the requests after the first `daily_limit` of a day with status
+    /// 429, like the limit of 300 requests of the real API
+    pub fn with_daily_limit(mut self, daily_limit: u32) -> Self {
+        self.daily_limit = Some(daily_limit);
+        self
+    }
+
+    /// Publish the data of a quarter of an hour `publication_delay` after its
+    /// end instead of right away
+    pub fn with_publication_delay(mut self, publication_delay: Duration) -> Self {
+        self.publication_delay = publication_delay;
+        self
+    }
+
+    /// Start the clock of the server at `now` and keep it there until
+    /// [`FakeServer::set_now`] or [`FakeServer::advance`]. The clock is the
+    /// local time of this machine by default
+    pub fn with_now(mut self, now: NaiveDateTime) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Use `power` for the power in W of a site at the start of a quarter of
+    /// an hour, or None for a missing value
+    pub fn with_power(
+        mut self,
+        power: impl Fn(u32, NaiveDateTime) -> Option<f64> + Send + Sync + 'static,
+    ) -> Self {
+        self.power = Some(Arc::new(power));
+        self
+    }
+
+    // the power of `site` in the quarter starting at `time`
+    fn power_at(&self, site: &FakeSite, time: NaiveDateTime) -> Option<f64> {
+        if time.date() < site.installation_date || site.status != "Active" {
+            return None;
+        }
+        match &self.power {
+            Some(power) => power(site.id, time),
+            None => Some(clear_sky_power(site.peak_power_kw, time)),
+        }
+    }
+
+    // the energy in Wh of `site` in the quarter starting at `time`
+    fn energy_at(&self, site: &FakeSite, time: NaiveDateTime) -> Option<f64> {
+        self.power_at(site, time).map(|power| power / 4.0)
+    }
+}
+
+// the state of the server that the test can change
+#[derive(Debug)]
+struct State {
+    scenario: Scenario,
+    now: Option<NaiveDateTime>,
+    // the path and query of every request, without the API key
+    requests: Vec<String>,
+    // the day and the number of accepted requests on that day
+    requests_today: (NaiveDate, u32),
+    // the status of the next failing requests
+    failures: VecDeque<u16>,
+}
+
+/// A fake of the SolarEdge API that answers requests on a local port in a
+/// background thread, until it is dropped
+#[derive(Debug)]
+pub struct FakeServer {
+    addr: SocketAddr,
+    api_key: String,
+    state: Arc<Mutex<State>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FakeServer {
+    /// Answer requests with `scenario` on a free port of 127.0.0.1
+    pub fn start(scenario: Scenario) -> Result<Self, SolarApiError> {
+        let listener = TcpListener::bind("127.0.0.1:0").map_err(SolarApiError::FakeServer)?;
+        let addr = listener.local_addr().map_err(SolarApiError::FakeServer)?;
+        let api_key = scenario.api_key.clone();
+        let state = Arc::new(Mutex::new(State {
+            now: scenario.now,
+            scenario,
+            requests: Vec::new(),
+            requests_today: (NaiveDate::MIN, 0),
+            failures: VecDeque::new(),
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let (state, stop) = (Arc::clone(&state), Arc::clone(&stop));
+            std::thread::spawn(move || serve(&listener, &state, &stop))
+        };
+        debug!("Fake API listens on {addr}");
+        Ok(FakeServer {
+            addr,
+            api_key,
+            state,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// The base URL of the server, i.e. `http://127.0.0.1:34567`
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// A client with the API key of the scenario that calls this server
+    pub fn client(&self) -> Client {
+        Client::new(&self.api_key).with_base_url(&self.url())
+    }
+
+    /// Set the clock of the server to `now`
+    pub fn set_now(&self, now: NaiveDateTime) {
+        self.state().now = Some(now);
+    }
+
+    /// Move the clock of the server `duration` ahead
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.state();
+        state.now = Some(state.now.unwrap_or_else(local_now) + duration);
+    }
+
+    /// Answer the next `count` requests with `status`, i.e. 500 or 503
+    pub fn fail_next(&self, status: u16, count: usize) {
+        self.state()
+            .failures
+            .extend(std::iter::repeat_n(status, count));
+    }
+
+    /// The path and query of every request so far, without the API key, i.e.
+    /// `/site/1234/overview`
+    pub fn requests(&self) -> Vec<String> {
+        self.state().requests.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // wake up the thread that waits for a request
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn local_now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+// answers requests until `stop` is set
+fn serve(listener: &TcpListener, state: &Mutex<State>, stop: &AtomicBool) {
+    for stream in listener.incoming() {
+        if stop.load(Ordering::SeqCst) {
+            return;
+        }
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Fake API could not accept request: {e}");
+                continue;
+            }
+        };
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        if let Err(e) = reader.read_line(&mut request_line) {
+            warn!("Fake API could not read request: {e}");
+            continue;
+        }
+        // read the headers, so the client is not reset while sending them
+        let mut header = String::new();
+        while reader.read_line(&mut header).is_ok_and(|n| n > 0) && header.trim_end() != "" {
+            header.clear();
+        }
+        let (status, body) = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["GET", target, _] => {
+                let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+                answer(&mut state, target)
+            }
+            _ => (405, error("Only GET is supported")),
+        };
+        debug!("Fake API answers {} with {status}", request_line.trim_end());
+        if let Err(e) = respond(&stream, status, &body.to_string()) {
+            warn!("Fake API could not answer request: {e}");
+        }
+    }
+}
+
+// the status and body of the answer to the request of `target`
+fn answer(state: &mut State, target: &str) -> (u16, Value) {
+    let Ok(url) = Url::parse(&format!("http://localhost{target}")) else {
+        return (400, error("Invalid request"));
+    };
+    let params: BTreeMap<String, String> = url
+        .query_pairs()
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    let logged: Vec<String> = params
+        .iter()
+        .filter(|(name, _)| *name != "api_key")
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect();
+    state.requests.push(match logged.is_empty() {
+        true => url.path().to_string(),
+        false => format!("{}?{}", url.path(), logged.join("&")),
+    });
+
+    if let Some(status) = state.failures.pop_front() {
+        return (status, error("Scripted failure"));
+    }
+    if params.get("api_key") != Some(&state.scenario.api_key) {
+        return (403, error("Invalid token"));
+    }
+    let now = state.now.unwrap_or_else(local_now);
+    if state.requests_today.0 != now.date() {
+        state.requests_today = (now.date(), 0);
+    }
+    state.requests_today.1 += 1;
+    if state
+        .scenario
+        .daily_limit
+        .is_some_and(|limit| state.requests_today.1 > limit)
+    {
+        return (429, error("Too many requests"));
+    }
+
+    // the start of the quarter after the last published one
+    let published = quarter_start(now - state.scenario.publication_delay);
+    let reply = Reply {
+        scenario: &state.scenario,
+        params: &params,
+        published,
+    };
+    let segments: Vec<&str> = url.path().trim_matches('/').split('/').collect();
+    let result = match segments[..] {
+        ["sites", "list"] => reply.list(),
+        ["site", id, endpoint] => match reply.sites(id) {
+            Ok(sites) => match endpoint {
+                "details" => Ok(json!({"details": sites[0].to_json(published)})),
+                "dataPeriod" => Ok(reply.data_period(sites[0])),
+                "overview" => Ok(json!({"overview": reply.overview(sites[0])})),
+                "energy" => reply.energy(sites[0]).map(|(time_unit, values)| {
+                    json!({"energy": {
+                        "timeUnit": time_unit.to_param(),
+                        "unit": "Wh",
+                        "measuredBy": "INVERTER",
+                        "values": values,
+                    }})
+                }),
+                "power" => reply.power(sites[0]),
+                "currentPowerFlow" => Ok(reply.power_flow(sites[0])),
+                _ => Err((404, "Not found")),
+            },
+            Err(e) => Err(e),
+        },
+        ["sites", ids, endpoint] => match reply.sites(ids) {
+            Ok(sites) => match endpoint {
+                "overview" => Ok(reply.overviews(&sites)),
+                "energy" => reply.sites_energy(&sites),
+                _ => Err((404, "Not found")),
+            },
+            Err(e) => Err(e),
+        },
+        _ => Err((404, "Not found")),
+    };
+    match result {
+        Ok(body) => (200, body),
+        Err((status, message)) => (status, error(message)),
+    }
+}
+
+// the replies of the endpoints at a time
+struct Reply<'a> {
+    scenario: &'a Scenario,
+    params: &'a BTreeMap<String, String>,
+    // the start of the quarter after the last published one
+    published: NaiveDateTime,
+}
+
+// the status and message of a failed request
+type Failure = (u16, &'static str);
+
+impl Reply<'_> {
+    fn list(&self) -> Result<Value, Failure> {
+        let number = |name: &str, default: usize| match self.params.get(name) {
+            Some(value) => value.parse().map_err(|_| (400, "Invalid number")),
+            None => Ok(default),
+        };
+        let size = number("size", MAX_PAGE_SIZE)?;
+        if size > MAX_PAGE_SIZE {
+            return Err((400, "size must be at most 100"));
+        }
+        let sites: Vec<Value> = self
+            .scenario
+            .sites
+            .iter()
+            .skip(number("startIndex", 0)?)
+            .take(size)
+            .map(|site| site.to_json(self.published))
+            .collect();
+        Ok(json!({"sites": {"count": self.scenario.sites.len(), "site": sites}}))
+    }
+
+    // the sites of the comma separated `ids`. Sites of other accounts are
+    // forbidden
+    fn sites(&self, ids: &str) -> Result<Vec<&FakeSite>, Failure> {
+        ids.split(',')
+            .map(|id| {
+                let id: u32 = id.parse().map_err(|_| (400, "Invalid site id"))?;
+                self.scenario
+                    .sites
+                    .iter()
+                    .find(|site| site.id == id)
+                    .ok_or((403, "Site is not accessible with this token"))
+            })
+            .collect()
+    }
+
+    fn data_period(&self, site: &FakeSite) -> Value {
+        if site.status != "Active" {
+            return fixtures::DATA_PERIOD_PENDING.json();
+        }
+        let end = (self.published - Duration::minutes(15)).date();
+        json!({"dataPeriod": {
+            "startDate": site.installation_date.to_string(),
+            "endDate": end.max(site.installation_date).to_string(),
+        }})
+    }
+
+    fn overview(&self, site: &FakeSite) -> Value {
+        if site.status != "Active" {
+            return fixtures::OVERVIEW_PENDING.json()["overview"].take();
+        }
+        let last = self.published - Duration::minutes(15);
+        let energy_since = |start: NaiveDateTime| {
+            quarters(
+                start.max(site.installation_date.and_time(NaiveTime::MIN)),
+                self.published,
+            )
+            .filter_map(|time| self.scenario.energy_at(site, time))
+            .sum::<f64>()
+        };
+        let midnight = self.published.date().and_time(NaiveTime::MIN);
+        json!({
+            "lastUpdateTime": self.published.format(TIME_FORMAT).to_string(),
+            "lifeTimeData": {"energy": energy_since(NaiveDateTime::MIN)},
+            "lastYearData": {"energy": energy_since(TimeUnit::Year.bucket_start(midnight))},
+            "lastMonthData": {"energy": energy_since(TimeUnit::Month.bucket_start(midnight))},
+            "lastDayData": {"energy": energy_since(midnight)},
+            "currentPower": {"power": self.scenario.power_at(site, last).unwrap_or(0.0)},
+            "measuredBy": "INVERTER",
+        })
+    }
+
+    // the current power of the overview flows from the PV to the consumption,
+    // for a site without grid connection and batteries
+    fn power_flow(&self, site: &FakeSite) -> Value {
+        let last = self.published - Duration::minutes(15);
+        let kw = self.scenario.power_at(site, last).unwrap_or(0.0) / 1000.0;
+        let status = if kw > 0.0 { "Active" } else { "Idle" };
+        json!({"siteCurrentPowerFlow": {
+            "updateRefreshRate": 3,
+            "unit": "kW",
+            "connections": if kw > 0.0 { json!([{"from": "PV", "to": "Load"}]) } else { json!([]) },
+            "LOAD": {"status": status, "currentPower": kw},
+            "PV": {"status": status, "currentPower": kw},
+        }})
+    }
+
+    fn overviews(&self, sites: &[&FakeSite]) -> Value {
+        let list: Vec<Value> = sites
+            .iter()
+            .map(|site| json!({"siteId": site.id, "siteOverview": self.overview(site)}))
+            .collect();
+        json!({"sitesOverviews": {"count": list.len(), "siteEnergyList": list}})
+    }
+
+    // the time unit and the energy per bucket of the period of the parameters
+    fn energy(&self, site: &FakeSite) -> Result<(TimeUnit, Vec<Value>), Failure> {
+        let date = |name: &str| {
+            let value = self.params.get(name).ok_or((400, "Missing date"))?;
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| (400, "Invalid date"))
+        };
+        let time_unit: TimeUnit = self
+            .params
+            .get("timeUnit")
+            .ok_or((400, "Missing timeUnit"))?
+            .parse()
+            .map_err(|_| (400, "Invalid timeUnit"))?;
+        let period = DataPeriod::between(date("startDate")?, date("endDate")?)
+            .map_err(|_| (400, "startDate is after endDate"))?;
+        period
+            .validate_for(time_unit)
+            .map_err(|_| (400, "Period is too long for the time unit"))?;
+
+        let (start_date, end_date) = period.bounds().map_err(|_| (400, "Missing dates"))?;
+        let start = start_date.and_time(NaiveTime::MIN);
+        let end = (end_date + Duration::days(1)).and_time(NaiveTime::MIN);
+        let mut buckets: BTreeMap<NaiveDateTime, Option<f64>> = BTreeMap::new();
+        for time in quarters(start, end) {
+            let energy = (time < self.published)
+                .then(|| self.scenario.energy_at(site, time))
+                .flatten();
+            let bucket = buckets.entry(time_unit.bucket_start(time)).or_default();
+            if let Some(energy) = energy {
+                *bucket = Some(bucket.unwrap_or(0.0) + energy);
+            }
+        }
+        Ok((time_unit, values(buckets)))
+    }
+
+    fn sites_energy(&self, sites: &[&FakeSite]) -> Result<Value, Failure> {
+        let mut time_unit = TimeUnit::Day;
+        let mut list = Vec::new();
+        for site in sites {
+            let (unit, values) = self.energy(site)?;
+            time_unit = unit;
+            list.push(json!({
+                "siteId": site.id,
+                "energyValues": {"measuredBy": "INVERTER", "values": values},
+            }));
+        }
+        Ok(json!({"sitesEnergy": {
+            "timeUnit": time_unit.to_param(),
+            "unit": "Wh",
+            "count": list.len(),
+            "siteEnergyList": list,
+        }}))
+    }
+
+    fn power(&self, site: &FakeSite) -> Result<Value, Failure> {
+        let time = |name: &str| {
+            let value = self.params.get(name).ok_or((400, "Missing time"))?;
+            NaiveDateTime::parse_from_str(value, TIME_FORMAT).map_err(|_| (400, "Invalid time"))
+        };
+        let (start, end) = (time("startTime")?, time("endTime")?);
+        if start > end {
+            return Err((400, "startTime is after endTime"));
+        }
+        if end - start > Duration::days(31) {
+            return Err((400, "Period is too long"));
+        }
+        let power: BTreeMap<NaiveDateTime, Option<f64>> =
+            quarters(quarter_start(start), end + Duration::seconds(1))
+                .map(|time| {
+                    let power = (time < self.published)
+                        .then(|| self.scenario.power_at(site, time))
+                        .flatten();
+                    (time, power)
+                })
+                .collect();
+        Ok(json!({"power": {
+            "timeUnit": "QUARTER_OF_AN_HOUR",
+            "unit": "W",
+            "measuredBy": "INVERTER",
+            "values": values(power),
+        }}))
+    }
+}
+
+// the values of the API with a date and an optional value
+fn values(values: BTreeMap<NaiveDateTime, Option<f64>>) -> Vec<Value> {
+    values
+        .into_iter()
+        .map(|(date, value)| json!({"date": date.format(TIME_FORMAT).to_string(), "value": value}))
+        .collect()
+}
+
+// the starts of the quarters of an hour from `start` until `end`
+fn quarters(start: NaiveDateTime, end: NaiveDateTime) -> impl Iterator<Item = NaiveDateTime> {
+    std::iter::successors(Some(start), |time| Some(*time + Duration::minutes(15)))
+        .take_while(move |time| *time < end)
+}
+
+fn quarter_start(time: NaiveDateTime) -> NaiveDateTime {
+    TimeUnit::QuarterOfAnHour.bucket_start(time)
+}
+
+// the power in W of a site of `peak_power_kw` on a clear day, a sine from 6:00
+// until 18:00 in the middle of the quarter of an hour that starts at `time`
+fn clear_sky_power(peak_power_kw: f64, time: NaiveDateTime) -> f64 {
+    let hour = time.hour() as f64 + (time.minute() as f64 + 7.5) / 60.0;
+    let sine = (std::f64::consts::PI * (hour - 6.0) / 12.0).sin();
+    (peak_power_kw * 1000.0 * sine.max(0.0) * 1000.0).round() / 1000.0
+}
+
+fn error(message: &str) -> Value {
+    json!({ "String": message })
+}
+
+fn respond(mut stream: impl Write, status: u16, body: &str) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        429 => "Too Many Requests",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[test]
+fn test_fake_server() {
+    let time = |s: &str| NaiveDateTime::parse_from_str(s, TIME_FORMAT).unwrap();
+    let server = FakeServer::start(
+        Scenario::new("KEY")
+            .with_site(FakeSite::new(1234, 5.0))
+            .with_site(FakeSite::new(5678, 10.0).with_status("Pending Communication"))
+            .with_daily_limit(10)
+            .with_publication_delay(Duration::minutes(20))
+            .with_now(time("2023-06-01 12:40:00")),
+    )
+    .unwrap();
+    let client = server.client();
+
+    assert_eq!(2, client.list().unwrap().len());
+    let overview = client.overview(1234).unwrap();
+    // the quarter until 12:15 is published 20 minutes later at 12:35
+    assert_eq!(
+        Some(time("2023-06-01 12:15:00")),
+        overview.last_updated_time
+    );
+    assert_eq!(
+        4997.0,
+        crate::units::as_watts(overview.current_power.power).round()
+    );
+    let power = client
+        .power(
+            1234,
+            time("2023-06-01 12:00:00"),
+            time("2023-06-01 12:30:00"),
+        )
+        .unwrap()
+        .to_series()
+        .unwrap();
+    let published: Vec<bool> = power.iter().map(|v| v.value.is_some()).collect();
+    assert_eq!(vec![true, false, false], published);
+
+    server.advance(Duration::minutes(15));
+    assert_eq!(
+        Some(time("2023-06-01 12:30:00")),
+        client.overview(1234).unwrap().last_updated_time
+    );
+    let period = DataPeriod::between(
+        NaiveDate::from_ymd_opt(2023, 5, 31).unwrap(),
+        NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(),
+    )
+    .unwrap();
+    let energy = client
+        .energy(1234, period, TimeUnit::Day)
+        .unwrap()
+        .to_series()
+        .unwrap();
+    // a clear day of a 5 kWp site yields about 38 kWh
+    let day = crate::units::as_watt_hours(energy.values[0].value.unwrap());
+    assert!((38_000.0..39_000.0).contains(&day), "{day}");
+    let overviews = client.overviews(&[1234, 5678]).unwrap();
+    assert_eq!(2, overviews.len());
+    assert_eq!(None, overviews[&5678].last_updated_time);
+
+    assert!(matches!(
+        client.overview(9999),
+        Err(SolarApiError::ForbiddenError(_))
+    ));
+    assert!(matches!(
+        Client::new("OTHER").with_base_url(&server.url()).list(),
+        Err(SolarApiError::ForbiddenError(_))
+    ));
+    server.fail_next(503, 1);
+    assert!(matches!(
+        client.overview(1234),
+        Err(SolarApiError::ApiError(e)) if e.status().map(|s| s.as_u16()) == Some(503)
+    ));
+    // 7 requests of today with the API key count, the failed ones as well
+    assert_eq!(None, client.data_period(5678).unwrap().start_date);
+    client.details(1234).unwrap();
+    client.overview(1234).unwrap();
+    assert!(matches!(
+        client.overview(1234),
+        Err(SolarApiError::ApiError(e)) if e.status().map(|s| s.as_u16()) == Some(429)
+    ));
+    server.advance(Duration::days(1));
+    client.overview(1234).unwrap();
+    assert_eq!("/sites/list", server.requests()[0]);
+    assert_eq!(
+        "/site/1234/power?endTime=2023-06-01 12:30:00&startTime=2023-06-01 12:00:00",
+        server.requests()[2]
+    );
+}
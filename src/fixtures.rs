@@ -0,0 +1,324 @@
+//! Replies of the SolarEdge API for every endpoint of [`crate::Client`], to
+//! mock this crate or the API in tests. Requires the `test-util` feature.
+//!
+//! The replies are real replies with the names, addresses, ids and serial
+//! numbers replaced. They include the edge cases that applications have to
+//! handle:
+//! * a site that is pending communication, without last update time, data
+//!   period, peak power or values
+//! * `null` values for the future and the night, and missing values in the
+//!   meters of the energy and power details
+//! * energy in kWh instead of Wh
+//! * a site without batteries
+//!
+//! Every [`Fixture`] has the path and parameters of its request, so it can be
+//! served by a mock server or the proxy of a test.
+//!
+//! ```
+//! use solar_api::fixtures::{self, SITE_ID};
+//!
+//! let fixture = fixtures::get("overview").unwrap();
+//! assert_eq!(format!("/site/{SITE_ID}/overview"), fixture.path);
+//! assert_eq!(1173.5, fixture.json()["overview"]["currentPower"]["power"]);
+//! ```
+
+#[cfg(test)]
+use crate::{
+    equipment::{EquipmentListReply, InverterDataReply},
+    meters::{EnergyDetailsReply, MeterType, PowerDetailsReply},
+    portfolio::{OverviewsReply, SitesEnergyReply},
+    power_flow::PowerFlowReply,
+    site::{
+        DataPeriodReply, GeneratedEnergyReply, GeneratedPowerReply, OverviewReply, SiteDetails,
+        SitesReply,
+    },
+    storage::StorageDataReply,
+    units::{as_watt_hours, as_watts},
+};
+
+#[cfg(feature = "test-util")]
+pub mod record;
+#[cfg(feature = "wiremock")]
+pub mod wiremock;
+
+/// The id of the active site of the fixtures
+pub const SITE_ID: u32 = 1234567;
+/// The id of the site of the fixtures that is pending communication
+pub const PENDING_SITE_ID: u32 = 7654321;
+/// The serial number of the inverter of [`SITE_ID`]
+pub const INVERTER_SERIAL_NUMBER: &str = "7E000000-2F";
+
+/// A reply of the API with its request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixture {
+    /// the name of the fixture, i.e. `energy_month_kwh`
+    pub name: &'static str,
+    /// the path of the request, i.e. `/site/1234567/energy`
+    pub path: &'static str,
+    /// the parameters of the request that select this reply, besides the API
+    /// key and the period
+    pub query: &'static [(&'static str, &'static str)],
+    /// the JSON of the reply
+    pub body: &'static str,
+}
+
+impl Fixture {
+    /// The reply as JSON value
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(self.body).expect("fixtures are valid JSON")
+    }
+}
+
+/// The sites of the account, one active and one pending communication
+pub const SITES_LIST: Fixture = Fixture {
+    name: "sites_list",
+    path: "/sites/list",
+    query: &[],
+    body: include_str!("fixtures/sites_list.json"),
+};
+
+/// The details of the active site
+pub const SITE_DETAILS: Fixture = Fixture {
+    name: "site_details",
+    path: "/site/1234567/details",
+    query: &[],
+    body: include_str!("fixtures/site_details.json"),
+};
+
+/// The details of a site that did not report yet, without last update time
+/// and peak power
+pub const SITE_DETAILS_PENDING: Fixture = Fixture {
+    name: "site_details_pending",
+    path: "/site/7654321/details",
+    query: &[],
+    body: include_str!("fixtures/site_details_pending.json"),
+};
+
+/// The period with data of the active site
+pub const DATA_PERIOD: Fixture = Fixture {
+    name: "data_period",
+    path: "/site/1234567/dataPeriod",
+    query: &[],
+    body: include_str!("fixtures/data_period.json"),
+};
+
+/// The data period of the pending site, without start and end date
+pub const DATA_PERIOD_PENDING: Fixture = Fixture {
+    name: "data_period_pending",
+    path: "/site/7654321/dataPeriod",
+    query: &[],
+    body: include_str!("fixtures/data_period_pending.json"),
+};
+
+/// The overview of the active site, with revenue for the lifetime only
+pub const OVERVIEW: Fixture = Fixture {
+    name: "overview",
+    path: "/site/1234567/overview",
+    query: &[],
+    body: include_str!("fixtures/overview.json"),
+};
+
+/// The overview of the pending site, without last update time and all zero
+pub const OVERVIEW_PENDING: Fixture = Fixture {
+    name: "overview_pending",
+    path: "/site/7654321/overview",
+    query: &[],
+    body: include_str!("fixtures/overview_pending.json"),
+};
+
+/// The overviews of both sites of the bulk API, all zero and without last
+/// update time for the pending site
+pub const OVERVIEWS: Fixture = Fixture {
+    name: "overviews",
+    path: "/sites/1234567,7654321/overview",
+    query: &[],
+    body: include_str!("fixtures/overviews.json"),
+};
+
+/// The energy per day of the active site in Wh, `null` for tomorrow
+pub const ENERGY_DAY: Fixture = Fixture {
+    name: "energy_day",
+    path: "/site/1234567/energy",
+    query: &[("timeUnit", "DAY")],
+    body: include_str!("fixtures/energy_day.json"),
+};
+
+/// The energy per month of the active site in kWh, `null` for next month
+pub const ENERGY_MONTH_KWH: Fixture = Fixture {
+    name: "energy_month_kwh",
+    path: "/site/1234567/energy",
+    query: &[("timeUnit", "MONTH")],
+    body: include_str!("fixtures/energy_month_kwh.json"),
+};
+
+/// The energy per day of the pending site, only `null` values
+pub const ENERGY_PENDING: Fixture = Fixture {
+    name: "energy_pending",
+    path: "/site/7654321/energy",
+    query: &[("timeUnit", "DAY")],
+    body: include_str!("fixtures/energy_pending.json"),
+};
+
+/// The energy per day of both sites of the bulk API
+pub const SITES_ENERGY: Fixture = Fixture {
+    name: "sites_energy",
+    path: "/sites/1234567,7654321/energy",
+    query: &[("timeUnit", "DAY")],
+    body: include_str!("fixtures/sites_energy.json"),
+};
+
+/// The power of the active site in the morning, `null` before sunrise and
+/// for the quarter that is not published yet
+pub const POWER: Fixture = Fixture {
+    name: "power",
+    path: "/site/1234567/power",
+    query: &[],
+    body: include_str!("fixtures/power.json"),
+};
+
+/// The current power flow of the active site, with the battery discharging
+pub const CURRENT_POWER_FLOW: Fixture = Fixture {
+    name: "current_power_flow",
+    path: "/site/1234567/currentPowerFlow",
+    query: &[],
+    body: include_str!("fixtures/current_power_flow.json"),
+};
+
+/// The energy per meter of the active site, a value of today is missing
+pub const ENERGY_DETAILS: Fixture = Fixture {
+    name: "energy_details",
+    path: "/site/1234567/energyDetails",
+    query: &[],
+    body: include_str!("fixtures/energy_details.json"),
+};
+
+/// The power per meter of the active site, the last values are missing
+pub const POWER_DETAILS: Fixture = Fixture {
+    name: "power_details",
+    path: "/site/1234567/powerDetails",
+    query: &[],
+    body: include_str!("fixtures/power_details.json"),
+};
+
+/// The telemetry of the battery of the active site, charging and then
+/// discharging
+pub const STORAGE_DATA: Fixture = Fixture {
+    name: "storage_data",
+    path: "/site/1234567/storageData",
+    query: &[],
+    body: include_str!("fixtures/storage_data.json"),
+};
+
+/// The storage data of a site without batteries
+pub const STORAGE_DATA_NONE: Fixture = Fixture {
+    name: "storage_data_none",
+    path: "/site/7654321/storageData",
+    query: &[],
+    body: include_str!("fixtures/storage_data_none.json"),
+};
+
+/// The inverters of the active site
+pub const EQUIPMENT_LIST: Fixture = Fixture {
+    name: "equipment_list",
+    path: "/equipment/1234567/list",
+    query: &[],
+    body: include_str!("fixtures/equipment_list.json"),
+};
+
+/// The telemetry of the inverter of the active site
+pub const INVERTER_DATA: Fixture = Fixture {
+    name: "inverter_data",
+    path: "/equipment/1234567/7E000000-2F/data",
+    query: &[],
+    body: include_str!("fixtures/inverter_data.json"),
+};
+
+/// Every fixture
+pub const ALL: &[Fixture] = &[
+    SITES_LIST,
+    SITE_DETAILS,
+    SITE_DETAILS_PENDING,
+    DATA_PERIOD,
+    DATA_PERIOD_PENDING,
+    OVERVIEW,
+    OVERVIEW_PENDING,
+    OVERVIEWS,
+    ENERGY_DAY,
+    ENERGY_MONTH_KWH,
+    ENERGY_PENDING,
+    SITES_ENERGY,
+    POWER,
+    CURRENT_POWER_FLOW,
+    ENERGY_DETAILS,
+    POWER_DETAILS,
+    STORAGE_DATA,
+    STORAGE_DATA_NONE,
+    EQUIPMENT_LIST,
+    INVERTER_DATA,
+];
+
+/// The fixture with `name` or None when there is no such fixture
+pub fn get(name: &str) -> Option<&'static Fixture> {
+    ALL.iter().find(|fixture| fixture.name == name)
+}
+
+#[test]
+fn test_fixtures_parse() {
+    let parse = |fixture: Fixture| -> Result<(), serde_json::Error> {
+        let body = fixture.body;
+        match fixture.path.rsplit('/').next().unwrap_or_default() {
+            "list" if fixture.path.starts_with("/sites") => {
+                serde_json::from_str::<SitesReply>(body).map(drop)
+            }
+            "list" => serde_json::from_str::<EquipmentListReply>(body).map(drop),
+            "details" => serde_json::from_str::<SiteDetails>(body).map(drop),
+            "dataPeriod" => serde_json::from_str::<DataPeriodReply>(body).map(drop),
+            "overview" if fixture.path.starts_with("/sites") => {
+                serde_json::from_str::<OverviewsReply>(body).map(drop)
+            }
+            "overview" => serde_json::from_str::<OverviewReply>(body).map(drop),
+            "energy" if fixture.path.starts_with("/sites") => {
+                serde_json::from_str::<SitesEnergyReply>(body).map(drop)
+            }
+            "energy" => serde_json::from_str::<GeneratedEnergyReply>(body).map(drop),
+            "power" => serde_json::from_str::<GeneratedPowerReply>(body).map(drop),
+            "currentPowerFlow" => serde_json::from_str::<PowerFlowReply>(body).map(drop),
+            "energyDetails" => serde_json::from_str::<EnergyDetailsReply>(body).map(drop),
+            "powerDetails" => serde_json::from_str::<PowerDetailsReply>(body).map(drop),
+            "storageData" => serde_json::from_str::<StorageDataReply>(body).map(drop),
+            "data" => serde_json::from_str::<InverterDataReply>(body).map(drop),
+            path => panic!("no reply type for {path}"),
+        }
+    };
+    for fixture in ALL {
+        parse(*fixture).unwrap_or_else(|e| panic!("{}: {e}", fixture.name));
+    }
+
+    let sites: SitesReply = serde_json::from_str(SITES_LIST.body).unwrap();
+    assert_eq!(None, sites.sites()[1].last_update_time);
+    let overview: OverviewReply = serde_json::from_str(OVERVIEW_PENDING.body).unwrap();
+    assert_eq!(None, overview.overview.last_updated_time);
+    let overviews: OverviewsReply = serde_json::from_str(OVERVIEWS.body).unwrap();
+    let pending = overviews.overviews().find(|(id, _)| *id == PENDING_SITE_ID);
+    assert_eq!(None, pending.unwrap().1.last_updated_time);
+    let period: DataPeriodReply = serde_json::from_str(DATA_PERIOD_PENDING.body).unwrap();
+    assert_eq!(None, period.data_period.start_date);
+    assert_eq!(None, period.data_period.end_date);
+    let month: GeneratedEnergyReply = serde_json::from_str(ENERGY_MONTH_KWH.body).unwrap();
+    let month = month.energy.to_series().unwrap();
+    assert_eq!(Some(912_512.0), month.values[0].value.map(as_watt_hours));
+    assert_eq!(None, month.values[4].value);
+    let power: GeneratedPowerReply = serde_json::from_str(POWER.body).unwrap();
+    let power = power.power.to_series().unwrap();
+    assert_eq!(Some(1173.5), power.values[4].value.map(as_watts));
+    let flow: PowerFlowReply = serde_json::from_str(CURRENT_POWER_FLOW.body).unwrap();
+    let storage = flow.site_current_power_flow.storage.unwrap();
+    assert_eq!(Some(61.0), storage.charge_level);
+    let details: EnergyDetailsReply = serde_json::from_str(ENERGY_DETAILS.body).unwrap();
+    assert_eq!(5, details.energy_details.meters().len());
+    let purchased = details.energy_details.series(MeterType::Purchased);
+    assert_eq!(None, purchased.unwrap().unwrap().values[1].value);
+    let storage: StorageDataReply = serde_json::from_str(STORAGE_DATA_NONE.body).unwrap();
+    assert!(storage.storage_data.batteries.is_empty());
+    assert_eq!(Some(&ENERGY_MONTH_KWH), get("energy_month_kwh"));
+}
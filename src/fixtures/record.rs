@@ -0,0 +1,381 @@
+//! Record replies of the API as fixtures that are safe to commit and to attach
+//! to bug reports. Requires the `test-util` feature.
+//!
+//! [`Client::with_recorder`](crate::Client::with_recorder) writes every reply
+//! to a file after [`Sanitizer`] replaced
+//! * the API key
+//! * site ids, in the reply, the path and in strings like the `uris` of a site
+//! * account ids
+//! * the name, address, zip code and notes of sites
+//! * serial numbers, in the reply and the path
+//!
+//! The pseudonyms are derived from the API key and the original value, so a
+//! site gets the same pseudonym in every recording with the same API key, and
+//! cannot be traced back without the API key.
+//!
+//! ```no_run
+//! # use solar_api::Client;
+//! let client = Client::new("API_KEY").with_recorder("fixtures");
+//! // writes fixtures/sites_list.json and fixtures/site_4518735_overview.json
+//! let sites = client.list()?;
+//! let overview = client.overview(sites[0].id)?;
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use crate::{cache::fnv1a, is_serial_number, SolarApiError};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+// the replacement of the API key
+const API_KEY: &str = "API_KEY";
+
+/// Replaces the identifying values in replies of the API by stable pseudonyms
+#[derive(Clone)]
+pub struct Sanitizer {
+    api_key: String,
+}
+
+impl fmt::Debug for Sanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sanitizer").finish_non_exhaustive()
+    }
+}
+
+// the pseudonyms of the site ids and serial numbers of one reply, which are
+// replaced in strings as well
+#[derive(Debug, Default)]
+struct Pseudonyms {
+    site_ids: BTreeMap<String, String>,
+    serial_numbers: BTreeMap<String, String>,
+}
+
+impl Sanitizer {
+    /// Create a sanitizer for the replies to `api_key`
+    pub fn new(api_key: &str) -> Self {
+        Sanitizer {
+            api_key: api_key.to_string(),
+        }
+    }
+
+    /// The pseudonym of `site_id`, a number of 7 digits
+    pub fn site_id(&self, site_id: u64) -> u64 {
+        1_000_000 + self.hash("site", &site_id.to_string()) % 9_000_000
+    }
+
+    /// The pseudonym of `account_id`, a number of 6 digits
+    pub fn account_id(&self, account_id: u64) -> u64 {
+        100_000 + self.hash("account", &account_id.to_string()) % 900_000
+    }
+
+    /// The pseudonym of `serial_number`, like `7E1A2B3C-4D`
+    pub fn serial_number(&self, serial_number: &str) -> String {
+        let hash = self.hash("serial", serial_number);
+        format!("{:08X}-{:02X}", hash as u32, (hash >> 32) as u8)
+    }
+
+    /// The sanitized path of a request and its reply. A reply that is not JSON
+    /// only gets the identifiers of the path and the API key replaced
+    pub fn sanitize(&self, path: &str, reply: &str) -> (String, String) {
+        let mut pseudonyms = Pseudonyms::default();
+        self.collect_path(path, &mut pseudonyms);
+        let reply = match serde_json::from_str::<Value>(reply) {
+            Ok(mut json) => {
+                self.collect(&json, &mut pseudonyms);
+                self.replace(&mut json, &pseudonyms);
+                json.to_string()
+            }
+            Err(_) => self.replace_text(reply, &pseudonyms),
+        };
+        (self.replace_text(path, &pseudonyms), reply)
+    }
+
+    // the site ids and serial numbers in paths like `/sites/1,2/overview` and
+    // `/equipment/1/SERIAL/data`
+    fn collect_path(&self, path: &str, pseudonyms: &mut Pseudonyms) {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        if let [kind @ ("site" | "sites" | "equipment"), ids, rest @ ..] = segments.as_slice() {
+            for id in ids.split(',').filter_map(|id| id.parse::<u64>().ok()) {
+                self.add_site_id(id, pseudonyms);
+            }
+            if let ("equipment", [serial_number, "data"]) = (*kind, rest) {
+                self.add_serial_number(serial_number, pseudonyms);
+            }
+        }
+    }
+
+    // the site ids and serial numbers in `json`
+    fn collect(&self, json: &Value, pseudonyms: &mut Pseudonyms) {
+        match json {
+            Value::Object(object) => {
+                for (key, value) in object {
+                    match (key.as_str(), value) {
+                        ("id", Value::Number(id)) if object.contains_key("accountId") => {
+                            if let Some(id) = id.as_u64() {
+                                self.add_site_id(id, pseudonyms);
+                            }
+                        }
+                        ("siteId", Value::Number(id)) => {
+                            if let Some(id) = id.as_u64() {
+                                self.add_site_id(id, pseudonyms);
+                            }
+                        }
+                        (key, Value::String(serial_number)) if is_serial_number(key) => {
+                            self.add_serial_number(serial_number, pseudonyms);
+                        }
+                        _ => self.collect(value, pseudonyms),
+                    }
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.collect(value, pseudonyms);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn replace(&self, json: &mut Value, pseudonyms: &Pseudonyms) {
+        match json {
+            Value::Object(object) => {
+                let is_site = object.contains_key("accountId");
+                let site_id = object.get("id").and_then(Value::as_u64);
+                for (key, value) in object.iter_mut() {
+                    self.replace_field(key, value, is_site, site_id, pseudonyms);
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.replace(value, pseudonyms);
+                }
+            }
+            Value::String(text) => *text = self.replace_text(text, pseudonyms),
+            _ => {}
+        }
+    }
+
+    // replaces the value of `key` in an object, which is a site when it has an
+    // account id
+    fn replace_field(
+        &self,
+        key: &str,
+        value: &mut Value,
+        is_site: bool,
+        site_id: Option<u64>,
+        pseudonyms: &Pseudonyms,
+    ) {
+        let pseudonym = |value: &Value, pseudonym: &dyn Fn(u64) -> u64| {
+            value
+                .as_u64()
+                .map_or(Value::Null, |id| Value::from(pseudonym(id)))
+        };
+        let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+        *value = match key {
+            "id" if is_site => pseudonym(value, &|id| self.site_id(id)),
+            "siteId" => pseudonym(value, &|id| self.site_id(id)),
+            "accountId" => pseudonym(value, &|id| self.account_id(id)),
+            "name" if is_site => match site_id {
+                Some(site_id) => Value::from(format!("Site {}", self.site_id(site_id))),
+                None => Value::from("Site"),
+            },
+            "notes" if is_site => Value::from(""),
+            "address" => Value::from(format!(
+                "Street {}",
+                self.hash("address", &text(value)) % 100 + 1
+            )),
+            "address2" if !text(value).is_empty() => Value::from(format!(
+                "Apartment {}",
+                self.hash("address2", &text(value)) % 100 + 1
+            )),
+            "zip" => Value::from(format!("{}", self.hash("zip", &text(value)) % 9000 + 1000)),
+            key if is_serial_number(key) && value.is_string() => {
+                Value::from(self.serial_number(&text(value)))
+            }
+            _ => {
+                self.replace(value, pseudonyms);
+                return;
+            }
+        };
+    }
+
+    // replaces the API key, the serial numbers and the numbers that are site
+    // ids in `text`
+    fn replace_text(&self, text: &str, pseudonyms: &Pseudonyms) -> String {
+        let mut text = match self.api_key.is_empty() {
+            true => text.to_string(),
+            false => text.replace(&self.api_key, API_KEY),
+        };
+        for (serial_number, pseudonym) in &pseudonyms.serial_numbers {
+            text = text.replace(serial_number, pseudonym);
+        }
+        // replace whole numbers only, so site 12 does not change 2012
+        let mut replaced = String::with_capacity(text.len());
+        let mut number = String::new();
+        for c in text.chars().chain(std::iter::once('\0')) {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let id = pseudonyms.site_ids.get(&number);
+            replaced.push_str(id.unwrap_or(&number));
+            number.clear();
+            if c != '\0' {
+                replaced.push(c);
+            }
+        }
+        replaced
+    }
+
+    fn add_site_id(&self, site_id: u64, pseudonyms: &mut Pseudonyms) {
+        pseudonyms
+            .site_ids
+            .insert(site_id.to_string(), self.site_id(site_id).to_string());
+    }
+
+    fn add_serial_number(&self, serial_number: &str, pseudonyms: &mut Pseudonyms) {
+        if !serial_number.is_empty() {
+            pseudonyms
+                .serial_numbers
+                .insert(serial_number.to_string(), self.serial_number(serial_number));
+        }
+    }
+
+    // a hash of `value` keyed with the API key, so pseudonyms cannot be traced
+    // back by hashing all possible ids
+    fn hash(&self, kind: &str, value: &str) -> u64 {
+        let hash = fnv1a(&format!("{}\n{kind}\n{value}", self.api_key));
+        // spread the bits of the hash, as FNV-1a mixes the last bytes poorly
+        let hash = (hash ^ (hash >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+        hash ^ (hash >> 33)
+    }
+}
+
+/// Writes sanitized replies to a directory, see the [module](self)
+#[derive(Debug, Clone)]
+pub struct FixtureRecorder {
+    dir: PathBuf,
+    sanitizer: Sanitizer,
+}
+
+impl FixtureRecorder {
+    /// Create a recorder that writes the replies sanitized with `sanitizer` to
+    /// `dir`. The directory is created on the first write
+    pub fn new(dir: impl Into<PathBuf>, sanitizer: Sanitizer) -> Self {
+        FixtureRecorder {
+            dir: dir.into(),
+            sanitizer,
+        }
+    }
+
+    /// The directory of the fixtures
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Write the sanitized `reply` of the request of `path` with `params` and
+    /// return the path of the file. The file is named after the sanitized path
+    /// and parameters, i.e. `site_4518735_energy_DAY_2023-11-01_2023-11-09.json`,
+    /// so recording the same request again replaces it
+    pub fn record(
+        &self,
+        path: &str,
+        params: &HashMap<String, String>,
+        reply: &str,
+    ) -> Result<PathBuf, SolarApiError> {
+        let (path, reply) = self.sanitizer.sanitize(path, reply);
+        let mut params: Vec<(&String, &String)> = params
+            .iter()
+            .filter(|(name, _)| *name != "api_key")
+            .collect();
+        params.sort();
+        let name: String = std::iter::once(path.trim_matches('/'))
+            .chain(params.iter().map(|(_, value)| value.as_str()))
+            .collect::<Vec<_>>()
+            .join("_")
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() || c == '-' {
+                true => c,
+                false => '_',
+            })
+            .collect();
+        let file = self.dir.join(format!("{name}.json"));
+        let fixture_io = |path: &Path, source| SolarApiError::FixtureIo {
+            path: path.display().to_string(),
+            source,
+        };
+        std::fs::create_dir_all(&self.dir).map_err(|source| fixture_io(&self.dir, source))?;
+        std::fs::write(&file, reply).map_err(|source| fixture_io(&file, source))?;
+        Ok(file)
+    }
+}
+
+#[test]
+fn test_sanitize() {
+    let sanitizer = Sanitizer::new("L4QLVQ1LOKCQX2193VSEICXW61NP6B1O");
+    let reply = r#"{"details":{"id":1234123,"name":"Jansen family","accountId":123456,
+        "status":"Active","notes":"call 0612345678","location":{"address":"Kerkstraat 12",
+        "address2":"","zip":"1234 AB"},"uris":{"DETAILS":"/site/1234123/details"}}}"#;
+    let (path, sanitized) = sanitizer.sanitize("/site/1234123/details", reply);
+    let site_id = sanitizer.site_id(1234123);
+    assert_eq!(format!("/site/{site_id}/details"), path);
+    for secret in [
+        "1234123",
+        "123456",
+        "Jansen",
+        "0612345678",
+        "Kerkstraat",
+        "1234 AB",
+    ] {
+        assert!(!sanitized.contains(secret), "{secret} in {sanitized}");
+    }
+    let json: Value = serde_json::from_str(&sanitized).unwrap();
+    assert_eq!(site_id, json["details"]["id"]);
+    assert_eq!(format!("Site {site_id}"), json["details"]["name"]);
+    assert_eq!(
+        format!("/site/{site_id}/details"),
+        json["details"]["uris"]["DETAILS"]
+    );
+    assert_eq!("", json["details"]["location"]["address2"]);
+    assert_eq!("Active", json["details"]["status"]);
+    // the pseudonyms are stable, but depend on the API key
+    assert_eq!(
+        (path.clone(), sanitized),
+        sanitizer.sanitize("/site/1234123/details", reply)
+    );
+    assert_ne!(site_id, Sanitizer::new("OTHER").site_id(1234123));
+
+    let serial_number = sanitizer.serial_number("7E140000-2F");
+    let (path, sanitized) = sanitizer.sanitize(
+        "/equipment/1234123/7E140000-2F/data",
+        r#"{"reporters":{"count":1,"list":[{"name":"Inverter 1","serialNumber":"7E140000-2F"}]},
+            "date":"2023-11-09 12:00:00","url":"https://x/?api_key=L4QLVQ1LOKCQX2193VSEICXW61NP6B1O"}"#,
+    );
+    assert_eq!(format!("/equipment/{site_id}/{serial_number}/data"), path);
+    let json: Value = serde_json::from_str(&sanitized).unwrap();
+    assert_eq!(serial_number, json["reporters"]["list"][0]["serialNumber"]);
+    assert_eq!("Inverter 1", json["reporters"]["list"][0]["name"]);
+    assert_eq!("2023-11-09 12:00:00", json["date"]);
+    assert_eq!("https://x/?api_key=API_KEY", json["url"]);
+}
+
+#[test]
+fn test_recorder() {
+    use crate::fake_server::{FakeServer, FakeSite, Scenario};
+
+    let dir = std::env::temp_dir().join(format!("solar-api-fixtures-{}", std::process::id()));
+    let server =
+        FakeServer::start(Scenario::new("SECRET").with_site(FakeSite::new(1234, 5.0))).unwrap();
+    let client = server.client().with_recorder(&dir);
+    client.details(1234).unwrap();
+
+    let site_id = Sanitizer::new("SECRET").site_id(1234);
+    let file = dir.join(format!("site_{site_id}_details.json"));
+    let fixture = std::fs::read_to_string(&file).unwrap();
+    assert!(!fixture.contains("1234"), "{fixture}");
+    assert!(fixture.contains(&format!("\"id\":{site_id}")), "{fixture}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
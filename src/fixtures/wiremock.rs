@@ -0,0 +1,128 @@
+//! Serve the [fixtures](super) with a [`wiremock::MockServer`], to test an
+//! application against the replies of every endpoint. Requires the `wiremock`
+//! feature.
+//!
+//! Requests with the API key are answered with the fixture of their path and
+//! parameters, requests with another API key with status 403 like the API
+//! does. The [`Client`] is blocking, so do not call it on a thread of an async
+//! runtime: use [`start_blocking`] in a plain test, or call the client in
+//! `tokio::task::spawn_blocking` after [`start`].
+//!
+//! ```
+//! use solar_api::fixtures::{self, wiremock::start_blocking};
+//!
+//! let (_server, client) = start_blocking("API_KEY");
+//! let overview = client.overview(fixtures::SITE_ID)?;
+//! assert_eq!(
+//!     "2023-11-09 10:28:56",
+//!     overview.last_updated_time.unwrap().to_string()
+//! );
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+//!
+//! Stubs mounted on the server after [`mount`] take precedence over the
+//! fixtures, i.e. to let an endpoint fail.
+
+use super::{Fixture, ALL};
+#[cfg(test)]
+use super::{PENDING_SITE_ID, SITE_ID};
+use crate::client::Client;
+#[cfg(test)]
+use crate::{SolarApiError, TimeUnit};
+use ::wiremock::{
+    matchers::{method, path, query_param},
+    Match, Mock, MockServer, Request, ResponseTemplate,
+};
+#[cfg(test)]
+use chrono::NaiveTime;
+
+// the priority of the stubs of the fixtures, below the default of 5 of
+// wiremock, so stubs of the test take precedence
+const FIXTURE_PRIORITY: u8 = 10;
+
+/// Start a mock server with the fixtures and return it with a client with
+/// `api_key` that calls it. The server stops when it is dropped
+pub async fn start(api_key: &str) -> (MockServer, Client) {
+    let server = MockServer::start().await;
+    mount(&server, api_key).await;
+    let client = Client::new(api_key).with_base_url(&server.uri());
+    (server, client)
+}
+
+/// Like [`start`], for tests that are not async
+pub fn start_blocking(api_key: &str) -> (MockServer, Client) {
+    futures_executor::block_on(start(api_key))
+}
+
+/// Mount a stub for every fixture on `server` that answers requests with
+/// `api_key`, and a stub that answers requests with another API key with
+/// status 403
+pub async fn mount(server: &MockServer, api_key: &str) {
+    for fixture in ALL {
+        stub(fixture, api_key).mount(server).await;
+    }
+    Mock::given(method("GET"))
+        .and(OtherApiKey(api_key.to_string()))
+        .respond_with(ResponseTemplate::new(403).set_body_string("Invalid token"))
+        .with_priority(FIXTURE_PRIORITY)
+        .named("another API key")
+        .mount(server)
+        .await;
+}
+
+/// The stub that answers the request of `fixture` with its reply
+pub fn stub(fixture: &Fixture, api_key: &str) -> Mock {
+    let mock = fixture.query.iter().fold(
+        Mock::given(method("GET"))
+            .and(path(fixture.path))
+            .and(query_param("api_key", api_key)),
+        |mock, (name, value)| mock.and(query_param(*name, *value)),
+    );
+    mock.respond_with(
+        ResponseTemplate::new(200).set_body_raw(fixture.body, "application/json;charset=UTF-8"),
+    )
+    .with_priority(FIXTURE_PRIORITY)
+    .named(fixture.name)
+}
+
+// matches requests without the API key
+struct OtherApiKey(String);
+
+impl Match for OtherApiKey {
+    fn matches(&self, request: &Request) -> bool {
+        !request
+            .url
+            .query_pairs()
+            .any(|(name, value)| name == "api_key" && value == self.0.as_str())
+    }
+}
+
+#[test]
+fn test_start_blocking() {
+    let (server, client) = start_blocking("KEY");
+    let sites = client.list().unwrap();
+    assert_eq!(2, sites.len());
+    assert!(client.details(PENDING_SITE_ID).is_ok());
+    let pending = client.overview(PENDING_SITE_ID).unwrap();
+    assert_eq!(None, pending.last_updated_time);
+    let period = client.data_period(SITE_ID).unwrap();
+    let energy = client.energy(SITE_ID, period, TimeUnit::Month).unwrap();
+    assert_eq!("kWh", energy.unit());
+    let now = sites[0].last_update_time.unwrap().and_time(NaiveTime::MIN);
+    let storage = client.storage_data(SITE_ID, now, now, &[]).unwrap();
+    assert_eq!(1, storage.battery_count);
+
+    futures_executor::block_on(
+        Mock::given(path("/site/1234567/overview"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server),
+    );
+    assert!(matches!(
+        client.overview(SITE_ID),
+        Err(SolarApiError::ApiError(_))
+    ));
+    assert!(matches!(
+        Client::new("OTHER").with_base_url(&server.uri()).list(),
+        Err(SolarApiError::ForbiddenError(_))
+    ));
+}
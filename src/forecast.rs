@@ -0,0 +1,131 @@
+//! Forecasts the production of a site for the next day from its history, i.e.
+//! to schedule charging a battery or running appliances when the sun shines
+
+#[cfg(test)]
+use crate::units::watt_hours;
+use crate::{
+    series::{Aggregation, TimeSeries, TimeSeriesValue},
+    site::TimeUnit,
+    units::{as_watt_hours, Energy, EnergyUnit},
+};
+use chrono::{Duration, NaiveDate, Timelike};
+use std::collections::BTreeMap;
+
+// the factor of the standard deviation for the 80% band of a normal distribution
+const BAND_FACTOR: f64 = 1.2816;
+
+/// The forecasted energy per hour of a day with an uncertainty band, see
+/// [`profile_forecast`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Forecast {
+    /// the date of the forecast
+    pub date: NaiveDate,
+    /// the expected energy per hour
+    pub expected: TimeSeries<Energy>,
+    /// the lower bound of the 80% band per hour
+    pub lower: TimeSeries<Energy>,
+    /// the upper bound of the 80% band per hour
+    pub upper: TimeSeries<Energy>,
+}
+
+impl Forecast {
+    /// The expected energy of the whole day
+    pub fn total(&self) -> Energy {
+        self.expected.total()
+    }
+}
+
+/// Forecasts the energy per hour on `date` from the last `days` days before
+/// `date` with values in `history`. The expected energy of an hour is the
+/// mean of that hour over these days and the band is the mean plus or minus
+/// 1.28 standard deviation, which holds 80% of the values when they are
+/// normally distributed. Using one day gives a seasonal naive forecast without
+/// band. Returns None when `history` has no values before `date`
+pub fn profile_forecast(
+    history: &TimeSeries<Energy>,
+    date: NaiveDate,
+    days: usize,
+) -> Option<Forecast> {
+    let mut per_day: BTreeMap<NaiveDate, BTreeMap<u32, f64>> = BTreeMap::new();
+    for v in history.resample(TimeUnit::Hour, Aggregation::Sum) {
+        if let Some(value) = v.value.filter(|_| v.date.date() < date) {
+            per_day
+                .entry(v.date.date())
+                .or_default()
+                .insert(v.date.hour(), as_watt_hours(value));
+        }
+    }
+    let mut per_hour: BTreeMap<u32, Vec<f64>> = BTreeMap::new();
+    for hours in per_day.values().rev().take(days) {
+        for (hour, value) in hours {
+            per_hour.entry(*hour).or_default().push(*value);
+        }
+    }
+    if per_hour.is_empty() {
+        return None;
+    }
+
+    let midnight = date.and_time(chrono::NaiveTime::MIN);
+    let mut expected = Vec::with_capacity(24);
+    let mut lower = Vec::with_capacity(24);
+    let mut upper = Vec::with_capacity(24);
+    for hour in 0..24 {
+        let timestamp = midnight + Duration::hours(hour.into());
+        let (mean, band) = match per_hour.get(&hour) {
+            Some(values) => {
+                let mean = values.iter().sum::<f64>() / values.len() as f64;
+                let variance =
+                    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+                (Some(mean), BAND_FACTOR * variance.sqrt())
+            }
+            None => (None, 0.0),
+        };
+        let value = |wh: Option<f64>| TimeSeriesValue {
+            date: timestamp,
+            value: wh.map(|wh| EnergyUnit::WattHour.energy(wh.max(0.0))),
+        };
+        expected.push(value(mean));
+        lower.push(value(mean.map(|mean| mean - band)));
+        upper.push(value(mean.map(|mean| mean + band)));
+    }
+    Some(Forecast {
+        date,
+        expected: TimeSeries::new(TimeUnit::Hour, "Wh", expected),
+        lower: TimeSeries::new(TimeUnit::Hour, "Wh", lower),
+        upper: TimeSeries::new(TimeUnit::Hour, "Wh", upper),
+    })
+}
+
+#[test]
+fn test_profile_forecast() {
+    let start = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap();
+    // three days producing 1000, 2000 and 3000 Wh at noon, and a day after the
+    // forecasted date that must be ignored
+    let values = [1000.0, 2000.0, 3000.0, 9000.0]
+        .iter()
+        .enumerate()
+        .map(|(day, wh)| TimeSeriesValue {
+            date: (start + Duration::days(day as i64))
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+            value: Some(watt_hours(*wh)),
+        })
+        .collect();
+    let history = TimeSeries::new(TimeUnit::Hour, "Wh", values);
+    let tomorrow = start + Duration::days(3);
+
+    let forecast = profile_forecast(&history, tomorrow, 7).unwrap();
+    assert_eq!(24, forecast.expected.len());
+    assert_eq!(2000.0, as_watt_hours(forecast.total()));
+    assert_eq!(None, forecast.expected.values[11].value);
+    let lower = as_watt_hours(forecast.lower.values[12].value.unwrap());
+    let upper = as_watt_hours(forecast.upper.values[12].value.unwrap());
+    assert!(lower < 2000.0 && lower > 900.0);
+    assert!((upper - 2000.0 - (2000.0 - lower)).abs() < 1e-6);
+
+    let naive = profile_forecast(&history, tomorrow, 1).unwrap();
+    assert_eq!(3000.0, as_watt_hours(naive.total()));
+    assert_eq!(naive.lower, naive.upper);
+
+    assert!(profile_forecast(&history, start, 7).is_none());
+}
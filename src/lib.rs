@@ -17,39 +17,136 @@
 //!
 //! ```ignore
 //! let site_overview: Overview = overview(api_key, site_id);
-//! let (next_update, duration_from_now) = site_overview.estimated_next_update();
+//! // None when the site has not communicated yet
+//! if let Some((next_update, duration_from_now)) = site_overview.estimated_next_update() {
+//!     // wait, then get power or energy data
+//! }
 //! ```
 //!
-//! Please note that sometimes the API is a bit later. The `duration_from_now` can be negative then and you have to wait a bit more like in the example below. Please note that `checked_add` is needed here to handle adding negative `duration_from_now`.
+//! Please note that sometimes the API is a bit later. The `duration_from_now` can be negative then and you have to wait a bit more. Use `estimated_next_update_with_margin` to wait longer than 10 seconds, or `next_update_schedule` to get the timestamps to retry at, i.e. 15 minutes after the last update plus 10 seconds, 1 minute and 5 minutes. When the time zone of the site is known, `next_attempt_in` returns the first of these that is still in the future and the non-negative duration to wait for it.
 //!
 //! ```ignore
 //! let site_overview: Overview = overview(api_key, site_id);
-//! let (next_update, duration_from_now) = site_overview.estimated_next_update();
+//! let tz = FixedOffset::east_opt(3600).unwrap();
 //!
-//! let next = Instant::now()
-//!     .checked_add(Duration::from_secs(duration_from_now as u64))
-//!     .unwrap_or(Instant::now() + Duration::from_secs(30));
-//!
-//! // wait next or set timeout at next_update before 
-//! // getting power or energy data
-// ```
+//! match site_overview.next_attempt_in(&tz)? {
+//!     // wait, then get power or energy data
+//!     Some((next_update, wait)) => std::thread::sleep(wait),
+//!     // the site did not send new data, check again later
+//!     None => {}
+//! }
+//! ```
 
+pub mod analytics;
+pub mod anomalies;
+pub mod cache;
+#[cfg(feature = "charts")]
+pub mod charts;
+mod client;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod cron;
+#[cfg(feature = "polars")]
+pub mod dataframe;
+#[cfg(feature = "display")]
+pub mod display;
+pub mod emissions;
+#[cfg(feature = "emitters")]
+pub mod emitters;
+mod equipment;
+pub mod export;
+#[cfg(feature = "test-util")]
+pub mod fake_server;
+#[cfg(any(test, feature = "test-util"))]
+pub mod fixtures;
+pub mod forecast;
+mod meters;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod payback;
+pub mod poller;
+mod portfolio;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+mod power_flow;
+pub mod prelude;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+#[cfg(feature = "proxy")]
+pub mod proxy;
+#[cfg(feature = "arrow")]
+pub mod record_batch;
+pub mod report;
+pub mod scheduler;
+pub mod series;
+pub mod simulation;
 mod site;
+#[cfg(feature = "solar-position")]
+pub mod solar_position;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+mod storage;
+mod summary;
+pub mod sync;
+pub mod tariff;
+#[cfg(feature = "time")]
+pub mod time_compat;
+pub mod timezone;
+pub mod units;
+pub mod weather;
+pub mod webhooks;
 
 use chrono::NaiveDateTime;
 use log::{debug, trace};
 use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
 use std::collections::HashMap;
 use thiserror::Error;
 
+pub use client::{Client, MonitoringApi, ParseMode};
+pub use equipment::{Equipment, InverterData, InverterTelemetry, PhaseData};
+pub use meters::{EnergyDetails, MeterType, PowerDetails};
+pub use portfolio::{Portfolio, PortfolioEnergy, SiteRanking, BULK_MAX_SITES};
+pub use power_flow::{PowerFlow, PowerFlowConnection, PowerFlowElement};
+pub use series::{
+    Aggregation, FillReport, FillStrategy, Gap, GapPolicy, Heatmap, Integration, Stats, TimeSeries,
+    TimeSeriesValue,
+};
 pub use site::{
-    DataPeriod, GeneratedEnergy, GeneratedEnergyValue, GeneratedPower, GeneratedPowerPerTimeUnit,
-    GeneratedPowerValue, Location, Overview, PrimaryModule, PublicSettings, Site, TimeData,
-    TimeUnit,
+    power_windows, DataPeriod, GeneratedEnergy, GeneratedEnergyIntoIter, GeneratedEnergyIter,
+    GeneratedEnergyValue, GeneratedPower, GeneratedPowerIntoIter, GeneratedPowerIter,
+    GeneratedPowerPerTimeUnit, GeneratedPowerValue, Location, Overview, PrimaryModule,
+    PublicSettings, Site, TimeData, TimeUnit, ValuesIter, NEXT_UPDATE_BACKOFF_IN_S,
+    REFRESH_TIME_IN_M,
 };
+pub use storage::{Battery, BatteryTelemetry, StorageData};
+pub use summary::{Summary, SummaryBuilder};
+pub use units::{Energy, EnergyUnit, Power, PowerUnit, QuantityUnit, RawQuantity};
+
+// the versions of the dependencies used in the API of this lib, so users do
+// not have to match them in their own Cargo.toml
+#[cfg(feature = "arrow")]
+pub use arrow_array;
+#[cfg(feature = "arrow")]
+pub use arrow_schema;
+pub use chrono;
+#[cfg(feature = "chrono-tz")]
+pub use chrono_tz;
+#[cfg(feature = "polars")]
+pub use polars;
+#[cfg(feature = "mqtt")]
+pub use rumqttc;
+#[cfg(feature = "sqlite")]
+pub use rusqlite;
+#[cfg(feature = "time")]
+pub use time;
+#[cfg(feature = "postgres")]
+pub use tokio_postgres;
+#[cfg(feature = "uom")]
+pub use uom;
 
 /// Possible errors that this lib can return. The underlying errors are included,
-/// either being [`request::Error``] or [`serde_json::Error`]
+/// either being [`reqwest::Error`] or [`serde_json::Error`]
 #[derive(Error, Debug)]
 pub enum SolarApiError {
     #[error("Could not retrieve data from SolarEdge Monitoring API")]
@@ -58,8 +155,146 @@ pub enum SolarApiError {
     ApiError(reqwest::Error),
     #[error("Not allowed to access API. Is the site id valid? Is your API token valid?")]
     ForbiddenError(reqwest::Error),
-    #[error("Could not parse result from SolardEdge monitoring api")]
-    ParseError(#[from] serde_json::Error),
+    #[error("Could not parse result from SolardEdge monitoring api at `{path}`")]
+    ParseError {
+        /// the path in the json reply that could not be parsed, i.e. `overview.lastUpdateTime`
+        path: String,
+        source: serde_json::Error,
+        /// the reply text of the API, truncated to [`MAX_BODY_IN_ERROR`]
+        /// characters, with the API key, the names, addresses and serial
+        /// numbers replaced by `REDACTED`
+        body: String,
+    },
+    #[error("Unsupported unit of measurement returned by API: {0}")]
+    UnsupportedUnit(String),
+    #[error("API returned fields that are not known: {0:?}")]
+    UnknownFields(Vec<String>),
+    #[error("Unknown time unit: {0}")]
+    UnknownTimeUnit(String),
+    #[error("Unknown meter type: {0}")]
+    UnknownMeterType(String),
+    #[error("Reply has no values for meter {0}")]
+    MissingMeter(MeterType),
+    #[error("No emission factor for country {country} in {year}")]
+    MissingEmissionFactor { country: String, year: i32 },
+    #[error("Start date {start} of period is after end date {end}")]
+    InvalidDateRange {
+        start: chrono::NaiveDate,
+        end: chrono::NaiveDate,
+    },
+    #[error("Period is too long for time unit {time_unit}, the API allows at most {max_months} month(s)")]
+    InvalidPeriod {
+        time_unit: TimeUnit,
+        max_months: u32,
+    },
+    #[error("Period has no start or end date, the site has not communicated yet")]
+    EmptyPeriod,
+    #[error("Unknown time zone: {0}")]
+    UnknownTimeZone(String),
+    #[error("Local time {0} does not exist in the time zone of the site")]
+    InvalidLocalTime(NaiveDateTime),
+    #[error("Peak power of the site must be positive, got {0} kWp")]
+    InvalidPeakPower(f64),
+    #[error("Invalid weather data on line {line}: {message}")]
+    InvalidWeatherData { line: usize, message: String },
+    #[error("Invalid cron schedule: {0}")]
+    InvalidCronSchedule(String),
+    #[error("Site {site_id} published no new data in time")]
+    Timeout {
+        site_id: u32,
+        /// the last update of the site, None when it has not communicated yet
+        last_updated: Option<NaiveDateTime>,
+    },
+    #[error("Could not read or write sync state at {path}")]
+    SyncStateIo {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("Could not parse sync state at {path}")]
+    InvalidSyncState {
+        path: String,
+        source: serde_json::Error,
+    },
+    #[error("Could not read or write cache at {path}")]
+    CacheIo {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("Could not parse cached reply at {path}")]
+    InvalidCache {
+        path: String,
+        source: serde_json::Error,
+    },
+    #[error("Could not write export")]
+    ExportIo(#[source] std::io::Error),
+    #[error("Site {0} is not one of the simulated sites")]
+    UnknownSite(u32),
+    #[cfg(feature = "arrow")]
+    #[error("Could not write Parquet file {path}")]
+    ParquetError {
+        path: String,
+        source: parquet::errors::ParquetError,
+    },
+    #[cfg(feature = "prometheus")]
+    #[error("Could not serve metrics")]
+    MetricsServer(#[source] std::io::Error),
+    #[cfg(feature = "mqtt")]
+    #[error("Could not publish to MQTT broker")]
+    MqttError(#[source] rumqttc::ClientError),
+    #[cfg(feature = "postgres")]
+    #[error("Could not write to PostgreSQL")]
+    PostgresError(#[source] tokio_postgres::Error),
+    #[cfg(feature = "charts")]
+    #[error("Could not render chart: {0}")]
+    ChartError(String),
+    #[cfg(feature = "xlsx")]
+    #[error("Could not write Excel workbook")]
+    XlsxError(#[source] rust_xlsxwriter::XlsxError),
+    #[cfg(feature = "polars")]
+    #[error("Could not create data frame")]
+    DataFrameError(#[source] polars::error::PolarsError),
+    #[cfg(feature = "proxy")]
+    #[error("Could not serve proxy")]
+    ProxyServer(#[source] std::io::Error),
+    #[cfg(feature = "emitters")]
+    #[error("Could not emit gauges")]
+    EmitterError(#[source] std::io::Error),
+    #[cfg(feature = "sqlite")]
+    #[error("Could not write to SQLite")]
+    SqliteError(#[source] rusqlite::Error),
+    #[cfg(feature = "config")]
+    #[error("Could not read config file {path}")]
+    ConfigIo {
+        path: String,
+        source: std::io::Error,
+    },
+    #[cfg(feature = "config")]
+    #[error("Could not parse config file {path}")]
+    InvalidConfig {
+        path: String,
+        source: toml::de::Error,
+    },
+    #[cfg(feature = "config")]
+    #[error("Invalid value {value} of {name}")]
+    InvalidConfigValue { name: String, value: String },
+    #[cfg(feature = "config")]
+    #[error("Missing configuration: {0}")]
+    MissingConfig(String),
+    #[cfg(feature = "keyring")]
+    #[error("Could not access the keyring")]
+    KeyringError(#[source] keyring::Error),
+    #[cfg(feature = "test-util")]
+    #[error("Could not serve the fake API")]
+    FakeServer(#[source] std::io::Error),
+    #[cfg(feature = "test-util")]
+    #[error("Could not write fixture {path}")]
+    FixtureIo {
+        path: String,
+        source: std::io::Error,
+    },
+    #[cfg(feature = "time")]
+    #[error("Date or time cannot be converted between chrono and time: {0}")]
+    TimeOutOfRange(String),
 }
 
 impl From<reqwest::Error> for SolarApiError {
@@ -76,7 +311,8 @@ impl From<reqwest::Error> for SolarApiError {
     }
 }
 
-const BASE_URL: &str = "monitoringapi.solaredge.com";
+/// The URL of the SolarEdge Monitoring API, see [`Client::with_base_url`]
+pub const BASE_URL: &str = "https://monitoringapi.solaredge.com";
 
 fn default_map(api_key: &str) -> HashMap<String, String> {
     let mut map = HashMap::new();
@@ -94,9 +330,9 @@ fn map_to_params(map: &HashMap<String, String>) -> String {
     params
 }
 
-fn to_url(path: &str, params: &HashMap<String, String>) -> String {
+fn to_url(base_url: &str, path: &str, params: &HashMap<String, String>) -> String {
     let params = map_to_params(params);
-    let url = format!("https://{}{}?{}", BASE_URL, path, params);
+    let url = format!("{}{}?{}", base_url, path, params);
     url
 }
 
@@ -110,60 +346,145 @@ fn call_url(url: &str) -> Result<String, reqwest::Error> {
     Ok(reply_text)
 }
 
+/// Maximum number of characters of the reply text that is included in a
+/// [`SolarApiError::ParseError`]
+pub const MAX_BODY_IN_ERROR: usize = 2048;
+
+// truncate the reply text to include it in an error
+fn truncate_body(reply_text: &str) -> String {
+    match reply_text.char_indices().nth(MAX_BODY_IN_ERROR) {
+        Some((index, _)) => format!("{}...(truncated)", &reply_text[..index]),
+        None => reply_text.to_string(),
+    }
+}
+
+// the replacement of the API key and identifying values in errors
+const REDACTED: &str = "REDACTED";
+
+// true for the keys of serial numbers, like `serialNumber` or `inverterSN`
+pub(crate) fn is_serial_number(key: &str) -> bool {
+    key.to_lowercase().ends_with("serialnumber") || key.ends_with("SN")
+}
+
+// true when the string value of `key` identifies the owner of a site
+fn is_identifying(key: &str) -> bool {
+    matches!(key, "name" | "notes" | "address" | "address2" | "zip") || is_serial_number(key)
+}
+
+// the length of the JSON string at the start of `text`, including the quotes,
+// or None when the string does not end
+fn string_len(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in text.char_indices().skip(1) {
+        match c {
+            '"' if !escaped => return Some(index + 1),
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+// replaces the API key and the identifying string values of the reply text by
+// `REDACTED`, to include it in an error. The text is scanned for
+// `"key": "value"` pairs, so this also works when the reply is not valid JSON
+fn redact_body(reply_text: &str, api_key: &str) -> String {
+    let text = match api_key.is_empty() {
+        true => reply_text.to_string(),
+        false => reply_text.replace(api_key, REDACTED),
+    };
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find('"') {
+        let Some(key_len) = string_len(&rest[start..]) else {
+            break;
+        };
+        let key_end = start + key_len;
+        redacted.push_str(&rest[..key_end]);
+        let key = &rest[start + 1..key_end - 1];
+        rest = &rest[key_end..];
+
+        let after_colon = rest.trim_start().strip_prefix(':').map(str::trim_start);
+        let Some(value) = after_colon.filter(|value| value.starts_with('"')) else {
+            continue;
+        };
+        let value_start = rest.len() - value.len();
+        let value_len = string_len(value);
+        if is_identifying(key) {
+            // a value that does not end is the rest of a truncated reply
+            redacted.push_str(&rest[..value_start]);
+            redacted.push_str(&format!("\"{REDACTED}\""));
+            rest = value_len.map_or("", |value_len| &value[value_len..]);
+        } else if let Some(value_len) = value_len {
+            // skip the value, so it is not taken for a key
+            redacted.push_str(&rest[..value_start + value_len]);
+            rest = &rest[value_start + value_len..];
+        }
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+// parse the reply of the API. Fields that are not known are logged, or
+// returned as [`SolarApiError::UnknownFields`] when using [`ParseMode::Strict`].
+// The reply text in a [`SolarApiError::ParseError`] is redacted with `api_key`
+fn parse<T: DeserializeOwned>(
+    reply_text: &str,
+    parse_mode: ParseMode,
+    api_key: &str,
+) -> Result<T, SolarApiError> {
+    let mut unknown_fields = Vec::new();
+    let mut track_unknown = |path: serde_ignored::Path| unknown_fields.push(path.to_string());
+    let mut deserializer = serde_json::Deserializer::from_str(reply_text);
+    let parsed: T = serde_path_to_error::deserialize(serde_ignored::Deserializer::new(
+        &mut deserializer,
+        &mut track_unknown,
+    ))
+    .map_err(|e| SolarApiError::ParseError {
+        path: e.path().to_string(),
+        source: e.into_inner(),
+        body: truncate_body(&redact_body(reply_text, api_key)),
+    })?;
+    deserializer.end().map_err(|e| SolarApiError::ParseError {
+        path: ".".to_string(),
+        source: e,
+        body: truncate_body(&redact_body(reply_text, api_key)),
+    })?;
+
+    if !unknown_fields.is_empty() {
+        debug!("Ignored unknown fields: {:?}", unknown_fields);
+        if parse_mode == ParseMode::Strict {
+            return Err(SolarApiError::UnknownFields(unknown_fields));
+        }
+    }
+    Ok(parsed)
+}
+
 /// List all sites of customer. Each [`Site`] has an id that can be
 /// used to retrieve detailled information using for example [`energy`]
 pub fn list(api_key: &str) -> Result<Vec<site::Site>, SolarApiError> {
-    debug!("Calling list of sites");
-    let map = default_map(api_key);
-    let url = to_url("/sites/list", &map);
-    let reply_text = call_url(&url)?;
-
-    trace!("Parsing");
-    let reply: site::SitesReply = serde_json::from_str(&reply_text)?;
-
-    Ok((*reply.sites()).clone())
+    Client::new(api_key).list()
 }
 
 /// Displays the site details, such as name, location, status, etc.
 pub fn details(api_key: &str, site_id: u32) -> Result<site::Site, SolarApiError> {
-    debug!("Getting details of {site_id}");
-    let params = default_map(api_key);
-    let path = format!("/site/{site_id}/details");
-    let url = to_url(&path, &params);
-    let reply_text = call_url(&url)?;
-
-    trace!("Parsing json");
-    let site: site::SiteDetails = serde_json::from_str(&reply_text)?;
-
-    Ok(site.details)
+    Client::new(api_key).details(site_id)
 }
 
 /// Return the energy production start and end dates of the site
 pub fn data_period(api_key: &str, site_id: u32) -> Result<site::DataPeriod, SolarApiError> {
-    debug!("Getting data_period of {site_id}");
-    let params = default_map(api_key);
-    let path = format!("/site/{site_id}/dataPeriod");
-    let url = to_url(&path, &params);
-    let reply_text = call_url(&url)?;
-
-    trace!("Parsing json");
-    let period: site::DataPeriodReply = serde_json::from_str(&reply_text)?;
-
-    Ok(period.data_period)
+    Client::new(api_key).data_period(site_id)
 }
 
 /// Display the site overview data.
 pub fn overview(api_key: &str, site_id: u32) -> Result<site::Overview, SolarApiError> {
-    debug!("Getting overview of {}", site_id);
-    let params = default_map(api_key);
-    let path = format!("/site/{}/overview", site_id);
-    let url = to_url(&path, &params);
-    let reply_text = call_url(&url)?;
-
-    trace!("Parsing json");
-    let overview: site::OverviewReply = serde_json::from_str(&reply_text)?;
+    Client::new(api_key).overview(site_id)
+}
 
-    Ok(overview.overview)
+/// Return the current power flow between the PV, the batteries, the grid and
+/// the consumption of the site
+pub fn current_power_flow(api_key: &str, site_id: u32) -> Result<PowerFlow, SolarApiError> {
+    Client::new(api_key).current_power_flow(site_id)
 }
 
 /// Return the site energy measurements. Usage limitation: This API is limited
@@ -171,32 +492,27 @@ pub fn overview(api_key: &str, site_id: u32) -> Result<site::Overview, SolarApiE
 /// and to one month when using `time_unit=`[`TimeUnit::QuarterOfAnHour`] or
 /// `time_unit=`[`TimeUnit::Hour`]`. This means that the period between
 /// `period.end_time` and `period.start_time` should not exceed one year or one
-/// month respectively. If the period is longer, the system will generate error
+/// month respectively. If the period is longer, [`SolarApiError::InvalidPeriod`]
+/// is returned without calling the API
 pub fn energy(
     api_key: &str,
     site_id: u32,
     period: DataPeriod,
     time_unit: TimeUnit,
 ) -> Result<site::GeneratedEnergy, SolarApiError> {
-    debug!(
-        "Getting energy for {}-{} with unit {}",
-        period.start_date,
-        period.end_date,
-        time_unit.to_param()
-    );
-
-    let mut params = default_map(api_key);
-    params.insert("startDate".into(), period.formatted_start_date());
-    params.insert("endDate".into(), period.formatted_end_date());
-    params.insert("timeUnit".into(), time_unit.to_param().into());
-    let path = format!("/site/{site_id}/energy");
-    let url = to_url(&path, &params);
-    let reply_text = call_url(&url)?;
-
-    trace!("Parsing json");
-    let energy: site::GeneratedEnergyReply = serde_json::from_str(&reply_text)?;
+    Client::new(api_key).energy(site_id, period, time_unit)
+}
 
-    Ok(energy.energy)
+/// Return the site energy measurements for a period that may exceed the limits
+/// of [`energy`]. The period is split into periods within the limits, which
+/// are requested one after the other and merged into one series
+pub fn energy_chunked(
+    api_key: &str,
+    site_id: u32,
+    period: DataPeriod,
+    time_unit: TimeUnit,
+) -> Result<site::GeneratedEnergy, SolarApiError> {
+    Client::new(api_key).energy_chunked(site_id, period, time_unit)
 }
 
 /// Return the site power measurements in 15 minutes resolution. This API is 
@@ -209,25 +525,109 @@ pub fn power(
     start_datetime: NaiveDateTime,
     end_datetime: NaiveDateTime,
 ) -> Result<site::GeneratedPowerPerTimeUnit, SolarApiError> {
-    debug!("Getting power for {}-{}", start_datetime, end_datetime,);
+    Client::new(api_key).power(site_id, start_datetime, end_datetime)
+}
 
-    let mut params = default_map(api_key);
-    params.insert(
-        "startTime".into(),
-        format!("{}", start_datetime.format("%Y-%m-%d %H:%M:%S")),
-    );
-    params.insert(
-        "endTime".into(),
-        format!("{}", end_datetime.format("%Y-%m-%d %H:%M:%S")),
-    );
-    let path = format!("/site/{site_id}/power");
-    let url = to_url(&path, &params);
-    let reply_text = call_url(&url)?;
+/// Return the energy per meter of the site, i.e. the energy exported to and
+/// imported from the grid. Returns all meters when `meters` is empty. The
+/// limits of this API are the same as the limits of [`energy`]
+pub fn energy_details(
+    api_key: &str,
+    site_id: u32,
+    start_datetime: NaiveDateTime,
+    end_datetime: NaiveDateTime,
+    time_unit: TimeUnit,
+    meters: &[MeterType],
+) -> Result<EnergyDetails, SolarApiError> {
+    Client::new(api_key).energy_details(site_id, start_datetime, end_datetime, time_unit, meters)
+}
+
+/// Return the power per meter of the site in 15 minutes resolution. Returns
+/// all meters when `meters` is empty. This API is limited to one-month period
+pub fn power_details(
+    api_key: &str,
+    site_id: u32,
+    start_datetime: NaiveDateTime,
+    end_datetime: NaiveDateTime,
+    meters: &[MeterType],
+) -> Result<PowerDetails, SolarApiError> {
+    Client::new(api_key).power_details(site_id, start_datetime, end_datetime, meters)
+}
+
+/// Return the telemetry of the batteries of the site. Returns all batteries
+/// when `serials` is empty. This API is limited to one-week period
+pub fn storage_data(
+    api_key: &str,
+    site_id: u32,
+    start_datetime: NaiveDateTime,
+    end_datetime: NaiveDateTime,
+    serials: &[&str],
+) -> Result<StorageData, SolarApiError> {
+    Client::new(api_key).storage_data(site_id, start_datetime, end_datetime, serials)
+}
+
+/// Return the inverters of the site
+pub fn equipment_list(api_key: &str, site_id: u32) -> Result<Vec<Equipment>, SolarApiError> {
+    Client::new(api_key).equipment_list(site_id)
+}
+
+/// Return the technical data of the inverter with `serial_number`, i.e. its
+/// power, voltages and lifetime energy. This API is limited to one-week period
+pub fn inverter_data(
+    api_key: &str,
+    site_id: u32,
+    serial_number: &str,
+    start_datetime: NaiveDateTime,
+    end_datetime: NaiveDateTime,
+) -> Result<InverterData, SolarApiError> {
+    Client::new(api_key).inverter_data(site_id, serial_number, start_datetime, end_datetime)
+}
 
-    trace!("Parsing json");
-    let power: site::GeneratedPowerReply = serde_json::from_str(&reply_text)?;
+/// Return the overview of every site in `site_ids` using the bulk API. See
+/// [`Client::overviews`]
+pub fn overviews(
+    api_key: &str,
+    site_ids: &[u32],
+) -> Result<std::collections::BTreeMap<u32, site::Overview>, SolarApiError> {
+    Client::new(api_key).overviews(site_ids)
+}
+
+/// Return the energy of every site in `site_ids` using the bulk API. The
+/// limits of this API are the same as the limits of [`energy`]. See
+/// [`Client::sites_energy`]
+pub fn sites_energy(
+    api_key: &str,
+    site_ids: &[u32],
+    period: DataPeriod,
+    time_unit: TimeUnit,
+) -> Result<std::collections::BTreeMap<u32, TimeSeries<Energy>>, SolarApiError> {
+    Client::new(api_key).sites_energy(site_ids, period, time_unit)
+}
 
-    Ok(power.power)
+/// Return the site power measurements in 15 minutes resolution for a period
+/// that may exceed the one month limit of [`power`]. The period is split into
+/// windows of at most one month, which are requested one after the other and
+/// merged into one series without duplicate timestamps
+pub fn power_chunked(
+    api_key: &str,
+    site_id: u32,
+    start_datetime: NaiveDateTime,
+    end_datetime: NaiveDateTime,
+) -> Result<site::GeneratedPowerPerTimeUnit, SolarApiError> {
+    Client::new(api_key).power_chunked(site_id, start_datetime, end_datetime)
+}
+
+/// Return the energy of the site that is new since the bucket starting at
+/// `last_seen` until `until`, requesting only the days since `last_seen`. See
+/// [`Client::sync_energy`]
+pub fn sync_energy(
+    api_key: &str,
+    site_id: u32,
+    last_seen: NaiveDateTime,
+    until: NaiveDateTime,
+    time_unit: TimeUnit,
+) -> Result<TimeSeries<Energy>, SolarApiError> {
+    Client::new(api_key).sync_energy(site_id, last_seen, until, time_unit)
 }
 
 #[test]
@@ -240,3 +640,66 @@ fn test_map_to_params() {
     // order of k/v-pairs not known
     assert!(params == "key=value&key2=value2" || params == "key2=value2&key=value");
 }
+
+#[test]
+fn test_parse_modes() {
+    let reply = r#"{"dataPeriod":{"startDate":"2021-02-25","endDate":"2021-05-03","newField":1}}"#;
+
+    let lenient: Result<site::DataPeriodReply, _> = parse(reply, ParseMode::Lenient, "");
+    assert!(lenient.is_ok());
+
+    let strict: Result<site::DataPeriodReply, _> = parse(reply, ParseMode::Strict, "");
+    assert!(matches!(
+        strict,
+        Err(SolarApiError::UnknownFields(fields)) if fields == vec!["dataPeriod.newField"]
+    ));
+}
+
+#[test]
+fn test_parse_error_path() {
+    let reply = r#"{"dataPeriod":{"startDate":"2021-02-25","endDate":"yesterday"}}"#;
+
+    let parsed: Result<site::DataPeriodReply, _> = parse(reply, ParseMode::Lenient, "");
+    match parsed {
+        Err(SolarApiError::ParseError { path, source, body }) => {
+            assert_eq!("dataPeriod.endDate", path);
+            assert!(source.to_string().contains("yesterday"));
+            assert_eq!(reply, body);
+        }
+        _ => panic!("expected a parse error"),
+    }
+}
+
+#[test]
+fn test_redact_body() {
+    let reply = r#"{"details":{"id":1234,"name":"Smith \"home\"","location":{"address":"Main Street 1","zip":"1234 AB"},
+        "peakPower":5.0,"inverters":[{"serialNumber":"7E1A2B3C-4D","SN": "7E1A2B3C-4D"}],"status":"name","uri":"?api_key=SECRET"}}"#;
+    assert_eq!(
+        r#"{"details":{"id":1234,"name":"REDACTED","location":{"address":"REDACTED","zip":"REDACTED"},
+        "peakPower":5.0,"inverters":[{"serialNumber":"REDACTED","SN": "REDACTED"}],"status":"name","uri":"?api_key=REDACTED"}}"#,
+        redact_body(reply, "SECRET")
+    );
+
+    // a reply that is not valid JSON is redacted as well
+    assert_eq!(
+        r#"{"name":"REDACTED","address":"REDACTED""#,
+        redact_body(r#"{"name":"Smith","address":"Main Str"#, "")
+    );
+
+    let reply = r#"{"site":{"name":"Smith","installationDate":"soon"}}"#;
+    let parsed: Result<site::SiteDetails, _> = parse(reply, ParseMode::Lenient, "KEY");
+    match parsed {
+        Err(SolarApiError::ParseError { body, .. }) => assert!(!body.contains("Smith")),
+        _ => panic!("expected a parse error"),
+    }
+}
+
+#[test]
+fn test_truncate_body() {
+    assert_eq!("short", truncate_body("short"));
+
+    let long = "é".repeat(MAX_BODY_IN_ERROR + 10);
+    let truncated = truncate_body(&long);
+    assert!(truncated.starts_with(&"é".repeat(MAX_BODY_IN_ERROR)));
+    assert!(truncated.ends_with("...(truncated)"));
+}
@@ -0,0 +1,288 @@
+#[cfg(test)]
+use crate::units::{as_watt_hours, watts};
+use crate::{
+    series::TimeSeries,
+    site::{RawValue, TimeUnit},
+    units::{Energy, EnergyUnit, Power, PowerUnit},
+    SolarApiError,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+/// The meters of a site that the detailed energy and power API can return
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MeterType {
+    /// the energy produced by the site
+    Production,
+    /// the energy consumed by the site
+    Consumption,
+    /// the produced energy that was consumed by the site
+    SelfConsumption,
+    /// the energy exported to the grid
+    FeedIn,
+    /// the energy imported from the grid
+    Purchased,
+}
+
+impl MeterType {
+    /// the name that the API uses for this meter, i.e. `FeedIn`
+    pub fn to_param(&self) -> &'static str {
+        match self {
+            MeterType::Production => "Production",
+            MeterType::Consumption => "Consumption",
+            MeterType::SelfConsumption => "SelfConsumption",
+            MeterType::FeedIn => "FeedIn",
+            MeterType::Purchased => "Purchased",
+        }
+    }
+
+    // the value of the `meters` parameter of the API. No meters means all meters
+    pub(crate) fn to_params(meters: &[MeterType]) -> String {
+        meters
+            .iter()
+            .map(|meter| meter.to_param())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl fmt::Display for MeterType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_param())
+    }
+}
+
+impl FromStr for MeterType {
+    type Err = SolarApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace(['_', '-', ' '], "").as_str() {
+            "production" => Ok(MeterType::Production),
+            "consumption" => Ok(MeterType::Consumption),
+            "selfconsumption" => Ok(MeterType::SelfConsumption),
+            "feedin" => Ok(MeterType::FeedIn),
+            "purchased" => Ok(MeterType::Purchased),
+            _ => Err(SolarApiError::UnknownMeterType(s.to_string())),
+        }
+    }
+}
+
+impl Serialize for MeterType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.to_param())
+    }
+}
+
+impl<'de> Deserialize<'de> for MeterType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// the values of one meter. The type is kept as string, so meters that this
+// lib does not know do not fail the whole reply
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct RawMeter<V> {
+    #[serde(rename = "type")]
+    meter_type: String,
+    values: Vec<V>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct EnergyDetailsReply {
+    #[serde(rename = "energyDetails")]
+    pub(crate) energy_details: EnergyDetails,
+}
+
+/// The energy per meter of a site, see [`crate::energy_details`]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnergyDetails {
+    #[serde(rename = "timeUnit", deserialize_with = "TimeUnit::from_const")]
+    pub time_unit: TimeUnit,
+    unit: String,
+    meters: Vec<RawMeter<RawValue>>,
+}
+
+impl EnergyDetails {
+    /// the unit of measurement as returned by the API, i.e. `Wh`
+    pub fn unit(&self) -> &str {
+        &self.unit
+    }
+
+    /// the meters in the reply that this lib knows
+    pub fn meters(&self) -> Vec<MeterType> {
+        self.meters
+            .iter()
+            .filter_map(|meter| meter.meter_type.parse().ok())
+            .collect()
+    }
+
+    /// the energy of `meter` as [`TimeSeries`] or None when the reply has no
+    /// values for `meter`. Returns [`SolarApiError::UnsupportedUnit`] when the
+    /// API used a unit of measurement that this lib does not know
+    pub fn series(&self, meter: MeterType) -> Result<Option<TimeSeries<Energy>>, SolarApiError> {
+        let Some(raw) = self
+            .meters
+            .iter()
+            .find(|raw| raw.meter_type == meter.to_param())
+        else {
+            return Ok(None);
+        };
+        let unit: EnergyUnit = self.unit.parse()?;
+        let values = raw.values.iter().map(|v| v.convert(unit)).collect();
+        Ok(Some(TimeSeries::new(self.time_unit, &self.unit, values)))
+    }
+
+    /// The JSON of this reply as returned by [`crate::energy_details`]
+    pub fn to_api_json(&self) -> serde_json::Value {
+        serde_json::json!({ "energyDetails": self })
+    }
+
+    // appends the values of `other`, which follows this reply in time, per
+    // meter. The values of `other` are converted to the unit of this reply
+    pub(crate) fn append(&mut self, other: EnergyDetails) -> Result<(), SolarApiError> {
+        let factor = other.unit.parse::<EnergyUnit>()?.watt_hours()
+            / self.unit.parse::<EnergyUnit>()?.watt_hours();
+        for meter in other.meters {
+            let values = meter.values.into_iter().map(|raw| RawValue {
+                date: raw.date,
+                value: raw.value.map(|v| v * factor),
+            });
+            match self
+                .meters
+                .iter_mut()
+                .find(|raw| raw.meter_type == meter.meter_type)
+            {
+                Some(raw) => raw.values.extend(values),
+                None => self.meters.push(RawMeter {
+                    meter_type: meter.meter_type,
+                    values: values.collect(),
+                }),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct PowerDetailsReply {
+    #[serde(rename = "powerDetails")]
+    pub(crate) power_details: PowerDetails,
+}
+
+/// The power per meter of a site in 15 minutes resolution, see
+/// [`crate::power_details`]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PowerDetails {
+    #[serde(rename = "timeUnit", deserialize_with = "TimeUnit::from_const")]
+    pub time_unit: TimeUnit,
+    unit: String,
+    meters: Vec<RawMeter<RawValue>>,
+}
+
+impl PowerDetails {
+    /// the unit of measurement as returned by the API, i.e. `W`
+    pub fn unit(&self) -> &str {
+        &self.unit
+    }
+
+    /// the meters in the reply that this lib knows
+    pub fn meters(&self) -> Vec<MeterType> {
+        self.meters
+            .iter()
+            .filter_map(|meter| meter.meter_type.parse().ok())
+            .collect()
+    }
+
+    /// the power of `meter` as [`TimeSeries`] or None when the reply has no
+    /// values for `meter`. Returns [`SolarApiError::UnsupportedUnit`] when the
+    /// API used a unit of measurement that this lib does not know
+    pub fn series(&self, meter: MeterType) -> Result<Option<TimeSeries<Power>>, SolarApiError> {
+        let Some(raw) = self
+            .meters
+            .iter()
+            .find(|raw| raw.meter_type == meter.to_param())
+        else {
+            return Ok(None);
+        };
+        let unit: PowerUnit = self.unit.parse()?;
+        let values = raw.values.iter().map(|v| v.convert(unit)).collect();
+        Ok(Some(TimeSeries::new(self.time_unit, &self.unit, values)))
+    }
+
+    /// The JSON of this reply as returned by [`crate::power_details`]
+    pub fn to_api_json(&self) -> serde_json::Value {
+        serde_json::json!({ "powerDetails": self })
+    }
+}
+
+#[test]
+fn test_energy_details() {
+    let reply = r#"
+    {"energyDetails":{
+        "timeUnit":"DAY",
+        "unit":"kWh",
+        "meters":[
+            {"type":"FeedIn","values":[
+                {"date":"2023-06-01 00:00:00","value":12.5},
+                {"date":"2023-06-02 00:00:00"}]},
+            {"type":"Purchased","values":[
+                {"date":"2023-06-01 00:00:00","value":2.0},
+                {"date":"2023-06-02 00:00:00","value":3.0}]},
+            {"type":"SomethingNew","values":[]}
+        ]}}
+    "#;
+    let details = serde_json::from_str::<EnergyDetailsReply>(reply)
+        .unwrap()
+        .energy_details;
+
+    assert_eq!(
+        vec![MeterType::FeedIn, MeterType::Purchased],
+        details.meters()
+    );
+    let feed_in = details.series(MeterType::FeedIn).unwrap().unwrap();
+    assert_eq!(12500.0, as_watt_hours(feed_in.total()));
+    assert_eq!(None, feed_in.values[1].value);
+    assert!(details.series(MeterType::Consumption).unwrap().is_none());
+
+    let json: serde_json::Value = serde_json::from_str(reply).unwrap();
+    assert_eq!(
+        json["energyDetails"]["meters"][1],
+        details.to_api_json()["energyDetails"]["meters"][1]
+    );
+}
+
+#[test]
+fn test_power_details() {
+    let reply = r#"
+    {"powerDetails":{
+        "timeUnit":"QUARTER_OF_AN_HOUR",
+        "unit":"W",
+        "meters":[
+            {"type":"Consumption","values":[
+                {"date":"2023-06-01 12:00:00","value":512.5},
+                {"date":"2023-06-01 12:15:00","value":null}]}
+        ]}}
+    "#;
+    let details = serde_json::from_str::<PowerDetailsReply>(reply)
+        .unwrap()
+        .power_details;
+
+    let consumption = details.series(MeterType::Consumption).unwrap().unwrap();
+    assert_eq!(Some(watts(512.5)), consumption.values[0].value);
+    assert_eq!(
+        Ok(MeterType::SelfConsumption),
+        "self_consumption".parse().map_err(|_| ())
+    );
+    assert_eq!(
+        "FeedIn,Purchased",
+        MeterType::to_params(&[MeterType::FeedIn, MeterType::Purchased])
+    );
+}
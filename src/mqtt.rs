@@ -0,0 +1,318 @@
+//! Publishes the readings of sites to an MQTT broker as JSON, for home
+//! automation systems like Home Assistant, openHAB or Node-RED, see
+//! [`MqttPublisher`]. Requires the `mqtt` feature.
+//!
+//! Every reading is published to its own topic below
+//! `{prefix}/{site_id}`, with `solaredge` as default prefix:
+//! * `power`: the current power of the overview in `W`
+//! * `energy_today`: the energy of the overview of today in `Wh`
+//! * `energy_lifetime`: the lifetime energy of the overview in `Wh`
+//! * `battery/{serial_number}/power`: the last power of a battery in `W`,
+//!   positive when charging
+//! * `battery/{serial_number}/state_of_charge`: the last state of charge of a
+//!   battery in `%`
+//!
+//! The payload is an object with the `value`, the `unit` and the `timestamp`
+//! in the local time of the site, i.e.
+//! `{"value":750.0,"unit":"W","timestamp":"2023-06-01 12:20:00"}`.
+//!
+//! ```no_run
+//! # use solar_api::mqtt::MqttPublisher;
+//! # use solar_api::rumqttc::MqttOptions;
+//! # use solar_api::Client;
+//! let client = Client::new("API_KEY");
+//! let publisher = MqttPublisher::connect(MqttOptions::new("solar-api", "localhost", 1883))
+//!     .with_topic_prefix("home/solar");
+//! // publishes every new overview until the API key is rejected
+//! publisher.publish_updates(1234, client.poller(1234))?;
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+pub mod homeassistant;
+
+use crate::{
+    site::Overview,
+    storage::StorageData,
+    units::{as_watt_hours, as_watts},
+    SolarApiError,
+};
+use chrono::NaiveDateTime;
+use log::{debug, warn};
+use rumqttc::{Client, MqttOptions, QoS};
+use serde::Serialize;
+use std::{fmt, time::Duration};
+
+// the number of messages that can be queued before publishing blocks
+const QUEUE_CAPACITY: usize = 64;
+// the time to wait before reconnecting to the broker
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// the payload of a reading
+#[derive(Debug, Serialize)]
+struct Reading<'a> {
+    value: f64,
+    unit: &'a str,
+    timestamp: String,
+}
+
+/// Publishes readings to MQTT topics. Messages are published with QoS 1 and
+/// retained by default, so subscribers get the last reading when they connect
+#[derive(Clone)]
+pub struct MqttPublisher {
+    client: Client,
+    topic_prefix: String,
+    discovery_prefix: String,
+    retain: bool,
+}
+
+impl fmt::Debug for MqttPublisher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MqttPublisher")
+            .field("topic_prefix", &self.topic_prefix)
+            .field("discovery_prefix", &self.discovery_prefix)
+            .field("retain", &self.retain)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MqttPublisher {
+    /// Connect to the broker of `options`. The connection is kept in a
+    /// background thread, which reconnects when the connection is lost and
+    /// stops when the publisher and its clones are dropped
+    pub fn connect(options: MqttOptions) -> Self {
+        let (client, mut connection) = Client::new(options, QUEUE_CAPACITY);
+        std::thread::spawn(move || {
+            for event in connection.iter() {
+                match event {
+                    Ok(event) => debug!("MQTT event {event:?}"),
+                    Err(e) => {
+                        warn!("MQTT connection failed: {e}");
+                        std::thread::sleep(RECONNECT_DELAY);
+                    }
+                }
+            }
+        });
+        Self::from_client(client)
+    }
+
+    /// Create a publisher that publishes with `client`, when the caller drives
+    /// the connection of the client
+    pub fn from_client(client: Client) -> Self {
+        MqttPublisher {
+            client,
+            topic_prefix: "solaredge".to_string(),
+            discovery_prefix: "homeassistant".to_string(),
+            retain: true,
+        }
+    }
+
+    /// Set the prefix of the topics, `solaredge` by default
+    pub fn with_topic_prefix(mut self, topic_prefix: &str) -> Self {
+        self.topic_prefix = topic_prefix.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set whether the broker retains the messages, true by default
+    pub fn with_retain(mut self, retain: bool) -> Self {
+        self.retain = retain;
+        self
+    }
+
+    /// The topic of `reading` of `site_id`, i.e. `solaredge/1234/power`
+    pub fn topic(&self, site_id: u32, reading: &str) -> String {
+        format!("{}/{site_id}/{reading}", self.topic_prefix)
+    }
+
+    /// Publish the current power, the energy of today and the lifetime energy
+    /// of `overview`. Publishes nothing for a site that has not communicated yet
+    pub fn publish_overview(&self, site_id: u32, overview: &Overview) -> Result<(), SolarApiError> {
+        self.publish_all(self.overview_messages(site_id, overview))
+    }
+
+    /// Publish the last power and state of charge of every battery of
+    /// `storage`
+    pub fn publish_storage(
+        &self,
+        site_id: u32,
+        storage: &StorageData,
+    ) -> Result<(), SolarApiError> {
+        self.publish_all(self.storage_messages(site_id, storage))
+    }
+
+    /// Publish every overview of `updates`, i.e. of a
+    /// [`Poller`](crate::poller::Poller), until the updates end. Errors of the
+    /// updates are logged and skipped, except
+    /// [`SolarApiError::ForbiddenError`], which is returned as the API will keep
+    /// rejecting the requests
+    pub fn publish_updates(
+        &self,
+        site_id: u32,
+        updates: impl IntoIterator<Item = Result<Overview, SolarApiError>>,
+    ) -> Result<(), SolarApiError> {
+        for update in updates {
+            match update {
+                Ok(overview) => self.publish_overview(site_id, &overview)?,
+                Err(e @ SolarApiError::ForbiddenError(_)) => return Err(e),
+                Err(e) => warn!("Could not get overview of site {site_id}: {e}"),
+            }
+        }
+        Ok(())
+    }
+
+    // publishes `payload` to `topic`
+    fn publish(&self, topic: String, payload: String, retain: bool) -> Result<(), SolarApiError> {
+        debug!("Publish {payload} to {topic}");
+        self.client
+            .publish(topic, QoS::AtLeastOnce, retain, payload)
+            .map_err(SolarApiError::MqttError)
+    }
+
+    // publishes every topic and payload of `messages`
+    fn publish_all(&self, messages: Vec<(String, String)>) -> Result<(), SolarApiError> {
+        for (topic, payload) in messages {
+            self.publish(topic, payload, self.retain)?;
+        }
+        Ok(())
+    }
+
+    // the topics and payloads of the readings of `overview`
+    fn overview_messages(&self, site_id: u32, overview: &Overview) -> Vec<(String, String)> {
+        let Some(timestamp) = overview.last_updated_time else {
+            return Vec::new();
+        };
+        vec![
+            (
+                self.topic(site_id, "power"),
+                payload(as_watts(overview.current_power.power), "W", timestamp),
+            ),
+            (
+                self.topic(site_id, "energy_today"),
+                payload(
+                    as_watt_hours(overview.last_day_data.energy),
+                    "Wh",
+                    timestamp,
+                ),
+            ),
+            (
+                self.topic(site_id, "energy_lifetime"),
+                payload(
+                    as_watt_hours(overview.life_time_data.energy),
+                    "Wh",
+                    timestamp,
+                ),
+            ),
+        ]
+    }
+
+    // the topics and payloads of the last readings of the batteries of `storage`
+    fn storage_messages(&self, site_id: u32, storage: &StorageData) -> Vec<(String, String)> {
+        let mut messages = Vec::new();
+        for battery in &storage.batteries {
+            let battery_topic = format!("battery/{}", topic_level(&battery.serial_number));
+            let telemetries = battery.sorted_telemetries();
+            if let Some((power, timestamp)) = telemetries
+                .iter()
+                .rev()
+                .find_map(|t| t.power.map(|power| (power, t.timestamp)))
+            {
+                messages.push((
+                    self.topic(site_id, &format!("{battery_topic}/power")),
+                    payload(as_watts(power), "W", timestamp),
+                ));
+            }
+            if let Some((percentage, timestamp)) = telemetries.iter().rev().find_map(|t| {
+                t.battery_percentage_state
+                    .map(|percentage| (percentage, t.timestamp))
+            }) {
+                messages.push((
+                    self.topic(site_id, &format!("{battery_topic}/state_of_charge")),
+                    payload(percentage, "%", timestamp),
+                ));
+            }
+        }
+        messages
+    }
+}
+
+fn payload(value: f64, unit: &str, timestamp: NaiveDateTime) -> String {
+    let reading = Reading {
+        value,
+        unit,
+        timestamp: timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+    // a struct of numbers and strings always serializes
+    serde_json::to_string(&reading).expect("reading serializes")
+}
+
+// replaces the characters with a meaning in topics
+fn topic_level(name: &str) -> String {
+    name.replace(['/', '+', '#'], "_")
+}
+
+#[test]
+fn test_mqtt_messages() {
+    let reply = r#"
+    {"overview":{
+        "lastUpdateTime":"2023-06-01 12:20:00",
+        "lifeTimeData":{"energy":1000000.0,"revenue":200.0},
+        "lastYearData":{"energy":500000.0},
+        "lastMonthData":{"energy":20000.0},
+        "lastDayData":{"energy":2000.0},
+        "currentPower":{"power":750.5},
+        "measuredBy":"INVERTER"
+    }}
+    "#;
+    let overview = serde_json::from_str::<crate::site::OverviewReply>(reply)
+        .unwrap()
+        .overview;
+    let reply = r#"
+    {"storageData":{
+        "batteryCount":1,
+        "batteries":[{
+            "nameplate":10000.0,
+            "serialNumber":"BAT/1",
+            "telemetries":[
+                {"timeStamp":"2023-06-01 12:15:00","power":-500.0,
+                 "lifeTimeEnergyDischarged":1000.0,"lifeTimeEnergyCharged":3000.0},
+                {"timeStamp":"2023-06-01 12:00:00","power":1000.0,
+                 "lifeTimeEnergyDischarged":1000.0,"lifeTimeEnergyCharged":3000.0,
+                 "batteryPercentageState":60.0}
+            ]}]}}
+    "#;
+    let storage = serde_json::from_str::<crate::storage::StorageDataReply>(reply)
+        .unwrap()
+        .storage_data;
+    let (client, _connection) = Client::new(MqttOptions::new("test", "localhost", 1883), 10);
+    let publisher = MqttPublisher::from_client(client).with_topic_prefix("home/solar/");
+
+    assert_eq!(
+        vec![
+            (
+                "home/solar/1234/power".to_string(),
+                r#"{"value":750.5,"unit":"W","timestamp":"2023-06-01 12:20:00"}"#.to_string()
+            ),
+            (
+                "home/solar/1234/energy_today".to_string(),
+                r#"{"value":2000.0,"unit":"Wh","timestamp":"2023-06-01 12:20:00"}"#.to_string()
+            ),
+            (
+                "home/solar/1234/energy_lifetime".to_string(),
+                r#"{"value":1000000.0,"unit":"Wh","timestamp":"2023-06-01 12:20:00"}"#.to_string()
+            ),
+        ],
+        publisher.overview_messages(1234, &overview)
+    );
+    assert_eq!(
+        vec![
+            (
+                "home/solar/1234/battery/BAT_1/power".to_string(),
+                r#"{"value":-500.0,"unit":"W","timestamp":"2023-06-01 12:15:00"}"#.to_string()
+            ),
+            (
+                "home/solar/1234/battery/BAT_1/state_of_charge".to_string(),
+                r#"{"value":60.0,"unit":"%","timestamp":"2023-06-01 12:00:00"}"#.to_string()
+            ),
+        ],
+        publisher.storage_messages(1234, &storage)
+    );
+}
@@ -0,0 +1,255 @@
+//! Publishes [MQTT discovery](https://www.home-assistant.io/integrations/mqtt/#mqtt-discovery)
+//! messages for Home Assistant, so the readings of [`MqttPublisher`] show up as
+//! sensors of a device per site without configuration. The energy sensors have
+//! the state class `total_increasing`, so they can be used in the energy
+//! dashboard of Home Assistant.
+//!
+//! ```no_run
+//! # use solar_api::mqtt::MqttPublisher;
+//! # use solar_api::rumqttc::MqttOptions;
+//! # use solar_api::Client;
+//! # use solar_api::chrono::{Duration, Local};
+//! let client = Client::new("API_KEY");
+//! let publisher = MqttPublisher::connect(MqttOptions::new("solar-api", "localhost", 1883));
+//! let site = client.details(1234)?;
+//! publisher.publish_discovery(&site)?;
+//! let now = Local::now().naive_local();
+//! let storage = client.storage_data(1234, now - Duration::hours(1), now, &[])?;
+//! publisher.publish_battery_discovery(&site, &storage)?;
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use super::{topic_level, MqttPublisher};
+use crate::{
+    site::Site,
+    storage::{Battery, StorageData},
+    SolarApiError,
+};
+use serde::Serialize;
+
+// the device of a site that the sensors belong to
+#[derive(Debug, Serialize)]
+struct Device {
+    identifiers: Vec<String>,
+    name: String,
+    manufacturer: &'static str,
+    model: String,
+}
+
+// the discovery config of a sensor
+#[derive(Debug, Serialize)]
+struct SensorConfig<'a> {
+    name: String,
+    unique_id: String,
+    state_topic: String,
+    value_template: &'static str,
+    unit_of_measurement: &'static str,
+    device_class: &'static str,
+    state_class: &'static str,
+    device: &'a Device,
+}
+
+// a sensor of a reading of the publisher
+struct Sensor {
+    // the reading below the topic of the site, which is also the object id
+    reading: String,
+    name: String,
+    unit: &'static str,
+    device_class: &'static str,
+    state_class: &'static str,
+}
+
+impl MqttPublisher {
+    /// Set the prefix of the discovery topics of Home Assistant,
+    /// `homeassistant` by default
+    pub fn with_discovery_prefix(mut self, discovery_prefix: &str) -> Self {
+        self.discovery_prefix = discovery_prefix.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Publish the discovery config of the power and energy sensors of
+    /// `site`. The config is retained, so publishing it once per start is
+    /// enough
+    pub fn publish_discovery(&self, site: &Site) -> Result<(), SolarApiError> {
+        let sensors = [
+            Sensor::new("power", "Power", "W", "power", "measurement"),
+            Sensor::new(
+                "energy_today",
+                "Energy today",
+                "Wh",
+                "energy",
+                "total_increasing",
+            ),
+            Sensor::new(
+                "energy_lifetime",
+                "Energy lifetime",
+                "Wh",
+                "energy",
+                "total_increasing",
+            ),
+        ];
+        self.publish_sensors(site, &sensors)
+    }
+
+    /// Publish the discovery config of the power and state of charge sensors
+    /// of every battery of `storage` of `site`
+    pub fn publish_battery_discovery(
+        &self,
+        site: &Site,
+        storage: &StorageData,
+    ) -> Result<(), SolarApiError> {
+        let sensors: Vec<_> = storage.batteries.iter().flat_map(battery_sensors).collect();
+        self.publish_sensors(site, &sensors)
+    }
+
+    // publishes the discovery config of `sensors` as sensors of the device of `site`
+    fn publish_sensors(&self, site: &Site, sensors: &[Sensor]) -> Result<(), SolarApiError> {
+        let device = Device {
+            identifiers: vec![format!("solaredge_{}", site.id)],
+            name: site.name.clone(),
+            manufacturer: "SolarEdge",
+            model: site.site_type.clone(),
+        };
+        for (topic, payload) in self.discovery_messages(site.id, &device, sensors) {
+            self.publish(topic, payload, true)?;
+        }
+        Ok(())
+    }
+
+    // the discovery topics and configs of `sensors`
+    fn discovery_messages(
+        &self,
+        site_id: u32,
+        device: &Device,
+        sensors: &[Sensor],
+    ) -> Vec<(String, String)> {
+        sensors
+            .iter()
+            .map(|sensor| {
+                let object_id = sensor
+                    .reading
+                    .replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "_");
+                let config = SensorConfig {
+                    name: sensor.name.clone(),
+                    unique_id: format!("solaredge_{site_id}_{object_id}"),
+                    state_topic: self.topic(site_id, &sensor.reading),
+                    value_template: "{{ value_json.value }}",
+                    unit_of_measurement: sensor.unit,
+                    device_class: sensor.device_class,
+                    state_class: sensor.state_class,
+                    device,
+                };
+                let topic = format!(
+                    "{}/sensor/solaredge_{site_id}/{object_id}/config",
+                    self.discovery_prefix
+                );
+                // a struct of strings always serializes
+                let payload = serde_json::to_string(&config).expect("config serializes");
+                (topic, payload)
+            })
+            .collect()
+    }
+}
+
+impl Sensor {
+    fn new(
+        reading: &str,
+        name: &str,
+        unit: &'static str,
+        device_class: &'static str,
+        state_class: &'static str,
+    ) -> Self {
+        Sensor {
+            reading: reading.to_string(),
+            name: name.to_string(),
+            unit,
+            device_class,
+            state_class,
+        }
+    }
+}
+
+// the power and state of charge sensors of `battery`
+fn battery_sensors(battery: &Battery) -> [Sensor; 2] {
+    let serial_number = &battery.serial_number;
+    let reading = format!("battery/{}", topic_level(serial_number));
+    [
+        Sensor::new(
+            &format!("{reading}/power"),
+            &format!("Battery {serial_number} power"),
+            "W",
+            "power",
+            "measurement",
+        ),
+        Sensor::new(
+            &format!("{reading}/state_of_charge"),
+            &format!("Battery {serial_number} state of charge"),
+            "%",
+            "battery",
+            "measurement",
+        ),
+    ]
+}
+
+#[test]
+fn test_discovery_messages() {
+    let (client, _connection) =
+        rumqttc::Client::new(rumqttc::MqttOptions::new("test", "localhost", 1883), 10);
+    let publisher = MqttPublisher::from_client(client).with_topic_prefix("home/solar");
+    let device = Device {
+        identifiers: vec!["solaredge_1234".to_string()],
+        name: "Home".to_string(),
+        manufacturer: "SolarEdge",
+        model: "Optimizers & Inverters".to_string(),
+    };
+    let sensors = [
+        Sensor::new(
+            "energy_today",
+            "Energy today",
+            "Wh",
+            "energy",
+            "total_increasing",
+        ),
+        Sensor::new(
+            "battery/BAT-1/state_of_charge",
+            "Battery BAT-1 state of charge",
+            "%",
+            "battery",
+            "measurement",
+        ),
+    ];
+
+    let messages = publisher.discovery_messages(1234, &device, &sensors);
+    assert_eq!(
+        "homeassistant/sensor/solaredge_1234/energy_today/config",
+        messages[0].0
+    );
+    assert_eq!(
+        serde_json::json!({
+            "name": "Energy today",
+            "unique_id": "solaredge_1234_energy_today",
+            "state_topic": "home/solar/1234/energy_today",
+            "value_template": "{{ value_json.value }}",
+            "unit_of_measurement": "Wh",
+            "device_class": "energy",
+            "state_class": "total_increasing",
+            "device": {
+                "identifiers": ["solaredge_1234"],
+                "name": "Home",
+                "manufacturer": "SolarEdge",
+                "model": "Optimizers & Inverters"
+            }
+        }),
+        serde_json::from_str::<serde_json::Value>(&messages[0].1).unwrap()
+    );
+
+    let publisher = publisher.with_discovery_prefix("ha/");
+    let messages = publisher.discovery_messages(1234, &device, &sensors);
+    assert_eq!(
+        "ha/sensor/solaredge_1234/battery_BAT-1_state_of_charge/config",
+        messages[1].0
+    );
+    assert!(messages[1]
+        .1
+        .contains(r#""state_topic":"home/solar/1234/battery/BAT-1/state_of_charge""#));
+}
@@ -0,0 +1,272 @@
+//! Calculates the savings of a site and when it pays back its cost, using the
+//! prices of a [`Tariff`] for the self consumed and exported energy
+
+#[cfg(test)]
+use crate::units::watt_hours;
+use crate::{
+    meters::{EnergyDetails, MeterType},
+    series::{Aggregation, GapPolicy, TimeSeries, TimeSeriesValue},
+    site::TimeUnit,
+    tariff::Tariff,
+    units::Energy,
+    SolarApiError,
+};
+use chrono::{Duration, NaiveDate};
+use std::collections::BTreeMap;
+
+// the average length of a year in days
+const DAYS_PER_YEAR: f64 = 365.25;
+
+/// The savings per timestamp: the `self_consumed` energy priced at the
+/// `import` tariff, which did not have to be bought, plus the `exported`
+/// energy priced at the `export` tariff. A missing value of one of the series
+/// counts as zero
+pub fn savings(
+    self_consumed: &TimeSeries<Energy>,
+    import: &Tariff,
+    exported: &TimeSeries<Energy>,
+    export: &Tariff,
+) -> TimeSeries<f64> {
+    import
+        .price(self_consumed)
+        .add(&export.price(exported), GapPolicy::Zero)
+}
+
+/// The savings of a month split by where the produced energy went, see
+/// [`monthly_savings`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonthlySavings {
+    /// the first day of the month
+    pub month: NaiveDate,
+    /// the money saved by consuming the produced energy instead of buying it
+    pub self_consumption: f64,
+    /// the money earned by exporting energy
+    pub export: f64,
+    /// the self consumption savings plus the export earnings
+    pub total: f64,
+}
+
+/// The savings per month of `self_consumed` energy priced at the `import`
+/// tariff and the earnings of `exported` energy priced at the `export` tariff.
+/// The energy is priced before it is summed per month, so use the resolution
+/// of the tariff, i.e. hourly energy for a dynamic tariff
+pub fn monthly_savings_from_series(
+    self_consumed: &TimeSeries<Energy>,
+    import: &Tariff,
+    exported: &TimeSeries<Energy>,
+    export: &Tariff,
+) -> Vec<MonthlySavings> {
+    let mut months: BTreeMap<NaiveDate, (f64, f64)> = BTreeMap::new();
+    let per_month = |series: TimeSeries<f64>| series.resample(TimeUnit::Month, Aggregation::Sum);
+    for v in per_month(import.price(self_consumed)) {
+        months.entry(v.date.date()).or_default().0 += v.value.unwrap_or_default();
+    }
+    for v in per_month(export.price(exported)) {
+        months.entry(v.date.date()).or_default().1 += v.value.unwrap_or_default();
+    }
+    months
+        .into_iter()
+        .map(|(month, (self_consumption, export))| MonthlySavings {
+            month,
+            self_consumption,
+            export,
+            total: self_consumption + export,
+        })
+        .collect()
+}
+
+/// The savings per month split into self consumption and export, using the
+/// `SelfConsumption` and `FeedIn` meters of `energy`. When the reply has no
+/// `SelfConsumption` meter, the self consumed energy is the `Production` minus
+/// the `FeedIn`. Returns [`SolarApiError::MissingMeter`] when these meters are
+/// missing. See [`monthly_savings_from_series`]
+pub fn monthly_savings(
+    energy: &EnergyDetails,
+    import: &Tariff,
+    export: &Tariff,
+) -> Result<Vec<MonthlySavings>, SolarApiError> {
+    let feed_in = energy
+        .series(MeterType::FeedIn)?
+        .ok_or(SolarApiError::MissingMeter(MeterType::FeedIn))?;
+    let self_consumed = match energy.series(MeterType::SelfConsumption)? {
+        Some(self_consumed) => self_consumed,
+        None => energy
+            .series(MeterType::Production)?
+            .ok_or(SolarApiError::MissingMeter(MeterType::SelfConsumption))?
+            .sub(&feed_in, GapPolicy::Propagate),
+    };
+    Ok(monthly_savings_from_series(
+        &self_consumed,
+        import,
+        &feed_in,
+        export,
+    ))
+}
+
+/// The payback of a site, see [`payback`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Payback {
+    /// the cumulative savings per timestamp
+    pub cumulative_savings: TimeSeries<f64>,
+    /// the savings of the whole history
+    pub total_savings: f64,
+    /// the average savings per year of the history
+    pub savings_per_year: f64,
+    /// the date on which the cumulative savings reached the cost, or None when
+    /// they did not reach it yet
+    pub payback_date: Option<NaiveDate>,
+    /// the date on which the cumulative savings reach the cost when the
+    /// average savings continue, or None when there are no savings
+    pub estimated_payback_date: Option<NaiveDate>,
+    /// the internal rate of return per year over the lifetime of the site, or
+    /// None when it cannot be calculated
+    pub irr: Option<f64>,
+}
+
+/// The payback of a site that cost `cost` with `savings` per timestamp as
+/// returned by [`savings`]. The savings per year of the history are assumed to
+/// continue for `lifetime_years` years from the first timestamp to calculate
+/// the estimated payback date and the internal rate of return
+pub fn payback(cost: f64, savings: &TimeSeries<f64>, lifetime_years: u32) -> Payback {
+    let mut values: Vec<_> = savings.iter().filter(|v| v.value.is_some()).collect();
+    values.sort_by_key(|v| v.date);
+
+    let mut total = 0.0;
+    let mut payback_date = None;
+    let mut cumulative = Vec::with_capacity(values.len());
+    for v in &values {
+        total += v.value.unwrap_or_default();
+        if total >= cost && payback_date.is_none() {
+            payback_date = Some(v.date.date());
+        }
+        cumulative.push(TimeSeriesValue {
+            date: v.date,
+            value: Some(total),
+        });
+    }
+
+    let (savings_per_year, estimated_payback_date) = match (values.first(), values.last()) {
+        (Some(first), Some(last)) => {
+            let end = savings.time_unit.bucket_end(last.date);
+            let days = (end - first.date).num_seconds() as f64 / 86_400.0;
+            let per_year = total / days * DAYS_PER_YEAR;
+            let estimated = payback_date.or_else(|| {
+                (per_year > 0.0).then(|| {
+                    let days = ((cost - total) / per_year * DAYS_PER_YEAR).ceil() as i64;
+                    end.date() + Duration::days(days)
+                })
+            });
+            (per_year, estimated)
+        }
+        _ => (0.0, None),
+    };
+
+    Payback {
+        cumulative_savings: TimeSeries::new(savings.time_unit, &savings.unit, cumulative),
+        total_savings: total,
+        savings_per_year,
+        payback_date,
+        estimated_payback_date,
+        irr: irr(cost, savings_per_year, lifetime_years),
+    }
+}
+
+// the rate at which the net present value of paying `cost` and saving
+// `per_year` for `years` years is zero, found by bisection
+fn irr(cost: f64, per_year: f64, years: u32) -> Option<f64> {
+    let npv = |rate: f64| {
+        (1..=years)
+            .map(|year| per_year / (1.0 + rate).powi(year as i32))
+            .sum::<f64>()
+            - cost
+    };
+    let (mut low, mut high) = (-0.99, 10.0);
+    if cost <= 0.0 || years == 0 || npv(low) < 0.0 || npv(high) > 0.0 {
+        return None;
+    }
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if npv(mid) > 0.0 {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some((low + high) / 2.0)
+}
+
+#[test]
+fn test_payback() {
+    let start = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+    let energy = |wh: f64| {
+        let values = (0..365)
+            .map(|day| TimeSeriesValue {
+                date: (start + Duration::days(day)).into(),
+                value: Some(watt_hours(wh)),
+            })
+            .collect();
+        TimeSeries::new(TimeUnit::Day, "Wh", values)
+    };
+    // 2 kWh self consumed at 0.40 and 2 kWh exported at 0.10 per day saves 1.0
+    let daily = savings(
+        &energy(2000.0),
+        &Tariff::Flat(0.40),
+        &energy(2000.0),
+        &Tariff::Flat(0.10),
+    );
+    assert!((daily.total() - 365.0).abs() < 1e-9);
+
+    let result = payback(1000.0, &daily, 20);
+    assert_eq!(None, result.payback_date);
+    assert!((result.savings_per_year - 365.25).abs() < 1e-9);
+    assert_eq!(
+        NaiveDate::from_ymd_opt(2025, 9, 27),
+        result.estimated_payback_date
+    );
+    let irr = result.irr.unwrap();
+    assert!(irr > 0.36 && irr < 0.37, "irr {irr}");
+
+    let paid = payback(100.0, &daily, 20);
+    assert_eq!(NaiveDate::from_ymd_opt(2023, 4, 10), paid.payback_date);
+    assert_eq!(paid.payback_date, paid.estimated_payback_date);
+}
+
+#[test]
+fn test_monthly_savings() {
+    let energy: EnergyDetails = serde_json::from_value(serde_json::json!({
+        "timeUnit": "DAY",
+        "unit": "Wh",
+        "meters": [
+            {"type": "Production", "values": [
+                {"date": "2023-06-30 00:00:00", "value": 10000.0},
+                {"date": "2023-07-01 00:00:00", "value": 20000.0},
+                {"date": "2023-07-02 00:00:00", "value": 10000.0}]},
+            {"type": "FeedIn", "values": [
+                {"date": "2023-06-30 00:00:00", "value": 4000.0},
+                {"date": "2023-07-01 00:00:00", "value": 15000.0},
+                {"date": "2023-07-02 00:00:00", "value": 5000.0}]}
+        ]
+    }))
+    .unwrap();
+
+    let months = monthly_savings(&energy, &Tariff::Flat(0.40), &Tariff::Flat(0.10)).unwrap();
+    assert_eq!(2, months.len());
+    assert_eq!(
+        NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(),
+        months[0].month
+    );
+    assert!((months[0].self_consumption - 2.4).abs() < 1e-9);
+    assert!((months[0].export - 0.4).abs() < 1e-9);
+    assert!((months[1].self_consumption - 4.0).abs() < 1e-9);
+    assert!((months[1].export - 2.0).abs() < 1e-9);
+    assert!((months[1].total - 6.0).abs() < 1e-9);
+
+    let production_only: EnergyDetails = serde_json::from_value(serde_json::json!({
+        "timeUnit": "DAY", "unit": "Wh", "meters": [{"type": "Production", "values": []}]
+    }))
+    .unwrap();
+    assert!(matches!(
+        monthly_savings(&production_only, &Tariff::Flat(0.40), &Tariff::Flat(0.10)),
+        Err(SolarApiError::MissingMeter(MeterType::FeedIn))
+    ));
+}
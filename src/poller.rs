@@ -0,0 +1,626 @@
+//! Polls the overview of a site and yields every new update, waiting for the
+//! next update of the site between the calls, see [`Client::poller`]. With
+//! [`Poller::with_power_flow`], the power flow of every update is requested
+//! as well
+
+use crate::{
+    anomalies::{DaylightSource, DaylightWindow},
+    client::Client,
+    power_flow::PowerFlow,
+    scheduler::TokenBucket,
+    site::{Overview, NEXT_UPDATE_BACKOFF_IN_S},
+    timezone::localize,
+    SolarApiError,
+};
+use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone, Utc};
+use log::debug;
+use reqwest::StatusCode;
+use std::fmt;
+
+// the time after the end of the daylight window that the poller continues, so
+// the data of the last interval of the day is fetched
+const DAYLIGHT_TAIL_IN_M: i64 = 20;
+// the number of days to look ahead for the next daylight window, i.e. during
+// the polar night
+const MAX_DAYS_WITHOUT_DAYLIGHT: u64 = 183;
+
+/// Polls the overview of a site. Iterating blocks until the site published new
+/// data and yields each new [`Overview`] once. The poller waits until
+/// [`Overview::next_update_schedule_in`] between calls, retries when the API
+/// publishes the data late and backs off after errors. Errors are yielded as
+/// well, after which the poller continues, so stop iterating on errors that
+/// cannot be recovered from, like [`SolarApiError::ForbiddenError`].
+///
+/// ```no_run
+/// # use solar_api::Client;
+/// let client = Client::new("API_KEY");
+/// for overview in client.poller(1234).take(4) {
+///     println!("{:?}", overview?.last_updated_time);
+/// }
+/// # Ok::<(), solar_api::SolarApiError>(())
+/// ```
+#[derive(Clone)]
+pub struct Poller<'a, Tz: TimeZone = Local> {
+    client: &'a Client,
+    site_id: u32,
+    tz: Tz,
+    retry_interval: Duration,
+    max_backoff: Duration,
+    daylight: Option<(&'a dyn DaylightSource, u32)>,
+    bucket: Option<TokenBucket>,
+    errors: u32,
+    last_updated: Option<chrono::NaiveDateTime>,
+    next_poll: Option<DateTime<Utc>>,
+}
+
+impl<Tz: TimeZone> fmt::Debug for Poller<'_, Tz> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Poller")
+            .field("site_id", &self.site_id)
+            .field("retry_interval", &self.retry_interval)
+            .field("max_backoff", &self.max_backoff)
+            .field("daily_budget", &self.daylight.map(|(_, budget)| budget))
+            .field("bucket", &self.bucket)
+            .field("errors", &self.errors)
+            .field("last_updated", &self.last_updated)
+            .field("next_poll", &self.next_poll)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> Poller<'a, Local> {
+    /// Create a poller for `site_id` that polls immediately. The timestamps of
+    /// the site are in the time zone of this machine until
+    /// [`Poller::with_timezone`] is used
+    pub fn new(client: &'a Client, site_id: u32) -> Self {
+        Poller {
+            client,
+            site_id,
+            tz: Local,
+            retry_interval: Duration::minutes(5),
+            max_backoff: Duration::hours(1),
+            daylight: None,
+            bucket: None,
+            errors: 0,
+            last_updated: None,
+            next_poll: None,
+        }
+    }
+}
+
+impl<'a, Tz: TimeZone> Poller<'a, Tz> {
+    /// Use `tz` as the time zone of the site, i.e. from
+    /// [`Location::tz`](crate::Location)
+    pub fn with_timezone<Tz2: TimeZone>(self, tz: Tz2) -> Poller<'a, Tz2> {
+        Poller {
+            client: self.client,
+            site_id: self.site_id,
+            tz,
+            retry_interval: self.retry_interval,
+            max_backoff: self.max_backoff,
+            daylight: self.daylight,
+            bucket: self.bucket,
+            errors: self.errors,
+            last_updated: self.last_updated,
+            next_poll: self.next_poll,
+        }
+    }
+
+    /// Set the time between polls when the site did not publish new data
+    /// after the whole update schedule, 5 minutes by default
+    pub fn with_retry_interval(mut self, retry_interval: Duration) -> Self {
+        self.retry_interval = retry_interval;
+        self
+    }
+
+    /// Set the longest time to wait after errors, 1 hour by default. The
+    /// poller waits this long when the API is rate limited
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Only poll during the daylight windows of `daylight`, which the site
+    /// needs to produce new data, and until 20 minutes after to fetch the last
+    /// data of the day. Between the updates of the site, the `daily_budget` of
+    /// requests is spread over the daylight window instead of the whole day, so
+    /// late data is picked up sooner than with the retry interval
+    pub fn with_daylight(mut self, daylight: &'a dyn DaylightSource, daily_budget: u32) -> Self {
+        self.daylight = Some((daylight, daily_budget.max(1)));
+        self
+    }
+
+    /// Take a token of `bucket` for every request, waiting for a token when
+    /// the bucket is empty. Limits the requests when the site publishes data
+    /// more often than expected or when the API keeps failing
+    pub fn with_rate_limit(mut self, bucket: TokenBucket) -> Self {
+        self.bucket = Some(bucket);
+        self
+    }
+
+    /// Also request the current power flow of the site on every new overview
+    /// and yield both. The power flow is requested right after the overview,
+    /// so it is as recent as the update of the site
+    pub fn with_power_flow(self) -> PowerFlowPoller<'a, Tz> {
+        PowerFlowPoller {
+            poller: self,
+            pending: None,
+        }
+    }
+
+    /// The time of the next poll, or None when the next poll is immediate
+    pub fn next_poll(&self) -> Option<DateTime<Utc>> {
+        self.next_poll
+    }
+
+    // the first time of the update schedule of `overview` after `now`, or a
+    // retry when the schedule passed. With daylight, polls at night move to the
+    // start of the next daylight window
+    fn schedule(&self, overview: &Overview, now: DateTime<Utc>) -> DateTime<Utc> {
+        let next =
+            next_scheduled(overview, &self.tz, now).unwrap_or(now + self.retry_interval(now));
+        self.in_daylight(next)
+    }
+
+    // the time between retries, which is the daily budget spread over the
+    // daylight window of `now` when polling with daylight
+    fn retry_interval(&self, now: DateTime<Utc>) -> Duration {
+        let Some((_, budget)) = self.daylight else {
+            return self.retry_interval;
+        };
+        match self.daylight_window(self.local(now)) {
+            Some(window) => ((window.end - window.start) / budget as i32).max(Duration::minutes(1)),
+            None => self.retry_interval,
+        }
+    }
+
+    // `at` when it is in a daylight window, otherwise the start of the next
+    // daylight window
+    fn in_daylight(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let local = self.local(at);
+        let Some((daylight, _)) = self.daylight else {
+            return at;
+        };
+        (0..=MAX_DAYS_WITHOUT_DAYLIGHT)
+            .filter_map(|days| daylight.daylight_window(local.date() + chrono::Days::new(days)))
+            .map(|window| DaylightWindow {
+                end: window.end + Duration::minutes(DAYLIGHT_TAIL_IN_M),
+                ..window
+            })
+            .find(|window| local < window.end)
+            .and_then(|window| {
+                if window.contains(local) {
+                    Some(at)
+                } else {
+                    localize(window.start, &self.tz)
+                        .ok()
+                        .map(|start| start.with_timezone(&Utc))
+                }
+            })
+            .unwrap_or(at)
+    }
+
+    // the daylight window that contains `local`
+    fn daylight_window(&self, local: NaiveDateTime) -> Option<DaylightWindow> {
+        let (daylight, _) = self.daylight?;
+        daylight
+            .daylight_window(local.date())
+            .filter(|window| window.contains(local))
+    }
+
+    // `at` in the local time of the site
+    fn local(&self, at: DateTime<Utc>) -> NaiveDateTime {
+        at.with_timezone(&self.tz).naive_local()
+    }
+
+    // sleeps until the time of the next poll
+    fn wait_for_next_poll(&self) {
+        if let Some(wait) = self
+            .next_poll
+            .and_then(|next| (next - Utc::now()).to_std().ok())
+        {
+            debug!("Waiting {wait:?} to poll site {}", self.site_id);
+            std::thread::sleep(wait);
+        }
+    }
+
+    // takes a token of the bucket for a request, waiting for a token when the
+    // bucket is empty
+    fn take_token(&mut self) {
+        if let Some(bucket) = &mut self.bucket {
+            while !bucket.try_take(Utc::now()) {
+                let now = Utc::now();
+                let wait = (bucket.available_at(now) - now)
+                    .to_std()
+                    .unwrap_or_default();
+                debug!(
+                    "Waiting {wait:?} for the rate limit of site {}",
+                    self.site_id
+                );
+                std::thread::sleep(wait);
+            }
+        }
+    }
+
+    // schedules the next poll after the backoff of `error`
+    fn failed(&mut self, error: &SolarApiError) {
+        self.errors += 1;
+        self.next_poll = Some(self.in_daylight(Utc::now() + self.backoff(error)));
+    }
+
+    // the time to wait after `error`, which doubles with every consecutive
+    // error starting at a minute
+    fn backoff(&self, error: &SolarApiError) -> Duration {
+        let rate_limited = matches!(
+            error,
+            SolarApiError::ApiError(error) if error.status() == Some(StatusCode::TOO_MANY_REQUESTS)
+        );
+        if rate_limited {
+            return self.max_backoff;
+        }
+        let minutes = 1_i64 << self.errors.saturating_sub(1).min(16);
+        Duration::minutes(minutes).min(self.max_backoff)
+    }
+}
+
+impl<Tz: TimeZone> Iterator for Poller<'_, Tz> {
+    type Item = Result<Overview, SolarApiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.wait_for_next_poll();
+            self.take_token();
+            match self.client.overview(self.site_id) {
+                Ok(overview) => {
+                    self.errors = 0;
+                    self.next_poll = Some(self.schedule(&overview, Utc::now()));
+                    let updated = overview
+                        .last_updated_time
+                        .filter(|updated| self.last_updated.is_none_or(|last| *updated > last));
+                    if let Some(updated) = updated {
+                        self.last_updated = Some(updated);
+                        return Some(Ok(overview));
+                    }
+                    debug!("No new data for site {} yet", self.site_id);
+                }
+                Err(error) => {
+                    self.failed(&error);
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+/// A [`Poller`] that also requests the power flow of the site on every new
+/// overview, see [`Poller::with_power_flow`]. When the power flow fails, the
+/// error is yielded and the power flow of the same overview is requested again
+/// after the backoff, so no update is lost
+#[derive(Debug, Clone)]
+pub struct PowerFlowPoller<'a, Tz: TimeZone = Local> {
+    poller: Poller<'a, Tz>,
+    // the new overview of which the power flow failed
+    pending: Option<Overview>,
+}
+
+impl<Tz: TimeZone> PowerFlowPoller<'_, Tz> {
+    /// The time of the next poll, or None when the next poll is immediate
+    pub fn next_poll(&self) -> Option<DateTime<Utc>> {
+        self.poller.next_poll()
+    }
+}
+
+impl<Tz: TimeZone> Iterator for PowerFlowPoller<'_, Tz> {
+    type Item = Result<(Overview, PowerFlow), SolarApiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let overview = match self.pending.take() {
+            Some(overview) => {
+                self.poller.wait_for_next_poll();
+                overview
+            }
+            None => match self.poller.next()? {
+                Ok(overview) => overview,
+                Err(error) => return Some(Err(error)),
+            },
+        };
+        self.poller.take_token();
+        match self.poller.client.current_power_flow(self.poller.site_id) {
+            Ok(flow) => {
+                self.poller.errors = 0;
+                self.poller.next_poll = Some(self.poller.schedule(&overview, Utc::now()));
+                Some(Ok((overview, flow)))
+            }
+            Err(error) => {
+                self.poller.failed(&error);
+                self.pending = Some(overview);
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+// the first time of the update schedule of `overview` in time zone `tz` after
+// `now`, or None when the schedule passed
+pub(crate) fn next_scheduled<Tz: TimeZone>(
+    overview: &Overview,
+    tz: &Tz,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    overview
+        .next_update_schedule_in(tz)
+        .ok()?
+        .into_iter()
+        .map(|next| next.with_timezone(&Utc))
+        .find(|next| *next > now)
+}
+
+/// The times to request the overview of a site after `previous`: the times of
+/// [`Overview::next_update_schedule_in`] after `now`, followed by one every 5
+/// minutes until `deadline`. When the whole schedule passed already, the
+/// first attempt is `now`. The times are calculated while iterating, so a
+/// far `deadline` like [`DateTime::<Utc>::MAX_UTC`] does not take memory
+pub fn wait_schedule<Tz: TimeZone>(
+    previous: &Overview,
+    tz: &Tz,
+    now: DateTime<Utc>,
+    deadline: DateTime<Utc>,
+) -> Result<impl Iterator<Item = DateTime<Utc>>, SolarApiError> {
+    let schedule: Vec<DateTime<Utc>> = previous
+        .next_update_schedule_in(tz)?
+        .into_iter()
+        .map(|next| next.with_timezone(&Utc))
+        .filter(|next| *next > now)
+        .collect();
+    let interval = Duration::seconds(NEXT_UPDATE_BACKOFF_IN_S[NEXT_UPDATE_BACKOFF_IN_S.len() - 1]);
+    let first_retry = schedule
+        .last()
+        .map_or(Some(now), |last| last.checked_add_signed(interval));
+    let retries = std::iter::successors(first_retry, move |next| next.checked_add_signed(interval));
+    Ok(schedule
+        .into_iter()
+        .chain(retries)
+        .take_while(move |attempt| *attempt <= deadline))
+}
+
+impl Client {
+    /// Create a [`Poller`] that yields every new overview of `site_id`
+    pub fn poller(&self, site_id: u32) -> Poller<'_> {
+        Poller::new(self, site_id)
+    }
+
+    /// Waits until `site_id` publishes an overview that is newer than
+    /// `previous` and returns it. The overview is requested at the times of
+    /// [`wait_schedule`], so the API may publish late. Failed requests are
+    /// retried at the next time, except for [`SolarApiError::ForbiddenError`].
+    /// Returns the error of the last request when it failed, or
+    /// [`SolarApiError::Timeout`] when there is no new overview within
+    /// `timeout`. A `timeout` beyond the range of dates waits without
+    /// deadline. `tz` is the time zone of the site
+    pub fn wait_for_new_overview<Tz: TimeZone>(
+        &self,
+        site_id: u32,
+        previous: &Overview,
+        tz: &Tz,
+        timeout: std::time::Duration,
+    ) -> Result<Overview, SolarApiError> {
+        let now = Utc::now();
+        let deadline = Duration::from_std(timeout)
+            .ok()
+            .and_then(|timeout| now.checked_add_signed(timeout))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        let mut last_error = None;
+        for attempt in wait_schedule(previous, tz, now, deadline)? {
+            if let Ok(wait) = (attempt - Utc::now()).to_std() {
+                debug!("Waiting {wait:?} for new overview of site {site_id}");
+                std::thread::sleep(wait);
+            }
+            match self.overview(site_id) {
+                Ok(overview) if overview.last_updated_time > previous.last_updated_time => {
+                    return Ok(overview)
+                }
+                Ok(_) => last_error = None,
+                Err(error @ SolarApiError::ForbiddenError(_)) => return Err(error),
+                Err(error) => {
+                    debug!("Retrying overview of site {site_id} after error: {error}");
+                    last_error = Some(error);
+                }
+            }
+        }
+        Err(last_error.unwrap_or(SolarApiError::Timeout {
+            site_id,
+            last_updated: previous.last_updated_time,
+        }))
+    }
+}
+
+#[test]
+fn test_poller_schedule() {
+    let client = Client::new("API_KEY");
+    let tz = chrono::FixedOffset::east_opt(3600).unwrap();
+    let mut poller = client
+        .poller(1234)
+        .with_timezone(tz)
+        .with_retry_interval(Duration::minutes(2));
+    assert_eq!(None, poller.next_poll());
+
+    let overview = serde_json::from_str::<crate::site::OverviewReply>(
+        r#"{"overview":{
+            "lastUpdateTime":"2023-11-09 10:28:56",
+            "lifeTimeData":{"energy":1.0},
+            "lastYearData":{"energy":1.0},
+            "lastMonthData":{"energy":1.0},
+            "lastDayData":{"energy":1.0},
+            "currentPower":{"power":1.0},
+            "measuredBy":"INVERTER"}}"#,
+    )
+    .unwrap()
+    .overview;
+    let utc = |s: &str| {
+        chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+            .and_utc()
+    };
+    // 10:28:56 in the site is 09:28:56 UTC, the next update is 15 minutes later
+    assert_eq!(
+        utc("2023-11-09 09:44:06"),
+        poller.schedule(&overview, utc("2023-11-09 09:30:00"))
+    );
+    // the data is late, so retry later
+    assert_eq!(
+        utc("2023-11-09 09:48:56"),
+        poller.schedule(&overview, utc("2023-11-09 09:45:00"))
+    );
+    assert_eq!(
+        utc("2023-11-09 09:52:00"),
+        poller.schedule(&overview, utc("2023-11-09 09:50:00"))
+    );
+
+    let error = SolarApiError::UnknownTimeUnit("SECOND".to_string());
+    poller.errors = 1;
+    assert_eq!(Duration::minutes(1), poller.backoff(&error));
+    poller.errors = 4;
+    assert_eq!(Duration::minutes(8), poller.backoff(&error));
+    poller.errors = 40;
+    assert_eq!(Duration::hours(1), poller.backoff(&error));
+}
+
+#[test]
+fn test_poller_daylight() {
+    let client = Client::new("API_KEY");
+    let daylight = (
+        chrono::NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
+        chrono::NaiveTime::from_hms_opt(21, 0, 0).unwrap(),
+    );
+    let poller = client
+        .poller(1234)
+        .with_timezone(Utc)
+        .with_daylight(&daylight, 300);
+    let utc = |s: &str| {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+            .and_utc()
+    };
+
+    let noon = utc("2023-06-01 12:00:00");
+    assert_eq!(noon, poller.in_daylight(noon));
+    // the last data of the day is fetched after sunset
+    let after_sunset = utc("2023-06-01 21:10:00");
+    assert_eq!(after_sunset, poller.in_daylight(after_sunset));
+    assert_eq!(
+        utc("2023-06-02 06:00:00"),
+        poller.in_daylight(utc("2023-06-01 23:00:00"))
+    );
+    assert_eq!(
+        utc("2023-06-02 06:00:00"),
+        poller.in_daylight(utc("2023-06-02 03:00:00"))
+    );
+    // 15 hours of daylight for 300 requests
+    assert_eq!(Duration::minutes(3), poller.retry_interval(noon));
+    assert_eq!(
+        Duration::minutes(5),
+        poller.retry_interval(utc("2023-06-02 03:00:00"))
+    );
+}
+
+#[test]
+fn test_wait_schedule() {
+    let overview = serde_json::from_str::<crate::site::OverviewReply>(
+        r#"{"overview":{
+            "lastUpdateTime":"2023-11-09 10:28:56",
+            "lifeTimeData":{"energy":1.0},
+            "lastYearData":{"energy":1.0},
+            "lastMonthData":{"energy":1.0},
+            "lastDayData":{"energy":1.0},
+            "currentPower":{"power":1.0},
+            "measuredBy":"INVERTER"}}"#,
+    )
+    .unwrap()
+    .overview;
+    let utc = |s: &str| {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+            .and_utc()
+    };
+    let times = |attempts: &mut dyn Iterator<Item = DateTime<Utc>>| {
+        attempts
+            .map(|attempt| attempt.format("%H:%M:%S").to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let mut attempts = wait_schedule(
+        &overview,
+        &Utc,
+        utc("2023-11-09 10:30:00"),
+        utc("2023-11-09 10:55:00"),
+    )
+    .unwrap();
+    assert_eq!(
+        vec!["10:44:06", "10:44:56", "10:48:56", "10:53:56"],
+        times(&mut attempts)
+    );
+
+    // already late
+    let mut attempts = wait_schedule(
+        &overview,
+        &Utc,
+        utc("2023-11-09 11:00:00"),
+        utc("2023-11-09 11:06:00"),
+    )
+    .unwrap();
+    assert_eq!(vec!["11:00:00", "11:05:00"], times(&mut attempts));
+
+    // without deadline the attempts are calculated while iterating
+    let mut attempts = wait_schedule(
+        &overview,
+        &Utc,
+        utc("2023-11-09 11:00:00"),
+        DateTime::<Utc>::MAX_UTC,
+    )
+    .unwrap()
+    .skip(1_000_000);
+    assert_eq!(Some(utc("2033-05-12 16:20:00")), attempts.next());
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_poller_power_flow() {
+    use crate::{
+        fake_server::{FakeServer, FakeSite, Scenario},
+        units::as_watts,
+    };
+
+    let now = NaiveDateTime::parse_from_str("2023-06-01 12:20:00", "%Y-%m-%d %H:%M:%S").unwrap();
+    let server = FakeServer::start(
+        Scenario::new("KEY")
+            .with_site(FakeSite::new(1234, 5.0))
+            .with_daily_limit(1)
+            .with_now(now),
+    )
+    .unwrap();
+    let client = server.client();
+    let mut poller = client
+        .poller(1234)
+        .with_max_backoff(Duration::zero())
+        .with_power_flow();
+
+    // the overview is the only request of the day, so the power flow fails
+    assert!(poller.next().unwrap().is_err());
+    // the power flow of the same overview is requested again
+    server.advance(Duration::days(1));
+    let (overview, flow) = poller.next().unwrap().unwrap();
+    assert_eq!(
+        "2023-06-01 12:15:00",
+        overview.last_updated_time.unwrap().to_string()
+    );
+    let pv = as_watts(flow.power(flow.pv.as_ref().unwrap()).unwrap());
+    assert!((as_watts(overview.current_power.power) - pv).abs() < 1e-6);
+    assert_eq!(
+        vec![
+            "/site/1234/overview",
+            "/site/1234/currentPowerFlow",
+            "/site/1234/currentPowerFlow"
+        ],
+        server.requests()
+    );
+}
@@ -0,0 +1,475 @@
+//! Compares the sites of an account using the bulk APIs, which request up to
+//! [`BULK_MAX_SITES`] sites in one call. See
+//! [`Client::portfolio`](crate::Client::portfolio)
+//!
+//! ```no_run
+//! # use solar_api::{Client, DataPeriod, TimeUnit};
+//! # use solar_api::chrono::NaiveDate;
+//! let client = Client::new("API_KEY");
+//! let portfolio = client.portfolio(&[1234, 5678, 9012]);
+//! let period = DataPeriod::between(
+//!     NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(),
+//!     NaiveDate::from_ymd_opt(2023, 6, 30).unwrap(),
+//! )?;
+//! let energy = portfolio.energy(period, TimeUnit::Day)?;
+//! // the site that yields the least compared with the other sites
+//! for site in energy.worst(&portfolio.sites()?, 1) {
+//!     println!("{}: {:?} of the median", site.site_id, site.ratio_to_median);
+//! }
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+#[cfg(test)]
+use crate::units::{as_watt_hours, watt_hours};
+use crate::{
+    client::Client,
+    series::{GapPolicy, TimeSeries},
+    site::{DataPeriod, Overview, RawValue, Site, TimeUnit},
+    units::{Energy, EnergyUnit, PowerUnit},
+    SolarApiError,
+};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The maximum number of sites in one call of a bulk API
+pub const BULK_MAX_SITES: usize = 100;
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct OverviewsReply {
+    #[serde(rename = "sitesOverviews")]
+    sites_overviews: SiteList<SiteOverview>,
+}
+
+impl OverviewsReply {
+    pub(crate) fn overviews(self) -> impl Iterator<Item = (u32, Overview)> {
+        self.sites_overviews
+            .site_energy_list
+            .into_iter()
+            .map(|site| (site.site_id, site.site_overview))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct SitesEnergyReply {
+    #[serde(rename = "sitesEnergy")]
+    sites_energy: SitesEnergy,
+}
+
+impl SitesEnergyReply {
+    // the energy per site. Returns [`SolarApiError::UnsupportedUnit`] when the
+    // API used a unit of measurement that this lib does not know
+    pub(crate) fn energy(
+        self,
+    ) -> Result<(TimeUnit, BTreeMap<u32, TimeSeries<Energy>>), SolarApiError> {
+        let energy = self.sites_energy;
+        let unit: EnergyUnit = energy.unit.parse()?;
+        let sites = energy
+            .site_energy_list
+            .into_iter()
+            .map(|site| {
+                let values = site
+                    .energy_values
+                    .values
+                    .iter()
+                    .map(|v| v.convert(unit))
+                    .collect();
+                let series = TimeSeries::new(energy.time_unit, &energy.unit, values);
+                (site.site_id, series)
+            })
+            .collect();
+        Ok((energy.time_unit, sites))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SiteList<S> {
+    #[serde(rename = "count")]
+    _count: u32,
+    #[serde(rename = "siteEnergyList")]
+    site_energy_list: Vec<S>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SiteOverview {
+    #[serde(rename = "siteId")]
+    site_id: u32,
+    #[serde(rename = "siteOverview")]
+    site_overview: Overview,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SitesEnergy {
+    #[serde(rename = "timeUnit", deserialize_with = "TimeUnit::from_const")]
+    time_unit: TimeUnit,
+    unit: String,
+    #[serde(rename = "count")]
+    _count: u32,
+    #[serde(rename = "siteEnergyList")]
+    site_energy_list: Vec<SiteEnergy>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SiteEnergy {
+    #[serde(rename = "siteId")]
+    site_id: u32,
+    #[serde(rename = "energyValues")]
+    energy_values: EnergyValues,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct EnergyValues {
+    #[serde(rename = "measuredBy")]
+    _measured_by: Option<String>,
+    values: Vec<RawValue>,
+}
+
+/// A group of sites that are requested together using the bulk APIs, created
+/// using [`Client::portfolio`]
+///
+/// ```no_run
+/// # use solar_api::{Client, DataPeriod, TimeUnit};
+/// # use chrono::NaiveDate;
+/// let client = Client::new("API_KEY");
+/// let portfolio = client.portfolio(&[1234, 5678]);
+/// let period = DataPeriod::between(
+///     NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(),
+///     NaiveDate::from_ymd_opt(2023, 6, 30).unwrap(),
+/// )?;
+/// let energy = portfolio.energy(period, TimeUnit::Day)?;
+/// let sites = portfolio.sites()?;
+/// println!("{:?} kWh/kWp", energy.weighted_specific_yield(&sites));
+/// # Ok::<(), solar_api::SolarApiError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Portfolio<'a> {
+    client: &'a Client,
+    site_ids: Vec<u32>,
+}
+
+impl Portfolio<'_> {
+    /// the ids of the sites in this portfolio
+    pub fn site_ids(&self) -> &[u32] {
+        &self.site_ids
+    }
+
+    /// The sites of this portfolio from the site list of the account, i.e. for
+    /// their peak power
+    pub fn sites(&self) -> Result<Vec<Site>, SolarApiError> {
+        Ok(self
+            .client
+            .list()?
+            .into_iter()
+            .filter(|site| self.site_ids.contains(&site.id))
+            .collect())
+    }
+
+    /// The overview per site, see [`Client::overviews`]
+    pub fn overviews(&self) -> Result<BTreeMap<u32, Overview>, SolarApiError> {
+        self.client.overviews(&self.site_ids)
+    }
+
+    /// The energy per site in `period`, see [`Client::sites_energy`]
+    pub fn energy(
+        &self,
+        period: DataPeriod,
+        time_unit: TimeUnit,
+    ) -> Result<PortfolioEnergy, SolarApiError> {
+        let sites = self
+            .client
+            .sites_energy(&self.site_ids, period, time_unit)?;
+        Ok(PortfolioEnergy { time_unit, sites })
+    }
+}
+
+/// The energy of the sites of a [`Portfolio`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortfolioEnergy {
+    pub time_unit: TimeUnit,
+    /// the energy per site id
+    pub sites: BTreeMap<u32, TimeSeries<Energy>>,
+}
+
+impl PortfolioEnergy {
+    /// The energy of all sites per timestamp. Missing values count as zero
+    pub fn series(&self) -> TimeSeries<Energy> {
+        let empty = TimeSeries::new(self.time_unit, "Wh", vec![]);
+        self.sites
+            .values()
+            .fold(empty, |sum, site| sum.add(site, GapPolicy::Zero))
+    }
+
+    /// The energy of all sites in the period
+    pub fn total(&self) -> Energy {
+        self.sites
+            .values()
+            .map(TimeSeries::total)
+            .fold(EnergyUnit::WattHour.energy(0.0), |sum, energy| sum + energy)
+    }
+
+    /// The energy per site in the period
+    pub fn totals(&self) -> BTreeMap<u32, Energy> {
+        self.sites
+            .iter()
+            .map(|(id, series)| (*id, series.total()))
+            .collect()
+    }
+
+    /// The specific yield in kWh/kWp in the period per site, using the peak
+    /// power of `sites`. Sites without peak power are left out
+    pub fn specific_yields(&self, sites: &[Site]) -> BTreeMap<u32, f64> {
+        self.with_peak_power(sites)
+            .map(|(id, energy, kwp)| (id, energy / kwp))
+            .collect()
+    }
+
+    /// The specific yield in kWh/kWp of all sites together, so larger sites
+    /// weigh more. Sites without peak power are left out. Returns None when no
+    /// site has energy and peak power
+    pub fn weighted_specific_yield(&self, sites: &[Site]) -> Option<f64> {
+        let (energy, kwp) = self
+            .with_peak_power(sites)
+            .fold((0.0, 0.0), |(energy, kwp), (_, e, p)| (energy + e, kwp + p));
+        (kwp > 0.0).then(|| energy / kwp)
+    }
+
+    /// Ranks the sites with peak power by their specific yield compared with
+    /// the median of the fleet, worst first. Only timestamps at which every
+    /// ranked site has a value are used, so all sites are compared in the same
+    /// weather and missing data does not make a site look bad
+    pub fn ranking(&self, sites: &[Site]) -> Vec<SiteRanking> {
+        let ranked: Vec<(u32, &TimeSeries<Energy>, f64)> = self
+            .sites
+            .iter()
+            .filter_map(|(id, series)| {
+                let site = sites.iter().find(|site| site.id == *id)?;
+                let kwp = PowerUnit::Kilowatt.value_of(site.peak_power);
+                (kwp > 0.0).then_some((*id, series, kwp))
+            })
+            .collect();
+        let with_value = |series: &TimeSeries<Energy>| -> BTreeSet<NaiveDateTime> {
+            series
+                .iter()
+                .filter(|v| v.value.is_some())
+                .map(|v| v.date)
+                .collect()
+        };
+        let Some(common) = ranked
+            .iter()
+            .map(|(_, series, _)| with_value(series))
+            .reduce(|common, dates| &common & &dates)
+        else {
+            return vec![];
+        };
+
+        let yields: Vec<(u32, f64)> = ranked
+            .iter()
+            .map(|(id, series, kwp)| {
+                let energy: f64 = series
+                    .iter()
+                    .filter(|v| common.contains(&v.date))
+                    .filter_map(|v| v.value)
+                    .map(|energy| EnergyUnit::KilowattHour.value_of(energy))
+                    .sum();
+                (*id, energy / kwp)
+            })
+            .collect();
+        let mut sorted: Vec<f64> = yields.iter().map(|(_, y)| *y).collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let median = match sorted.len() {
+            0 => return vec![],
+            n if n % 2 == 0 => (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0,
+            n => sorted[n / 2],
+        };
+
+        let mut ranking: Vec<SiteRanking> = yields
+            .into_iter()
+            .map(|(site_id, specific_yield)| SiteRanking {
+                site_id,
+                rank: 0,
+                specific_yield,
+                fleet_median: median,
+                ratio_to_median: (median > 0.0).then(|| specific_yield / median),
+            })
+            .collect();
+        ranking.sort_by(|a, b| {
+            a.specific_yield
+                .partial_cmp(&b.specific_yield)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        for (i, site) in ranking.iter_mut().enumerate() {
+            site.rank = i + 1;
+        }
+        ranking
+    }
+
+    /// The `n` sites with the lowest specific yield compared with the fleet,
+    /// see [`PortfolioEnergy::ranking`]
+    pub fn worst(&self, sites: &[Site], n: usize) -> Vec<SiteRanking> {
+        let mut ranking = self.ranking(sites);
+        ranking.truncate(n);
+        ranking
+    }
+
+    // the energy in kWh and peak power in kWp per site with positive peak power
+    fn with_peak_power<'a>(
+        &'a self,
+        sites: &'a [Site],
+    ) -> impl Iterator<Item = (u32, f64, f64)> + 'a {
+        self.sites.iter().filter_map(|(id, series)| {
+            let site = sites.iter().find(|site| site.id == *id)?;
+            let kwp = PowerUnit::Kilowatt.value_of(site.peak_power);
+            let energy = EnergyUnit::KilowattHour.value_of(series.total());
+            (kwp > 0.0).then_some((*id, energy, kwp))
+        })
+    }
+}
+
+/// The performance of a site compared with the other sites of a portfolio,
+/// see [`PortfolioEnergy::ranking`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SiteRanking {
+    pub site_id: u32,
+    /// the position in the ranking, 1 for the worst site
+    pub rank: usize,
+    /// the specific yield in kWh/kWp
+    pub specific_yield: f64,
+    /// the median specific yield of the ranked sites in kWh/kWp
+    pub fleet_median: f64,
+    /// `specific_yield` divided by `fleet_median`, or None when the median is
+    /// zero
+    pub ratio_to_median: Option<f64>,
+}
+
+impl Client {
+    /// Create a [`Portfolio`] of the sites with `site_ids`
+    pub fn portfolio(&self, site_ids: &[u32]) -> Portfolio<'_> {
+        Portfolio {
+            client: self,
+            site_ids: site_ids.to_vec(),
+        }
+    }
+}
+
+// a site with `id` and `peak_power_kw` for tests
+#[cfg(test)]
+fn test_site(id: u32, peak_power_kw: f64) -> Site {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "name": format!("Site {id}"),
+        "accountId": 1,
+        "status": "Active",
+        "peakPower": peak_power_kw,
+        "type": "Optimizers & Inverters",
+        "location": {
+            "country": "Netherlands",
+            "city": "A city",
+            "address": "Some address",
+            "zip": "zipy",
+            "timeZone": "Europe/Amsterdam",
+            "countryCode": "NL"
+        },
+        "primaryModule": {"manufacturerName": "", "modelName": ""},
+        "uris": {},
+        "publicSettings": {"isPublic": false}
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_sites_overviews() {
+    let reply = r#"
+    {"sitesOverviews":{"count":1,"siteEnergyList":[
+        {"siteId":1,"siteOverview":{
+            "lastUpdateTime":"2023-11-09 10:28:56",
+            "lifeTimeData":{"energy":1.9191678E7},
+            "lastYearData":{"energy":6143745.0},
+            "lastMonthData":{"energy":38709.0},
+            "lastDayData":{"energy":2028.0},
+            "currentPower":{"power":1173.7279},
+            "measuredBy":"INVERTER"}}
+    ]}}
+    "#;
+    let overviews: BTreeMap<_, _> = serde_json::from_str::<OverviewsReply>(reply)
+        .unwrap()
+        .overviews()
+        .collect();
+    assert_eq!(2028.0, as_watt_hours(overviews[&1].last_day_data.energy));
+}
+
+#[test]
+fn test_portfolio_energy() {
+    let reply = r#"
+    {"sitesEnergy":{"timeUnit":"DAY","unit":"kWh","count":2,"siteEnergyList":[
+        {"siteId":1,"energyValues":{"measuredBy":"INVERTER","values":[
+            {"date":"2023-06-01 00:00:00","value":20.0},
+            {"date":"2023-06-02 00:00:00","value":null}]}},
+        {"siteId":2,"energyValues":{"measuredBy":"INVERTER","values":[
+            {"date":"2023-06-01 00:00:00","value":30.0},
+            {"date":"2023-06-02 00:00:00","value":50.0}]}}
+    ]}}
+    "#;
+    let (time_unit, sites) = serde_json::from_str::<SitesEnergyReply>(reply)
+        .unwrap()
+        .energy()
+        .unwrap();
+    let energy = PortfolioEnergy { time_unit, sites };
+
+    assert_eq!(100_000.0, as_watt_hours(energy.total()));
+    assert_eq!(20_000.0, as_watt_hours(energy.totals()[&1]));
+    assert_eq!(Some(watt_hours(50_000.0)), energy.series().values[0].value);
+
+    let sites = [test_site(1, 10.0), test_site(2, 30.0)];
+    assert_eq!(Some(2.0), energy.specific_yields(&sites).get(&1).copied());
+    assert_eq!(Some(2.5), energy.weighted_specific_yield(&sites));
+}
+
+#[test]
+fn test_fleet_ranking() {
+    let series = |values: &[Option<f64>]| {
+        let start = chrono::NaiveDate::from_ymd_opt(2023, 6, 1).unwrap();
+        let values = values
+            .iter()
+            .enumerate()
+            .map(|(day, kwh)| crate::TimeSeriesValue {
+                date: (start + chrono::Duration::days(day as i64)).into(),
+                value: kwh.map(|kwh| watt_hours(kwh * 1000.0)),
+            })
+            .collect();
+        TimeSeries::new(TimeUnit::Day, "Wh", values)
+    };
+    let energy = PortfolioEnergy {
+        time_unit: TimeUnit::Day,
+        sites: BTreeMap::from([
+            (1, series(&[Some(40.0), Some(40.0), Some(40.0)])),
+            (2, series(&[Some(20.0), Some(20.0), Some(20.0)])),
+            // the missing day is left out for all sites
+            (3, series(&[Some(50.0), None, Some(50.0)])),
+            (4, series(&[Some(10.0), Some(10.0), Some(10.0)])),
+        ]),
+    };
+    let sites = [
+        test_site(1, 10.0),
+        test_site(2, 10.0),
+        test_site(3, 10.0),
+        test_site(4, 0.0),
+    ];
+
+    let ranking = energy.ranking(&sites);
+    assert_eq!(3, ranking.len());
+    assert_eq!(2, ranking[0].site_id);
+    assert_eq!(1, ranking[0].rank);
+    assert_eq!(4.0, ranking[0].specific_yield);
+    assert_eq!(8.0, ranking[0].fleet_median);
+    assert_eq!(Some(0.5), ranking[0].ratio_to_median);
+    assert_eq!(3, ranking[2].site_id);
+
+    assert_eq!(
+        vec![2],
+        energy
+            .worst(&sites, 1)
+            .iter()
+            .map(|r| r.site_id)
+            .collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,236 @@
+//! Writes series to a PostgreSQL table, i.e. a TimescaleDB hypertable, see
+//! [`PostgresSink`]. Requires the `postgres` feature. The sink is async and
+//! uses a [`tokio_postgres::Client`], which needs a tokio runtime.
+//!
+//! The table has a row per [`Reading`] of the normalized
+//! [`schema`](crate::export::schema) with the columns `time` (timestamp
+//! without time zone, in the local time of the site), `site_id` (bigint),
+//! `channel` (text), `value` (double precision, null when the API had no
+//! value), `unit` (text) and `quality` (text). The primary key is
+//! `(site_id, channel, time)`, so writing a value again replaces it. The
+//! version of the schema is in the comment of the table.
+//!
+//! ```no_run
+//! # use solar_api::postgres::PostgresSink;
+//! # use solar_api::{Client, DataPeriod, TimeUnit};
+//! # use solar_api::chrono::NaiveDate;
+//! # async fn run(db: &solar_api::tokio_postgres::Client) -> Result<(), solar_api::SolarApiError> {
+//! let client = Client::new("API_KEY");
+//! let period = DataPeriod::between(
+//!     NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(),
+//!     NaiveDate::from_ymd_opt(2023, 6, 30).unwrap(),
+//! )?;
+//! let energy = client.energy(1234, period, TimeUnit::Day)?.to_series()?;
+//! let sink = PostgresSink::new(db).with_table("solar.readings");
+//! sink.create_table().await?;
+//! sink.create_hypertable().await?;
+//! sink.write_energy(1234, "energy", &energy).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    export::schema::{self, Reading, SCHEMA_VERSION},
+    meters::{EnergyDetails, PowerDetails},
+    series::TimeSeries,
+    units::{Energy, Power},
+    SolarApiError,
+};
+use log::debug;
+use std::fmt;
+use tokio_postgres::{types::ToSql, Client};
+
+// the number of columns of the table
+const COLUMNS: usize = 6;
+// PostgreSQL allows at most 65535 parameters per statement
+const MAX_BATCH_SIZE: usize = 65535 / COLUMNS;
+
+/// Writes series to a table with batched inserts that replace existing values
+pub struct PostgresSink<'a> {
+    client: &'a Client,
+    table: String,
+    batch_size: usize,
+}
+
+impl fmt::Debug for PostgresSink<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PostgresSink")
+            .field("table", &self.table)
+            .field("batch_size", &self.batch_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> PostgresSink<'a> {
+    /// Create a sink that writes to the table `solar_readings` with `client`
+    pub fn new(client: &'a Client) -> Self {
+        PostgresSink {
+            client,
+            table: "solar_readings".to_string(),
+            batch_size: 1000,
+        }
+    }
+
+    /// Set the table, optionally with its schema like `solar.readings`
+    pub fn with_table(mut self, table: &str) -> Self {
+        self.table = table.to_string();
+        self
+    }
+
+    /// Set the number of rows per insert, 1000 by default. PostgreSQL limits
+    /// the batch size to 10922 rows
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, MAX_BATCH_SIZE);
+        self
+    }
+
+    /// Create the table when it does not exist
+    pub async fn create_table(&self) -> Result<(), SolarApiError> {
+        let table = quote_identifier(&self.table);
+        let statement = format!(
+            "CREATE TABLE IF NOT EXISTS {table} (
+                time TIMESTAMP NOT NULL,
+                site_id BIGINT NOT NULL,
+                channel TEXT NOT NULL,
+                value DOUBLE PRECISION,
+                unit TEXT NOT NULL,
+                quality TEXT NOT NULL,
+                PRIMARY KEY (site_id, channel, time)
+            );
+            COMMENT ON TABLE {table} IS 'solar-api readings, schema version {SCHEMA_VERSION}'"
+        );
+        self.client
+            .batch_execute(&statement)
+            .await
+            .map_err(SolarApiError::PostgresError)
+    }
+
+    /// Turn the table into a TimescaleDB hypertable partitioned on `time`,
+    /// when it is not a hypertable yet. The table needs to be empty
+    pub async fn create_hypertable(&self) -> Result<(), SolarApiError> {
+        self.client
+            .execute(
+                "SELECT create_hypertable($1::text::regclass, 'time', if_not_exists => TRUE)",
+                &[&quote_identifier(&self.table)],
+            )
+            .await
+            .map(|_| ())
+            .map_err(SolarApiError::PostgresError)
+    }
+
+    /// Write the values of `energy` of `site_id` in Wh and return the number
+    /// of written rows
+    pub async fn write_energy(
+        &self,
+        site_id: u32,
+        channel: &str,
+        energy: &TimeSeries<Energy>,
+    ) -> Result<u64, SolarApiError> {
+        self.write_readings(&schema::energy_readings(site_id, channel, energy))
+            .await
+    }
+
+    /// Write the values of `power` of `site_id` in W and return the number of
+    /// written rows
+    pub async fn write_power(
+        &self,
+        site_id: u32,
+        channel: &str,
+        power: &TimeSeries<Power>,
+    ) -> Result<u64, SolarApiError> {
+        self.write_readings(&schema::power_readings(site_id, channel, power))
+            .await
+    }
+
+    /// Write the series of every meter of `details`
+    pub async fn write_energy_details(
+        &self,
+        site_id: u32,
+        details: &EnergyDetails,
+    ) -> Result<u64, SolarApiError> {
+        self.write_readings(&schema::energy_details_readings(site_id, details)?)
+            .await
+    }
+
+    /// Write the series of every meter of `details`
+    pub async fn write_power_details(
+        &self,
+        site_id: u32,
+        details: &PowerDetails,
+    ) -> Result<u64, SolarApiError> {
+        self.write_readings(&schema::power_details_readings(site_id, details)?)
+            .await
+    }
+
+    /// Write `readings` in batches of the batch size and return the number of
+    /// written rows
+    pub async fn write_readings(&self, readings: &[Reading]) -> Result<u64, SolarApiError> {
+        let mut written = 0;
+        for batch in readings.chunks(self.batch_size) {
+            let statement = insert_statement(&self.table, batch.len());
+            let site_ids: Vec<i64> = batch.iter().map(|r| i64::from(r.site_id)).collect();
+            let qualities: Vec<&str> = batch.iter().map(|r| r.quality.as_str()).collect();
+            let params: Vec<&(dyn ToSql + Sync)> = batch
+                .iter()
+                .enumerate()
+                .flat_map(|(i, reading)| {
+                    [
+                        &reading.timestamp as &(dyn ToSql + Sync),
+                        &site_ids[i],
+                        &reading.channel,
+                        &reading.value,
+                        &reading.unit,
+                        &qualities[i],
+                    ]
+                })
+                .collect();
+            written += self
+                .client
+                .execute(&statement, &params)
+                .await
+                .map_err(SolarApiError::PostgresError)?;
+        }
+        debug!("Wrote {written} rows to {}", self.table);
+        Ok(written)
+    }
+}
+
+// an insert of `rows` rows that replaces the values of existing rows
+fn insert_statement(table: &str, rows: usize) -> String {
+    let values: Vec<_> = (0..rows)
+        .map(|row| {
+            let params: Vec<_> = (1..=COLUMNS)
+                .map(|column| format!("${}", row * COLUMNS + column))
+                .collect();
+            format!("({})", params.join(", "))
+        })
+        .collect();
+    format!(
+        "INSERT INTO {} (time, site_id, channel, value, unit, quality) VALUES {} \
+         ON CONFLICT (site_id, channel, time) DO UPDATE SET \
+         value = EXCLUDED.value, unit = EXCLUDED.unit, quality = EXCLUDED.quality",
+        quote_identifier(table),
+        values.join(", ")
+    )
+}
+
+// quotes every part of a table name like `schema.table`
+fn quote_identifier(table: &str) -> String {
+    table
+        .split('.')
+        .map(|part| format!("\"{}\"", part.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[test]
+fn test_postgres_statements() {
+    assert_eq!(
+        "INSERT INTO \"solar\".\"readings\" (time, site_id, channel, value, unit, quality) VALUES \
+         ($1, $2, $3, $4, $5, $6), ($7, $8, $9, $10, $11, $12) \
+         ON CONFLICT (site_id, channel, time) DO UPDATE SET \
+         value = EXCLUDED.value, unit = EXCLUDED.unit, quality = EXCLUDED.quality",
+        insert_statement("solar.readings", 2)
+    );
+    assert_eq!("\"a\"\"b\"", quote_identifier("a\"b"));
+}
@@ -0,0 +1,112 @@
+use crate::{
+    units::{Power, PowerUnit},
+    SolarApiError,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct PowerFlowReply {
+    #[serde(rename = "siteCurrentPowerFlow")]
+    pub(crate) site_current_power_flow: PowerFlow,
+}
+
+/// The current power between the PV, the batteries, the grid and the
+/// consumption of a site, see [`crate::current_power_flow`]. The elements are
+/// None when the site has no such element or does not measure it
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerFlow {
+    /// the seconds between updates of the power flow
+    #[serde(default)]
+    pub update_refresh_rate: u32,
+    /// the unit of the current power of the elements, i.e. `kW`
+    #[serde(default)]
+    pub unit: String,
+    /// the directions of the power between the elements
+    #[serde(default)]
+    pub connections: Vec<PowerFlowConnection>,
+    #[serde(rename = "GRID", default, skip_serializing_if = "Option::is_none")]
+    pub grid: Option<PowerFlowElement>,
+    #[serde(rename = "LOAD", default, skip_serializing_if = "Option::is_none")]
+    pub load: Option<PowerFlowElement>,
+    #[serde(rename = "PV", default, skip_serializing_if = "Option::is_none")]
+    pub pv: Option<PowerFlowElement>,
+    #[serde(rename = "STORAGE", default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<PowerFlowElement>,
+}
+
+impl PowerFlow {
+    /// The current power of `element`, which is an element of this power flow.
+    /// Returns [`SolarApiError::UnsupportedUnit`] when the API used a unit
+    /// that this lib does not know
+    pub fn power(&self, element: &PowerFlowElement) -> Result<Power, SolarApiError> {
+        Ok(self.unit.parse::<PowerUnit>()?.power(element.current_power))
+    }
+
+    /// The JSON of this reply as returned by [`crate::current_power_flow`]
+    pub fn to_api_json(&self) -> serde_json::Value {
+        serde_json::json!({ "siteCurrentPowerFlow": self })
+    }
+}
+
+/// The direction of the power between two elements of a [`PowerFlow`]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PowerFlowConnection {
+    /// the element the power flows from, i.e. `PV`
+    pub from: String,
+    /// the element the power flows to, i.e. `Load`
+    pub to: String,
+}
+
+/// An element of a [`PowerFlow`], like the PV or the grid
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerFlowElement {
+    /// i.e. `Active`, `Idle`, `Charging` or `Discharging`
+    pub status: String,
+    /// the current power in the unit of the [`PowerFlow`], see
+    /// [`PowerFlow::power`]
+    pub current_power: f64,
+    /// the state of charge in percent of the batteries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub charge_level: Option<f64>,
+    /// true when the state of charge of the batteries is critical
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub critical: Option<bool>,
+}
+
+#[test]
+fn test_power_flow() {
+    use crate::units::as_watts;
+
+    let reply = r#"
+    {"siteCurrentPowerFlow":{
+        "updateRefreshRate":3,
+        "unit":"kW",
+        "connections":[{"from":"PV","to":"Load"},{"from":"GRID","to":"Load"}],
+        "GRID":{"status":"Active","currentPower":0.5},
+        "LOAD":{"status":"Active","currentPower":1.7},
+        "PV":{"status":"Active","currentPower":1.2}}}
+    "#;
+    let flow = serde_json::from_str::<PowerFlowReply>(reply)
+        .unwrap()
+        .site_current_power_flow;
+    assert_eq!(
+        1200.0,
+        as_watts(flow.power(flow.pv.as_ref().unwrap()).unwrap())
+    );
+    assert!(flow.storage.is_none());
+    assert_eq!("Load", flow.connections[1].to);
+
+    let json: serde_json::Value = serde_json::from_str(reply).unwrap();
+    assert_eq!(json, flow.to_api_json());
+
+    let flow = PowerFlow {
+        unit: "hp".to_string(),
+        ..flow
+    };
+    assert!(matches!(
+        flow.power(flow.grid.as_ref().unwrap()),
+        Err(SolarApiError::UnsupportedUnit(_))
+    ));
+}
@@ -0,0 +1,21 @@
+//! Re-exports the commonly needed types of this lib and the `chrono` types used
+//! in its API, so one import is enough:
+//!
+//! ```
+//! use solar_api::prelude::*;
+//!
+//! let period = DataPeriod::between(
+//!     NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+//!     NaiveDate::from_ymd_opt(2023, 1, 31).unwrap(),
+//! )?;
+//! assert!(period.validate_for(TimeUnit::Day).is_ok());
+//! # Ok::<(), SolarApiError>(())
+//! ```
+
+pub use crate::{
+    Aggregation, Client, DataPeriod, Energy, EnergyUnit, FillStrategy, GapPolicy, GeneratedEnergy,
+    GeneratedEnergyValue, GeneratedPowerPerTimeUnit, GeneratedPowerValue, Integration,
+    MonitoringApi, Overview, ParseMode, Power, PowerUnit, Site, SolarApiError, TimeSeries,
+    TimeSeriesValue, TimeUnit,
+};
+pub use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
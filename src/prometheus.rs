@@ -0,0 +1,358 @@
+//! Renders the latest readings of sites as gauges in the Prometheus text
+//! format, see [`Metrics`], and serves them to Prometheus with a small HTTP
+//! listener, see [`MetricsExporter`]. Requires the `prometheus` feature.
+//!
+//! The gauges are labeled with the `site_id` of the site:
+//! * `solaredge_current_power_watts`: the current power of the overview
+//! * `solaredge_energy_watt_hours`: the energy of the overview per `period`,
+//!   which is `last_day`, `last_month`, `last_year` or `life_time`
+//! * `solaredge_meter_power_watts`: the last power per `meter` of power details
+//! * `solaredge_battery_power_watts`: the last power per `battery`, positive
+//!   when charging
+//! * `solaredge_battery_state_of_charge_percent`: the last state of charge per
+//!   `battery`
+//!
+//! ```
+//! # use solar_api::prometheus::Metrics;
+//! # use solar_api::Overview;
+//! # fn overview() -> Overview {
+//! #     serde_json::from_str(r#"{"lastUpdateTime":"2023-06-01 12:20:00",
+//! #         "lifeTimeData":{"energy":1000000.0},"lastYearData":{"energy":500000.0},
+//! #         "lastMonthData":{"energy":20000.0},"lastDayData":{"energy":2000.0},
+//! #         "currentPower":{"power":750.0},"measuredBy":"INVERTER"}"#).unwrap()
+//! # }
+//! let mut metrics = Metrics::new();
+//! metrics.add_overview(1234, &overview());
+//! assert!(metrics
+//!     .to_string()
+//!     .contains("solaredge_current_power_watts{site_id=\"1234\"} 750\n"));
+//! ```
+
+use crate::{
+    meters::PowerDetails,
+    site::Overview,
+    storage::StorageData,
+    units::{as_watt_hours, as_watts},
+    SolarApiError,
+};
+use log::{debug, warn};
+#[cfg(test)]
+use std::io::Read;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+};
+
+// the help text and the value per set of labels of a gauge
+#[derive(Debug, Clone, PartialEq)]
+struct Gauge {
+    help: &'static str,
+    samples: BTreeMap<String, f64>,
+}
+
+/// The latest readings of sites as Prometheus gauges. Adding a reading of a
+/// site again replaces the previous value. [`Metrics`] is displayed in the
+/// Prometheus text format, which can be served to Prometheus with
+/// [`MetricsExporter`] or pushed to a Pushgateway
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metrics {
+    gauges: BTreeMap<&'static str, Gauge>,
+}
+
+impl Metrics {
+    /// Create metrics without gauges
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the current power and energy of `overview` of `site_id`
+    pub fn add_overview(&mut self, site_id: u32, overview: &Overview) {
+        self.set(
+            "solaredge_current_power_watts",
+            "Current power of the site",
+            &[("site_id", &site_id.to_string())],
+            as_watts(overview.current_power.power),
+        );
+        for (period, data) in [
+            ("last_day", &overview.last_day_data),
+            ("last_month", &overview.last_month_data),
+            ("last_year", &overview.last_year_data),
+            ("life_time", &overview.life_time_data),
+        ] {
+            self.set(
+                "solaredge_energy_watt_hours",
+                "Energy produced by the site in the period",
+                &[("site_id", &site_id.to_string()), ("period", period)],
+                as_watt_hours(data.energy),
+            );
+        }
+    }
+
+    /// Add the last power of every meter of `details` of `site_id`
+    pub fn add_power_details(
+        &mut self,
+        site_id: u32,
+        details: &PowerDetails,
+    ) -> Result<(), SolarApiError> {
+        for meter in details.meters() {
+            let last = details
+                .series(meter)?
+                .and_then(|series| series.iter().rev().find_map(|v| v.value));
+            if let Some(power) = last {
+                self.set(
+                    "solaredge_meter_power_watts",
+                    "Last power of the meter",
+                    &[
+                        ("site_id", &site_id.to_string()),
+                        ("meter", meter.to_param()),
+                    ],
+                    as_watts(power),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the last power and state of charge of every battery of `storage`
+    /// of `site_id`
+    pub fn add_storage(&mut self, site_id: u32, storage: &StorageData) {
+        for battery in &storage.batteries {
+            let site_id = site_id.to_string();
+            let labels = [
+                ("site_id", site_id.as_str()),
+                ("battery", &battery.serial_number),
+            ];
+            let telemetries = battery.sorted_telemetries();
+            if let Some(power) = telemetries.iter().rev().find_map(|t| t.power) {
+                self.set(
+                    "solaredge_battery_power_watts",
+                    "Last power of the battery, positive when charging",
+                    &labels,
+                    as_watts(power),
+                );
+            }
+            let state_of_charge = telemetries
+                .iter()
+                .rev()
+                .find_map(|t| t.battery_percentage_state);
+            if let Some(percentage) = state_of_charge {
+                self.set(
+                    "solaredge_battery_state_of_charge_percent",
+                    "Last state of charge of the battery",
+                    &labels,
+                    percentage,
+                );
+            }
+        }
+    }
+
+    // sets the value of the gauge `name` with `labels`
+    fn set(&mut self, name: &'static str, help: &'static str, labels: &[(&str, &str)], value: f64) {
+        let labels = labels
+            .iter()
+            .map(|(name, value)| format!("{name}=\"{}\"", escape(value)))
+            .collect::<Vec<_>>()
+            .join(",");
+        self.gauges
+            .entry(name)
+            .or_insert_with(|| Gauge {
+                help,
+                samples: BTreeMap::new(),
+            })
+            .samples
+            .insert(labels, value);
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, gauge) in &self.gauges {
+            writeln!(f, "# HELP {name} {}", gauge.help)?;
+            writeln!(f, "# TYPE {name} gauge")?;
+            for (labels, value) in &gauge.samples {
+                writeln!(f, "{name}{{{labels}}} {value}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// escapes a label value of the text format
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// A small blocking HTTP listener that serves [`Metrics`] on `/metrics` for
+/// Prometheus to scrape. The metrics are created per scrape, so return readings
+/// collected by the [`Scheduler`](crate::scheduler::Scheduler) instead of
+/// calling the API when Prometheus scrapes more often than the rate limit of
+/// the API allows
+///
+/// ```no_run
+/// # use solar_api::prometheus::{Metrics, MetricsExporter};
+/// # use solar_api::Client;
+/// let client = Client::new("API_KEY");
+/// let exporter = MetricsExporter::bind("0.0.0.0:9898")?;
+/// exporter.serve(|| {
+///     let mut metrics = Metrics::new();
+///     metrics.add_overview(1234, &client.overview(1234)?);
+///     Ok(metrics)
+/// })?;
+/// # Ok::<(), solar_api::SolarApiError>(())
+/// ```
+#[derive(Debug)]
+pub struct MetricsExporter {
+    listener: TcpListener,
+}
+
+impl MetricsExporter {
+    /// Listen on `addr`, i.e. `0.0.0.0:9898`
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self, SolarApiError> {
+        let listener = TcpListener::bind(addr).map_err(SolarApiError::MetricsServer)?;
+        Ok(MetricsExporter { listener })
+    }
+
+    /// The address the exporter listens on
+    pub fn local_addr(&self) -> Result<SocketAddr, SolarApiError> {
+        self.listener
+            .local_addr()
+            .map_err(SolarApiError::MetricsServer)
+    }
+
+    /// Answer requests until the listener fails. `metrics` is called for every
+    /// request of `/metrics`. When it fails, the request is answered with
+    /// status 500
+    pub fn serve(
+        &self,
+        mut metrics: impl FnMut() -> Result<Metrics, SolarApiError>,
+    ) -> Result<(), SolarApiError> {
+        loop {
+            self.serve_next(&mut metrics)?;
+        }
+    }
+
+    /// Answer the next request
+    pub fn serve_next(
+        &self,
+        metrics: impl FnOnce() -> Result<Metrics, SolarApiError>,
+    ) -> Result<(), SolarApiError> {
+        let (stream, peer) = self
+            .listener
+            .accept()
+            .map_err(SolarApiError::MetricsServer)?;
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        if let Err(e) = reader.read_line(&mut request_line) {
+            warn!("Could not read request of {peer}: {e}");
+            return Ok(());
+        }
+        debug!("Request of {peer}: {}", request_line.trim_end());
+        let (status, body) = match request_line.split_whitespace().nth(1) {
+            Some("/metrics") => match metrics() {
+                Ok(metrics) => ("200 OK", metrics.to_string()),
+                Err(e) => {
+                    warn!("Could not collect metrics: {e}");
+                    ("500 Internal Server Error", format!("{e}\n"))
+                }
+            },
+            _ => ("404 Not Found", "Not found\n".to_string()),
+        };
+        if let Err(e) = respond(&stream, status, &body) {
+            warn!("Could not answer request of {peer}: {e}");
+        }
+        Ok(())
+    }
+}
+
+fn respond(mut stream: impl Write, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[test]
+fn test_metrics() {
+    let reply = r#"
+    {"storageData":{
+        "batteryCount":1,
+        "batteries":[{
+            "nameplate":10000.0,
+            "serialNumber":"BAT\"1",
+            "telemetries":[
+                {"timeStamp":"2023-06-01 12:15:00","power":-500.0,
+                 "lifeTimeEnergyDischarged":1000.0,"lifeTimeEnergyCharged":3000.0},
+                {"timeStamp":"2023-06-01 12:00:00","power":1000.0,
+                 "lifeTimeEnergyDischarged":1000.0,"lifeTimeEnergyCharged":3000.0,
+                 "batteryPercentageState":60.0}
+            ]}]}}
+    "#;
+    let storage = serde_json::from_str::<crate::storage::StorageDataReply>(reply)
+        .unwrap()
+        .storage_data;
+    let reply = r#"
+    {"powerDetails":{
+        "timeUnit":"QUARTER_OF_AN_HOUR",
+        "unit":"W",
+        "meters":[
+            {"type":"Consumption","values":[
+                {"date":"2023-06-01 12:00:00","value":300.0},
+                {"date":"2023-06-01 12:15:00"}
+            ]}
+        ]}}
+    "#;
+    let details = serde_json::from_str::<crate::meters::PowerDetailsReply>(reply)
+        .unwrap()
+        .power_details;
+
+    let mut metrics = Metrics::new();
+    metrics.add_storage(1234, &storage);
+    metrics.add_power_details(1234, &details).unwrap();
+    metrics.add_power_details(1234, &details).unwrap();
+    assert_eq!(
+        r#"# HELP solaredge_battery_power_watts Last power of the battery, positive when charging
+# TYPE solaredge_battery_power_watts gauge
+solaredge_battery_power_watts{site_id="1234",battery="BAT\"1"} -500
+# HELP solaredge_battery_state_of_charge_percent Last state of charge of the battery
+# TYPE solaredge_battery_state_of_charge_percent gauge
+solaredge_battery_state_of_charge_percent{site_id="1234",battery="BAT\"1"} 60
+# HELP solaredge_meter_power_watts Last power of the meter
+# TYPE solaredge_meter_power_watts gauge
+solaredge_meter_power_watts{site_id="1234",meter="Consumption"} 300
+"#,
+        metrics.to_string()
+    );
+}
+
+#[test]
+fn test_metrics_exporter() {
+    let exporter = MetricsExporter::bind("127.0.0.1:0").unwrap();
+    let addr = exporter.local_addr().unwrap();
+    let get = move |path: &str| {
+        let path = path.to_string();
+        std::thread::spawn(move || {
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        })
+    };
+
+    let response = get("/metrics");
+    exporter.serve_next(|| Ok(Metrics::new())).unwrap();
+    let response = response.join().unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("Content-Length: 0\r\nConnection: close\r\n\r\n"));
+
+    let response = get("/");
+    exporter.serve_next(|| Ok(Metrics::new())).unwrap();
+    assert!(response.join().unwrap().starts_with("HTTP/1.1 404"));
+}
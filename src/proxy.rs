@@ -0,0 +1,520 @@
+//! A small HTTP server in front of the SolarEdge API, so several local
+//! consumers like Home Assistant, Grafana and scripts share one request budget,
+//! see [`ProxyServer`]. Requires the `proxy` feature.
+//!
+//! The server answers the endpoints of the API that this crate supports with
+//! the same paths, parameters and JSON, without the `api_key` parameter:
+//! * `/sites/list`
+//! * `/site/{site_id}/details`, `/site/{site_id}/overview` and
+//!   `/site/{site_id}/dataPeriod`
+//! * `/site/{site_id}/energy` with `startDate`, `endDate` and `timeUnit`
+//! * `/site/{site_id}/power` with `startTime` and `endTime`
+//! * `/site/{site_id}/energyDetails` with `startTime`, `endTime`, `timeUnit`
+//!   and optionally `meters`
+//! * `/site/{site_id}/powerDetails` with `startTime`, `endTime` and
+//!   optionally `meters`
+//! * `/site/{site_id}/storageData` with `startTime` and `endTime`
+//!
+//! It also answers two aggregates:
+//! * `/aggregate/site/{site_id}/today`: the current power and the energy of
+//!   today, i.e. `{"siteId":1234,"lastUpdateTime":"2023-06-01 12:20:00","powerW":750.0,"energyTodayWh":2000.0}`
+//! * `/aggregate/site/{site_id}/energy` with `startDate` and `endDate`: the
+//!   total energy and the energy per month of a period of any length, i.e.
+//!   `{"siteId":1234,"startDate":"2023-01-01","endDate":"2023-12-31","energyWh":4000000.0,"months":[{"month":"2023-01","energyWh":150000.0}]}`
+//!
+//! ```no_run
+//! # use solar_api::proxy::ProxyServer;
+//! # use solar_api::cache::ResponseCache;
+//! # use solar_api::Client;
+//! let client = Client::new("API_KEY").with_cache(ResponseCache::new("cache"));
+//! let mut proxy = ProxyServer::bind(&client, "127.0.0.1:8080")?;
+//! proxy.serve()?;
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use crate::{
+    client::Client,
+    meters::MeterType,
+    scheduler::TokenBucket,
+    series::Aggregation,
+    site::{DataPeriod, TimeUnit},
+    units::{as_watt_hours, as_watts},
+    SolarApiError,
+};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use log::{debug, warn};
+use reqwest::{StatusCode, Url};
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+};
+
+// a request of a consumer
+#[derive(Debug, Clone, PartialEq)]
+enum Request {
+    List,
+    Details(u32),
+    Overview(u32),
+    DataPeriod(u32),
+    Energy(u32, DataPeriod, TimeUnit),
+    Power(u32, NaiveDateTime, NaiveDateTime),
+    EnergyDetails(u32, NaiveDateTime, NaiveDateTime, TimeUnit, Vec<MeterType>),
+    PowerDetails(u32, NaiveDateTime, NaiveDateTime, Vec<MeterType>),
+    StorageData(u32, NaiveDateTime, NaiveDateTime),
+    Today(u32),
+    EnergyTotal(u32, DataPeriod),
+}
+
+// an answer to a consumer
+#[derive(Debug, Clone, PartialEq)]
+struct Response {
+    status: u16,
+    body: String,
+    retry_after: Option<i64>,
+}
+
+/// A blocking HTTP server that answers requests with the replies of the API.
+/// Replies are kept in memory for a time to live, so consumers that poll the
+/// same endpoint share one request to the API. Requests to the API are rate
+/// limited with a [`TokenBucket`], and answered with status 429 and a
+/// `Retry-After` header when the bucket is empty or the API itself answers
+/// with status 429. Settled historical replies are served from the
+/// [`ResponseCache`](crate::cache::ResponseCache) of the client, when it has
+/// one. Consumers that do not send their request or read the answer within the
+/// connection timeout are disconnected, so they cannot block the server
+pub struct ProxyServer<'a> {
+    client: &'a Client,
+    listener: TcpListener,
+    bucket: TokenBucket,
+    ttl: Duration,
+    connection_timeout: std::time::Duration,
+    // the answer and the time it was fetched per request
+    replies: HashMap<String, (DateTime<Utc>, String)>,
+}
+
+impl fmt::Debug for ProxyServer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyServer")
+            .field("listener", &self.listener)
+            .field("bucket", &self.bucket)
+            .field("ttl", &self.ttl)
+            .field("connection_timeout", &self.connection_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> ProxyServer<'a> {
+    /// Listen on `addr`, i.e. `127.0.0.1:8080`, and answer requests with
+    /// `client`. The requests are limited by [`TokenBucket::daily`] with a
+    /// burst of 10 requests, replies live 5 minutes and connections time out
+    /// after 10 seconds
+    pub fn bind(client: &'a Client, addr: impl ToSocketAddrs) -> Result<Self, SolarApiError> {
+        let listener = TcpListener::bind(addr).map_err(SolarApiError::ProxyServer)?;
+        Ok(ProxyServer {
+            client,
+            listener,
+            bucket: TokenBucket::daily(10),
+            ttl: Duration::minutes(5),
+            connection_timeout: std::time::Duration::from_secs(10),
+            replies: HashMap::new(),
+        })
+    }
+
+    /// Limit the requests to the API with `bucket`
+    pub fn with_rate_limit(mut self, bucket: TokenBucket) -> Self {
+        self.bucket = bucket;
+        self
+    }
+
+    /// Set the time that replies are kept in memory, 5 minutes by default
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set the time to read a request and to write its answer, 10 seconds by
+    /// default
+    pub fn with_connection_timeout(mut self, connection_timeout: std::time::Duration) -> Self {
+        self.connection_timeout = connection_timeout;
+        self
+    }
+
+    /// The address the server listens on
+    pub fn local_addr(&self) -> Result<SocketAddr, SolarApiError> {
+        self.listener
+            .local_addr()
+            .map_err(SolarApiError::ProxyServer)
+    }
+
+    /// Answer requests until the listener fails
+    pub fn serve(&mut self) -> Result<(), SolarApiError> {
+        loop {
+            self.serve_next()?;
+        }
+    }
+
+    /// Answer the next request
+    pub fn serve_next(&mut self) -> Result<(), SolarApiError> {
+        let (stream, peer) = self.listener.accept().map_err(SolarApiError::ProxyServer)?;
+        let timeout = Some(self.connection_timeout);
+        if let Err(e) = stream
+            .set_read_timeout(timeout)
+            .and_then(|()| stream.set_write_timeout(timeout))
+        {
+            warn!("Could not set timeout of connection of {peer}: {e}");
+            return Ok(());
+        }
+        let mut request_line = String::new();
+        if let Err(e) = BufReader::new(&stream).read_line(&mut request_line) {
+            warn!("Could not read request of {peer}: {e}");
+            return Ok(());
+        }
+        debug!("Request of {peer}: {}", request_line.trim_end());
+        let response = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["GET", target, _] => self.answer(target, Utc::now()),
+            _ => error_response(405, "Only GET is supported"),
+        };
+        if let Err(e) = respond(&stream, &response) {
+            warn!("Could not answer request of {peer}: {e}");
+        }
+        Ok(())
+    }
+
+    // answers the request of `target` at `now`
+    fn answer(&mut self, target: &str, now: DateTime<Utc>) -> Response {
+        let Ok(url) = Url::parse(&format!("http://localhost{target}")) else {
+            return error_response(400, "Invalid request");
+        };
+        let params: BTreeMap<String, String> = url
+            .query_pairs()
+            .filter(|(name, _)| name != "api_key")
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect();
+        let request = match parse_request(url.path(), &params) {
+            Ok(Some(request)) => request,
+            Ok(None) => return error_response(404, "Not found"),
+            Err(message) => return error_response(400, &message),
+        };
+
+        let key = format!("{}?{params:?}", url.path());
+        if let Some((fetched, body)) = self.replies.get(&key) {
+            if now - *fetched < self.ttl {
+                debug!("Using reply of {key} fetched at {fetched}");
+                return json_response(body.clone());
+            }
+        }
+        if !self.bucket.try_take(now) {
+            let retry_after = (self.bucket.available_at(now) - now).num_seconds() + 1;
+            return Response {
+                retry_after: Some(retry_after),
+                ..error_response(429, "Request budget is used up")
+            };
+        }
+        match fetch(self.client, &request) {
+            Ok(reply) => {
+                let body = reply.to_string();
+                self.replies
+                    .retain(|_, (fetched, _)| now - *fetched < self.ttl);
+                self.replies.insert(key, (now, body.clone()));
+                json_response(body)
+            }
+            Err(e @ SolarApiError::ForbiddenError(_)) => error_response(403, &e.to_string()),
+            Err(SolarApiError::ApiError(e))
+                if e.status() == Some(StatusCode::TOO_MANY_REQUESTS) =>
+            {
+                // the API does not tell when to retry, so the consumer retries
+                // when a new reply could be fetched
+                warn!("The API rate limited {key}: {e}");
+                Response {
+                    retry_after: Some(self.ttl.num_seconds().max(1)),
+                    ..error_response(429, &e.to_string())
+                }
+            }
+            Err(e) => {
+                warn!("Could not fetch {key}: {e}");
+                error_response(502, &e.to_string())
+            }
+        }
+    }
+}
+
+// the request of `path` with `params`, None when the path is unknown
+fn parse_request(path: &str, params: &BTreeMap<String, String>) -> Result<Option<Request>, String> {
+    let param = |name: &str| {
+        params
+            .get(name)
+            .map(String::as_str)
+            .ok_or(format!("Missing parameter {name}"))
+    };
+    let date = |name: &str| {
+        NaiveDate::parse_from_str(param(name)?, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid {name}: {e}"))
+    };
+    let time = |name: &str| {
+        NaiveDateTime::parse_from_str(param(name)?, "%Y-%m-%d %H:%M:%S")
+            .map_err(|e| format!("Invalid {name}: {e}"))
+    };
+    let period =
+        || DataPeriod::between(date("startDate")?, date("endDate")?).map_err(|e| e.to_string());
+    let time_unit = || {
+        param("timeUnit")?
+            .parse::<TimeUnit>()
+            .map_err(|e| e.to_string())
+    };
+    let meters = || match params.get("meters") {
+        Some(meters) => meters
+            .split(',')
+            .map(|meter| meter.parse::<MeterType>().map_err(|e| e.to_string()))
+            .collect(),
+        None => Ok(vec![]),
+    };
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let site_id = |segment: &str| {
+        segment
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid site id {segment}"))
+    };
+    let request = match segments[..] {
+        ["sites", "list"] => Request::List,
+        ["site", id, "details"] => Request::Details(site_id(id)?),
+        ["site", id, "overview"] => Request::Overview(site_id(id)?),
+        ["site", id, "dataPeriod"] => Request::DataPeriod(site_id(id)?),
+        ["site", id, "energy"] => Request::Energy(site_id(id)?, period()?, time_unit()?),
+        ["site", id, "power"] => Request::Power(site_id(id)?, time("startTime")?, time("endTime")?),
+        ["site", id, "energyDetails"] => Request::EnergyDetails(
+            site_id(id)?,
+            time("startTime")?,
+            time("endTime")?,
+            time_unit()?,
+            meters()?,
+        ),
+        ["site", id, "powerDetails"] => Request::PowerDetails(
+            site_id(id)?,
+            time("startTime")?,
+            time("endTime")?,
+            meters()?,
+        ),
+        ["site", id, "storageData"] => {
+            Request::StorageData(site_id(id)?, time("startTime")?, time("endTime")?)
+        }
+        ["aggregate", "site", id, "today"] => Request::Today(site_id(id)?),
+        ["aggregate", "site", id, "energy"] => Request::EnergyTotal(site_id(id)?, period()?),
+        _ => return Ok(None),
+    };
+    Ok(Some(request))
+}
+
+// the JSON of `request`, as returned by the API for its endpoints
+fn fetch(client: &Client, request: &Request) -> Result<Value, SolarApiError> {
+    let reply = match request {
+        Request::List => {
+            let sites = client.list()?;
+            json!({ "sites": { "count": sites.len(), "site": sites } })
+        }
+        Request::Details(site_id) => client.details(*site_id)?.to_api_json(),
+        Request::Overview(site_id) => client.overview(*site_id)?.to_api_json(),
+        Request::DataPeriod(site_id) => client.data_period(*site_id)?.to_api_json(),
+        Request::Energy(site_id, period, time_unit) => {
+            client.energy(*site_id, *period, *time_unit)?.to_api_json()
+        }
+        Request::Power(site_id, start, end) => client.power(*site_id, *start, *end)?.to_api_json(),
+        Request::EnergyDetails(site_id, start, end, time_unit, meters) => client
+            .energy_details(*site_id, *start, *end, *time_unit, meters)?
+            .to_api_json(),
+        Request::PowerDetails(site_id, start, end, meters) => client
+            .power_details(*site_id, *start, *end, meters)?
+            .to_api_json(),
+        Request::StorageData(site_id, start, end) => client
+            .storage_data(*site_id, *start, *end, &[])?
+            .to_api_json(),
+        Request::Today(site_id) => {
+            let overview = client.overview(*site_id)?;
+            json!({
+                "siteId": site_id,
+                "lastUpdateTime": overview
+                    .last_updated_time
+                    .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string()),
+                "powerW": as_watts(overview.current_power.power),
+                "energyTodayWh": as_watt_hours(overview.last_day_data.energy),
+            })
+        }
+        Request::EnergyTotal(site_id, period) => {
+            let energy = client
+                .energy_chunked(*site_id, *period, TimeUnit::Day)?
+                .to_series()?;
+            let months: Vec<Value> = energy
+                .resample(TimeUnit::Month, Aggregation::Sum)
+                .iter()
+                .map(|month| {
+                    json!({
+                        "month": month.date.format("%Y-%m").to_string(),
+                        "energyWh": month.value.map(as_watt_hours),
+                    })
+                })
+                .collect();
+            json!({
+                "siteId": site_id,
+                "startDate": period.formatted_start_date(),
+                "endDate": period.formatted_end_date(),
+                "energyWh": as_watt_hours(energy.total()),
+                "months": months,
+            })
+        }
+    };
+    Ok(reply)
+}
+
+fn json_response(body: String) -> Response {
+    Response {
+        status: 200,
+        body,
+        retry_after: None,
+    }
+}
+
+// a response with the error `message` as JSON
+fn error_response(status: u16, message: &str) -> Response {
+    Response {
+        status,
+        body: json!({ "error": message }).to_string(),
+        retry_after: None,
+    }
+}
+
+fn respond(mut stream: impl Write, response: &Response) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        429 => "Too Many Requests",
+        _ => "Bad Gateway",
+    };
+    write!(stream, "HTTP/1.1 {} {reason}\r\n", response.status)?;
+    if let Some(retry_after) = response.retry_after {
+        write!(stream, "Retry-After: {retry_after}\r\n")?;
+    }
+    write!(
+        stream,
+        "Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+#[test]
+fn test_parse_request() {
+    let params = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    };
+    let time = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+
+    assert_eq!(
+        Ok(Some(Request::Overview(1234))),
+        parse_request("/site/1234/overview", &params(&[]))
+    );
+    assert_eq!(
+        Ok(Some(Request::PowerDetails(
+            1234,
+            time("2023-06-01 00:00:00"),
+            time("2023-06-01 23:59:59"),
+            vec![MeterType::Production, MeterType::FeedIn]
+        ))),
+        parse_request(
+            "/site/1234/powerDetails",
+            &params(&[
+                ("startTime", "2023-06-01 00:00:00"),
+                ("endTime", "2023-06-01 23:59:59"),
+                ("meters", "Production,FeedIn")
+            ])
+        )
+    );
+    assert_eq!(
+        Err("Missing parameter timeUnit".to_string()),
+        parse_request(
+            "/site/1234/energy",
+            &params(&[("startDate", "2023-06-01"), ("endDate", "2023-06-30")])
+        )
+    );
+    assert_eq!(
+        Err("Invalid site id abc".to_string()),
+        parse_request("/site/abc/overview", &params(&[]))
+    );
+    assert_eq!(Ok(None), parse_request("/site/1234/unknown", &params(&[])));
+}
+
+#[test]
+fn test_proxy_answer() {
+    let client = Client::new("API_KEY");
+    let now = Utc::now();
+    let mut proxy = ProxyServer::bind(&client, "127.0.0.1:0")
+        .unwrap()
+        .with_rate_limit(TokenBucket::new(0, Duration::minutes(1), now));
+
+    assert_eq!(404, proxy.answer("/unknown", now).status);
+    assert_eq!(400, proxy.answer("/site/1234/power", now).status);
+    let response = proxy.answer("/site/1234/overview?api_key=KEY", now);
+    assert_eq!(429, response.status);
+    assert_eq!(Some(61), response.retry_after);
+
+    proxy.replies.insert(
+        "/site/1234/overview?{}".to_string(),
+        (now - Duration::minutes(1), "{}".to_string()),
+    );
+    assert_eq!(
+        json_response("{}".to_string()),
+        proxy.answer("/site/1234/overview?api_key=KEY", now)
+    );
+    assert_eq!(
+        429,
+        proxy
+            .answer("/site/1234/overview", now + Duration::minutes(5))
+            .status
+    );
+
+    let mut written = Vec::new();
+    respond(&mut written, &response).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    assert!(written.starts_with("HTTP/1.1 429 Too Many Requests\r\nRetry-After: 61\r\n"));
+    assert!(written.ends_with("\r\n\r\n{\"error\":\"Request budget is used up\"}"));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_proxy_upstream_rate_limit() {
+    use crate::fake_server::{FakeServer, FakeSite, Scenario};
+
+    let server = FakeServer::start(
+        Scenario::new("KEY")
+            .with_site(FakeSite::new(1234, 5.0))
+            .with_daily_limit(0),
+    )
+    .unwrap();
+    let client = server.client();
+    let mut proxy = ProxyServer::bind(&client, "127.0.0.1:0").unwrap();
+    let response = proxy.answer("/site/1234/overview", Utc::now());
+    assert_eq!(429, response.status);
+    assert_eq!(Some(300), response.retry_after);
+}
+
+#[test]
+fn test_proxy_connection_timeout() {
+    let client = Client::new("API_KEY");
+    let mut proxy = ProxyServer::bind(&client, "127.0.0.1:0")
+        .unwrap()
+        .with_connection_timeout(std::time::Duration::from_millis(100));
+
+    // a consumer that never sends its request does not block the server
+    let _idle = std::net::TcpStream::connect(proxy.local_addr().unwrap()).unwrap();
+    let started = std::time::Instant::now();
+    proxy.serve_next().unwrap();
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+}
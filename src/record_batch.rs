@@ -0,0 +1,265 @@
+//! Converts series, portfolio energy and readings to Arrow record batches with
+//! [`ToRecordBatch`], to hand them to DataFusion, DuckDB or Arrow Flight in
+//! memory. Requires the `arrow` feature. Timestamps are timestamp columns in
+//! seconds without time zone, in the local time of the site, like in the files
+//! of [`ParquetWriter`](crate::export::parquet::ParquetWriter), and values are
+//! float64 columns in the base unit of [`Quantity`]: Wh for energy and W for
+//! power. The `value` field has this unit as `unit` in its metadata
+//!
+//! ```
+//! # use solar_api::record_batch::ToRecordBatch;
+//! # use solar_api::{TimeSeries, TimeSeriesValue, TimeUnit};
+//! # use solar_api::units::watt_hours;
+//! # use solar_api::chrono::NaiveDate;
+//! let date = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+//! let energy = TimeSeries::new(TimeUnit::Day, "Wh", vec![TimeSeriesValue {
+//!     date,
+//!     value: Some(watt_hours(12500.0)),
+//! }]);
+//! let batch = energy.to_record_batch();
+//! assert_eq!((1, 2), (batch.num_rows(), batch.num_columns()));
+//! ```
+
+use crate::{
+    export::schema::{Reading, SCHEMA_VERSION},
+    portfolio::PortfolioEnergy,
+    series::TimeSeries,
+    units::{EnergyUnit, PowerUnit, Quantity},
+};
+#[cfg(test)]
+use crate::{
+    series::TimeSeriesValue,
+    site::TimeUnit,
+    units::{watt_hours, Energy},
+};
+use arrow_array::{
+    ArrayRef, Float64Array, RecordBatch, StringArray, TimestampSecondArray, UInt32Array,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit as ArrowTimeUnit};
+use chrono::NaiveDateTime;
+use std::{collections::HashMap, sync::Arc};
+
+/// Conversion to an Arrow [`RecordBatch`]
+pub trait ToRecordBatch {
+    /// A record batch with a row per value
+    fn to_record_batch(&self) -> RecordBatch;
+}
+
+/// The columns `timestamp` and `value`
+impl<Q: Quantity> ToRecordBatch for TimeSeries<Q> {
+    fn to_record_batch(&self) -> RecordBatch {
+        let (timestamps, values): (Vec<_>, Vec<_>) = self
+            .iter()
+            .map(|v| (v.date, v.value.map(Quantity::base_value)))
+            .unzip();
+        let (timestamp_field, timestamps) = timestamp_column(&timestamps);
+        let value_field = value_field(base_unit(&self.unit));
+        record_batch(vec![
+            (timestamp_field, timestamps),
+            (value_field, Arc::new(Float64Array::from(values))),
+        ])
+    }
+}
+
+/// The columns `site_id`, `timestamp` and `value`, ordered by site id and
+/// timestamp
+impl ToRecordBatch for PortfolioEnergy {
+    fn to_record_batch(&self) -> RecordBatch {
+        let mut site_ids = Vec::new();
+        let mut timestamps = Vec::new();
+        let mut values = Vec::new();
+        for (site_id, series) in &self.sites {
+            for v in series.iter() {
+                site_ids.push(*site_id);
+                timestamps.push(v.date);
+                values.push(v.value.map(Quantity::base_value));
+            }
+        }
+        let (timestamp_field, timestamps) = timestamp_column(&timestamps);
+        record_batch(vec![
+            (
+                Field::new("site_id", DataType::UInt32, false),
+                Arc::new(UInt32Array::from(site_ids)),
+            ),
+            (timestamp_field, timestamps),
+            (value_field("Wh"), Arc::new(Float64Array::from(values))),
+        ])
+    }
+}
+
+/// The columns `site_id`, `channel`, `timestamp`, `value`, `unit` and
+/// `quality` of the normalized [`schema`](crate::export::schema). The version
+/// of the schema is `schema_version` in the metadata of the schema
+impl ToRecordBatch for [Reading] {
+    fn to_record_batch(&self) -> RecordBatch {
+        let timestamps: Vec<_> = self.iter().map(|reading| reading.timestamp).collect();
+        let (timestamp_field, timestamps) = timestamp_column(&timestamps);
+        let batch = record_batch(vec![
+            (
+                Field::new("site_id", DataType::UInt32, false),
+                Arc::new(UInt32Array::from_iter_values(
+                    self.iter().map(|reading| reading.site_id),
+                )),
+            ),
+            (
+                Field::new("channel", DataType::Utf8, false),
+                Arc::new(StringArray::from_iter_values(
+                    self.iter().map(|reading| &reading.channel),
+                )),
+            ),
+            (timestamp_field, timestamps),
+            (
+                Field::new("value", DataType::Float64, true),
+                Arc::new(Float64Array::from_iter(
+                    self.iter().map(|reading| reading.value),
+                )),
+            ),
+            (
+                Field::new("unit", DataType::Utf8, false),
+                Arc::new(StringArray::from_iter_values(
+                    self.iter().map(|reading| reading.unit),
+                )),
+            ),
+            (
+                Field::new("quality", DataType::Utf8, false),
+                Arc::new(StringArray::from_iter_values(
+                    self.iter().map(|reading| reading.quality.as_str()),
+                )),
+            ),
+        ]);
+        let metadata = HashMap::from([("schema_version".to_string(), SCHEMA_VERSION.to_string())]);
+        let schema = Arc::new(batch.schema().as_ref().clone().with_metadata(metadata));
+        // only the metadata of the schema changed
+        batch.with_schema(schema).expect("schema matches columns")
+    }
+}
+
+// a timestamp column in seconds without time zone
+fn timestamp_column(timestamps: &[NaiveDateTime]) -> (Field, ArrayRef) {
+    let field = Field::new(
+        "timestamp",
+        DataType::Timestamp(ArrowTimeUnit::Second, None),
+        false,
+    );
+    let array = TimestampSecondArray::from_iter_values(
+        timestamps.iter().map(|date| date.and_utc().timestamp()),
+    );
+    (field, Arc::new(array))
+}
+
+// the base unit of the values of a series in `unit`
+fn base_unit(unit: &str) -> &str {
+    if unit.parse::<EnergyUnit>().is_ok() {
+        "Wh"
+    } else if unit.parse::<PowerUnit>().is_ok() {
+        "W"
+    } else {
+        unit
+    }
+}
+
+// the nullable value field with `unit` in its metadata
+fn value_field(unit: &str) -> Field {
+    Field::new("value", DataType::Float64, true)
+        .with_metadata(HashMap::from([("unit".to_string(), unit.to_string())]))
+}
+
+fn record_batch(columns: Vec<(Field, ArrayRef)>) -> RecordBatch {
+    let (fields, arrays): (Vec<_>, Vec<_>) = columns.into_iter().unzip();
+    // the columns match the fields, so creating the batch cannot fail
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays).expect("columns match schema")
+}
+
+#[test]
+fn test_to_record_batch() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let series = |values: Vec<Option<Energy>>| {
+        TimeSeries::new(
+            TimeUnit::Day,
+            "kWh",
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(day, value)| TimeSeriesValue {
+                    date: date(&format!("2023-06-0{} 00:00:00", day + 1)),
+                    value,
+                })
+                .collect(),
+        )
+    };
+    let energy = PortfolioEnergy {
+        time_unit: TimeUnit::Day,
+        sites: [
+            (1, series(vec![Some(watt_hours(1000.0)), None])),
+            (2, series(vec![Some(watt_hours(2000.0))])),
+        ]
+        .into(),
+    };
+
+    let batch = energy.sites[&1].to_record_batch();
+    assert_eq!(2, batch.num_rows());
+    let timestamps = batch
+        .column_by_name("timestamp")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<TimestampSecondArray>()
+        .unwrap();
+    assert_eq!(
+        date("2023-06-02 00:00:00").and_utc().timestamp(),
+        timestamps.value(1)
+    );
+    let values = batch
+        .column_by_name("value")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .unwrap();
+    assert_eq!(
+        vec![Some(1000.0), None],
+        values.into_iter().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        Some(&"Wh".to_string()),
+        batch.schema().field(1).metadata().get("unit")
+    );
+
+    let batch = energy.to_record_batch();
+    assert_eq!(3, batch.num_rows());
+    let site_ids = batch
+        .column_by_name("site_id")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<UInt32Array>()
+        .unwrap();
+    assert_eq!(vec![1, 1, 2], site_ids.values().to_vec());
+
+    let readings = crate::export::schema::energy_readings(1, "energy", &energy.sites[&1]);
+    let batch = readings.to_record_batch();
+    assert_eq!(
+        vec![
+            "site_id",
+            "channel",
+            "timestamp",
+            "value",
+            "unit",
+            "quality"
+        ],
+        batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        Some(&"1".to_string()),
+        batch.schema().metadata().get("schema_version")
+    );
+    let quality = batch
+        .column_by_name("quality")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    assert_eq!("missing", quality.value(1));
+}
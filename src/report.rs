@@ -0,0 +1,366 @@
+//! Renders a monthly report of a site to Markdown or simple HTML from data that
+//! was already fetched, i.e. to send to the owner of the site
+//!
+//! ```
+//! # use solar_api::report::{MonthlyReport, ReportFormat};
+//! # use solar_api::{TimeSeries, TimeUnit};
+//! let energy = TimeSeries::new(TimeUnit::Day, "Wh", vec![]);
+//! let report = MonthlyReport::new("Home", 2023, 6, &energy)
+//!     .with_co2_avoided(120.5)
+//!     .render(ReportFormat::Markdown);
+//! assert!(report.starts_with("# Home: June 2023"));
+//! ```
+
+use crate::{
+    analytics::ExpectedProduction,
+    anomalies::{Anomaly, AnomalyKind},
+    series::{Aggregation, TimeSeries},
+    site::TimeUnit,
+    units::{as_watt_hours, Energy, EnergyUnit},
+};
+#[cfg(test)]
+use crate::{series::TimeSeriesValue, units::watt_hours};
+use chrono::{Datelike, NaiveDate};
+
+/// The format of a rendered report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+/// The report of a site for one month. Sections without data, like the
+/// comparisons when neither the previous year nor the expected production is
+/// set, are left out
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonthlyReport {
+    site_name: String,
+    month: NaiveDate,
+    energy: TimeSeries<Energy>,
+    previous_year: Option<Energy>,
+    expected: Option<Energy>,
+    anomalies: Option<Vec<Anomaly>>,
+    co2_avoided: Option<f64>,
+    top_days: usize,
+}
+
+// a part of a report, rendered per format
+enum Block {
+    Heading(u8, String),
+    Paragraph(String),
+    Table(Vec<String>, Vec<Vec<String>>),
+}
+
+impl MonthlyReport {
+    /// Create the report of `site_name` for `month` of `year`, using the
+    /// values of `energy` in that month. Any time unit up to a day can be used
+    pub fn new(site_name: &str, year: i32, month: u32, energy: &TimeSeries<Energy>) -> Self {
+        let month = NaiveDate::from_ymd_opt(year, month, 1).unwrap_or_default();
+        let values = energy
+            .resample(TimeUnit::Day, Aggregation::Sum)
+            .into_iter()
+            .filter(|v| v.date.year() == month.year() && v.date.month() == month.month())
+            .collect();
+        MonthlyReport {
+            site_name: site_name.to_string(),
+            month,
+            energy: TimeSeries::new(TimeUnit::Day, "Wh", values),
+            previous_year: None,
+            expected: None,
+            anomalies: None,
+            co2_avoided: None,
+            top_days: 3,
+        }
+    }
+
+    /// Compare the production with `energy` of the same month a year earlier
+    pub fn with_previous_year(mut self, energy: Energy) -> Self {
+        self.previous_year = Some(energy);
+        self
+    }
+
+    /// Compare the production with the expected production of the month
+    pub fn with_expected(mut self, expected: &ExpectedProduction) -> Self {
+        self.expected = expected.expected(self.month.month()).map(|daily| {
+            EnergyUnit::WattHour.energy(as_watt_hours(daily) * self.days_in_month() as f64)
+        });
+        self
+    }
+
+    /// List the anomalies that start in the month, i.e. found by
+    /// [`ZeroProductionDetector`](crate::anomalies::ZeroProductionDetector)
+    pub fn with_anomalies(mut self, anomalies: &[Anomaly]) -> Self {
+        let anomalies = anomalies
+            .iter()
+            .filter(|anomaly| {
+                anomaly.start.year() == self.month.year()
+                    && anomaly.start.month() == self.month.month()
+            })
+            .copied()
+            .collect();
+        self.anomalies = Some(anomalies);
+        self
+    }
+
+    /// Report the CO2 emissions in kg avoided in the month, i.e. the total of
+    /// [`co2_avoided`](crate::emissions::co2_avoided)
+    pub fn with_co2_avoided(mut self, kg: f64) -> Self {
+        self.co2_avoided = Some(kg);
+        self
+    }
+
+    /// Set the number of days with the most production to list, 3 by default
+    pub fn with_top_days(mut self, n: usize) -> Self {
+        self.top_days = n;
+        self
+    }
+
+    /// Render the report in `format`
+    pub fn render(&self, format: ReportFormat) -> String {
+        let blocks = self.blocks();
+        match format {
+            ReportFormat::Markdown => render_markdown(&blocks),
+            ReportFormat::Html => render_html(&blocks),
+        }
+    }
+
+    // the number of days of the month of the report
+    fn days_in_month(&self) -> i64 {
+        let next = self
+            .month
+            .checked_add_months(chrono::Months::new(1))
+            .unwrap_or(self.month);
+        (next - self.month).num_days()
+    }
+
+    // the sections of the report
+    fn blocks(&self) -> Vec<Block> {
+        let total = self.energy.total();
+        let days = self.energy.iter().filter(|v| v.value.is_some()).count();
+        let mut blocks = vec![
+            Block::Heading(
+                1,
+                format!("{}: {}", self.site_name, self.month.format("%B %Y")),
+            ),
+            Block::Heading(2, "Production".to_string()),
+            Block::Table(
+                vec!["".to_string(), "Value".to_string()],
+                vec![
+                    vec!["Total".to_string(), kwh(total)],
+                    vec!["Days with data".to_string(), days.to_string()],
+                    vec![
+                        "Average per day".to_string(),
+                        match days {
+                            0 => "-".to_string(),
+                            days => format!("{:.1} kWh", kilowatt_hours(total) / days as f64),
+                        },
+                    ],
+                ],
+            ),
+        ];
+
+        let mut comparisons = Vec::new();
+        let mut compare = |name: &str, other: Energy| {
+            comparisons.push(vec![
+                name.to_string(),
+                kwh(other),
+                percentage(kilowatt_hours(total), kilowatt_hours(other)),
+            ]);
+        };
+        if let Some(previous_year) = self.previous_year {
+            compare("Previous year", previous_year);
+        }
+        if let Some(expected) = self.expected {
+            compare("Expected", expected);
+        }
+        if !comparisons.is_empty() {
+            blocks.push(Block::Heading(2, "Comparisons".to_string()));
+            blocks.push(Block::Table(
+                vec![
+                    "".to_string(),
+                    "Energy".to_string(),
+                    "Difference".to_string(),
+                ],
+                comparisons,
+            ));
+        }
+
+        let top_days = self.energy.largest(self.top_days);
+        if !top_days.is_empty() {
+            blocks.push(Block::Heading(2, "Top days".to_string()));
+            blocks.push(Block::Table(
+                vec!["Date".to_string(), "Energy".to_string()],
+                top_days
+                    .iter()
+                    .filter_map(|v| {
+                        Some(vec![v.date.format("%Y-%m-%d").to_string(), kwh(v.value?)])
+                    })
+                    .collect(),
+            ));
+        }
+
+        if let Some(anomalies) = &self.anomalies {
+            blocks.push(Block::Heading(2, "Anomalies".to_string()));
+            if anomalies.is_empty() {
+                blocks.push(Block::Paragraph("No anomalies found.".to_string()));
+            } else {
+                blocks.push(Block::Table(
+                    vec![
+                        "Kind".to_string(),
+                        "Start".to_string(),
+                        "End".to_string(),
+                        "Duration".to_string(),
+                    ],
+                    anomalies
+                        .iter()
+                        .map(|anomaly| {
+                            let kind = match anomaly.kind {
+                                AnomalyKind::ZeroProduction => "Zero production",
+                                AnomalyKind::MissingData => "Missing data",
+                            };
+                            vec![
+                                kind.to_string(),
+                                anomaly.start.format("%Y-%m-%d %H:%M").to_string(),
+                                anomaly.end.format("%Y-%m-%d %H:%M").to_string(),
+                                format!("{} min", anomaly.duration().num_minutes()),
+                            ]
+                        })
+                        .collect(),
+                ));
+            }
+        }
+
+        if let Some(kg) = self.co2_avoided {
+            blocks.push(Block::Heading(2, "Environmental benefits".to_string()));
+            blocks.push(Block::Paragraph(format!(
+                "CO2 emissions avoided: {kg:.1} kg"
+            )));
+        }
+        blocks
+    }
+}
+
+fn kilowatt_hours(energy: Energy) -> f64 {
+    EnergyUnit::KilowattHour.value_of(energy)
+}
+
+fn kwh(energy: Energy) -> String {
+    format!("{:.1} kWh", kilowatt_hours(energy))
+}
+
+// the difference of `value` with `base` in percent of `base`
+fn percentage(value: f64, base: f64) -> String {
+    if base == 0.0 {
+        "-".to_string()
+    } else {
+        format!("{:+.1}%", (value - base) / base * 100.0)
+    }
+}
+
+fn render_markdown(blocks: &[Block]) -> String {
+    let mut out = String::new();
+    for block in blocks {
+        match block {
+            Block::Heading(level, text) => {
+                out.push_str(&format!("{} {text}\n\n", "#".repeat(*level as usize)));
+            }
+            Block::Paragraph(text) => out.push_str(&format!("{text}\n\n")),
+            Block::Table(header, rows) => {
+                let row = |cells: &[String]| format!("| {} |\n", cells.join(" | "));
+                out.push_str(&row(header));
+                out.push_str(&row(&vec!["---".to_string(); header.len()]));
+                for cells in rows {
+                    out.push_str(&row(cells));
+                }
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+fn render_html(blocks: &[Block]) -> String {
+    let mut out = String::from("<html>\n<body>\n");
+    for block in blocks {
+        match block {
+            Block::Heading(level, text) => {
+                out.push_str(&format!("<h{level}>{}</h{level}>\n", escape(text)));
+            }
+            Block::Paragraph(text) => out.push_str(&format!("<p>{}</p>\n", escape(text))),
+            Block::Table(header, rows) => {
+                let row = |tag: &str, cells: &[String]| {
+                    let cells: String = cells
+                        .iter()
+                        .map(|cell| format!("<{tag}>{}</{tag}>", escape(cell)))
+                        .collect();
+                    format!("<tr>{cells}</tr>\n")
+                };
+                out.push_str("<table>\n");
+                out.push_str(&row("th", header));
+                for cells in rows {
+                    out.push_str(&row("td", cells));
+                }
+                out.push_str("</table>\n");
+            }
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+// escapes the characters of `text` that have a meaning in HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[test]
+fn test_monthly_report() {
+    let start = NaiveDate::from_ymd_opt(2023, 5, 30).unwrap();
+    // two days of May that are not part of the report and 10 days of June
+    let values = (0..12)
+        .map(|day| TimeSeriesValue {
+            date: (start + chrono::Duration::days(day)).into(),
+            value: Some(watt_hours(10_000.0 + day as f64 * 1000.0)),
+        })
+        .collect();
+    let energy = TimeSeries::new(TimeUnit::Day, "Wh", values);
+    let zero = Anomaly {
+        kind: AnomalyKind::ZeroProduction,
+        start: NaiveDate::from_ymd_opt(2023, 6, 3)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap(),
+        end: NaiveDate::from_ymd_opt(2023, 6, 3)
+            .unwrap()
+            .and_hms_opt(13, 0, 0)
+            .unwrap(),
+    };
+    let expected = ExpectedProduction::new().with_month(6, watt_hours(5_000.0));
+    let report = MonthlyReport::new("Home & garden", 2023, 6, &energy)
+        .with_previous_year(watt_hours(165_000.0))
+        .with_expected(&expected)
+        .with_anomalies(&[zero])
+        .with_co2_avoided(50.0)
+        .with_top_days(2);
+
+    let markdown = report.render(ReportFormat::Markdown);
+    assert!(markdown.starts_with("# Home & garden: June 2023\n"));
+    assert!(markdown.contains("| Total | 165.0 kWh |\n"));
+    assert!(markdown.contains("| Days with data | 10 |\n"));
+    assert!(markdown.contains("| Previous year | 165.0 kWh | +0.0% |\n"));
+    assert!(markdown.contains("| Expected | 150.0 kWh | +10.0% |\n"));
+    assert!(markdown.contains("| 2023-06-10 | 21.0 kWh |\n| 2023-06-09 | 20.0 kWh |\n"));
+    assert!(markdown.contains("| Zero production | 2023-06-03 12:00 | 2023-06-03 13:00 | 60 min |"));
+    assert!(markdown.contains("CO2 emissions avoided: 50.0 kg"));
+
+    let html = report.render(ReportFormat::Html);
+    assert!(html.contains("<h1>Home &amp; garden: June 2023</h1>"));
+    assert!(html.contains("<tr><td>Total</td><td>165.0 kWh</td></tr>"));
+
+    let empty = MonthlyReport::new("Home", 2023, 7, &energy).render(ReportFormat::Markdown);
+    assert!(!empty.contains("Comparisons") && !empty.contains("Anomalies"));
+    assert!(empty.contains("| Average per day | - |"));
+}
@@ -0,0 +1,297 @@
+//! Polls the overviews of many sites under one shared request budget, see
+//! [`Scheduler`]
+
+use crate::{
+    client::Client, poller::next_scheduled, portfolio::BULK_MAX_SITES, site::Overview,
+    sync::DAILY_REQUEST_LIMIT, SolarApiError,
+};
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use log::debug;
+use std::collections::{BTreeMap, VecDeque};
+
+/// A token bucket that limits the number of requests. It holds at most
+/// `capacity` tokens and refills them evenly, so bursts up to `capacity`
+/// are allowed while the average rate stays within the budget
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill: Duration,
+    updated: DateTime<Utc>,
+}
+
+impl TokenBucket {
+    /// Create a full bucket of `capacity` tokens that refills one token every
+    /// `refill`
+    pub fn new(capacity: u32, refill: Duration, now: DateTime<Utc>) -> Self {
+        TokenBucket {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            refill: refill.max(Duration::milliseconds(1)),
+            updated: now,
+        }
+    }
+
+    /// Create a bucket for the [`DAILY_REQUEST_LIMIT`] of the API that allows a
+    /// burst of `burst` requests
+    pub fn daily(burst: u32) -> Self {
+        let refill = Duration::days(1) / DAILY_REQUEST_LIMIT as i32;
+        TokenBucket::new(burst, refill, Utc::now())
+    }
+
+    /// Takes a token at `now`. Returns false when the bucket is empty
+    pub fn try_take(&mut self, now: DateTime<Utc>) -> bool {
+        self.update(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The time at which a token is available, `now` when the bucket is not
+    /// empty
+    pub fn available_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let mut bucket = *self;
+        bucket.update(now);
+        if bucket.tokens >= 1.0 {
+            now
+        } else {
+            let missing = self.refill.num_milliseconds() as f64 * (1.0 - bucket.tokens);
+            now + Duration::milliseconds(missing.ceil() as i64)
+        }
+    }
+
+    // adds the tokens refilled since the last update
+    fn update(&mut self, now: DateTime<Utc>) {
+        let elapsed = (now - self.updated).num_milliseconds().max(0) as f64;
+        self.tokens =
+            (self.tokens + elapsed / self.refill.num_milliseconds() as f64).min(self.capacity);
+        self.updated = now;
+    }
+}
+
+// the polling state of one site
+#[derive(Debug, Clone)]
+struct ScheduledSite<Tz: TimeZone> {
+    tz: Tz,
+    due: DateTime<Utc>,
+    last_updated: Option<NaiveDateTime>,
+    last_polled: Option<DateTime<Utc>>,
+}
+
+/// Polls the overviews of many sites, sharing one [`TokenBucket`]. A site is
+/// only polled when its next update is due according to
+/// [`Overview::next_update_schedule_in`], so sites whose data cannot have
+/// changed are skipped. Sites that are due at the same time are polled in one
+/// request of the bulk API, the ones that waited longest first. Iterating
+/// blocks until the next update and yields every new overview with its site
+/// id. Errors are yielded as well, after which the sites of the failed request
+/// are retried later.
+///
+/// ```no_run
+/// # use solar_api::Client;
+/// # use solar_api::scheduler::{Scheduler, TokenBucket};
+/// # use solar_api::chrono::Utc;
+/// let client = Client::new("API_KEY");
+/// let scheduler = Scheduler::new(&client, TokenBucket::daily(10))
+///     .with_site(1234, Utc)
+///     .with_site(5678, Utc);
+/// for update in scheduler.take(10) {
+///     let (site_id, overview) = update?;
+///     println!("{site_id}: {:?}", overview.last_updated_time);
+/// }
+/// # Ok::<(), solar_api::SolarApiError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scheduler<'a, Tz: TimeZone> {
+    client: &'a Client,
+    bucket: TokenBucket,
+    retry_interval: Duration,
+    sites: BTreeMap<u32, ScheduledSite<Tz>>,
+    updates: VecDeque<(u32, Overview)>,
+}
+
+impl<'a, Tz: TimeZone> Scheduler<'a, Tz> {
+    /// Create a scheduler without sites that takes a token of `bucket` for
+    /// every request
+    pub fn new(client: &'a Client, bucket: TokenBucket) -> Self {
+        Scheduler {
+            client,
+            bucket,
+            retry_interval: Duration::minutes(5),
+            sites: BTreeMap::new(),
+            updates: VecDeque::new(),
+        }
+    }
+
+    /// Add `site_id` with time zone `tz`, which is polled immediately
+    pub fn with_site(mut self, site_id: u32, tz: Tz) -> Self {
+        self.sites.insert(
+            site_id,
+            ScheduledSite {
+                tz,
+                due: DateTime::<Utc>::MIN_UTC,
+                last_updated: None,
+                last_polled: None,
+            },
+        );
+        self
+    }
+
+    /// Set the time between polls of a site that did not publish new data
+    /// after its whole update schedule, 5 minutes by default
+    pub fn with_retry_interval(mut self, retry_interval: Duration) -> Self {
+        self.retry_interval = retry_interval;
+        self
+    }
+
+    // the time of the next request and the sites to poll then: the site that
+    // is due first and the other sites that are due by then, the ones that
+    // waited longest first
+    fn next_batch(&self, now: DateTime<Utc>) -> Option<(DateTime<Utc>, Vec<u32>)> {
+        let first_due = self.sites.values().map(|site| site.due).min()?;
+        let at = first_due.max(now).max(self.bucket.available_at(now));
+        let mut due: Vec<(&u32, &ScheduledSite<Tz>)> = self
+            .sites
+            .iter()
+            .filter(|(_, site)| site.due <= at)
+            .collect();
+        due.sort_by_key(|(site_id, site)| (site.last_polled, **site_id));
+        let site_ids = due
+            .into_iter()
+            .take(BULK_MAX_SITES)
+            .map(|(site_id, _)| *site_id)
+            .collect();
+        Some((at, site_ids))
+    }
+
+    // updates the schedule of the sites with the overviews of a request at
+    // `now` and returns the overviews that are new
+    fn update(
+        &mut self,
+        site_ids: &[u32],
+        overviews: BTreeMap<u32, Overview>,
+        now: DateTime<Utc>,
+    ) -> Vec<(u32, Overview)> {
+        let mut updates = Vec::new();
+        for site_id in site_ids {
+            let Some(site) = self.sites.get_mut(site_id) else {
+                continue;
+            };
+            site.last_polled = Some(now);
+            site.due = now + self.retry_interval;
+            if let Some(overview) = overviews.get(site_id) {
+                site.due = next_scheduled(overview, &site.tz, now).unwrap_or(site.due);
+                let updated = overview
+                    .last_updated_time
+                    .filter(|updated| site.last_updated.is_none_or(|last| *updated > last));
+                if let Some(updated) = updated {
+                    site.last_updated = Some(updated);
+                    updates.push((*site_id, overview.clone()));
+                }
+            }
+        }
+        updates
+    }
+}
+
+impl<Tz: TimeZone> Iterator for Scheduler<'_, Tz> {
+    type Item = Result<(u32, Overview), SolarApiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(update) = self.updates.pop_front() {
+                return Some(Ok(update));
+            }
+            let (at, site_ids) = self.next_batch(Utc::now())?;
+            if let Ok(wait) = (at - Utc::now()).to_std() {
+                debug!("Waiting {wait:?} to poll {} site(s)", site_ids.len());
+                std::thread::sleep(wait);
+            }
+            if !self.bucket.try_take(Utc::now()) {
+                continue;
+            }
+            match self.client.overviews(&site_ids) {
+                Ok(overviews) => {
+                    let updates = self.update(&site_ids, overviews, Utc::now());
+                    self.updates.extend(updates);
+                }
+                Err(error) => {
+                    self.update(&site_ids, BTreeMap::new(), Utc::now());
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_token_bucket() {
+    let now = Utc::now();
+    let mut bucket = TokenBucket::new(2, Duration::minutes(10), now);
+    assert!(bucket.try_take(now));
+    assert!(bucket.try_take(now));
+    assert!(!bucket.try_take(now));
+    assert_eq!(now + Duration::minutes(10), bucket.available_at(now));
+    assert_eq!(
+        now + Duration::minutes(10),
+        bucket.available_at(now + Duration::minutes(5))
+    );
+    assert!(bucket.try_take(now + Duration::minutes(10)));
+    // never more than the capacity
+    let later = now + Duration::days(1);
+    assert!(bucket.try_take(later) && bucket.try_take(later));
+    assert!(!bucket.try_take(later));
+}
+
+#[test]
+fn test_scheduler_batches() {
+    let client = Client::new("API_KEY");
+    let now = NaiveDateTime::parse_from_str("2023-11-09 10:30:00", "%Y-%m-%d %H:%M:%S")
+        .unwrap()
+        .and_utc();
+    let bucket = TokenBucket::new(1, Duration::minutes(4), now);
+    let mut scheduler = Scheduler::new(&client, bucket)
+        .with_site(1, Utc)
+        .with_site(2, Utc)
+        .with_site(3, Utc);
+
+    let (at, site_ids) = scheduler.next_batch(now).unwrap();
+    assert_eq!(now, at);
+    assert_eq!(vec![1, 2, 3], site_ids);
+
+    let overview = |last_updated: &str| {
+        serde_json::from_str::<crate::site::OverviewReply>(&format!(
+            r#"{{"overview":{{
+                "lastUpdateTime":"{last_updated}",
+                "lifeTimeData":{{"energy":1.0}},
+                "lastYearData":{{"energy":1.0}},
+                "lastMonthData":{{"energy":1.0}},
+                "lastDayData":{{"energy":1.0}},
+                "currentPower":{{"power":1.0}},
+                "measuredBy":"INVERTER"}}}}"#
+        ))
+        .unwrap()
+        .overview
+    };
+    let overviews = BTreeMap::from([
+        (1, overview("2023-11-09 10:28:56")),
+        (2, overview("2023-11-09 10:20:00")),
+    ]);
+    assert!(scheduler.bucket.try_take(now));
+    let updates = scheduler.update(&[1, 2, 3], overviews, now);
+    assert_eq!(2, updates.len());
+
+    // site 3 had no overview and is retried after 5 minutes, before site 2 and
+    // site 1 are due at 10:35:10 and 10:44:06
+    let (at, site_ids) = scheduler.next_batch(now).unwrap();
+    assert_eq!("10:35:00", at.format("%H:%M:%S").to_string());
+    assert_eq!(vec![3], site_ids);
+    let due = |site_id| scheduler.sites[&site_id].due.format("%H:%M:%S").to_string();
+    assert_eq!(
+        ("10:35:10".to_string(), "10:44:06".to_string()),
+        (due(2), due(1))
+    );
+}
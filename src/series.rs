@@ -0,0 +1,1004 @@
+//! A generic series of timestamped values. The energy and power replies of the
+//! API can be converted into a [`TimeSeries`], so analysis and export code can
+//! handle them the same way
+
+#[cfg(test)]
+use crate::units::{as_watt_hours, watt_hours, watts};
+use crate::{
+    site::{GeneratedEnergy, GeneratedPowerPerTimeUnit, TimeUnit},
+    units::{energy_of, Energy, Power},
+    SolarApiError,
+};
+use chrono::{NaiveDate, NaiveDateTime, Timelike};
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::{Add, Div, Sub},
+};
+
+/// A timestamped value of quantity `Q`, i.e. [`Energy`] or [`Power`]. The value
+/// may be None when there wasn't a value at that timestamp. There is no
+/// [`Ord`], because values are equal when both the timestamp and the value are
+/// equal, while they are ordered in time by the timestamp only. Use
+/// [`TimeSeriesValue::cmp_by_time`] to order values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeSeriesValue<Q> {
+    /// timestamp of value
+    pub date: NaiveDateTime,
+    /// the value measures at the timestamp or None if there wasn't a value at
+    /// that timestamp
+    pub value: Option<Q>,
+}
+
+impl<Q> TimeSeriesValue<Q> {
+    /// Compares the timestamps of this value and `other`, i.e. to sort values
+    /// in time with `values.sort_by(TimeSeriesValue::cmp_by_time)`
+    pub fn cmp_by_time(&self, other: &Self) -> std::cmp::Ordering {
+        self.date.cmp(&other.date)
+    }
+}
+
+/// A series of timestamped values of quantity `Q` with a resolution of `time_unit`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeSeries<Q> {
+    /// the resolution of the values
+    pub time_unit: TimeUnit,
+    /// the unit of measurement the API used for the values, i.e. `Wh`
+    pub unit: String,
+    /// the values, ordered by timestamp
+    pub values: Vec<TimeSeriesValue<Q>>,
+}
+
+impl<Q> TimeSeries<Q> {
+    /// Create a series from `values` ordered by timestamp
+    pub fn new(time_unit: TimeUnit, unit: &str, values: Vec<TimeSeriesValue<Q>>) -> Self {
+        TimeSeries {
+            time_unit,
+            unit: unit.to_string(),
+            values,
+        }
+    }
+
+    /// the number of values in the series, including timestamps without a value
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// true when the series contains no values
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// returns the value at `index` or None when `index` is out of bounds
+    pub fn get(&self, index: usize) -> Option<&TimeSeriesValue<Q>> {
+        self.values.get(index)
+    }
+
+    /// returns an iterator over the values
+    pub fn iter(&self) -> std::slice::Iter<'_, TimeSeriesValue<Q>> {
+        self.values.iter()
+    }
+
+    /// sorts the values by timestamp. Values with the same timestamp keep
+    /// their order
+    pub fn sort(&mut self) {
+        self.values.sort_by(TimeSeriesValue::cmp_by_time);
+    }
+}
+
+/// Determines how a missing value in one of two aligned series is handled when
+/// combining them, see [`TimeSeries::zip_with`]. A value is missing when the
+/// timestamp is not in the series or when it has no value at that timestamp
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GapPolicy {
+    /// the combined value is None when one of the values is missing
+    #[default]
+    Propagate,
+    /// a missing value is treated as zero, the combined value is only None
+    /// when both values are missing
+    Zero,
+    /// timestamps where one of the values is missing are left out
+    Skip,
+}
+
+impl<Q: Copy> TimeSeries<Q> {
+    /// Aligns this series with `other` by timestamp. Returns every timestamp
+    /// that is in either series, ordered by timestamp, with the value of this
+    /// series and the value of `other` at that timestamp
+    pub fn align_with<R: Copy>(
+        &self,
+        other: &TimeSeries<R>,
+    ) -> Vec<(NaiveDateTime, Option<Q>, Option<R>)> {
+        let mut aligned: BTreeMap<NaiveDateTime, (Option<Q>, Option<R>)> = BTreeMap::new();
+        for v in &self.values {
+            aligned.entry(v.date).or_insert((None, None)).0 = v.value;
+        }
+        for v in &other.values {
+            aligned.entry(v.date).or_insert((None, None)).1 = v.value;
+        }
+        aligned
+            .into_iter()
+            .map(|(date, (left, right))| (date, left, right))
+            .collect()
+    }
+
+    /// Combines this series with `other` value by value using `f`, after
+    /// aligning them using [`TimeSeries::align_with`]. Missing values are
+    /// handled according to `policy`. The result has the time unit and unit of
+    /// this series
+    pub fn zip_with<R, T>(
+        &self,
+        other: &TimeSeries<R>,
+        policy: GapPolicy,
+        f: impl Fn(Q, R) -> T,
+    ) -> TimeSeries<T>
+    where
+        Q: Default,
+        R: Copy + Default,
+    {
+        let values = self
+            .align_with(other)
+            .into_iter()
+            .filter_map(|(date, left, right)| {
+                let value = match (policy, left, right) {
+                    (_, Some(l), Some(r)) => Some(f(l, r)),
+                    (GapPolicy::Skip, _, _) => return None,
+                    (GapPolicy::Zero, None, None) | (GapPolicy::Propagate, _, _) => None,
+                    (GapPolicy::Zero, l, r) => {
+                        Some(f(l.unwrap_or_default(), r.unwrap_or_default()))
+                    }
+                };
+                Some(TimeSeriesValue { date, value })
+            })
+            .collect();
+        TimeSeries::new(self.time_unit, &self.unit, values)
+    }
+
+    /// Adds the values of `other` to the values of this series per timestamp,
+    /// i.e. to combine the production of two inverters
+    pub fn add(&self, other: &TimeSeries<Q>, policy: GapPolicy) -> TimeSeries<Q>
+    where
+        Q: Default + Add<Output = Q>,
+    {
+        self.zip_with(other, policy, |l, r| l + r)
+    }
+
+    /// Merges `other` into this series, i.e. to stitch chunked or re-fetched
+    /// ranges. The result is ordered by timestamp and has one value per
+    /// timestamp. When both series have a timestamp, a value is preferred over
+    /// a missing value and otherwise the value of `other`, the newer data, is
+    /// used. The result has the time unit and unit of this series
+    pub fn merge(&self, other: &TimeSeries<Q>) -> TimeSeries<Q> {
+        let mut merged: BTreeMap<NaiveDateTime, Option<Q>> = BTreeMap::new();
+        for v in self.values.iter().chain(&other.values) {
+            let value = merged.entry(v.date).or_insert(None);
+            if v.value.is_some() {
+                *value = v.value;
+            }
+        }
+        let values = merged
+            .into_iter()
+            .map(|(date, value)| TimeSeriesValue { date, value })
+            .collect();
+        TimeSeries::new(self.time_unit, &self.unit, values)
+    }
+
+    /// Subtracts the values of `other` from the values of this series per
+    /// timestamp, i.e. production minus consumption for the net grid flow
+    pub fn sub(&self, other: &TimeSeries<Q>, policy: GapPolicy) -> TimeSeries<Q>
+    where
+        Q: Default + Sub<Output = Q>,
+    {
+        self.zip_with(other, policy, |l, r| l - r)
+    }
+}
+
+impl<Q: Copy + Default + Add<Output = Q>> TimeSeries<Q> {
+    /// returns the sum of all values. Timestamps without a value are skipped
+    pub fn total(&self) -> Q {
+        self.values
+            .iter()
+            .filter_map(|v| v.value)
+            .fold(Q::default(), |total, v| total + v)
+    }
+}
+
+impl<Q: Copy + PartialOrd> TimeSeries<Q> {
+    /// returns the value with the highest value or None if there are no
+    /// values. When several values share the highest value, the first is returned
+    pub fn max_value(&self) -> Option<TimeSeriesValue<Q>> {
+        self.values
+            .iter()
+            .filter(|v| v.value.is_some())
+            .copied()
+            .reduce(|max, v| if v.value > max.value { v } else { max })
+    }
+
+    /// returns the value with the lowest value or None if there are no
+    /// values. When several values share the lowest value, the first is returned
+    pub fn min_value(&self) -> Option<TimeSeriesValue<Q>> {
+        self.values
+            .iter()
+            .filter(|v| v.value.is_some())
+            .copied()
+            .reduce(|min, v| if v.value < min.value { v } else { min })
+    }
+
+    /// returns the `n` values with the highest value, highest first. Timestamps
+    /// without a value are skipped
+    pub fn largest(&self, n: usize) -> Vec<TimeSeriesValue<Q>> {
+        let mut values = self.sorted_by_value();
+        values.reverse();
+        values.truncate(n);
+        values
+    }
+
+    /// returns the `n` values with the lowest value, lowest first. Timestamps
+    /// without a value are skipped
+    pub fn smallest(&self, n: usize) -> Vec<TimeSeriesValue<Q>> {
+        let mut values = self.sorted_by_value();
+        values.truncate(n);
+        values
+    }
+
+    // the values with a value, ordered by value. Equal values stay ordered by
+    // timestamp
+    fn sorted_by_value(&self) -> Vec<TimeSeriesValue<Q>> {
+        let mut values: Vec<_> = self.iter().filter(|v| v.value.is_some()).copied().collect();
+        values.sort_by(|a, b| {
+            a.value
+                .partial_cmp(&b.value)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        values
+    }
+}
+
+/// An interval of a series without values, see [`TimeSeries::gaps`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    /// timestamp of the first missing value
+    pub start: NaiveDateTime,
+    /// end of the gap (exclusive), which is the timestamp of the next expected value
+    pub end: NaiveDateTime,
+    /// the number of missing values in the gap
+    pub missing: usize,
+}
+
+impl<Q> TimeSeries<Q> {
+    /// Returns the intervals between the first and last value of this series
+    /// where values are missing or have no value, given the cadence of the
+    /// time unit of the series. Use [`TimeSeries::gaps_between`] to also detect
+    /// missing values at the start or end of a requested period
+    pub fn gaps(&self) -> Vec<Gap> {
+        match (self.values.first(), self.values.last()) {
+            (Some(first), Some(last)) => {
+                self.gaps_between(first.date, self.time_unit.bucket_end(last.date))
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the intervals from `start` until `end` (exclusive) where values
+    /// are missing or have no value, given the cadence of the time unit of the
+    /// series
+    pub fn gaps_between(&self, start: NaiveDateTime, end: NaiveDateTime) -> Vec<Gap> {
+        let present: HashSet<NaiveDateTime> = self
+            .values
+            .iter()
+            .filter(|v| v.value.is_some())
+            .map(|v| v.date)
+            .collect();
+
+        let mut gaps: Vec<Gap> = Vec::new();
+        let mut current: Option<Gap> = None;
+        let mut slot = self.time_unit.bucket_start(start);
+        while slot < end {
+            let next = self.time_unit.bucket_end(slot);
+            if present.contains(&slot) {
+                gaps.extend(current.take());
+            } else {
+                let gap = current.get_or_insert(Gap {
+                    start: slot,
+                    end: next,
+                    missing: 0,
+                });
+                gap.end = next;
+                gap.missing += 1;
+            }
+            slot = next;
+        }
+        gaps.extend(current);
+        gaps
+    }
+}
+
+/// How missing values are filled by [`TimeSeries::fill_gaps`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillStrategy {
+    /// missing values become zero
+    Zero,
+    /// missing values get the last value before the gap
+    ForwardFill,
+}
+
+/// The timestamps that [`TimeSeries::fill_gaps`] or
+/// [`TimeSeries::interpolate_gaps`] filled or could not fill
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FillReport {
+    /// timestamps of missing values that were filled
+    pub filled: Vec<NaiveDateTime>,
+    /// timestamps of missing values that could not be filled, i.e. with
+    /// [`FillStrategy::ForwardFill`] at the start of the series or by
+    /// interpolation at its ends
+    pub unfilled: Vec<NaiveDateTime>,
+}
+
+impl<Q: Copy + Default> TimeSeries<Q> {
+    /// Returns a series with a value for every expected timestamp between the
+    /// first and last value of this series, given the cadence of its time unit.
+    /// Missing timestamps and timestamps without a value are filled using
+    /// `strategy`. The [`FillReport`] lists the timestamps that were filled
+    pub fn fill_gaps(&self, strategy: FillStrategy) -> (TimeSeries<Q>, FillReport) {
+        self.fill_with(|known, slot| match strategy {
+            FillStrategy::Zero => Some(Q::default()),
+            FillStrategy::ForwardFill => known.range(..slot).next_back().map(|(_, v)| *v),
+        })
+    }
+
+    // fills every expected timestamp without a value with the value that
+    // `fill` returns given the known values
+    fn fill_with<F>(&self, fill: F) -> (TimeSeries<Q>, FillReport)
+    where
+        F: Fn(&BTreeMap<NaiveDateTime, Q>, NaiveDateTime) -> Option<Q>,
+    {
+        let known: BTreeMap<NaiveDateTime, Q> = self
+            .values
+            .iter()
+            .filter_map(|v| v.value.map(|value| (v.date, value)))
+            .collect();
+
+        let mut report = FillReport::default();
+        let mut values = Vec::new();
+        if let (Some(first), Some(last)) = (self.values.first(), self.values.last()) {
+            let mut slot = self.time_unit.bucket_start(first.date);
+            while slot <= last.date {
+                let value = known.get(&slot).copied().or_else(|| {
+                    let filled = fill(&known, slot);
+                    match filled {
+                        Some(_) => report.filled.push(slot),
+                        None => report.unfilled.push(slot),
+                    }
+                    filled
+                });
+                values.push(TimeSeriesValue { date: slot, value });
+                slot = self.time_unit.bucket_end(slot);
+            }
+        }
+        (TimeSeries::new(self.time_unit, &self.unit, values), report)
+    }
+}
+
+impl TimeSeries<Power> {
+    /// Like [`TimeSeries::fill_gaps`], but missing values are linearly
+    /// interpolated between the values around the gap. Only power can be
+    /// interpolated, as energy is accumulated per bucket
+    pub fn interpolate_gaps(&self) -> (TimeSeries<Power>, FillReport) {
+        self.fill_with(interpolate)
+    }
+}
+
+// linearly interpolates the value at `date` between the known values before
+// and after `date`
+fn interpolate(known: &BTreeMap<NaiveDateTime, Power>, date: NaiveDateTime) -> Option<Power> {
+    let (before_date, before) = known.range(..date).next_back()?;
+    let (after_date, after) = known.range(date..).next()?;
+    let fraction = (date - *before_date).num_seconds() as f64
+        / (*after_date - *before_date).num_seconds() as f64;
+    Some(*before + (*after - *before) * fraction)
+}
+
+/// How the values in a bucket are combined by [`TimeSeries::resample`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    /// the sum of the values, i.e. for energy
+    Sum,
+    /// the average of the values, i.e. for power
+    Mean,
+    /// the highest value
+    Max,
+    /// the lowest value
+    Min,
+}
+
+impl<Q> TimeSeries<Q>
+where
+    Q: Copy + Default + PartialOrd + Add<Output = Q> + Div<f64, Output = Q>,
+{
+    /// Aggregates the values into buckets of `time_unit` using `aggregation`,
+    /// i.e. quarter of an hour energy into daily energy using [`Aggregation::Sum`]
+    /// or power into the hourly maximum using [`Aggregation::Max`]. Each value
+    /// of the result has the start of its bucket as timestamp. Buckets are
+    /// calendar aware, see [`TimeUnit::bucket_start`], and use the local time of
+    /// the site that the API returns. Timestamps without a value are skipped and
+    /// the value of a bucket is None when none of its values had a value
+    pub fn resample(&self, time_unit: TimeUnit, aggregation: Aggregation) -> TimeSeries<Q> {
+        let mut buckets: BTreeMap<NaiveDateTime, Vec<Q>> = BTreeMap::new();
+        for v in &self.values {
+            let bucket = buckets.entry(time_unit.bucket_start(v.date)).or_default();
+            bucket.extend(v.value);
+        }
+        let values = buckets
+            .into_iter()
+            .map(|(date, values)| TimeSeriesValue {
+                date,
+                value: aggregate(&values, aggregation),
+            })
+            .collect();
+        TimeSeries::new(time_unit, &self.unit, values)
+    }
+}
+
+/// A matrix of the values of a series per hour of the day (rows) and per day
+/// (columns), i.e. to render a heatmap that shows shading patterns. See
+/// [`TimeSeries::heatmap`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heatmap {
+    /// the days of the columns, every day from the first until the last day
+    /// of the series
+    pub days: Vec<NaiveDate>,
+    /// the values per hour of the day, so `values[hour][day]`. A value is
+    /// None when the series has no value in that hour
+    pub values: Vec<Vec<Option<f64>>>,
+}
+
+impl Heatmap {
+    /// the labels of the rows, i.e. `13:00`
+    pub fn hour_labels(&self) -> Vec<String> {
+        (0..self.values.len())
+            .map(|hour| format!("{hour:02}:00"))
+            .collect()
+    }
+
+    /// the labels of the columns, i.e. `2023-11-09`
+    pub fn day_labels(&self) -> Vec<String> {
+        self.days
+            .iter()
+            .map(|day| day.format("%Y-%m-%d").to_string())
+            .collect()
+    }
+}
+
+impl<Q> TimeSeries<Q>
+where
+    Q: Copy + Default + PartialOrd + Add<Output = Q> + Div<f64, Output = Q>,
+{
+    /// The values per hour and day as [`Heatmap`]. The values of an hour are
+    /// combined using `aggregation` and converted to a number using `value`,
+    /// i.e. [`Aggregation::Mean`] and `as_watts` for power
+    pub fn heatmap(&self, aggregation: Aggregation, value: impl Fn(Q) -> f64) -> Heatmap {
+        let hourly = self.resample(TimeUnit::Hour, aggregation);
+        let (Some(first), Some(last)) = (hourly.values.first(), hourly.values.last()) else {
+            return Heatmap {
+                days: vec![],
+                values: vec![vec![]; 24],
+            };
+        };
+        let days: Vec<NaiveDate> = first
+            .date
+            .date()
+            .iter_days()
+            .take_while(|day| *day <= last.date.date())
+            .collect();
+        let mut values = vec![vec![None; days.len()]; 24];
+        for v in &hourly {
+            let day = (v.date.date() - first.date.date()).num_days() as usize;
+            values[v.date.hour() as usize][day] = v.value.map(&value);
+        }
+        Heatmap { days, values }
+    }
+}
+
+/// Descriptive statistics of a series, see [`TimeSeries::stats`]. The
+/// quantities are None when the series has no values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats<Q> {
+    /// the number of timestamps with a value
+    pub count: usize,
+    /// the number of timestamps without a value
+    pub null_count: usize,
+    /// the lowest value
+    pub min: Option<Q>,
+    /// the highest value
+    pub max: Option<Q>,
+    /// the average of the values
+    pub mean: Option<Q>,
+    /// the sum of the values
+    pub sum: Option<Q>,
+    /// the 5th percentile
+    pub p5: Option<Q>,
+    /// the 25th percentile
+    pub p25: Option<Q>,
+    /// the 50th percentile
+    pub median: Option<Q>,
+    /// the 75th percentile
+    pub p75: Option<Q>,
+    /// the 95th percentile
+    pub p95: Option<Q>,
+}
+
+impl<Q> TimeSeries<Q>
+where
+    Q: Copy + Default + PartialOrd + Add<Output = Q> + Div<f64, Output = Q>,
+{
+    /// The count, minimum, maximum, mean, sum and percentiles of the values
+    /// and the number of timestamps without a value
+    pub fn stats(&self) -> Stats<Q> {
+        let sorted: Vec<Q> = self
+            .sorted_by_value()
+            .into_iter()
+            .filter_map(|v| v.value)
+            .collect();
+        let percentile = |p| nearest_rank(&sorted, p);
+        Stats {
+            count: sorted.len(),
+            null_count: self.len() - sorted.len(),
+            min: sorted.first().copied(),
+            max: sorted.last().copied(),
+            mean: aggregate(&sorted, Aggregation::Mean),
+            sum: aggregate(&sorted, Aggregation::Sum),
+            p5: percentile(5.0),
+            p25: percentile(25.0),
+            median: percentile(50.0),
+            p75: percentile(75.0),
+            p95: percentile(95.0),
+        }
+    }
+
+    /// The `p`th percentile of the values using the nearest rank method, so the
+    /// result is always one of the values. Returns None when the series has no
+    /// values or `p` is not between 0 and 100
+    pub fn percentile(&self, p: f64) -> Option<Q> {
+        let sorted: Vec<Q> = self
+            .sorted_by_value()
+            .into_iter()
+            .filter_map(|v| v.value)
+            .collect();
+        nearest_rank(&sorted, p)
+    }
+}
+
+// the `p`th percentile of the `sorted` values using the nearest rank method
+fn nearest_rank<Q: Copy>(sorted: &[Q], p: f64) -> Option<Q> {
+    if sorted.is_empty() || !(0.0..=100.0).contains(&p) {
+        return None;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.max(1) - 1])
+}
+
+// combines `values` using `aggregation`. Returns None when there are no values
+fn aggregate<Q>(values: &[Q], aggregation: Aggregation) -> Option<Q>
+where
+    Q: Copy + Default + PartialOrd + Add<Output = Q> + Div<f64, Output = Q>,
+{
+    let first = *values.first()?;
+    let rest = values.iter().skip(1).copied();
+    Some(match aggregation {
+        Aggregation::Sum => rest.fold(first, |sum, v| sum + v),
+        Aggregation::Mean => rest.fold(first, |sum, v| sum + v) / values.len() as f64,
+        Aggregation::Max => rest.fold(first, |max, v| if v > max { v } else { max }),
+        Aggregation::Min => rest.fold(first, |min, v| if v < min { v } else { min }),
+    })
+}
+
+/// The rule used by [`TimeSeries::integrate`] to calculate energy from power
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Integration {
+    /// the power of each value is constant for the duration of the time unit
+    /// of the series
+    #[default]
+    Rectangle,
+    /// the power changes linearly between two consecutive values
+    Trapezoidal,
+}
+
+impl TimeSeries<Power> {
+    /// Integrates the power values over time to energy. With
+    /// [`Integration::Rectangle`] each energy value is the energy in the bucket
+    /// starting at the timestamp of the power value. With
+    /// [`Integration::Trapezoidal`] each energy value is the energy between the
+    /// timestamp of the power value and the timestamp of the next power value,
+    /// so the result has one value less. Energy is None when a power value
+    /// that is needed is missing. Use [`TimeSeries::total`] on the result to get
+    /// the energy of the whole period
+    pub fn integrate(&self, rule: Integration) -> TimeSeries<Energy> {
+        let values = match rule {
+            Integration::Rectangle => {
+                let bucket = self.time_unit.duration();
+                self.values
+                    .iter()
+                    .map(|v| TimeSeriesValue {
+                        date: v.date,
+                        value: v.value.map(|power| energy_of(power, bucket)),
+                    })
+                    .collect()
+            }
+            Integration::Trapezoidal => self
+                .values
+                .windows(2)
+                .map(|pair| TimeSeriesValue {
+                    date: pair[0].date,
+                    value: pair[0].value.zip(pair[1].value).map(|(from, to)| {
+                        energy_of((from + to) / 2.0, pair[1].date - pair[0].date)
+                    }),
+                })
+                .collect(),
+        };
+        TimeSeries::new(self.time_unit, "Wh", values)
+    }
+}
+
+impl<Q> IntoIterator for TimeSeries<Q> {
+    type Item = TimeSeriesValue<Q>;
+    type IntoIter = std::vec::IntoIter<TimeSeriesValue<Q>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter()
+    }
+}
+
+impl<'a, Q> IntoIterator for &'a TimeSeries<Q> {
+    type Item = &'a TimeSeriesValue<Q>;
+    type IntoIter = std::slice::Iter<'a, TimeSeriesValue<Q>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
+
+impl TryFrom<&GeneratedEnergy> for TimeSeries<Energy> {
+    type Error = SolarApiError;
+
+    fn try_from(energy: &GeneratedEnergy) -> Result<Self, Self::Error> {
+        energy.to_series()
+    }
+}
+
+impl TryFrom<&GeneratedPowerPerTimeUnit> for TimeSeries<Power> {
+    type Error = SolarApiError;
+
+    fn try_from(power: &GeneratedPowerPerTimeUnit) -> Result<Self, Self::Error> {
+        power.to_series()
+    }
+}
+
+#[test]
+fn test_energy_to_series() {
+    let reply = r#"
+    {"energy":{
+        "timeUnit":"DAY",
+        "unit":"kWh",
+        "values":[
+            {"date":"2023-11-09 00:00:00","value":12.5},
+            {"date":"2023-11-10 00:00:00","value":null}
+        ]
+    }}
+    "#;
+    let energy = serde_json::from_str::<crate::site::GeneratedEnergyReply>(reply)
+        .unwrap()
+        .energy;
+
+    let series = TimeSeries::try_from(&energy).unwrap();
+    assert_eq!(TimeUnit::Day, series.time_unit);
+    assert_eq!("kWh", series.unit);
+    assert_eq!(2, series.len());
+    assert_eq!(Some(watt_hours(12500.0)), series.values[0].value);
+    assert_eq!(
+        vec![Some(watt_hours(12500.0)), None],
+        series.into_iter().map(|v| v.value).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_align_and_arithmetic() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let wh = |v| Some(watt_hours(v));
+    let series = |values: Vec<(&str, Option<Energy>)>| {
+        TimeSeries::new(
+            TimeUnit::Hour,
+            "Wh",
+            values
+                .into_iter()
+                .map(|(d, value)| TimeSeriesValue {
+                    date: date(d),
+                    value,
+                })
+                .collect(),
+        )
+    };
+    let production = series(vec![
+        ("2023-11-09 10:00:00", wh(500.0)),
+        ("2023-11-09 11:00:00", wh(800.0)),
+        ("2023-11-09 12:00:00", None),
+    ]);
+    let consumption = series(vec![
+        ("2023-11-09 11:00:00", wh(300.0)),
+        ("2023-11-09 12:00:00", wh(200.0)),
+        ("2023-11-09 13:00:00", wh(100.0)),
+    ]);
+
+    let aligned = production.align_with(&consumption);
+    assert_eq!(4, aligned.len());
+    assert_eq!((date("2023-11-09 10:00:00"), wh(500.0), None), aligned[0]);
+
+    let net = production.sub(&consumption, GapPolicy::Propagate);
+    let values: Vec<_> = net.iter().map(|v| v.value).collect();
+    assert_eq!(vec![None, wh(500.0), None, None], values);
+
+    let net = production.sub(&consumption, GapPolicy::Zero);
+    let values: Vec<_> = net.iter().map(|v| v.value).collect();
+    assert_eq!(vec![wh(500.0), wh(500.0), wh(-200.0), wh(-100.0)], values);
+
+    let total = production.add(&consumption, GapPolicy::Skip);
+    assert_eq!(1, total.len());
+    assert_eq!(wh(1100.0), total.values[0].value);
+}
+
+#[test]
+fn test_integrate_power() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let power = TimeSeries::new(
+        TimeUnit::QuarterOfAnHour,
+        "W",
+        vec![
+            TimeSeriesValue {
+                date: date("2023-11-09 12:00:00"),
+                value: Some(watts(400.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-11-09 12:15:00"),
+                value: Some(watts(800.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-11-09 12:30:00"),
+                value: None,
+            },
+        ],
+    );
+
+    let rectangle = power.integrate(Integration::Rectangle);
+    assert_eq!(3, rectangle.len());
+    assert_eq!(300.0, as_watt_hours(rectangle.total()).round());
+
+    let trapezoidal = power.integrate(Integration::Trapezoidal);
+    assert_eq!(2, trapezoidal.len());
+    assert_eq!(None, trapezoidal.values[1].value);
+    assert_eq!(150.0, as_watt_hours(trapezoidal.total()).round());
+}
+
+#[test]
+fn test_resample() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let w = |v| Some(watts(v));
+    let power = TimeSeries::new(
+        TimeUnit::QuarterOfAnHour,
+        "W",
+        [
+            ("2023-11-09 10:30:00", w(100.0)),
+            ("2023-11-09 10:45:00", w(300.0)),
+            ("2023-11-09 11:00:00", None),
+            ("2023-11-10 09:00:00", w(50.0)),
+        ]
+        .into_iter()
+        .map(|(d, value)| TimeSeriesValue {
+            date: date(d),
+            value,
+        })
+        .collect(),
+    );
+
+    let hourly = power.resample(TimeUnit::Hour, Aggregation::Mean);
+    assert_eq!(TimeUnit::Hour, hourly.time_unit);
+    assert_eq!(3, hourly.len());
+    assert_eq!(date("2023-11-09 10:00:00"), hourly.values[0].date);
+    assert_eq!(w(200.0), hourly.values[0].value);
+    assert_eq!(None, hourly.values[1].value);
+
+    let daily = power.resample(TimeUnit::Day, Aggregation::Max);
+    assert_eq!(
+        vec![w(300.0), w(50.0)],
+        daily.iter().map(|v| v.value).collect::<Vec<_>>()
+    );
+    let daily = power.resample(TimeUnit::Day, Aggregation::Sum);
+    assert_eq!(w(400.0), daily.values[0].value);
+    let daily = power.resample(TimeUnit::Day, Aggregation::Min);
+    assert_eq!(w(100.0), daily.values[0].value);
+}
+
+#[test]
+fn test_gaps() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let wh = |v| Some(watt_hours(v));
+    let energy = TimeSeries::new(
+        TimeUnit::Hour,
+        "Wh",
+        [
+            ("2023-11-09 08:00:00", wh(10.0)),
+            ("2023-11-09 09:00:00", None),
+            ("2023-11-09 11:00:00", wh(10.0)),
+            ("2023-11-09 12:00:00", wh(10.0)),
+        ]
+        .into_iter()
+        .map(|(d, value)| TimeSeriesValue {
+            date: date(d),
+            value,
+        })
+        .collect(),
+    );
+
+    let gaps = energy.gaps();
+    assert_eq!(
+        vec![Gap {
+            start: date("2023-11-09 09:00:00"),
+            end: date("2023-11-09 11:00:00"),
+            missing: 2
+        }],
+        gaps
+    );
+
+    let gaps = energy.gaps_between(date("2023-11-09 07:00:00"), date("2023-11-09 15:00:00"));
+    assert_eq!(3, gaps.len());
+    assert_eq!(1, gaps[0].missing);
+    assert_eq!(date("2023-11-09 13:00:00"), gaps[2].start);
+    assert_eq!(date("2023-11-09 15:00:00"), gaps[2].end);
+}
+
+#[test]
+fn test_fill_gaps() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let w = |v| Some(watts(v));
+    let power = TimeSeries::new(
+        TimeUnit::QuarterOfAnHour,
+        "W",
+        [
+            ("2023-11-09 10:00:00", None),
+            ("2023-11-09 10:15:00", w(100.0)),
+            ("2023-11-09 10:30:00", None),
+            ("2023-11-09 11:00:00", w(400.0)),
+        ]
+        .into_iter()
+        .map(|(d, value)| TimeSeriesValue {
+            date: date(d),
+            value,
+        })
+        .collect(),
+    );
+    let values = |series: &TimeSeries<Power>| series.iter().map(|v| v.value).collect::<Vec<_>>();
+
+    let (linear, report) = power.interpolate_gaps();
+    assert_eq!(
+        vec![None, w(100.0), w(200.0), w(300.0), w(400.0)],
+        values(&linear)
+    );
+    assert_eq!(
+        vec![date("2023-11-09 10:30:00"), date("2023-11-09 10:45:00")],
+        report.filled
+    );
+    assert_eq!(vec![date("2023-11-09 10:00:00")], report.unfilled);
+
+    let (forward, _) = power.fill_gaps(FillStrategy::ForwardFill);
+    assert_eq!(
+        vec![None, w(100.0), w(100.0), w(100.0), w(400.0)],
+        values(&forward)
+    );
+
+    let (zero, report) = power.fill_gaps(FillStrategy::Zero);
+    assert_eq!(
+        vec![w(0.0), w(100.0), w(0.0), w(0.0), w(400.0)],
+        values(&zero)
+    );
+    assert!(report.unfilled.is_empty());
+}
+
+#[test]
+fn test_sort_and_merge() {
+    let parse = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let value = |date: &str, value: Option<f64>| TimeSeriesValue {
+        date: parse(date),
+        value: value.map(watt_hours),
+    };
+
+    let early = value("2023-11-09 10:00:00", Some(5.0));
+    let late = value("2023-11-09 10:15:00", Some(1.0));
+    assert_eq!(std::cmp::Ordering::Less, early.cmp_by_time(&late));
+    // a larger value at the same timestamp is not later
+    let same_time = value("2023-11-09 10:00:00", Some(9.0));
+    assert_eq!(std::cmp::Ordering::Equal, early.cmp_by_time(&same_time));
+
+    let mut series = TimeSeries::new(TimeUnit::QuarterOfAnHour, "Wh", vec![late, early]);
+    series.sort();
+    assert_eq!(vec![early, late], series.values);
+
+    let refetched = TimeSeries::new(
+        TimeUnit::QuarterOfAnHour,
+        "Wh",
+        vec![
+            value("2023-11-09 10:30:00", Some(3.0)),
+            value("2023-11-09 10:15:00", Some(2.0)),
+            value("2023-11-09 10:00:00", None),
+        ],
+    );
+    let merged = series.merge(&refetched);
+    assert_eq!(
+        vec![
+            value("2023-11-09 10:00:00", Some(5.0)),
+            value("2023-11-09 10:15:00", Some(2.0)),
+            value("2023-11-09 10:30:00", Some(3.0)),
+        ],
+        merged.values
+    );
+    assert_eq!(10.0, as_watt_hours(merged.total()));
+}
+
+#[test]
+fn test_stats() {
+    let date = NaiveDateTime::parse_from_str("2023-06-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+    let mut values: Vec<_> = (1..=20)
+        .map(|i| TimeSeriesValue {
+            date: date + chrono::Duration::days(i),
+            value: Some(watt_hours(i as f64 * 100.0)),
+        })
+        .collect();
+    values.push(TimeSeriesValue { date, value: None });
+    let series = TimeSeries::new(TimeUnit::Day, "Wh", values);
+
+    let stats = series.stats();
+    assert_eq!(20, stats.count);
+    assert_eq!(1, stats.null_count);
+    assert_eq!(Some(100.0), stats.min.map(as_watt_hours));
+    assert_eq!(Some(2000.0), stats.max.map(as_watt_hours));
+    assert_eq!(Some(21000.0), stats.sum.map(as_watt_hours));
+    assert_eq!(Some(1050.0), stats.mean.map(as_watt_hours));
+    assert_eq!(Some(100.0), stats.p5.map(as_watt_hours));
+    assert_eq!(Some(1000.0), stats.median.map(as_watt_hours));
+    assert_eq!(Some(1900.0), stats.p95.map(as_watt_hours));
+    assert_eq!(None, series.percentile(101.0));
+
+    let empty: TimeSeries<Energy> = TimeSeries::new(TimeUnit::Day, "Wh", vec![]);
+    assert_eq!(0, empty.stats().count);
+    assert_eq!(None, empty.stats().median);
+}
+
+#[test]
+fn test_heatmap() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let power = TimeSeries::new(
+        TimeUnit::QuarterOfAnHour,
+        "W",
+        vec![
+            TimeSeriesValue {
+                date: date("2023-06-01 12:00:00"),
+                value: Some(watts(1000.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-06-01 12:15:00"),
+                value: Some(watts(2000.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-06-03 08:00:00"),
+                value: Some(watts(500.0)),
+            },
+        ],
+    );
+
+    let heatmap = power.heatmap(Aggregation::Mean, crate::units::as_watts);
+    assert_eq!(
+        vec!["2023-06-01", "2023-06-02", "2023-06-03"],
+        heatmap.day_labels()
+    );
+    assert_eq!(24, heatmap.values.len());
+    assert_eq!("12:00", heatmap.hour_labels()[12]);
+    assert_eq!(Some(1500.0), heatmap.values[12][0]);
+    assert_eq!(None, heatmap.values[12][1]);
+    assert_eq!(Some(500.0), heatmap.values[8][2]);
+}
@@ -0,0 +1,442 @@
+//! Synthetic production data for demos and the development of dashboards,
+//! without an API key or request limit. [`SimulatedClient`] implements
+//! [`MonitoringApi`] like [`Client`](crate::Client), so an application that
+//! uses the sites, overviews, energy and power of [`MonitoringApi`] can switch
+//! between both. The bulk, meter, storage and equipment endpoints and the
+//! helpers built on [`Client`](crate::Client), like the pollers, the
+//! portfolio, summaries and sync, are not simulated.
+//!
+//! The power of a site follows the elevation of the sun at the latitude of the
+//! site, so the curve is longer and higher in summer than in winter. Every day
+//! gets a clearness between overcast and clear, and every quarter of an hour
+//! varies around it like passing clouds. The noise is derived from the seed,
+//! the site and the time, so the same request returns the same values and the
+//! energy of a day equals the sum of its quarters of an hour. Values after the
+//! current time are `null` like in the replies of the API.
+//!
+//! ```
+//! # use solar_api::chrono::NaiveDate;
+//! use solar_api::{
+//!     simulation::{SimulatedClient, SimulatedSite},
+//!     DataPeriod, MonitoringApi, TimeUnit,
+//! };
+//!
+//! let client = SimulatedClient::new()
+//!     .with_site(SimulatedSite::new(1, 5.0).with_name("Demo"))
+//!     .with_now(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap());
+//! let year = DataPeriod::between(
+//!     NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+//!     NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(),
+//! )?;
+//! let months = client.energy(1, year, TimeUnit::Month)?.to_series()?;
+//! assert_eq!(12, months.values.len());
+//! assert!(months.values[5].value > months.values[11].value);
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+#[cfg(test)]
+use crate::units::{as_watt_hours, as_watts};
+use crate::{
+    client::MonitoringApi,
+    site::{
+        DataPeriod, DataPeriodReply, GeneratedEnergy, GeneratedEnergyReply,
+        GeneratedPowerPerTimeUnit, GeneratedPowerReply, Overview, OverviewReply, Site, TimeUnit,
+    },
+    SolarApiError,
+};
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use serde_json::{json, Value};
+use std::f64::consts::PI;
+
+// the format of the times of the API
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+// the part of the peak power that reaches the grid with the sun in the zenith,
+// after the losses of the modules, wiring and inverter
+const PERFORMANCE_RATIO: f64 = 0.85;
+
+/// A site of a [`SimulatedClient`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedSite {
+    pub id: u32,
+    pub name: String,
+    /// the peak power in kWp
+    pub peak_power_kw: f64,
+    /// the latitude in degrees, positive on the northern hemisphere
+    pub latitude: f64,
+    /// the first day with data
+    pub installation_date: NaiveDate,
+}
+
+impl SimulatedSite {
+    /// Create a site in Amsterdam that produces since 2020
+    pub fn new(id: u32, peak_power_kw: f64) -> Self {
+        SimulatedSite {
+            id,
+            name: format!("Simulated site {id}"),
+            peak_power_kw,
+            latitude: 52.37,
+            installation_date: NaiveDate::from_ymd_opt(2020, 1, 1).expect("valid date"),
+        }
+    }
+
+    /// Set the name
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Set the latitude in degrees, which determines the length of the days
+    /// and the seasons
+    pub fn with_latitude(mut self, latitude: f64) -> Self {
+        self.latitude = latitude;
+        self
+    }
+
+    /// Set the first day with data
+    pub fn with_installation_date(mut self, installation_date: NaiveDate) -> Self {
+        self.installation_date = installation_date;
+        self
+    }
+
+    // the site as in the replies of the API
+    fn to_site(&self, last_update: NaiveDateTime) -> Result<Site, SolarApiError> {
+        from_json(json!({
+            "id": self.id,
+            "name": self.name,
+            "accountId": 0,
+            "status": "Active",
+            "peakPower": self.peak_power_kw,
+            "lastUpdateTime": last_update.date().to_string(),
+            "installationDate": self.installation_date.to_string(),
+            "ptoDate": null,
+            "notes": "Simulated by solar-api",
+            "type": "Optimizers & Inverters",
+            "location": {
+                "country": "Netherlands",
+                "city": "Amsterdam",
+                "address": "",
+                "zip": "",
+                "timeZone": "Europe/Amsterdam",
+                "countryCode": "NL",
+            },
+            "primaryModule": {"manufacturerName": "", "modelName": ""},
+            "uris": {},
+            "publicSettings": {"isPublic": false},
+        }))
+    }
+}
+
+/// A [`MonitoringApi`] that generates the production of its sites, see the
+/// [module](self)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedClient {
+    sites: Vec<SimulatedSite>,
+    seed: u64,
+    now: Option<NaiveDateTime>,
+}
+
+impl Default for SimulatedClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulatedClient {
+    /// Create a client without sites whose clock is the local time of this
+    /// machine
+    pub fn new() -> Self {
+        SimulatedClient {
+            sites: Vec::new(),
+            seed: 0,
+            now: None,
+        }
+    }
+
+    /// Add `site` to the sites of the account
+    pub fn with_site(mut self, site: SimulatedSite) -> Self {
+        self.sites.push(site);
+        self
+    }
+
+    /// Use `seed` for the weather, so clients with another seed get other
+    /// days
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Use `now` as current time instead of the local time of this machine
+    pub fn with_now(mut self, now: NaiveDateTime) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// The power in W of `site` during the quarter of an hour that starts at
+    /// `time`, or None before the installation of the site
+    pub fn power_at(&self, site: &SimulatedSite, time: NaiveDateTime) -> Option<f64> {
+        if time.date() < site.installation_date {
+            return None;
+        }
+        let sun = sun_elevation_sine(site.latitude, time + Duration::seconds(450));
+        if sun <= 0.0 {
+            return Some(0.0);
+        }
+        let day = time.date().num_days_from_ce() as u64;
+        let clearness = 0.15 + 0.85 * self.noise(site.id, day).sqrt();
+        // clouds pass quicker on days that are neither clear nor overcast
+        let variation = 0.6 * clearness * (1.0 - clearness);
+        let quarter = self.noise(site.id, time.and_utc().timestamp() as u64) - 0.5;
+        let factor = (clearness + 2.0 * variation * quarter).clamp(0.05, 1.0);
+        let power = site.peak_power_kw * 1000.0 * PERFORMANCE_RATIO * sun * factor;
+        Some((power * 100.0).round() / 100.0)
+    }
+
+    fn site(&self, site_id: u32) -> Result<&SimulatedSite, SolarApiError> {
+        self.sites
+            .iter()
+            .find(|site| site.id == site_id)
+            .ok_or(SolarApiError::UnknownSite(site_id))
+    }
+
+    // the start of the quarter of an hour after the last one with data
+    fn published(&self) -> NaiveDateTime {
+        let now = self.now.unwrap_or_else(|| Local::now().naive_local());
+        TimeUnit::QuarterOfAnHour.bucket_start(now)
+    }
+
+    // the power in W of the quarters from `start` until `end`, None for the
+    // quarters without data
+    fn quarters(
+        &self,
+        site: &SimulatedSite,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> impl Iterator<Item = (NaiveDateTime, Option<f64>)> + '_ {
+        let site = site.clone();
+        let published = self.published();
+        std::iter::successors(Some(start), |time| Some(*time + Duration::minutes(15)))
+            .take_while(move |time| *time < end)
+            .map(move |time| {
+                let power = (time < published)
+                    .then(|| self.power_at(&site, time))
+                    .flatten();
+                (time, power)
+            })
+    }
+
+    // the energy in Wh of the quarters from `start` until `end`
+    fn energy_between(
+        &self,
+        site: &SimulatedSite,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> f64 {
+        self.quarters(site, start, end)
+            .filter_map(|(_, power)| power)
+            .sum::<f64>()
+            / 4.0
+    }
+
+    // a number in [0, 1) that is the same for the same seed, site and `value`
+    fn noise(&self, site_id: u32, value: u64) -> f64 {
+        // splitmix64
+        let mut z = (self.seed ^ (site_id as u64).rotate_left(32) ^ value)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl MonitoringApi for SimulatedClient {
+    fn list(&self) -> Result<Vec<Site>, SolarApiError> {
+        let published = self.published();
+        self.sites
+            .iter()
+            .map(|site| site.to_site(published))
+            .collect()
+    }
+
+    fn details(&self, site_id: u32) -> Result<Site, SolarApiError> {
+        self.site(site_id)?.to_site(self.published())
+    }
+
+    fn data_period(&self, site_id: u32) -> Result<DataPeriod, SolarApiError> {
+        let site = self.site(site_id)?;
+        let end = (self.published() - Duration::minutes(15)).date();
+        let reply: DataPeriodReply = from_json(json!({"dataPeriod": {
+            "startDate": site.installation_date.to_string(),
+            "endDate": end.max(site.installation_date).to_string(),
+        }}))?;
+        Ok(reply.data_period)
+    }
+
+    fn overview(&self, site_id: u32) -> Result<Overview, SolarApiError> {
+        let site = self.site(site_id)?;
+        let published = self.published();
+        let midnight = published.date().and_time(NaiveTime::MIN);
+        let installation = site.installation_date.and_time(NaiveTime::MIN);
+        let energy_since = |start: NaiveDateTime| json!({"energy": self.energy_between(site, start.max(installation), published)});
+        let current_power = self
+            .power_at(site, published - Duration::minutes(15))
+            .unwrap_or(0.0);
+        let reply: OverviewReply = from_json(json!({"overview": {
+            "lastUpdateTime": published.format(TIME_FORMAT).to_string(),
+            "lifeTimeData": energy_since(installation),
+            "lastYearData": energy_since(TimeUnit::Year.bucket_start(midnight)),
+            "lastMonthData": energy_since(TimeUnit::Month.bucket_start(midnight)),
+            "lastDayData": energy_since(midnight),
+            "currentPower": {"power": current_power},
+            "measuredBy": "INVERTER",
+        }}))?;
+        Ok(reply.overview)
+    }
+
+    fn energy(
+        &self,
+        site_id: u32,
+        period: DataPeriod,
+        time_unit: TimeUnit,
+    ) -> Result<GeneratedEnergy, SolarApiError> {
+        period.validate_for(time_unit)?;
+        let site = self.site(site_id)?;
+        let (start_date, end_date) = period.bounds()?;
+        let start = time_unit.bucket_start(start_date.and_time(NaiveTime::MIN));
+        let end = (end_date + Duration::days(1)).and_time(NaiveTime::MIN);
+        let mut values: Vec<(NaiveDateTime, Option<f64>)> = Vec::new();
+        for (time, power) in self.quarters(site, start, end) {
+            let bucket = time_unit.bucket_start(time);
+            if values.last().is_none_or(|(date, _)| *date != bucket) {
+                values.push((bucket, None));
+            }
+            if let (Some((_, energy)), Some(power)) = (values.last_mut(), power) {
+                *energy = Some(energy.unwrap_or(0.0) + power / 4.0);
+            }
+        }
+        let reply: GeneratedEnergyReply = from_json(json!({"energy": {
+            "timeUnit": time_unit.to_param(),
+            "unit": "Wh",
+            "measuredBy": "INVERTER",
+            "values": to_values(values),
+        }}))?;
+        Ok(reply.energy)
+    }
+
+    fn power(
+        &self,
+        site_id: u32,
+        start_datetime: NaiveDateTime,
+        end_datetime: NaiveDateTime,
+    ) -> Result<GeneratedPowerPerTimeUnit, SolarApiError> {
+        let site = self.site(site_id)?;
+        let start = TimeUnit::QuarterOfAnHour.bucket_start(start_datetime);
+        let values: Vec<(NaiveDateTime, Option<f64>)> = self
+            .quarters(site, start, end_datetime + Duration::seconds(1))
+            .collect();
+        let reply: GeneratedPowerReply = from_json(json!({"power": {
+            "timeUnit": "QUARTER_OF_AN_HOUR",
+            "unit": "W",
+            "measuredBy": "INVERTER",
+            "values": to_values(values),
+        }}))?;
+        Ok(reply.power)
+    }
+}
+
+// the sine of the elevation of the sun at `latitude` at the local solar time
+// `time`, negative at night
+fn sun_elevation_sine(latitude: f64, time: NaiveDateTime) -> f64 {
+    let day = time.ordinal() as f64;
+    let declination = (23.44 * (2.0 * PI * (284.0 + day) / 365.0).sin()).to_radians();
+    let hours = time.hour() as f64 + time.minute() as f64 / 60.0 + time.second() as f64 / 3600.0;
+    let hour_angle = (15.0 * (hours - 12.0)).to_radians();
+    let latitude = latitude.to_radians();
+    latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos()
+}
+
+// the values of the API with a date and an optional value
+fn to_values(values: Vec<(NaiveDateTime, Option<f64>)>) -> Vec<Value> {
+    values
+        .into_iter()
+        .map(|(date, value)| json!({"date": date.format(TIME_FORMAT).to_string(), "value": value}))
+        .collect()
+}
+
+// parses the generated reply with the types of the replies of the API
+fn from_json<T: serde::de::DeserializeOwned>(value: Value) -> Result<T, SolarApiError> {
+    serde_json::from_value(value).map_err(|source| SolarApiError::ParseError {
+        path: String::new(),
+        source,
+        body: String::new(),
+    })
+}
+
+#[test]
+fn test_simulated_client() {
+    let time = |s: &str| NaiveDateTime::parse_from_str(s, TIME_FORMAT).unwrap();
+    let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let client = SimulatedClient::new()
+        .with_site(SimulatedSite::new(1, 5.0).with_installation_date(date("2023-01-01")))
+        .with_now(time("2023-06-21 12:20:00"));
+
+    assert_eq!("Simulated site 1", client.list().unwrap()[0].name);
+    assert!(matches!(
+        client.details(2),
+        Err(SolarApiError::UnknownSite(2))
+    ));
+    assert_eq!(
+        Some(date("2023-06-21")),
+        client.data_period(1).unwrap().end_date
+    );
+
+    let power = client
+        .power(1, time("2023-06-21 00:00:00"), time("2023-06-21 23:45:00"))
+        .unwrap()
+        .to_series()
+        .unwrap();
+    assert_eq!(96, power.values.len());
+    assert_eq!(Some(0.0), power.values[0].value.map(as_watts));
+    let noon = power.values[48].value.map(as_watts).unwrap();
+    assert!((100.0..=4250.0).contains(&noon), "{noon}");
+    // the quarter from 12:15 is not published yet
+    assert!(power.values[49].value.is_none());
+    assert_eq!(
+        power,
+        client
+            .power(1, time("2023-06-21 00:00:00"), time("2023-06-21 23:45:00"))
+            .unwrap()
+            .to_series()
+            .unwrap()
+    );
+
+    let overview = client.overview(1).unwrap();
+    assert_eq!(
+        Some(time("2023-06-21 12:15:00")),
+        overview.last_updated_time
+    );
+    let today: f64 = power
+        .iter()
+        .filter_map(|v| v.value.map(as_watts))
+        .sum::<f64>()
+        / 4.0;
+    let last_day = as_watt_hours(overview.last_day_data.energy);
+    assert!((today - last_day).abs() < 0.01, "{today} {last_day}");
+    assert_eq!(
+        as_watt_hours(overview.last_year_data.energy),
+        as_watt_hours(overview.life_time_data.energy)
+    );
+
+    let period = DataPeriod::between(date("2022-12-01"), date("2023-06-30")).unwrap();
+    let months = client
+        .energy(1, period, TimeUnit::Month)
+        .unwrap()
+        .to_series()
+        .unwrap();
+    assert_eq!(7, months.values.len());
+    assert_eq!(None, months.values[0].value);
+    let january = as_watt_hours(months.values[1].value.unwrap());
+    let may = as_watt_hours(months.values[5].value.unwrap());
+    assert!(may > 2.0 * january, "{january} {may}");
+    assert!((300_000.0..800_000.0).contains(&may), "{may}");
+}
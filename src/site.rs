@@ -1,12 +1,22 @@
-use serde::{Deserialize, Deserializer};
-use std::collections::HashMap;
-use uom::si::{
-    energy::watt_hour,
-    f64::{Energy, Power},
-    power::{kilowatt, watt},
+#[cfg(test)]
+use crate::units::{as_watt_hours, watt_hours, watts};
+use crate::{
+    series::{Integration, TimeSeries, TimeSeriesValue},
+    units::{Energy, EnergyUnit, Power, PowerUnit, QuantityUnit, RawQuantity},
+    SolarApiError,
+};
+use chrono::{Datelike, Timelike};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
 };
 
 pub const REFRESH_TIME_IN_M: i64 = 15;
+/// Extra time in seconds after [`REFRESH_TIME_IN_M`] to wait before trying to
+/// get new data, used by [`Overview::next_update_schedule`]
+pub const NEXT_UPDATE_BACKOFF_IN_S: [i64; 3] = [10, 60, 5 * 60];
 
 #[derive(Debug, Clone, Deserialize)]
 pub(crate) struct SitesReply {
@@ -19,19 +29,19 @@ impl SitesReply {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Sites {
     #[serde(rename = "count")]
     _count: u32,
     site: Vec<Site>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SiteDetails {
     pub details: Site,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Site {
     /// the site id
     pub id: u32,
@@ -43,16 +53,33 @@ pub struct Site {
     /// the site status
     pub status: String,
     /// site peak power
-    #[serde(rename = "peakPower", deserialize_with = "parse_power_kw")]
+    #[serde(
+        rename = "peakPower",
+        deserialize_with = "parse_power_kw",
+        serialize_with = "serialize_power_kw"
+    )]
     pub peak_power: Power,
-    #[serde(rename = "lastUpdateTime", deserialize_with = "parse_date")]
-    pub last_update_time: chrono::NaiveDate,
-    /// site installation date
-    #[serde(rename = "installationDate", deserialize_with = "parse_date")]
-    pub installation_date: chrono::NaiveDate, 
+    /// the date of the last update. None for new sites that have not
+    /// communicated yet
+    #[serde(
+        rename = "lastUpdateTime",
+        default,
+        deserialize_with = "parse_optional_date",
+        serialize_with = "serialize_optional_date"
+    )]
+    pub last_update_time: Option<chrono::NaiveDate>,
+    /// site installation date. None when not set for the site
+    #[serde(
+        rename = "installationDate",
+        default,
+        deserialize_with = "parse_optional_date",
+        serialize_with = "serialize_optional_date"
+    )]
+    pub installation_date: Option<chrono::NaiveDate>,
     /// permission to operate date
     #[serde(rename = "ptoDate")]
     pub pto_date: Option<String>,
+    #[serde(default)]
     pub notes: String,
     /// site type
     #[serde(rename = "type")]
@@ -68,7 +95,7 @@ pub struct Site {
 }
 
 /// Location of a site
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Location {
     pub country: String,
     pub city: String,
@@ -78,48 +105,192 @@ pub struct Location {
     pub time_zone: String,
     #[serde(rename = "countryCode")]
     pub country_code: String,
+    /// None when the API does not return the coordinates of the site
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
 }
 
 /// The information about the model of the primary module of the site
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PrimaryModule {
-    #[serde(rename = "manufacturerName")]
+    #[serde(rename = "manufacturerName", default)]
     pub manufacturer_name: String,
-    #[serde(rename = "modelName")]
+    #[serde(rename = "modelName", default)]
     pub model_name: String,
-    #[serde(rename = "maximumPower", deserialize_with = "parse_power_kw")]
-    pub maximum_power: Power,
+    /// None when the module is not configured yet, i.e. for new sites
+    #[serde(
+        rename = "maximumPower",
+        default,
+        deserialize_with = "parse_optional_power_kw",
+        serialize_with = "serialize_optional_power_kw"
+    )]
+    pub maximum_power: Option<Power>,
     #[serde(rename = "temperatureCoef")]
-    pub temperature_coef: f32,
+    pub temperature_coef: Option<f32>,
 }
 
 /// Setting showing if information about this site is public
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PublicSettings {
     #[serde(rename = "isPublic")]
     pub public: bool,
 }
 
 /// The period defined by start_date and end_date that this site is producting energy
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub struct DataPeriod {
-    #[serde(rename = "startDate", deserialize_with = "parse_date")]
-    pub start_date: chrono::NaiveDate,
-    #[serde(rename = "endDate", deserialize_with = "parse_date")]
-    pub end_date: chrono::NaiveDate,
+    /// None for sites that have not communicated yet
+    #[serde(
+        rename = "startDate",
+        default,
+        deserialize_with = "parse_optional_date",
+        serialize_with = "serialize_optional_date"
+    )]
+    pub start_date: Option<chrono::NaiveDate>,
+    /// None for sites that have not communicated yet
+    #[serde(
+        rename = "endDate",
+        default,
+        deserialize_with = "parse_optional_date",
+        serialize_with = "serialize_optional_date"
+    )]
+    pub end_date: Option<chrono::NaiveDate>,
 }
 
 impl DataPeriod {
+    /// The period from `start_date` until `end_date`, both inclusive. Returns
+    /// [`SolarApiError::InvalidDateRange`] when `start_date` is after `end_date`
+    pub fn between(
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<DataPeriod, SolarApiError> {
+        if start_date > end_date {
+            return Err(SolarApiError::InvalidDateRange {
+                start: start_date,
+                end: end_date,
+            });
+        }
+        Ok(Self::between_unchecked(start_date, end_date))
+    }
+
+    /// The start and end date of this period. Returns
+    /// [`SolarApiError::EmptyPeriod`] when one of them is missing, i.e. for the
+    /// data period of a site that has not communicated yet
+    pub fn bounds(&self) -> Result<(chrono::NaiveDate, chrono::NaiveDate), SolarApiError> {
+        match (self.start_date, self.end_date) {
+            (Some(start_date), Some(end_date)) => Ok((start_date, end_date)),
+            _ => Err(SolarApiError::EmptyPeriod),
+        }
+    }
+
+    /// The period containing only today, using the local time of this machine
+    pub fn today() -> DataPeriod {
+        Self::single_day(chrono::Local::now().date_naive())
+    }
+
+    /// The period containing only yesterday, using the local time of this machine
+    pub fn yesterday() -> DataPeriod {
+        Self::single_day(chrono::Local::now().date_naive() - chrono::Duration::days(1))
+    }
+
+    /// The period of the last `n` days, including today. `last_n_days(1)` is
+    /// the same as [`DataPeriod::today`]
+    pub fn last_n_days(n: u32) -> DataPeriod {
+        let today = chrono::Local::now().date_naive();
+        Self::between_unchecked(
+            today - chrono::Duration::days(n.saturating_sub(1) as i64),
+            today,
+        )
+    }
+
+    /// The period containing all days of `month` (1-12) in `year`. Returns None
+    /// when the month is not valid
+    pub fn month(year: i32, month: u32) -> Option<DataPeriod> {
+        let start_date = chrono::NaiveDate::from_ymd_opt(year, month, 1)?;
+        let end_date = start_date
+            .checked_add_months(chrono::Months::new(1))?
+            .pred_opt()?;
+        Some(Self::between_unchecked(start_date, end_date))
+    }
+
+    /// The period containing all days of `year`. Returns None when the year is
+    /// out of range
+    pub fn year(year: i32) -> Option<DataPeriod> {
+        Some(Self::between_unchecked(
+            chrono::NaiveDate::from_ymd_opt(year, 1, 1)?,
+            chrono::NaiveDate::from_ymd_opt(year, 12, 31)?,
+        ))
+    }
+
+    /// Checks that this period does not exceed the maximum the API allows for
+    /// `time_unit`, see [`TimeUnit::max_period_months`]. The end date is
+    /// inclusive, so a period of one month ends the day before the same day of
+    /// the next month, like the periods of [`DataPeriod::split_for`]. Returns
+    /// [`SolarApiError::InvalidPeriod`] when the period is too long and
+    /// [`SolarApiError::EmptyPeriod`] when it has no dates
+    pub fn validate_for(&self, time_unit: TimeUnit) -> Result<(), SolarApiError> {
+        let (start_date, end_date) = self.bounds()?;
+        let Some(max_months) = time_unit.max_period_months() else {
+            return Ok(());
+        };
+        match start_date.checked_add_months(chrono::Months::new(max_months)) {
+            Some(max_end_date) if end_date >= max_end_date => Err(SolarApiError::InvalidPeriod {
+                time_unit,
+                max_months,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Splits this period into consecutive periods that each satisfy
+    /// [`DataPeriod::validate_for`] for `time_unit`. Returns this period when
+    /// it does not need to be split and no periods when it has no dates
+    pub fn split_for(&self, time_unit: TimeUnit) -> Vec<DataPeriod> {
+        let Ok((mut start_date, last_date)) = self.bounds() else {
+            return Vec::new();
+        };
+        let Some(max_months) = time_unit.max_period_months() else {
+            return vec![*self];
+        };
+        let mut periods = Vec::new();
+        loop {
+            let end_date = start_date
+                .checked_add_months(chrono::Months::new(max_months))
+                .and_then(|date| date.pred_opt())
+                .map_or(last_date, |date| date.min(last_date));
+            periods.push(Self::between_unchecked(start_date, end_date));
+            match end_date.succ_opt() {
+                Some(next) if end_date < last_date => start_date = next,
+                _ => return periods,
+            }
+        }
+    }
+
+    fn single_day(date: chrono::NaiveDate) -> DataPeriod {
+        Self::between_unchecked(date, date)
+    }
+
+    fn between_unchecked(start_date: chrono::NaiveDate, end_date: chrono::NaiveDate) -> DataPeriod {
+        DataPeriod {
+            start_date: Some(start_date),
+            end_date: Some(end_date),
+        }
+    }
+
     /// create a formatted [`String`] for the start date 
-    /// in `%Y-%m-%d` format, i.e. `2023-11-9` for november 9th 2023
-    pub fn formatted_start_date(&self) -> String {
-        Self::formatted_date(&self.start_date)
+    /// in `%Y-%m-%d` format, i.e. `2023-11-9` for november 9th 2023. None
+    /// when the period has no start date
+    pub fn formatted_start_date(&self) -> Option<String> {
+        self.start_date.as_ref().map(Self::formatted_date)
     }
 
     /// create a formatted [`String`] for the end date 
-    /// in `%Y-%m-%d` format, i.e. `2023-11-9` for november 9th 2023
-    pub fn formatted_end_date(&self) -> String {
-        Self::formatted_date(&self.end_date)
+    /// in `%Y-%m-%d` format, i.e. `2023-11-9` for november 9th 2023. None
+    /// when the period has no end date
+    pub fn formatted_end_date(&self) -> Option<String> {
+        self.end_date.as_ref().map(Self::formatted_date)
     }
 
     fn formatted_date(date: &chrono::NaiveDate) -> String {
@@ -139,10 +310,16 @@ pub(crate) struct OverviewReply {
 }
 
 /// The overview of a site includes the site current power, daily energy, monthly energy, yearly energy and life time energy.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Overview {
-    #[serde(rename = "lastUpdateTime", deserialize_with = "parse_date_time")]
-    pub last_updated_time: chrono::NaiveDateTime,
+    /// None for sites that have not communicated yet
+    #[serde(
+        rename = "lastUpdateTime",
+        default,
+        deserialize_with = "parse_optional_date_time",
+        serialize_with = "serialize_optional_date_time"
+    )]
+    pub last_updated_time: Option<chrono::NaiveDateTime>,
     #[serde(rename = "lifeTimeData")]
     pub life_time_data: TimeData,
     #[serde(rename = "lastYearData")]
@@ -160,38 +337,78 @@ pub struct Overview {
 impl Overview {
     /// Calculates the next timestamp and the duration from now when new data 
     /// should be available on the API. It uses `last_update_time` and 15 
-    /// minutes and 10 seconds as delta between updates
-    pub fn estimated_next_update(&self) -> (chrono::NaiveDateTime, chrono::Duration) {
+    /// minutes and 10 seconds as delta between updates. Returns None when the
+    /// site has not communicated yet
+    pub fn estimated_next_update(&self) -> Option<(chrono::NaiveDateTime, chrono::Duration)> {
         // add 10s extra time
-        let next = self.last_updated_time + chrono::Duration::seconds(REFRESH_TIME_IN_M * 60 + 10);
+        self.estimated_next_update_with_margin(chrono::Duration::seconds(
+            NEXT_UPDATE_BACKOFF_IN_S[0],
+        ))
+    }
+
+    /// Like [`Overview::estimated_next_update`], but uses `margin` as extra
+    /// time after the 15 minutes between updates instead of 10 seconds
+    pub fn estimated_next_update_with_margin(
+        &self,
+        margin: chrono::Duration,
+    ) -> Option<(chrono::NaiveDateTime, chrono::Duration)> {
+        let next = self.last_updated_time? + chrono::Duration::minutes(REFRESH_TIME_IN_M) + margin;
         let delta = next - chrono::Local::now().naive_local();
-        (next, delta)
+        Some((next, delta))
+    }
+
+    /// The timestamps to try to get new data from the API. The API sometimes
+    /// publishes new data later than 15 minutes after `last_update_time`, so
+    /// this backs off using [`NEXT_UPDATE_BACKOFF_IN_S`], i.e. 15 minutes plus
+    /// 10 seconds, 1 minute and 5 minutes. The schedule is empty when the site
+    /// has not communicated yet
+    pub fn next_update_schedule(&self) -> Vec<chrono::NaiveDateTime> {
+        NEXT_UPDATE_BACKOFF_IN_S
+            .iter()
+            .filter_map(|backoff| {
+                self.estimated_next_update_with_margin(chrono::Duration::seconds(*backoff))
+                    .map(|(next, _)| next)
+            })
+            .collect()
     }
 }
 
 /// Amount of [`Energy`] and optional the revenue of this energy
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TimeData {
-    #[serde(deserialize_with = "parse_energy_wh")]
+    #[serde(
+        deserialize_with = "parse_energy_wh",
+        serialize_with = "serialize_energy_wh"
+    )]
     pub energy: Energy,
     pub revenue: Option<f32>,
 }
 
 /// Generated power in Kw
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GeneratedPower {
-    #[serde(deserialize_with = "parse_power_kw")]
+    #[serde(
+        deserialize_with = "parse_power_kw",
+        serialize_with = "serialize_power_kw"
+    )]
     pub power: Power,
 }
 
 /// Generated power in W
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GeneratedPowerW {
-    #[serde(deserialize_with = "parse_power_w")]
+    #[serde(
+        deserialize_with = "parse_power_w",
+        serialize_with = "serialize_power_w"
+    )]
     pub power: Power,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// The resolution of measurements returned by the API. [`TimeUnit`] is
+/// displayed and serialized as the constant used by the API, i.e. `QUARTER_OF_AN_HOUR`.
+/// Parsing is case insensitive and ignores `_`, `-` and spaces, so both `hour`
+/// and `QUARTER_OF_AN_HOUR` can be parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TimeUnit {
     QuarterOfAnHour,
     Hour,
@@ -220,6 +437,67 @@ impl TimeUnit {
         }
     }
 
+    /// The nominal duration of one bucket of this [`TimeUnit`]. A month is
+    /// nominally 30 days and a year 365 days. Use [`TimeUnit::bucket_start`] and
+    /// [`TimeUnit::bucket_end`] for calendar aware boundaries
+    pub fn duration(&self) -> chrono::Duration {
+        match self {
+            TimeUnit::QuarterOfAnHour => chrono::Duration::minutes(15),
+            TimeUnit::Hour => chrono::Duration::hours(1),
+            TimeUnit::Day => chrono::Duration::days(1),
+            TimeUnit::Week => chrono::Duration::weeks(1),
+            TimeUnit::Month => chrono::Duration::days(30),
+            TimeUnit::Year => chrono::Duration::days(365),
+        }
+    }
+
+    /// The maximum length in months of a period that the API allows for an
+    /// energy request with this [`TimeUnit`]. This is one year for
+    /// [`TimeUnit::Day`] and one month for [`TimeUnit::Hour`] and
+    /// [`TimeUnit::QuarterOfAnHour`]. Other time units are not limited
+    pub fn max_period_months(&self) -> Option<u32> {
+        match self {
+            TimeUnit::QuarterOfAnHour | TimeUnit::Hour => Some(1),
+            TimeUnit::Day => Some(12),
+            TimeUnit::Week | TimeUnit::Month | TimeUnit::Year => None,
+        }
+    }
+
+    /// The start of the bucket that contains `datetime`, i.e. `2023-11-09 10:37:12`
+    /// is in the [`TimeUnit::Hour`] bucket starting at `2023-11-09 10:00:00`.
+    /// Weeks start on monday
+    pub fn bucket_start(&self, datetime: chrono::NaiveDateTime) -> chrono::NaiveDateTime {
+        let date = datetime.date();
+        let midnight = chrono::NaiveTime::MIN;
+        match self {
+            TimeUnit::QuarterOfAnHour => date.and_time(
+                chrono::NaiveTime::from_hms_opt(datetime.hour(), datetime.minute() / 15 * 15, 0)
+                    .expect("valid time"),
+            ),
+            TimeUnit::Hour => date.and_time(
+                chrono::NaiveTime::from_hms_opt(datetime.hour(), 0, 0).expect("valid time"),
+            ),
+            TimeUnit::Day => date.and_time(midnight),
+            TimeUnit::Week => (date
+                - chrono::Duration::days(date.weekday().num_days_from_monday() as i64))
+            .and_time(midnight),
+            TimeUnit::Month => date.with_day(1).expect("valid date").and_time(midnight),
+            TimeUnit::Year => date.with_ordinal(1).expect("valid date").and_time(midnight),
+        }
+    }
+
+    /// The (exclusive) end of the bucket that contains `datetime`, which is the
+    /// start of the next bucket. Months and years take the calendar into
+    /// account
+    pub fn bucket_end(&self, datetime: chrono::NaiveDateTime) -> chrono::NaiveDateTime {
+        let start = self.bucket_start(datetime);
+        match self {
+            TimeUnit::Month => start + chrono::Months::new(1),
+            TimeUnit::Year => start + chrono::Months::new(12),
+            _ => start + self.duration(),
+        }
+    }
+
     pub fn from_const<'de, D>(deserializer: D) -> Result<TimeUnit, D::Error>
     where
         D: Deserializer<'de>,
@@ -232,70 +510,309 @@ impl TimeUnit {
             WEEK => Ok(TimeUnit::Week),
             MONTH => Ok(TimeUnit::Month),
             YEAR => Ok(TimeUnit::Year),
-            _ => Err(serde::de::Error::custom("Cannot parse value")),
+            _ => Err(serde::de::Error::custom(format!(
+                "Cannot parse time unit value `{s}`"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_param())
+    }
+}
+
+impl FromStr for TimeUnit {
+    type Err = SolarApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized: String = s
+            .chars()
+            .filter(|c| !matches!(c, '_' | '-' | ' '))
+            .collect::<String>()
+            .to_lowercase();
+        match normalized.as_str() {
+            "quarterofanhour" | "quarter" => Ok(TimeUnit::QuarterOfAnHour),
+            "hour" => Ok(TimeUnit::Hour),
+            "day" => Ok(TimeUnit::Day),
+            "week" => Ok(TimeUnit::Week),
+            "month" => Ok(TimeUnit::Month),
+            "year" => Ok(TimeUnit::Year),
+            _ => Err(SolarApiError::UnknownTimeUnit(s.to_string())),
         }
     }
 }
 
+impl Serialize for TimeUnit {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.to_param())
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeUnit {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub(crate) struct GeneratedEnergyReply {
     pub(crate) energy: GeneratedEnergy,
 }
 
 /// Contains all values of the generated energy per time unit
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GeneratedEnergy {
     #[serde(rename = "timeUnit", deserialize_with = "TimeUnit::from_const")]
     pub time_unit: TimeUnit,
     unit: String,
-    values: Vec<RawGeneratedEnergyValue>,
+    /// the type of device that measured the values, i.e. `INVERTER` or `METER`
+    #[serde(rename = "measuredBy", skip_serializing_if = "Option::is_none")]
+    pub measured_by: Option<String>,
+    values: Vec<RawValue>,
 }
 
 impl GeneratedEnergy {
-    /// returns the timestamped energy values
-    pub fn values(&self) -> Vec<GeneratedEnergyValue> {
+    /// the unit of measurement as returned by the API, i.e. `Wh`
+    pub fn unit(&self) -> &str {
+        &self.unit
+    }
+
+    /// returns the values as returned by the API with the unit string. Unlike
+    /// [`GeneratedEnergy::values`] this also works for units that this lib does not
+    /// know, i.e. to log them or convert them yourself
+    pub fn raw_values(&self) -> impl Iterator<Item = TimeSeriesValue<RawQuantity>> + '_ {
+        self.values.iter().map(|raw| TimeSeriesValue {
+            date: raw.date,
+            value: raw.value.map(|value| RawQuantity::raw(value, &self.unit)),
+        })
+    }
+
+    /// returns the timestamped energy values. Returns
+    /// [`SolarApiError::UnsupportedUnit`] when the API used a unit of
+    /// measurement that this lib does not know
+    pub fn values(&self) -> Result<Vec<GeneratedEnergyValue>, SolarApiError> {
+        Ok(self.iter_values()?.collect())
+    }
+
+    /// returns a lazy iterator over the timestamped energy values that converts
+    /// each value when it is visited. The unit is checked up front, so this
+    /// returns [`SolarApiError::UnsupportedUnit`] before iterating
+    pub fn iter_values(
+        &self,
+    ) -> Result<impl Iterator<Item = GeneratedEnergyValue> + '_, SolarApiError> {
+        let unit = energy_unit(&self.unit)?;
+        Ok(self.values.iter().map(move |raw| raw.convert(unit)))
+    }
+
+    /// converts this reply to a [`TimeSeries`] of [`Energy`]
+    pub fn to_series(&self) -> Result<TimeSeries<Energy>, SolarApiError> {
+        Ok(TimeSeries::new(self.time_unit, &self.unit, self.values()?))
+    }
+
+    /// the number of values in the series, including timestamps without a value
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// true when the series contains no values
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// returns the value at `index` or None when `index` is out of bounds
+    pub fn get(&self, index: usize) -> Result<Option<GeneratedEnergyValue>, SolarApiError> {
+        let unit = energy_unit(&self.unit)?;
+        Ok(self.values.get(index).map(|raw| raw.convert(unit)))
+    }
+
+    /// returns the total [`Energy`] of all values. Timestamps without a value
+    /// are skipped
+    pub fn total(&self) -> Result<Energy, SolarApiError> {
+        Ok(self
+            .iter_values()?
+            .filter_map(|v| v.value)
+            .fold(Energy::default(), |total, e| total + e))
+    }
+
+    /// returns the value with the highest [`Energy`] or None if there are no
+    /// values. When several values share the highest energy, the first is returned
+    pub fn max_value(&self) -> Result<Option<GeneratedEnergyValue>, SolarApiError> {
+        Ok(self
+            .iter_values()?
+            .filter(|v| v.value.is_some())
+            .reduce(|max, v| if v.value > max.value { v } else { max }))
+    }
+
+    /// returns the sum of the values per day, ordered by date. The date of
+    /// each value is the start of the day
+    pub fn sum_by_day(&self) -> Result<Vec<GeneratedEnergyValue>, SolarApiError> {
+        self.sum_by(TimeUnit::Day)
+    }
+
+    /// returns the sum of the values per month, ordered by date. The date of
+    /// each value is the start of the month
+    pub fn sum_by_month(&self) -> Result<Vec<GeneratedEnergyValue>, SolarApiError> {
+        self.sum_by(TimeUnit::Month)
+    }
+
+    /// returns the sum of the values per bucket of `time_unit`, ordered by date.
+    /// The date of each value is the start of the bucket, see [`TimeUnit::bucket_start`].
+    /// The value is None when none of the values in the bucket had a value
+    pub fn sum_by(&self, time_unit: TimeUnit) -> Result<Vec<GeneratedEnergyValue>, SolarApiError> {
+        let mut buckets: BTreeMap<chrono::NaiveDateTime, Option<Energy>> = BTreeMap::new();
+        for v in self.iter_values()? {
+            let bucket = buckets.entry(time_unit.bucket_start(v.date)).or_default();
+            if let Some(value) = v.value {
+                *bucket = Some(bucket.map_or(value, |sum| sum + value));
+            }
+        }
+        Ok(buckets
+            .into_iter()
+            .map(|(date, value)| GeneratedEnergyValue { date, value })
+            .collect())
+    }
+
+    // appends the values of `other` to this series, converting them to the
+    // unit of this series when `other` uses a different unit
+    pub(crate) fn append(&mut self, other: GeneratedEnergy) -> Result<(), SolarApiError> {
+        let factor = energy_unit(&other.unit)?.watt_hours() / energy_unit(&self.unit)?.watt_hours();
         self.values
-            .iter()
-            .map(|raw| raw.convert(&self.unit))
-            .collect()
+            .extend(other.values.into_iter().map(|raw| RawValue {
+                date: raw.date,
+                value: raw.value.map(|v| v * factor),
+            }));
+        Ok(())
     }
 }
 
-// struct used to parse reply from API. Can be converted to 
-//[`GeneratedEnergyValue`] to contain correct unit of measurement 
-// using the unit value returned by [`GeneratedEnergy`]
-#[derive(Debug, Clone, Deserialize, Copy)]
-struct RawGeneratedEnergyValue {
-    #[serde(deserialize_with = "parse_date_time")]
-    date: chrono::NaiveDateTime,
-    value: Option<f64>,
+impl<'a> IntoIterator for &'a GeneratedEnergy {
+    type Item = Result<GeneratedEnergyValue, SolarApiError>;
+    type IntoIter = GeneratedEnergyIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ValuesIter::new(RawValues::Borrowed(self.values.iter()), &self.unit)
+    }
 }
 
-impl RawGeneratedEnergyValue {
-    // converts f64 value to [`Energy`] using supplied `unit`. 
-    // Currenty only `Wh` is supported
-    fn convert(&self, unit: &str) -> GeneratedEnergyValue {
-        let value = match unit {
-            "Wh" => self.value.map(Energy::new::<watt_hour>),
-            _ => todo!("unsupported unit: {unit}"),
-        };
-        GeneratedEnergyValue {
+impl IntoIterator for GeneratedEnergy {
+    type Item = Result<GeneratedEnergyValue, SolarApiError>;
+    type IntoIter = GeneratedEnergyIntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ValuesIter::new(RawValues::Owned(self.values.into_iter()), &self.unit)
+    }
+}
+
+/// Iterator over the values of a [`GeneratedEnergy`], see [`ValuesIter`]
+pub type GeneratedEnergyIter<'a> = ValuesIter<'a, EnergyUnit>;
+
+/// Owning iterator over the values of a [`GeneratedEnergy`], see [`ValuesIter`]
+pub type GeneratedEnergyIntoIter = ValuesIter<'static, EnergyUnit>;
+
+// parses the `unit` of an energy reply. Supported units are `Wh`, `kWh`,
+// `MWh` and `GWh`
+fn energy_unit(unit: &str) -> Result<EnergyUnit, SolarApiError> {
+    unit.parse()
+}
+
+// struct used to parse the values of a reply from the API. Can be converted
+// to a [`TimeSeriesValue`] with the correct unit of measurement using the unit
+// of the reply, i.e. to a [`GeneratedEnergyValue`] for [`GeneratedEnergy`]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub(crate) struct RawValue {
+    #[serde(
+        deserialize_with = "parse_date_time",
+        serialize_with = "serialize_date_time"
+    )]
+    pub(crate) date: chrono::NaiveDateTime,
+    pub(crate) value: Option<f64>,
+}
+
+impl RawValue {
+    // converts f64 value to a quantity using the unit of the series
+    pub(crate) fn convert<U: QuantityUnit>(&self, unit: U) -> TimeSeriesValue<U::Quantity> {
+        TimeSeriesValue {
             date: self.date,
-            value,
+            value: self.value.map(|value| unit.quantity(value)),
+        }
+    }
+}
+
+// the raw values that a [`ValuesIter`] visits, borrowed from or owned by the
+// reply
+#[derive(Debug, Clone)]
+enum RawValues<'a> {
+    Borrowed(std::slice::Iter<'a, RawValue>),
+    Owned(std::vec::IntoIter<RawValue>),
+}
+
+impl Iterator for RawValues<'_> {
+    type Item = RawValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            RawValues::Borrowed(values) => values.next().copied(),
+            RawValues::Owned(values) => values.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            RawValues::Borrowed(values) => values.size_hint(),
+            RawValues::Owned(values) => values.size_hint(),
+        }
+    }
+}
+
+/// Iterator over the values of a [`GeneratedEnergy`] or a
+/// [`GeneratedPowerPerTimeUnit`] with unit `U`. The unit of the reply is parsed
+/// once, when the iterator is created. Every item is
+/// [`SolarApiError::UnsupportedUnit`] when the unit is not supported
+#[derive(Debug, Clone)]
+pub struct ValuesIter<'a, U> {
+    values: RawValues<'a>,
+    // the unit of the reply, or the unit as returned by the API when it is not
+    // supported
+    unit: Result<U, String>,
+}
+
+impl<'a, U: QuantityUnit> ValuesIter<'a, U> {
+    fn new(values: RawValues<'a>, unit: &str) -> Self {
+        ValuesIter {
+            values,
+            unit: unit.parse().map_err(|_| unit.to_string()),
         }
     }
 }
 
+impl<U: QuantityUnit> Iterator for ValuesIter<'_, U> {
+    type Item = Result<TimeSeriesValue<U::Quantity>, SolarApiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let raw = self.values.next()?;
+        Some(match &self.unit {
+            Ok(unit) => Ok(raw.convert(*unit)),
+            Err(unit) => Err(SolarApiError::UnsupportedUnit(unit.clone())),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
 /// A timestamped [`Energy`] value. The value may be None when there wasn't a 
 /// value at that timestamp
-#[derive(Debug, Clone, Copy)]
-pub struct GeneratedEnergyValue {
-    /// timestamp of value
-    pub date: chrono::NaiveDateTime,
-    /// the value measures at the timestamp or None if there wasn't a value at
-    /// that timestamp
-    pub value: Option<Energy>,
-}
+pub type GeneratedEnergyValue = TimeSeriesValue<Energy>;
 
 // struct used to parse the API reply for Power
 #[derive(Debug, Clone, Deserialize)]
@@ -304,72 +821,361 @@ pub(crate) struct GeneratedPowerReply {
 }
 
 /// Contains all values of the generated power per time unit
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GeneratedPowerPerTimeUnit {
     #[serde(rename = "timeUnit", deserialize_with = "TimeUnit::from_const")]
     pub time_unit: TimeUnit,
     unit: String,
-    values: Vec<RawGeneratedPowerValue>,
+    /// the type of device that measured the values, i.e. `INVERTER` or `METER`
+    #[serde(rename = "measuredBy", skip_serializing_if = "Option::is_none")]
+    pub measured_by: Option<String>,
+    values: Vec<RawValue>,
 }
 
 impl GeneratedPowerPerTimeUnit {
-    /// returns all Power values that were present in the time period
-    pub fn values(&self) -> Vec<GeneratedPowerValue> {
-        self.values
-            .iter()
-            .map(|raw| raw.convert(&self.unit))
-            .collect()
+    /// the unit of measurement as returned by the API, i.e. `W`
+    pub fn unit(&self) -> &str {
+        &self.unit
+    }
+
+    /// returns the values as returned by the API with the unit string. Unlike
+    /// [`GeneratedPowerPerTimeUnit::values`] this also works for units that this lib does not
+    /// know, i.e. to log them or convert them yourself
+    pub fn raw_values(&self) -> impl Iterator<Item = TimeSeriesValue<RawQuantity>> + '_ {
+        self.values.iter().map(|raw| TimeSeriesValue {
+            date: raw.date,
+            value: raw.value.map(|value| RawQuantity::raw(value, &self.unit)),
+        })
+    }
+
+    /// returns all Power values that were present in the time period. Returns
+    /// [`SolarApiError::UnsupportedUnit`] when the API used a unit of
+    /// measurement that this lib does not know
+    pub fn values(&self) -> Result<Vec<GeneratedPowerValue>, SolarApiError> {
+        Ok(self.iter_values()?.collect())
+    }
+
+    /// returns a lazy iterator over the Power values that converts each value
+    /// when it is visited. The unit is checked up front, so this returns
+    /// [`SolarApiError::UnsupportedUnit`] before iterating
+    pub fn iter_values(
+        &self,
+    ) -> Result<impl Iterator<Item = GeneratedPowerValue> + '_, SolarApiError> {
+        let unit = power_unit(&self.unit)?;
+        Ok(self.values.iter().map(move |raw| raw.convert(unit)))
+    }
+
+    /// converts this reply to a [`TimeSeries`] of [`Power`]
+    pub fn to_series(&self) -> Result<TimeSeries<Power>, SolarApiError> {
+        Ok(TimeSeries::new(self.time_unit, &self.unit, self.values()?))
+    }
+
+    /// Integrates the power values over time to energy using `rule`, see
+    /// [`TimeSeries::integrate`]
+    pub fn integrate(&self, rule: Integration) -> Result<TimeSeries<Energy>, SolarApiError> {
+        Ok(self.to_series()?.integrate(rule))
+    }
+
+    /// the number of values in the series, including timestamps without a value
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// true when the series contains no values
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// returns the value at `index` or None when `index` is out of bounds
+    pub fn get(&self, index: usize) -> Result<Option<GeneratedPowerValue>, SolarApiError> {
+        let unit = power_unit(&self.unit)?;
+        Ok(self.values.get(index).map(|raw| raw.convert(unit)))
+    }
+
+    // appends the values of `other` to this series, converting them to the
+    // unit of this series when `other` uses a different unit. Values of
+    // `other` that are not after the last value of this series are skipped
+    pub(crate) fn append(&mut self, other: GeneratedPowerPerTimeUnit) -> Result<(), SolarApiError> {
+        let factor = power_unit(&other.unit)?.watts() / power_unit(&self.unit)?.watts();
+        let last_date = self.values.last().map(|raw| raw.date);
+        self.values.extend(
+            other
+                .values
+                .into_iter()
+                .filter(|raw| last_date.is_none_or(|last| raw.date > last))
+                .map(|raw| RawValue {
+                    date: raw.date,
+                    value: raw.value.map(|v| v * factor),
+                }),
+        );
+        Ok(())
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
-struct RawGeneratedPowerValue {
-    #[serde(deserialize_with = "parse_date_time")]
-    date: chrono::NaiveDateTime,
-    value: Option<f64>,
-}
-
-impl RawGeneratedPowerValue {
-    // converts f64 value to [`Power`] using supplied `unit`. 
-    // Currenty only `W` is supported
-    pub fn convert(&self, unit: &str) -> GeneratedPowerValue {
-        let value: Option<Power> = match unit {
-            "W" => self.value.map(Power::new::<watt>),
-            _ => todo!("unsupported unit: {unit}"),
-        };
-        GeneratedPowerValue {
-            date: self.date,
-            value,
+impl<'a> IntoIterator for &'a GeneratedPowerPerTimeUnit {
+    type Item = Result<GeneratedPowerValue, SolarApiError>;
+    type IntoIter = GeneratedPowerIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ValuesIter::new(RawValues::Borrowed(self.values.iter()), &self.unit)
+    }
+}
+
+impl IntoIterator for GeneratedPowerPerTimeUnit {
+    type Item = Result<GeneratedPowerValue, SolarApiError>;
+    type IntoIter = GeneratedPowerIntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ValuesIter::new(RawValues::Owned(self.values.into_iter()), &self.unit)
+    }
+}
+
+/// Iterator over the values of a [`GeneratedPowerPerTimeUnit`], see [`ValuesIter`]
+pub type GeneratedPowerIter<'a> = ValuesIter<'a, PowerUnit>;
+
+/// Owning iterator over the values of a [`GeneratedPowerPerTimeUnit`], see
+/// [`ValuesIter`]
+pub type GeneratedPowerIntoIter = ValuesIter<'static, PowerUnit>;
+
+/// Splits the period from `start_datetime` until `end_datetime` into windows
+/// of at most one month, which is the limit of the power API. Consecutive
+/// windows share their boundary timestamp
+pub fn power_windows(
+    start_datetime: chrono::NaiveDateTime,
+    end_datetime: chrono::NaiveDateTime,
+) -> Vec<(chrono::NaiveDateTime, chrono::NaiveDateTime)> {
+    let mut windows = Vec::new();
+    let mut start = start_datetime;
+    loop {
+        let end = start
+            .checked_add_months(chrono::Months::new(1))
+            .map_or(end_datetime, |end| end.min(end_datetime));
+        windows.push((start, end));
+        if end >= end_datetime {
+            return windows;
         }
+        start = end;
     }
 }
 
+// parses the `unit` of a power reply. Supported units are `W`, `kW`, `MW`
+// and `GW`
+fn power_unit(unit: &str) -> Result<PowerUnit, SolarApiError> {
+    unit.parse()
+}
+
 /// A timestamped [`Power`] value. The value may be None when there wasn't a 
 /// value at that timestamp
-#[derive(Debug, Clone)]
-pub struct GeneratedPowerValue {
-    pub date: chrono::NaiveDateTime,
-    pub value: Option<Power>,
+pub type GeneratedPowerValue = TimeSeriesValue<Power>;
+
+impl Site {
+    /// The JSON of this site as returned by [`crate::details`]
+    pub fn to_api_json(&self) -> serde_json::Value {
+        serde_json::json!({ "details": self })
+    }
+
+    /// The JSON of `sites` as returned by [`crate::list`]
+    pub fn list_to_api_json(sites: &[Site]) -> serde_json::Value {
+        serde_json::json!({ "sites": { "count": sites.len(), "site": sites } })
+    }
+}
+
+impl DataPeriod {
+    /// The JSON of this period as returned by [`crate::data_period`]
+    pub fn to_api_json(&self) -> serde_json::Value {
+        serde_json::json!({ "dataPeriod": self })
+    }
+}
+
+impl Overview {
+    /// The JSON of this overview as returned by [`crate::overview`]
+    pub fn to_api_json(&self) -> serde_json::Value {
+        serde_json::json!({ "overview": self })
+    }
+}
+
+impl GeneratedEnergy {
+    /// The JSON of this series as returned by [`crate::energy`]
+    pub fn to_api_json(&self) -> serde_json::Value {
+        serde_json::json!({ "energy": self })
+    }
+}
+
+impl GeneratedPowerPerTimeUnit {
+    /// The JSON of this series as returned by [`crate::power`]
+    pub fn to_api_json(&self) -> serde_json::Value {
+        serde_json::json!({ "power": self })
+    }
+}
+
+// serialize a datetime value in the format of the API
+pub(crate) fn serialize_date_time<S>(
+    datetime: &chrono::NaiveDateTime,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_str(&datetime.format("%Y-%m-%d %H:%M:%S"))
+}
+
+// serialize an optional datetime value in the format of the API. None results in null
+fn serialize_optional_date_time<S>(
+    datetime: &Option<chrono::NaiveDateTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match datetime {
+        Some(datetime) => serialize_date_time(datetime, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+// serialize a date value in the format of the API
+pub(crate) fn serialize_date<S>(date: &chrono::NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_str(&date.format("%Y-%m-%d"))
+}
+
+// serialize an optional date value in the format of the API. None results in null
+fn serialize_optional_date<S>(
+    date: &Option<chrono::NaiveDate>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match date {
+        Some(date) => serialize_date(date, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+// serialize a [`Power`] value as float in kilowatt
+fn serialize_power_kw<S>(power: &Power, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_f64(PowerUnit::Kilowatt.value_of(*power))
+}
+
+// serialize an optional [`Power`] value as float in kilowatt. None results in null
+fn serialize_optional_power_kw<S>(power: &Option<Power>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match power {
+        Some(power) => serialize_power_kw(power, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+// serialize a [`Power`] value as float in watt
+pub(crate) fn serialize_power_w<S>(power: &Power, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_f64(PowerUnit::Watt.value_of(*power))
+}
+
+// serialize an [`Energy`] value as float in watt-hours
+pub(crate) fn serialize_energy_wh<S>(energy: &Energy, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_f64(EnergyUnit::WattHour.value_of(*energy))
+}
+
+// serialize an optional [`Power`] value as float in watt. None results in null
+pub(crate) fn serialize_optional_power_w<S>(
+    power: &Option<Power>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match power {
+        Some(power) => serialize_power_w(power, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+// serialize an optional [`Energy`] value as float in watt-hours. None results in null
+pub(crate) fn serialize_optional_energy_wh<S>(
+    energy: &Option<Energy>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match energy {
+        Some(energy) => serialize_energy_wh(energy, serializer),
+        None => serializer.serialize_none(),
+    }
 }
 
 // parse a datetime value that the API returned to a [`NaiveDateTime`]
-fn parse_date_time<'de, D>(deserializer: D) -> Result<chrono::NaiveDateTime, D::Error>
+pub(crate) fn parse_date_time<'de, D>(deserializer: D) -> Result<chrono::NaiveDateTime, D::Error>
 where
     D: Deserializer<'de>,
 {
     let s: String = String::deserialize(deserializer)?;
     chrono::NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S")
-        .map_err(|_| serde::de::Error::custom("Cannot parse value"))
+        .map_err(|e| serde::de::Error::custom(format!("Cannot parse date time `{s}`: {e}")))
 }
 
 // parse a datetime value that the API returned to a [`NaiveDate`]
-fn parse_date<'de, D>(deserializer: D) -> Result<chrono::NaiveDate, D::Error>
+pub(crate) fn parse_date<'de, D>(deserializer: D) -> Result<chrono::NaiveDate, D::Error>
 where
     D: Deserializer<'de>,
 {
     let s: String = String::deserialize(deserializer)?;
     chrono::NaiveDate::parse_from_str(&s, "%Y-%m-%d")
-        .map_err(|_| serde::de::Error::custom("Cannot parse value"))
+        .map_err(|e| serde::de::Error::custom(format!("Cannot parse date `{s}`: {e}")))
+}
+
+// parse an optional date value that the API returned to a [`NaiveDate`]. A null
+// or empty value results in None
+fn parse_optional_date<'de, D>(deserializer: D) -> Result<Option<chrono::NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(s) if !s.is_empty() => chrono::NaiveDate::parse_from_str(&s, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|e| serde::de::Error::custom(format!("Cannot parse date `{s}`: {e}"))),
+        _ => Ok(None),
+    }
+}
+
+// parse an optional datetime value that the API returned to a [`NaiveDateTime`].
+// A null or empty value results in None
+fn parse_optional_date_time<'de, D>(
+    deserializer: D,
+) -> Result<Option<chrono::NaiveDateTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(s) if !s.is_empty() => chrono::NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S")
+            .map(Some)
+            .map_err(|e| serde::de::Error::custom(format!("Cannot parse date time `{s}`: {e}"))),
+        _ => Ok(None),
+    }
+}
+
+// parse an optional float value that the API returned to a [`Power`] value.
+// Assumes the value is in kilowatt
+fn parse_optional_power_kw<'de, D>(deserializer: D) -> Result<Option<Power>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Option<f64> = Option::deserialize(deserializer)?;
+    Ok(value.map(|value| PowerUnit::Kilowatt.power(value)))
 }
 
 // parse a float value that the API returned to a [`Power`] value. Assumes the value is in kilowatt
@@ -378,25 +1184,43 @@ where
     D: Deserializer<'de>,
 {
     let value: f64 = f64::deserialize(deserializer)?;
-    Ok(Power::new::<kilowatt>(value))
+    Ok(PowerUnit::Kilowatt.power(value))
 }
 
 // parse a float value that the API returned to a [`Power`] value. Assumes the value is in watt
-fn parse_power_w<'de, D>(deserializer: D) -> Result<Power, D::Error>
+pub(crate) fn parse_power_w<'de, D>(deserializer: D) -> Result<Power, D::Error>
 where
     D: Deserializer<'de>,
 {
     let value: f64 = f64::deserialize(deserializer)?;
-    Ok(Power::new::<watt>(value))
+    Ok(PowerUnit::Watt.power(value))
+}
+
+// parse an optional float value that the API returned to a [`Power`] value. Assumes the value is in watt
+pub(crate) fn parse_optional_power_w<'de, D>(deserializer: D) -> Result<Option<Power>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Option<f64> = Option::deserialize(deserializer)?;
+    Ok(value.map(|value| PowerUnit::Watt.power(value)))
+}
+
+// parse an optional float value that the API returned to a [`Energy`] value. Assumes the value is in watt-hours
+pub(crate) fn parse_optional_energy_wh<'de, D>(deserializer: D) -> Result<Option<Energy>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Option<f64> = Option::deserialize(deserializer)?;
+    Ok(value.map(|value| EnergyUnit::WattHour.energy(value)))
 }
 
 // parse a float value that the API returned to a [`Energy`] value. Assumes the value is in watt-hours
-fn parse_energy_wh<'de, D>(deserializer: D) -> Result<Energy, D::Error>
+pub(crate) fn parse_energy_wh<'de, D>(deserializer: D) -> Result<Energy, D::Error>
 where
     D: Deserializer<'de>,
 {
     let value: f64 = f64::deserialize(deserializer)?;
-    Ok(Energy::new::<watt_hour>(value))
+    Ok(EnergyUnit::WattHour.energy(value))
 }
 
 #[test]
@@ -445,23 +1269,141 @@ fn test_parse_sites_data() {
     let reply: SitesReply = serde_json::from_str(output).unwrap();
     println!("{:?}", reply);
     assert_eq!(reply.sites._count, 1);
-    let power = Power::new::<kilowatt>(7.41);
+    let power = PowerUnit::Kilowatt.power(7.41);
     assert_eq!(power, reply.sites.site[0].peak_power);
 }
 
+#[test]
+fn test_parse_pending_site() {
+    let output = r#"
+       {"sites":{
+           "count":2,
+           "site":[
+               {"id":2345234,
+                "name":"NewSite",
+                "accountId":123456,
+                "status":"Pending Communication",
+                "peakPower":5.2,
+                "lastUpdateTime":null,
+                "ptoDate":null,
+                "notes":"",
+                "type":"Optimizers & Inverters",
+                "location":{
+                    "country":"Netherlands",
+                    "city":"A city",
+                    "address":"Some address",
+                    "zip":"zipy",
+                    "timeZone":"Europe/Amsterdam",
+                    "countryCode":"NL"
+                },
+                "primaryModule":{
+                    "manufacturerName":"",
+                    "modelName":""
+                },
+                "uris":{},
+                "publicSettings":{
+                    "isPublic":false
+                }},
+               {"id":3456345,
+                "name":"OtherNewSite",
+                "accountId":123456,
+                "status":"Pending",
+                "peakPower":0.0,
+                "lastUpdateTime":"",
+                "installationDate":"2024-05-01",
+                "type":"Optimizers & Inverters",
+                "location":{
+                    "country":"Netherlands",
+                    "city":"A city",
+                    "address":"Some address",
+                    "zip":"zipy",
+                    "timeZone":"Europe/Amsterdam",
+                    "countryCode":"NL"
+                },
+                "primaryModule":{
+                    "manufacturerName":"JinkoSolar",
+                    "modelName":"390",
+                    "maximumPower":null
+                },
+                "uris":{},
+                "publicSettings":{
+                    "isPublic":false
+                }}
+            ]
+        }
+    }"#;
+
+    let reply: SitesReply = serde_json::from_str(output).unwrap();
+    let pending = &reply.sites()[0];
+    assert_eq!(None, pending.last_update_time);
+    assert_eq!(None, pending.installation_date);
+    assert!(pending.primary_module.maximum_power.is_none());
+    assert_eq!(None, pending.primary_module.temperature_coef);
+
+    let other = &reply.sites()[1];
+    assert_eq!(None, other.last_update_time);
+    assert_eq!(
+        chrono::NaiveDate::from_ymd_opt(2024, 5, 1),
+        other.installation_date
+    );
+    assert_eq!("", other.notes);
+}
+
 #[test]
 fn test_parse_data_period() {
     let reply = r#"{"dataPeriod":{"startDate":"2021-02-25","endDate":"2021-05-03"}}"#;
     println!("{}", reply);
     let parsed: DataPeriodReply = serde_json::from_str(reply).unwrap();
-    assert_eq!("2021-02-25", parsed.data_period.formatted_start_date());
-    assert_eq!("2021-05-03", parsed.data_period.formatted_end_date());
+    assert_eq!(
+        Some("2021-02-25"),
+        parsed.data_period.formatted_start_date().as_deref()
+    );
+    assert_eq!(
+        Some("2021-05-03"),
+        parsed.data_period.formatted_end_date().as_deref()
+    );
 }
 
 #[test]
-fn test_energy() {
-    use uom::si::energy::watt_hour;
+fn test_parse_pending_overview_and_data_period() {
+    let reply = r#"
+    {"overview":{
+        "lastUpdateTime":null,
+        "lifeTimeData":{"energy":0.0},
+        "lastYearData":{"energy":0.0},
+        "lastMonthData":{"energy":0.0},
+        "lastDayData":{"energy":0.0},
+        "currentPower":{"power":0.0},
+        "measuredBy":"INVERTER"}
+    }
+    "#;
+    let overview = serde_json::from_str::<OverviewReply>(reply)
+        .unwrap()
+        .overview;
+    assert_eq!(None, overview.last_updated_time);
+    assert_eq!(None, overview.estimated_next_update());
+    assert!(overview.next_update_schedule().is_empty());
+    assert_eq!(
+        serde_json::Value::Null,
+        overview.to_api_json()["overview"]["lastUpdateTime"]
+    );
+
+    let reply = r#"{"dataPeriod":{"startDate":null,"endDate":null}}"#;
+    let period = serde_json::from_str::<DataPeriodReply>(reply)
+        .unwrap()
+        .data_period;
+    assert_eq!(None, period.start_date);
+    assert_eq!(None, period.formatted_end_date());
+    assert!(matches!(period.bounds(), Err(SolarApiError::EmptyPeriod)));
+    assert!(matches!(
+        period.validate_for(TimeUnit::Day),
+        Err(SolarApiError::EmptyPeriod)
+    ));
+    assert!(period.split_for(TimeUnit::Hour).is_empty());
+}
 
+#[test]
+fn test_energy() {
     let reply = r#"
     {"energy":{
         "timeUnit":"MONTH",
@@ -477,9 +1419,9 @@ fn test_energy() {
     let parsed: GeneratedEnergyReply = serde_json::from_str(reply).unwrap();
     assert_eq!(
         45718.0,
-        parsed.energy.values()[0]
+        parsed.energy.values().unwrap()[0]
             .value
-            .map(|e| e.get::<watt_hour>())
+            .map(as_watt_hours)
             .unwrap()
     );
 }
@@ -500,13 +1442,10 @@ fn test_overview() {
 
     let parsed: OverviewReply = serde_json::from_str(reply).unwrap();
     assert_eq!(
-        Energy::new::<watt_hour>(1.9191678E7),
+        watt_hours(1.9191678E7),
         parsed.overview.life_time_data.energy
     );
-    assert_eq!(
-        Power::new::<watt>(1173.7279),
-        parsed.overview.current_power.power
-    );
+    assert_eq!(watts(1173.7279), parsed.overview.current_power.power);
 }
 
 #[test]
@@ -547,11 +1486,59 @@ fn test_energy_in_period() {
     "#;
 
     let parsed: GeneratedEnergyReply = serde_json::from_str(reply).unwrap();
-    assert_eq!(24, parsed.energy.values().len());
-    assert_eq!(
-        Some(Energy::new::<watt_hour>(222.0)),
-        parsed.energy.values()[11].value
-    );
+    let values = parsed.energy.values().unwrap();
+    assert_eq!(24, values.len());
+    assert_eq!(Some(watt_hours(222.0)), values[11].value);
+}
+
+#[test]
+fn test_energy_in_kwh() {
+    let reply = r#"
+    {"energy":{
+        "timeUnit":"DAY",
+        "unit":"kWh",
+        "measuredBy":"INVERTER",
+        "values":[
+            {"date":"2023-11-09 00:00:00","value":12.5},
+            {"date":"2023-11-10 00:00:00","value":null}
+        ]
+    }}
+    "#;
+
+    let parsed: GeneratedEnergyReply = serde_json::from_str(reply).unwrap();
+    let values = parsed.energy.values().unwrap();
+    assert_eq!(Some(watt_hours(12500.0)), values[0].value);
+    assert_eq!(None, values[1].value);
+}
+
+#[test]
+fn test_energy_unsupported_unit() {
+    let reply = r#"
+    {"energy":{
+        "timeUnit":"DAY",
+        "unit":"J",
+        "values":[{"date":"2023-11-09 00:00:00","value":12.5}]
+    }}
+    "#;
+
+    let parsed: GeneratedEnergyReply = serde_json::from_str(reply).unwrap();
+    assert!(matches!(
+        parsed.energy.values(),
+        Err(SolarApiError::UnsupportedUnit(unit)) if unit == "J"
+    ));
+
+    // the raw values are still available
+    assert_eq!("J", parsed.energy.unit());
+    let raw: Vec<_> = parsed.energy.raw_values().collect();
+    assert_eq!(Some(RawQuantity::raw(12.5, "J")), raw[0].value);
+
+    // iterating yields the unsupported unit for every value
+    let mut values = parsed.energy.into_iter();
+    assert!(matches!(
+        values.next(),
+        Some(Err(SolarApiError::UnsupportedUnit(unit))) if unit == "J"
+    ));
+    assert!(values.next().is_none());
 }
 
 #[test]
@@ -572,9 +1559,374 @@ fn test_power_in_period() {
     "#;
 
     let parsed: GeneratedPowerReply = serde_json::from_str(reply).unwrap();
-    assert_eq!(5, parsed.power.values().len());
+    let values = parsed.power.values().unwrap();
+    assert_eq!(5, values.len());
+    assert_eq!(Some(watts(761.538)), values[0].value);
+
+    let mut iter = parsed.power.iter_values().unwrap().skip(4);
+    assert_eq!(Some(watts(554.06836)), iter.next().unwrap().value);
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn test_power_in_kw() {
+    let reply = r#"
+    {"power":{
+        "timeUnit":"QUARTER_OF_AN_HOUR",
+        "unit":"kW",
+        "values":[
+            {"date":"2023-11-09 12:15:00","value":1.5},
+            {"date":"2023-11-09 12:30:00","value":null}
+        ]
+    }}
+    "#;
+
+    let parsed: GeneratedPowerReply = serde_json::from_str(reply).unwrap();
+    let values = parsed.power.values().unwrap();
+    assert_eq!(Some(watts(1500.0)), values[0].value);
+    assert_eq!(None, values[1].value);
+}
+
+#[test]
+fn test_empty_series_with_unsupported_unit() {
+    let reply = r#"{"power":{"timeUnit":"QUARTER_OF_AN_HOUR","unit":"hp","values":[]}}"#;
+
+    let parsed: GeneratedPowerReply = serde_json::from_str(reply).unwrap();
+    assert!(parsed.power.values().is_err());
+}
+
+#[test]
+fn test_power_unsupported_unit() {
+    let reply = r#"
+    {"power":{
+        "timeUnit":"QUARTER_OF_AN_HOUR",
+        "unit":"hp",
+        "values":[{"date":"2023-11-09 12:15:00","value":1.5}]
+    }}
+    "#;
+
+    let parsed: GeneratedPowerReply = serde_json::from_str(reply).unwrap();
+    assert!(matches!(
+        parsed.power.values(),
+        Err(SolarApiError::UnsupportedUnit(unit)) if unit == "hp"
+    ));
+}
+
+#[test]
+fn test_time_unit_from_str_and_display() {
+    assert_eq!(TimeUnit::Hour, "hour".parse().unwrap());
+    assert_eq!(
+        TimeUnit::QuarterOfAnHour,
+        "QUARTER_OF_AN_HOUR".parse().unwrap()
+    );
+    assert_eq!(
+        TimeUnit::QuarterOfAnHour,
+        "quarter-of-an-hour".parse().unwrap()
+    );
+    assert!("fortnight".parse::<TimeUnit>().is_err());
+
+    for unit in [
+        TimeUnit::QuarterOfAnHour,
+        TimeUnit::Hour,
+        TimeUnit::Day,
+        TimeUnit::Week,
+        TimeUnit::Month,
+        TimeUnit::Year,
+    ] {
+        assert_eq!(unit, unit.to_string().parse().unwrap());
+    }
+}
+
+#[test]
+fn test_time_unit_serde() {
+    let json = serde_json::to_string(&TimeUnit::QuarterOfAnHour).unwrap();
+    assert_eq!(r#""QUARTER_OF_AN_HOUR""#, json);
+    assert_eq!(
+        TimeUnit::QuarterOfAnHour,
+        serde_json::from_str::<TimeUnit>(&json).unwrap()
+    );
+    assert_eq!(
+        TimeUnit::Day,
+        serde_json::from_str::<TimeUnit>(r#""day""#).unwrap()
+    );
+}
+
+#[test]
+fn test_time_unit_buckets() {
+    let dt = |s: &str| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let moment = dt("2024-02-15 10:37:12");
+
+    assert_eq!(
+        chrono::Duration::minutes(15),
+        TimeUnit::QuarterOfAnHour.duration()
+    );
+    assert_eq!(
+        dt("2024-02-15 10:30:00"),
+        TimeUnit::QuarterOfAnHour.bucket_start(moment)
+    );
+    assert_eq!(
+        dt("2024-02-15 10:45:00"),
+        TimeUnit::QuarterOfAnHour.bucket_end(moment)
+    );
     assert_eq!(
-        Some(Power::new::<watt>(761.538)),
-        parsed.power.values()[0].value
+        dt("2024-02-15 10:00:00"),
+        TimeUnit::Hour.bucket_start(moment)
     );
+    assert_eq!(dt("2024-02-16 00:00:00"), TimeUnit::Day.bucket_end(moment));
+    // 2024-02-15 is a thursday
+    assert_eq!(
+        dt("2024-02-12 00:00:00"),
+        TimeUnit::Week.bucket_start(moment)
+    );
+    assert_eq!(
+        dt("2024-02-01 00:00:00"),
+        TimeUnit::Month.bucket_start(moment)
+    );
+    // leap year
+    assert_eq!(
+        dt("2024-03-01 00:00:00"),
+        TimeUnit::Month.bucket_end(moment)
+    );
+    assert_eq!(
+        dt("2024-01-01 00:00:00"),
+        TimeUnit::Year.bucket_start(moment)
+    );
+    assert_eq!(dt("2025-01-01 00:00:00"), TimeUnit::Year.bucket_end(moment));
+}
+
+#[test]
+fn test_energy_aggregation() {
+    let reply = r#"
+    {"energy":{
+        "timeUnit":"HOUR",
+        "unit":"Wh",
+        "values":[
+            {"date":"2023-11-30 22:00:00","value":null},
+            {"date":"2023-11-30 23:00:00","value":null},
+            {"date":"2023-12-01 10:00:00","value":250.0},
+            {"date":"2023-12-01 11:00:00","value":750.0},
+            {"date":"2023-12-02 11:00:00","value":500.0}
+        ]
+    }}
+    "#;
+
+    let energy: GeneratedEnergyReply = serde_json::from_str(reply).unwrap();
+    let energy = energy.energy;
+    assert_eq!(watt_hours(1500.0), energy.total().unwrap());
+
+    let max = energy.max_value().unwrap().unwrap();
+    assert_eq!(Some(watt_hours(750.0)), max.value);
+    assert_eq!("2023-12-01 11:00:00", max.date.to_string());
+
+    let per_day = energy.sum_by_day().unwrap();
+    assert_eq!(3, per_day.len());
+    assert_eq!(None, per_day[0].value);
+    assert_eq!(Some(watt_hours(1000.0)), per_day[1].value);
+    assert_eq!("2023-12-01 00:00:00", per_day[1].date.to_string());
+
+    let per_month = energy.sum_by_month().unwrap();
+    assert_eq!(2, per_month.len());
+    assert_eq!(Some(watt_hours(1500.0)), per_month[1].value);
+}
+
+#[test]
+fn test_series_as_collection() {
+    let reply = r#"
+    {"power":{
+        "timeUnit":"QUARTER_OF_AN_HOUR",
+        "unit":"W",
+        "values":[
+            {"date":"2023-11-09 12:15:00","value":761.538},
+            {"date":"2023-11-09 12:30:00","value":null}
+        ]
+    }}
+    "#;
+
+    let power = serde_json::from_str::<GeneratedPowerReply>(reply)
+        .unwrap()
+        .power;
+    assert_eq!(2, power.len());
+    assert!(!power.is_empty());
+    assert_eq!(None, power.get(1).unwrap().unwrap().value);
+    assert!(power.get(2).unwrap().is_none());
+
+    let mut count = 0;
+    for value in &power {
+        assert!(value.is_ok());
+        count += 1;
+    }
+    assert_eq!(2, count);
+
+    let owned: Result<Vec<GeneratedPowerValue>, _> = power.into_iter().collect();
+    assert_eq!(Some(watts(761.538)), owned.unwrap()[0].value);
+}
+
+#[test]
+fn test_data_period_constructors() {
+    let date = |s| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+
+    let february = DataPeriod::month(2024, 2).unwrap();
+    assert_eq!(Some(date("2024-02-01")), february.start_date);
+    assert_eq!(Some(date("2024-02-29")), february.end_date);
+    assert_eq!(
+        Some(date("2023-12-31")),
+        DataPeriod::month(2023, 12).unwrap().end_date
+    );
+    assert!(DataPeriod::month(2024, 13).is_none());
+
+    let year = DataPeriod::year(2023).unwrap();
+    assert_eq!(Some("2023-01-01"), year.formatted_start_date().as_deref());
+    assert_eq!(Some("2023-12-31"), year.formatted_end_date().as_deref());
+
+    assert!(DataPeriod::between(date("2023-01-02"), date("2023-01-01")).is_err());
+    assert!(DataPeriod::between(date("2023-01-01"), date("2023-01-01")).is_ok());
+
+    let (start_date, end_date) = DataPeriod::last_n_days(7).bounds().unwrap();
+    assert_eq!(chrono::Duration::days(6), end_date - start_date);
+    assert_eq!(DataPeriod::today(), DataPeriod::last_n_days(1));
+    assert_eq!(
+        DataPeriod::today().start_date.unwrap() - chrono::Duration::days(1),
+        DataPeriod::yesterday().start_date.unwrap()
+    );
+}
+
+#[test]
+fn test_validate_period() {
+    let date = |s| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let period = |start, end| DataPeriod::between(date(start), date(end)).unwrap();
+
+    assert!(period("2023-01-15", "2023-02-14")
+        .validate_for(TimeUnit::Hour)
+        .is_ok());
+    assert!(matches!(
+        period("2023-01-15", "2023-02-15").validate_for(TimeUnit::QuarterOfAnHour),
+        Err(SolarApiError::InvalidPeriod { max_months: 1, .. })
+    ));
+    // exactly one year ends on the last day before the same date next year
+    assert!(period("2023-01-01", "2023-12-31")
+        .validate_for(TimeUnit::Day)
+        .is_ok());
+    assert!(matches!(
+        period("2023-01-01", "2024-01-01").validate_for(TimeUnit::Day),
+        Err(SolarApiError::InvalidPeriod { max_months: 12, .. })
+    ));
+    assert!(period("2023-01-01", "2024-01-02")
+        .validate_for(TimeUnit::Day)
+        .is_err());
+    assert!(period("2010-01-01", "2024-01-02")
+        .validate_for(TimeUnit::Month)
+        .is_ok());
+}
+
+#[test]
+fn test_split_period() {
+    let date = |s| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let period = DataPeriod::between(date("2023-01-15"), date("2023-04-01")).unwrap();
+
+    let periods = period.split_for(TimeUnit::Hour);
+    assert_eq!(3, periods.len());
+    assert_eq!(Some(date("2023-01-15")), periods[0].start_date);
+    assert_eq!(Some(date("2023-02-14")), periods[0].end_date);
+    assert_eq!(Some(date("2023-02-15")), periods[1].start_date);
+    assert_eq!(Some(date("2023-03-15")), periods[2].start_date);
+    assert_eq!(Some(date("2023-04-01")), periods[2].end_date);
+    assert!(periods
+        .iter()
+        .all(|p| p.validate_for(TimeUnit::Hour).is_ok()));
+
+    assert_eq!(vec![period], period.split_for(TimeUnit::Day));
+    assert_eq!(vec![period], period.split_for(TimeUnit::Month));
+}
+
+#[test]
+fn test_append_energy() {
+    let parse = |unit: &str, date: &str| {
+        let reply = format!(
+            r#"{{"energy":{{"timeUnit":"DAY","unit":"{unit}","values":[{{"date":"{date} 00:00:00","value":1.5}}]}}}}"#
+        );
+        serde_json::from_str::<GeneratedEnergyReply>(&reply)
+            .unwrap()
+            .energy
+    };
+
+    let mut energy = parse("Wh", "2023-01-01");
+    energy.append(parse("kWh", "2023-01-02")).unwrap();
+    let values = energy.values().unwrap();
+    assert_eq!(2, values.len());
+    assert_eq!(Some(watt_hours(1500.0)), values[1].value);
+}
+
+#[test]
+fn test_power_windows() {
+    let dt = |s: &str| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+
+    let windows = power_windows(dt("2023-01-15 12:00:00"), dt("2023-03-01 00:00:00"));
+    assert_eq!(2, windows.len());
+    assert_eq!(dt("2023-02-15 12:00:00"), windows[0].1);
+    assert_eq!(windows[0].1, windows[1].0);
+    assert_eq!(dt("2023-03-01 00:00:00"), windows[1].1);
+
+    let same = power_windows(dt("2023-01-15 12:00:00"), dt("2023-01-15 13:00:00"));
+    assert_eq!(1, same.len());
+}
+
+#[test]
+fn test_append_power_deduplicates() {
+    let parse = |values: &str| {
+        let reply = format!(
+            r#"{{"power":{{"timeUnit":"QUARTER_OF_AN_HOUR","unit":"W","values":[{values}]}}}}"#
+        );
+        serde_json::from_str::<GeneratedPowerReply>(&reply)
+            .unwrap()
+            .power
+    };
+
+    let mut power = parse(
+        r#"{"date":"2023-11-09 12:15:00","value":1.0},{"date":"2023-11-09 12:30:00","value":2.0}"#,
+    );
+    power
+        .append(parse(
+            r#"{"date":"2023-11-09 12:30:00","value":2.0},{"date":"2023-11-09 12:45:00","value":3.0}"#,
+        ))
+        .unwrap();
+    let values = power.values().unwrap();
+    assert_eq!(3, values.len());
+    assert_eq!(Some(watts(3.0)), values[2].value);
+}
+
+#[test]
+fn test_round_trip_api_json() {
+    let replies = [
+        r#"{"sites":{"count":1,"site":[{"id":1234123,"name":"MySiteName","accountId":123456,
+            "status":"Active","peakPower":7.5,"lastUpdateTime":"2021-04-29",
+            "installationDate":"2021-02-25","ptoDate":null,"notes":"","type":"Optimizers & Inverters",
+            "location":{"country":"Netherlands","city":"A city","address":"Some address","zip":"zipy",
+            "timeZone":"Europe/Amsterdam","countryCode":"NL"},
+            "primaryModule":{"manufacturerName":"JinkoSolar","modelName":"390","maximumPower":0.0,
+            "temperatureCoef":0.0},"uris":{"DETAILS":"/site/1234123/details"},
+            "publicSettings":{"isPublic":false}}]}}"#,
+        r#"{"dataPeriod":{"startDate":"2021-02-25","endDate":"2021-05-03"}}"#,
+        r#"{"overview":{"lastUpdateTime":"2023-11-09 10:28:56","lifeTimeData":{"energy":1.9191678E7,"revenue":12.5},
+            "lastYearData":{"energy":6143745.0,"revenue":null},"lastMonthData":{"energy":38709.0,"revenue":null},
+            "lastDayData":{"energy":2028.0,"revenue":null},"currentPower":{"power":1173.5},"measuredBy":"INVERTER"}}"#,
+        r#"{"energy":{"timeUnit":"DAY","unit":"kWh","measuredBy":"INVERTER",
+            "values":[{"date":"2023-11-09 00:00:00","value":12.5},{"date":"2023-11-10 00:00:00","value":null}]}}"#,
+        r#"{"power":{"timeUnit":"QUARTER_OF_AN_HOUR","unit":"W",
+            "values":[{"date":"2023-11-09 10:00:00","value":1500.0},{"date":"2023-11-09 10:15:00","value":null}]}}"#,
+    ];
+    let expected: Vec<serde_json::Value> = replies
+        .iter()
+        .map(|reply| serde_json::from_str(reply).unwrap())
+        .collect();
+
+    let sites: SitesReply = serde_json::from_str(replies[0]).unwrap();
+    assert_eq!(expected[0], Site::list_to_api_json(sites.sites()));
+    let period: DataPeriodReply = serde_json::from_str(replies[1]).unwrap();
+    assert_eq!(expected[1], period.data_period.to_api_json());
+    let overview: OverviewReply = serde_json::from_str(replies[2]).unwrap();
+    assert_eq!(expected[2], overview.overview.to_api_json());
+    let energy: GeneratedEnergyReply = serde_json::from_str(replies[3]).unwrap();
+    assert_eq!(expected[3], energy.energy.to_api_json());
+    let power: GeneratedPowerReply = serde_json::from_str(replies[4]).unwrap();
+    assert_eq!(expected[4], power.power.to_api_json());
 }
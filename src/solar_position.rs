@@ -0,0 +1,161 @@
+//! Calculates sunrise, solar noon and sunset from the coordinates of a site
+//! using the sunrise equation, accurate to about a minute. Only available with
+//! the `solar-position` feature. The daylight window can be used by
+//! [`ZeroProductionDetector`](crate::anomalies::ZeroProductionDetector)
+
+use crate::anomalies::{DaylightSource, DaylightWindow};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+// the julian day of 2000-01-01 12:00 UTC
+const J2000: f64 = 2451545.0;
+// the julian day of 1970-01-01 00:00 UTC
+const UNIX_EPOCH: f64 = 2440587.5;
+// the altitude of the center of the sun at sunrise, corrected for refraction
+// and the radius of the sun
+const SUNRISE_ALTITUDE: f64 = -0.833;
+// the tilt of the axis of the earth
+const OBLIQUITY: f64 = 23.4397;
+
+/// Sunrise, solar noon and sunset of a day in the local time of a site, see
+/// [`Sun::times`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SunTimes {
+    /// None when the sun does not rise or set on this day
+    pub sunrise: Option<NaiveDateTime>,
+    /// the moment the sun is highest
+    pub solar_noon: NaiveDateTime,
+    /// None when the sun does not rise or set on this day
+    pub sunset: Option<NaiveDateTime>,
+    /// true when the sun does not set on this day, i.e. the midnight sun. When
+    /// false and there is no sunrise, the sun does not rise on this day
+    pub always_up: bool,
+}
+
+/// The sun as seen from a site at `latitude` and `longitude` in degrees, with
+/// north and east positive, in time zone `tz`.
+///
+/// ```
+/// # use solar_api::solar_position::Sun;
+/// # use solar_api::anomalies::DaylightSource;
+/// # use chrono::{FixedOffset, NaiveDate};
+/// let amsterdam = Sun::new(52.37, 4.90, FixedOffset::east_opt(2 * 3600).unwrap());
+/// let date = NaiveDate::from_ymd_opt(2023, 6, 21).unwrap();
+/// let window = amsterdam.daylight_window(date).unwrap();
+/// assert_eq!("05", window.start.format("%H").to_string());
+/// assert_eq!("22", window.end.format("%H").to_string());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sun<Tz: TimeZone> {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub tz: Tz,
+}
+
+impl<Tz: TimeZone> Sun<Tz> {
+    /// Create the sun as seen from `latitude` and `longitude`, using `tz` for
+    /// the local times
+    pub fn new(latitude: f64, longitude: f64, tz: Tz) -> Self {
+        Sun {
+            latitude,
+            longitude,
+            tz,
+        }
+    }
+
+    /// The sunrise, solar noon and sunset on `date`
+    pub fn times(&self, date: NaiveDate) -> SunTimes {
+        let j2000 = NaiveDate::from_ymd_opt(2000, 1, 1).expect("valid date");
+        // the mean solar noon at the longitude of the site
+        let mean_noon = (date - j2000).num_days() as f64 - self.longitude / 360.0;
+        let anomaly = (357.5291 + 0.98560028 * mean_noon).rem_euclid(360.0);
+        let m = anomaly.to_radians();
+        let center = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+        let ecliptic_longitude = (anomaly + center + 180.0 + 102.9372)
+            .rem_euclid(360.0)
+            .to_radians();
+        let transit =
+            J2000 + mean_noon + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+        let declination = (ecliptic_longitude.sin() * OBLIQUITY.to_radians().sin()).asin();
+
+        let latitude = self.latitude.to_radians();
+        let cos_hour_angle = (SUNRISE_ALTITUDE.to_radians().sin()
+            - latitude.sin() * declination.sin())
+            / (latitude.cos() * declination.cos());
+        let (sunrise, sunset) = if (-1.0..=1.0).contains(&cos_hour_angle) {
+            let hour_angle = cos_hour_angle.acos().to_degrees() / 360.0;
+            (
+                Some(self.local(transit - hour_angle)),
+                Some(self.local(transit + hour_angle)),
+            )
+        } else {
+            (None, None)
+        };
+        SunTimes {
+            sunrise,
+            solar_noon: self.local(transit),
+            sunset,
+            always_up: cos_hour_angle < -1.0,
+        }
+    }
+
+    // the local time of the site at `julian_day`
+    fn local(&self, julian_day: f64) -> NaiveDateTime {
+        let millis = ((julian_day - UNIX_EPOCH) * 86_400_000.0).round() as i64;
+        let utc: DateTime<Utc> = DateTime::UNIX_EPOCH + Duration::milliseconds(millis);
+        utc.with_timezone(&self.tz).naive_local()
+    }
+}
+
+/// The period from sunrise until sunset, the whole day when the sun does not
+/// set and None when the sun does not rise
+impl<Tz: TimeZone> DaylightSource for Sun<Tz> {
+    fn daylight_window(&self, date: NaiveDate) -> Option<DaylightWindow> {
+        let times = self.times(date);
+        match (times.sunrise, times.sunset) {
+            (Some(start), Some(end)) => Some(DaylightWindow { start, end }),
+            _ if times.always_up => Some(DaylightWindow {
+                start: date.and_time(chrono::NaiveTime::MIN),
+                end: date.and_time(chrono::NaiveTime::MIN) + Duration::days(1),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "chrono-tz")]
+impl crate::Site {
+    /// The sun as seen from this site, using the coordinates and time zone of
+    /// its location. Returns None when the location has no coordinates
+    pub fn sun(&self) -> Result<Option<Sun<chrono_tz::Tz>>, crate::SolarApiError> {
+        let tz = self.location.tz()?;
+        Ok(self
+            .location
+            .latitude
+            .zip(self.location.longitude)
+            .map(|(latitude, longitude)| Sun::new(latitude, longitude, tz)))
+    }
+}
+
+#[test]
+fn test_sun_times() {
+    let date = |m: u32, d: u32| NaiveDate::from_ymd_opt(2023, m, d).unwrap();
+    // rounded to the nearest minute
+    let hm = |datetime: Option<NaiveDateTime>| {
+        datetime.map(|d| (d + Duration::seconds(30)).format("%H:%M").to_string())
+    };
+
+    // Amsterdam in winter time
+    let amsterdam = Sun::new(52.37, 4.90, chrono::FixedOffset::east_opt(3600).unwrap());
+    let times = amsterdam.times(date(12, 21));
+    assert_eq!(Some("08:48".to_string()), hm(times.sunrise));
+    assert_eq!(Some("16:29".to_string()), hm(times.sunset));
+    assert_eq!(Some("12:38".to_string()), hm(Some(times.solar_noon)));
+
+    // Tromsø has midnight sun in June and polar night in December
+    let tromso = Sun::new(69.65, 18.96, Utc);
+    let summer = tromso.times(date(6, 21));
+    assert!(summer.always_up && summer.sunrise.is_none());
+    let window = tromso.daylight_window(date(6, 21)).unwrap();
+    assert_eq!(Duration::days(1), window.end - window.start);
+    assert!(tromso.daylight_window(date(12, 21)).is_none());
+}
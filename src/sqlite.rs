@@ -0,0 +1,254 @@
+//! Writes series to a SQLite table, see [`SqliteSink`]. Requires the `sqlite`
+//! feature, which bundles SQLite.
+//!
+//! The table has a row per [`Reading`] of the normalized
+//! [`schema`](crate::export::schema) with the columns `time` (text like
+//! `2023-06-01 12:00:00`, in the local time of the site), `site_id`
+//! (integer), `channel` (text), `value` (real, null when the API had no
+//! value), `unit` (text) and `quality` (text). The primary key is
+//! `(site_id, channel, time)`, so writing a value again replaces it. The
+//! version of the schema is the `user_version` of the database.
+//!
+//! ```no_run
+//! # use solar_api::sqlite::SqliteSink;
+//! # use solar_api::{Client, DataPeriod, TimeUnit};
+//! # use solar_api::chrono::NaiveDate;
+//! let client = Client::new("API_KEY");
+//! let period = DataPeriod::between(
+//!     NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(),
+//!     NaiveDate::from_ymd_opt(2023, 6, 30).unwrap(),
+//! )?;
+//! let energy = client.energy(1234, period, TimeUnit::Day)?.to_series()?;
+//! let db = solar_api::rusqlite::Connection::open("solar.db").unwrap();
+//! let sink = SqliteSink::new(&db);
+//! sink.create_table()?;
+//! sink.write_energy(1234, "energy", &energy)?;
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use crate::{
+    export::schema::{self, Reading, SCHEMA_VERSION},
+    meters::{EnergyDetails, PowerDetails},
+    series::TimeSeries,
+    storage::StorageData,
+    units::{Energy, Power},
+    SolarApiError,
+};
+#[cfg(test)]
+use crate::{series::TimeSeriesValue, site::TimeUnit, units::watt_hours};
+#[cfg(test)]
+use chrono::NaiveDateTime;
+use log::debug;
+use rusqlite::{params, Connection};
+use std::fmt;
+
+/// Writes series to a table in one transaction per write, replacing existing
+/// values
+pub struct SqliteSink<'a> {
+    connection: &'a Connection,
+    table: String,
+}
+
+impl fmt::Debug for SqliteSink<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqliteSink")
+            .field("table", &self.table)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> SqliteSink<'a> {
+    /// Create a sink that writes to the table `solar_readings` with
+    /// `connection`
+    pub fn new(connection: &'a Connection) -> Self {
+        SqliteSink {
+            connection,
+            table: "solar_readings".to_string(),
+        }
+    }
+
+    /// Set the table
+    pub fn with_table(mut self, table: &str) -> Self {
+        self.table = table.to_string();
+        self
+    }
+
+    /// Create the table when it does not exist and set the `user_version` of
+    /// a new database to the version of the schema
+    pub fn create_table(&self) -> Result<(), SolarApiError> {
+        let statement = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                time TEXT NOT NULL,
+                site_id INTEGER NOT NULL,
+                channel TEXT NOT NULL,
+                value REAL,
+                unit TEXT NOT NULL,
+                quality TEXT NOT NULL,
+                PRIMARY KEY (site_id, channel, time)
+            )",
+            quote_identifier(&self.table)
+        );
+        self.connection
+            .execute_batch(&statement)
+            .map_err(SolarApiError::SqliteError)?;
+        let version: u32 = self
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(SolarApiError::SqliteError)?;
+        if version == 0 {
+            self.connection
+                .execute_batch(&format!("PRAGMA user_version = {SCHEMA_VERSION}"))
+                .map_err(SolarApiError::SqliteError)?;
+        }
+        Ok(())
+    }
+
+    /// Write the values of `energy` of `site_id` in Wh and return the number
+    /// of written rows
+    pub fn write_energy(
+        &self,
+        site_id: u32,
+        channel: &str,
+        energy: &TimeSeries<Energy>,
+    ) -> Result<usize, SolarApiError> {
+        self.write_readings(&schema::energy_readings(site_id, channel, energy))
+    }
+
+    /// Write the values of `power` of `site_id` in W and return the number of
+    /// written rows
+    pub fn write_power(
+        &self,
+        site_id: u32,
+        channel: &str,
+        power: &TimeSeries<Power>,
+    ) -> Result<usize, SolarApiError> {
+        self.write_readings(&schema::power_readings(site_id, channel, power))
+    }
+
+    /// Write the series of every meter of `details`
+    pub fn write_energy_details(
+        &self,
+        site_id: u32,
+        details: &EnergyDetails,
+    ) -> Result<usize, SolarApiError> {
+        self.write_readings(&schema::energy_details_readings(site_id, details)?)
+    }
+
+    /// Write the series of every meter of `details`
+    pub fn write_power_details(
+        &self,
+        site_id: u32,
+        details: &PowerDetails,
+    ) -> Result<usize, SolarApiError> {
+        self.write_readings(&schema::power_details_readings(site_id, details)?)
+    }
+
+    /// Write the power and state of charge of every battery of `storage`
+    pub fn write_storage(
+        &self,
+        site_id: u32,
+        storage: &StorageData,
+    ) -> Result<usize, SolarApiError> {
+        self.write_readings(&schema::storage_readings(site_id, storage))
+    }
+
+    /// Write `readings` in one transaction and return the number of written
+    /// rows
+    pub fn write_readings(&self, readings: &[Reading]) -> Result<usize, SolarApiError> {
+        let statement = format!(
+            "INSERT OR REPLACE INTO {} (time, site_id, channel, value, unit, quality)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            quote_identifier(&self.table)
+        );
+        let transaction = self
+            .connection
+            .unchecked_transaction()
+            .map_err(SolarApiError::SqliteError)?;
+        let mut rows = 0;
+        {
+            let mut insert = transaction
+                .prepare_cached(&statement)
+                .map_err(SolarApiError::SqliteError)?;
+            for reading in readings {
+                rows += insert
+                    .execute(params![
+                        reading.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                        reading.site_id,
+                        reading.channel,
+                        reading.value,
+                        reading.unit,
+                        reading.quality.as_str(),
+                    ])
+                    .map_err(SolarApiError::SqliteError)?;
+            }
+        }
+        transaction.commit().map_err(SolarApiError::SqliteError)?;
+        debug!("Wrote {rows} rows to {}", self.table);
+        Ok(rows)
+    }
+}
+
+// quotes the name of a table as identifier
+fn quote_identifier(table: &str) -> String {
+    format!("\"{}\"", table.replace('"', "\"\""))
+}
+
+#[test]
+fn test_sqlite_sink() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let energy = |wh: f64| {
+        TimeSeries::new(
+            TimeUnit::Day,
+            "Wh",
+            vec![
+                TimeSeriesValue {
+                    date: date("2023-06-01 00:00:00"),
+                    value: Some(watt_hours(wh)),
+                },
+                TimeSeriesValue {
+                    date: date("2023-06-02 00:00:00"),
+                    value: None,
+                },
+            ],
+        )
+    };
+    let db = Connection::open_in_memory().unwrap();
+    let sink = SqliteSink::new(&db).with_table("solar readings");
+    sink.create_table().unwrap();
+    assert_eq!(
+        2,
+        sink.write_energy(1234, "energy", &energy(1000.0)).unwrap()
+    );
+    // writing a value again replaces it
+    assert_eq!(
+        2,
+        sink.write_energy(1234, "energy", &energy(1500.0)).unwrap()
+    );
+
+    let rows: Vec<(String, Option<f64>, String)> = db
+        .prepare("SELECT time, value, quality FROM \"solar readings\" ORDER BY time")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        vec![
+            (
+                "2023-06-01 00:00:00".to_string(),
+                Some(1500.0),
+                "measured".to_string()
+            ),
+            (
+                "2023-06-02 00:00:00".to_string(),
+                None,
+                "missing".to_string()
+            )
+        ],
+        rows
+    );
+    let version: u32 = db
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(SCHEMA_VERSION, version);
+}
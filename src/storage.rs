@@ -0,0 +1,188 @@
+#[cfg(test)]
+use crate::units::{as_watt_hours, watts};
+use crate::{
+    series::{TimeSeries, TimeSeriesValue},
+    site::{
+        parse_date_time, parse_energy_wh, parse_optional_energy_wh, parse_optional_power_w,
+        serialize_date_time, serialize_energy_wh, serialize_optional_energy_wh,
+        serialize_optional_power_w, TimeUnit,
+    },
+    units::{Energy, Power},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct StorageDataReply {
+    #[serde(rename = "storageData")]
+    pub(crate) storage_data: StorageData,
+}
+
+/// The telemetry of the batteries of a site, see [`crate::storage_data`]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageData {
+    pub battery_count: u32,
+    pub batteries: Vec<Battery>,
+}
+
+impl StorageData {
+    /// The battery with `serial_number` or None when the site has no such battery
+    pub fn battery(&self, serial_number: &str) -> Option<&Battery> {
+        self.batteries
+            .iter()
+            .find(|battery| battery.serial_number == serial_number)
+    }
+
+    /// The JSON of this reply as returned by [`crate::storage_data`]
+    pub fn to_api_json(&self) -> serde_json::Value {
+        serde_json::json!({ "storageData": self })
+    }
+}
+
+/// A battery of a site with its telemetry
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Battery {
+    /// the nameplate capacity of the battery
+    #[serde(
+        deserialize_with = "parse_energy_wh",
+        serialize_with = "serialize_energy_wh"
+    )]
+    pub nameplate: Energy,
+    pub serial_number: String,
+    #[serde(default)]
+    pub model_number: String,
+    #[serde(default)]
+    pub telemetry_count: u32,
+    #[serde(default)]
+    pub telemetries: Vec<BatteryTelemetry>,
+}
+
+impl Battery {
+    /// The telemetry ordered by timestamp
+    pub fn sorted_telemetries(&self) -> Vec<&BatteryTelemetry> {
+        let mut telemetries: Vec<_> = self.telemetries.iter().collect();
+        telemetries.sort_by_key(|telemetry| telemetry.timestamp);
+        telemetries
+    }
+
+    /// The state of charge in percent per timestamp
+    pub fn state_of_charge(&self) -> TimeSeries<f64> {
+        let values = self
+            .sorted_telemetries()
+            .into_iter()
+            .map(|telemetry| TimeSeriesValue {
+                date: telemetry.timestamp,
+                value: telemetry.battery_percentage_state,
+            })
+            .collect();
+        TimeSeries::new(TimeUnit::QuarterOfAnHour, "%", values)
+    }
+
+    /// The power per timestamp. Positive values charge the battery, negative
+    /// values discharge it
+    pub fn power(&self) -> TimeSeries<Power> {
+        let values = self
+            .sorted_telemetries()
+            .into_iter()
+            .map(|telemetry| TimeSeriesValue {
+                date: telemetry.timestamp,
+                value: telemetry.power,
+            })
+            .collect();
+        TimeSeries::new(TimeUnit::QuarterOfAnHour, "W", values)
+    }
+}
+
+/// A measurement of a battery
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatteryTelemetry {
+    #[serde(
+        rename = "timeStamp",
+        deserialize_with = "parse_date_time",
+        serialize_with = "serialize_date_time"
+    )]
+    pub timestamp: chrono::NaiveDateTime,
+    /// positive when charging, negative when discharging
+    #[serde(
+        default,
+        deserialize_with = "parse_optional_power_w",
+        serialize_with = "serialize_optional_power_w"
+    )]
+    pub power: Option<Power>,
+    #[serde(default)]
+    pub battery_state: Option<u32>,
+    /// the energy discharged during the lifetime of the battery
+    #[serde(
+        rename = "lifeTimeEnergyDischarged",
+        deserialize_with = "parse_energy_wh",
+        serialize_with = "serialize_energy_wh"
+    )]
+    pub lifetime_energy_discharged: Energy,
+    /// the energy charged during the lifetime of the battery
+    #[serde(
+        rename = "lifeTimeEnergyCharged",
+        deserialize_with = "parse_energy_wh",
+        serialize_with = "serialize_energy_wh"
+    )]
+    pub lifetime_energy_charged: Energy,
+    /// the energy the battery can store when fully charged
+    #[serde(
+        default,
+        deserialize_with = "parse_optional_energy_wh",
+        serialize_with = "serialize_optional_energy_wh"
+    )]
+    pub full_pack_energy_available: Option<Energy>,
+    /// the internal temperature in degrees Celsius
+    #[serde(default)]
+    pub internal_temp: Option<f64>,
+    /// the energy charged from the grid
+    #[serde(
+        rename = "ACGridCharging",
+        default,
+        deserialize_with = "parse_optional_energy_wh",
+        serialize_with = "serialize_optional_energy_wh"
+    )]
+    pub ac_grid_charging: Option<Energy>,
+    /// the state of charge in percent
+    #[serde(default)]
+    pub battery_percentage_state: Option<f64>,
+}
+
+#[test]
+fn test_storage_data() {
+    let reply = r#"
+    {"storageData":{
+        "batteryCount":1,
+        "batteries":[{
+            "nameplate":10000.0,
+            "serialNumber":"BAT-1",
+            "modelNumber":"LG RESU",
+            "telemetryCount":2,
+            "telemetries":[
+                {"timeStamp":"2023-06-01 12:15:00","power":-500.0,"batteryState":4,
+                 "lifeTimeEnergyDischarged":1500.0,"lifeTimeEnergyCharged":3000.0,
+                 "fullPackEnergyAvailable":9800.0,"internalTemp":25.0,
+                 "ACGridCharging":0.0,"batteryPercentageState":55.0},
+                {"timeStamp":"2023-06-01 12:00:00","power":1000.0,"batteryState":3,
+                 "lifeTimeEnergyDischarged":1000.0,"lifeTimeEnergyCharged":3000.0,
+                 "batteryPercentageState":60.0}
+            ]}]}}
+    "#;
+    let data = serde_json::from_str::<StorageDataReply>(reply)
+        .unwrap()
+        .storage_data;
+
+    let battery = data.battery("BAT-1").unwrap();
+    assert_eq!(10000.0, as_watt_hours(battery.nameplate));
+    assert_eq!(Some(60.0), battery.state_of_charge().values[0].value);
+    assert_eq!(Some(watts(-500.0)), battery.power().values[1].value);
+    assert!(data.battery("BAT-2").is_none());
+
+    let json: serde_json::Value = serde_json::from_str(reply).unwrap();
+    assert_eq!(
+        json["storageData"]["batteries"][0]["telemetries"][0],
+        data.to_api_json()["storageData"]["batteries"][0]["telemetries"][0]
+    );
+}
@@ -0,0 +1,187 @@
+#[cfg(test)]
+use crate::units::{as_watt_hours, watt_hours, watts};
+use crate::{
+    client::Client,
+    series::{Aggregation, TimeSeries, TimeSeriesValue},
+    site::{DataPeriod, TimeUnit},
+    units::{Energy, Power},
+    SolarApiError,
+};
+use log::debug;
+
+/// Totals, averages and extremes of the production of a site in a period,
+/// created using [`Client::summary`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// the period of the summary
+    pub period: DataPeriod,
+    /// the time unit used for `average`
+    pub time_unit: TimeUnit,
+    /// the energy per day
+    pub daily_energy: TimeSeries<Energy>,
+    /// the total energy in the period
+    pub total: Energy,
+    /// the average energy per `time_unit`, i.e. per month. None when there
+    /// was no energy in the period
+    pub average: Option<Energy>,
+    /// the day with the most energy
+    pub best_day: Option<TimeSeriesValue<Energy>>,
+    /// the day with the least energy, skipping days without a value
+    pub worst_day: Option<TimeSeriesValue<Energy>>,
+    /// the highest power measured in the period. None when the power was not
+    /// requested or there was no power in the period
+    pub peak_power: Option<TimeSeriesValue<Power>>,
+}
+
+impl Summary {
+    /// Creates a summary from the energy per day and optionally the power of
+    /// the period, without calling the API
+    pub fn from_series(
+        period: DataPeriod,
+        time_unit: TimeUnit,
+        daily_energy: TimeSeries<Energy>,
+        power: Option<&TimeSeries<Power>>,
+    ) -> Self {
+        let buckets = daily_energy.resample(time_unit, Aggregation::Sum);
+        let count = buckets.iter().filter(|v| v.value.is_some()).count();
+        let average = (count > 0).then(|| buckets.total() / count as f64);
+        Summary {
+            period,
+            time_unit,
+            total: daily_energy.total(),
+            average,
+            best_day: daily_energy.max_value(),
+            worst_day: daily_energy.min_value(),
+            peak_power: power.and_then(|power| power.max_value()),
+            daily_energy,
+        }
+    }
+}
+
+/// Builder for a [`Summary`], created using [`Client::summary`]
+#[derive(Debug, Clone)]
+pub struct SummaryBuilder<'a> {
+    client: &'a Client,
+    site_id: u32,
+    period: DataPeriod,
+    time_unit: TimeUnit,
+    with_power: bool,
+}
+
+impl SummaryBuilder<'_> {
+    /// Set the [`TimeUnit`] used for the average, i.e. [`TimeUnit::Month`] for
+    /// the average energy per month. The default is [`TimeUnit::Day`]
+    pub fn with_time_unit(mut self, time_unit: TimeUnit) -> Self {
+        self.time_unit = time_unit;
+        self
+    }
+
+    /// Do not request the power of the period to determine the peak power.
+    /// This saves one request per month of the period
+    pub fn without_power(mut self) -> Self {
+        self.with_power = false;
+        self
+    }
+
+    /// Requests the energy per day and the power of the period, split into
+    /// requests that satisfy the limits of the API, and creates the [`Summary`]
+    pub fn fetch(self) -> Result<Summary, SolarApiError> {
+        let (start_date, end_date) = self.period.bounds()?;
+        debug!(
+            "Getting summary of {} for {}-{}",
+            self.site_id, start_date, end_date
+        );
+        let energy = self
+            .client
+            .energy_chunked(self.site_id, self.period, TimeUnit::Day)?
+            .to_series()?;
+        let power = if self.with_power {
+            let start = start_date.and_hms_opt(0, 0, 0).unwrap_or_default();
+            let end = (end_date + chrono::Days::new(1))
+                .and_hms_opt(0, 0, 0)
+                .unwrap_or_default();
+            Some(
+                self.client
+                    .power_chunked(self.site_id, start, end)?
+                    .to_series()?,
+            )
+        } else {
+            None
+        };
+        Ok(Summary::from_series(
+            self.period,
+            self.time_unit,
+            energy,
+            power.as_ref(),
+        ))
+    }
+}
+
+impl Client {
+    /// Create a [`SummaryBuilder`] for the production of site `site_id` in
+    /// `period`. Call [`SummaryBuilder::fetch`] to request the data
+    pub fn summary(&self, site_id: u32, period: DataPeriod) -> SummaryBuilder<'_> {
+        SummaryBuilder {
+            client: self,
+            site_id,
+            period,
+            time_unit: TimeUnit::Day,
+            with_power: true,
+        }
+    }
+}
+
+#[test]
+fn test_summary_from_series() {
+    let date = |s: &str| {
+        chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    };
+    let energy = TimeSeries::new(
+        TimeUnit::Day,
+        "Wh",
+        [
+            ("2023-05-31", Some(6000.0)),
+            ("2023-06-01", Some(10000.0)),
+            ("2023-06-02", None),
+            ("2023-06-03", Some(2000.0)),
+        ]
+        .iter()
+        .map(|(d, value)| TimeSeriesValue {
+            date: date(d),
+            value: value.map(watt_hours),
+        })
+        .collect(),
+    );
+    let power = TimeSeries::new(
+        TimeUnit::QuarterOfAnHour,
+        "W",
+        vec![TimeSeriesValue {
+            date: date("2023-06-01") + chrono::Duration::hours(12),
+            value: Some(watts(4500.0)),
+        }],
+    );
+    let period = DataPeriod::between(
+        chrono::NaiveDate::from_ymd_opt(2023, 5, 31).unwrap(),
+        chrono::NaiveDate::from_ymd_opt(2023, 6, 3).unwrap(),
+    )
+    .unwrap();
+
+    let summary = Summary::from_series(period, TimeUnit::Month, energy, Some(&power));
+    assert_eq!(18000.0, as_watt_hours(summary.total));
+    assert_eq!(Some(9000.0), summary.average.map(as_watt_hours));
+    assert_eq!(date("2023-06-01"), summary.best_day.unwrap().date);
+    assert_eq!(date("2023-06-03"), summary.worst_day.unwrap().date);
+    assert_eq!(Some(watts(4500.0)), summary.peak_power.unwrap().value);
+
+    let empty = Summary::from_series(
+        period,
+        TimeUnit::Day,
+        TimeSeries::new(TimeUnit::Day, "Wh", vec![]),
+        None,
+    );
+    assert_eq!(None, empty.average);
+    assert_eq!(None, empty.best_day);
+}
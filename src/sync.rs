@@ -0,0 +1,591 @@
+//! Fetches only the data that is new since the last stored timestamp, for
+//! collectors that store the data of a site in a database, see
+//! [`Client::sync_energy`]. A [`SyncState`] keeps these timestamps and the
+//! number of requests made today in a JSON file, so a collector can continue
+//! after a restart. [`Client::backfill`] downloads the whole history of a site
+//! with the same state
+
+use crate::{
+    client::Client,
+    series::TimeSeries,
+    site::{
+        parse_date, parse_date_time, serialize_date, serialize_date_time, DataPeriod,
+        GeneratedEnergy, TimeUnit,
+    },
+    units::Energy,
+    SolarApiError,
+};
+#[cfg(test)]
+use crate::{series::TimeSeriesValue, units::watt_hours};
+use chrono::{NaiveDate, NaiveDateTime};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The number of requests per day that the API allows for an account or a
+/// site
+pub const DAILY_REQUEST_LIMIT: u32 = 300;
+
+/// The period to request to get the buckets of `time_unit` after the bucket
+/// starting at `last_seen` until `until`, both in the local time of the site.
+/// Returns None when there is nothing new to request. The period may exceed
+/// the limits of the API, use [`DataPeriod::split_for`] to split it
+pub fn sync_period(
+    last_seen: NaiveDateTime,
+    until: NaiveDateTime,
+    time_unit: TimeUnit,
+) -> Option<DataPeriod> {
+    let start = time_unit.bucket_end(last_seen);
+    (start < until)
+        .then(|| DataPeriod::between(start.date(), until.date()).ok())
+        .flatten()
+}
+
+/// The values of `energy` after `last_seen` whose bucket ended at `until`, so
+/// buckets that are still counting are left out. Values without energy at the
+/// end are left out as well, as the site may still send them, i.e. when it was
+/// offline for a while
+pub fn new_values(
+    energy: &TimeSeries<Energy>,
+    last_seen: NaiveDateTime,
+    until: NaiveDateTime,
+) -> TimeSeries<Energy> {
+    let mut values: Vec<_> = energy
+        .iter()
+        .filter(|v| v.date > last_seen && energy.time_unit.bucket_end(v.date) <= until)
+        .copied()
+        .collect();
+    values.sort_by_key(|v| v.date);
+    let end = values
+        .iter()
+        .rposition(|v| v.value.is_some())
+        .map_or(0, |last| last + 1);
+    values.truncate(end);
+    TimeSeries::new(energy.time_unit, &energy.unit, values)
+}
+
+/// The last synced timestamp per site and endpoint and the number of requests
+/// made today. Endpoints are identified by a name of your choice, i.e.
+/// [`SyncState::energy_endpoint`] for [`Client::sync_energy_with_state`].
+///
+/// ```no_run
+/// # use solar_api::sync::SyncState;
+/// let mut state = SyncState::load("sync.json")?;
+/// // sync the data of the site
+/// state.save("sync.json")?;
+/// # Ok::<(), solar_api::SolarApiError>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncState {
+    #[serde(default)]
+    cursors: Vec<SyncCursor>,
+    #[serde(default)]
+    budget: Option<RequestBudget>,
+}
+
+// the last synced timestamp of an endpoint of a site
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SyncCursor {
+    site_id: u32,
+    endpoint: String,
+    #[serde(
+        deserialize_with = "parse_date_time",
+        serialize_with = "serialize_date_time"
+    )]
+    last_seen: NaiveDateTime,
+}
+
+/// The number of requests made on a day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RequestBudget {
+    #[serde(deserialize_with = "parse_date", serialize_with = "serialize_date")]
+    pub date: NaiveDate,
+    pub requests: u32,
+}
+
+impl SyncState {
+    /// Create an empty state
+    pub fn new() -> Self {
+        SyncState::default()
+    }
+
+    /// Read the state from the JSON file at `path`. Returns an empty state when
+    /// the file does not exist
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SolarApiError> {
+        let path = path.as_ref();
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(SyncState::default())
+            }
+            Err(source) => {
+                return Err(SolarApiError::SyncStateIo {
+                    path: path.display().to_string(),
+                    source,
+                })
+            }
+        };
+        serde_json::from_str(&json).map_err(|source| SolarApiError::InvalidSyncState {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+
+    /// Write the state as JSON to `path`. The state is written to a temporary
+    /// file first, so the file at `path` is never half written
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SolarApiError> {
+        let path = path.as_ref();
+        let io_error = |source| SolarApiError::SyncStateIo {
+            path: path.display().to_string(),
+            source,
+        };
+        let json = serde_json::to_string_pretty(self).map_err(|source| {
+            SolarApiError::InvalidSyncState {
+                path: path.display().to_string(),
+                source,
+            }
+        })?;
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, json).map_err(io_error)?;
+        std::fs::rename(&temporary, path).map_err(io_error)
+    }
+
+    /// The name of the endpoint of [`Client::sync_energy_with_state`] for
+    /// `time_unit`, i.e. `energy:DAY`
+    pub fn energy_endpoint(time_unit: TimeUnit) -> String {
+        format!("energy:{}", time_unit.to_param())
+    }
+
+    /// The last synced timestamp of `endpoint` of `site_id`, or None when it
+    /// was not synced yet
+    pub fn last_seen(&self, site_id: u32, endpoint: &str) -> Option<NaiveDateTime> {
+        self.cursors
+            .iter()
+            .find(|cursor| cursor.site_id == site_id && cursor.endpoint == endpoint)
+            .map(|cursor| cursor.last_seen)
+    }
+
+    /// Set the last synced timestamp of `endpoint` of `site_id`
+    pub fn set_last_seen(&mut self, site_id: u32, endpoint: &str, last_seen: NaiveDateTime) {
+        match self
+            .cursors
+            .iter_mut()
+            .find(|cursor| cursor.site_id == site_id && cursor.endpoint == endpoint)
+        {
+            Some(cursor) => cursor.last_seen = last_seen,
+            None => self.cursors.push(SyncCursor {
+                site_id,
+                endpoint: endpoint.to_string(),
+                last_seen,
+            }),
+        }
+    }
+
+    /// Count `requests` made on `date`. The count starts again on a new day
+    pub fn record_requests(&mut self, date: NaiveDate, requests: u32) {
+        match &mut self.budget {
+            Some(budget) if budget.date == date => budget.requests += requests,
+            _ => self.budget = Some(RequestBudget { date, requests }),
+        }
+    }
+
+    /// The number of requests made on `date`
+    pub fn requests_on(&self, date: NaiveDate) -> u32 {
+        self.budget
+            .filter(|budget| budget.date == date)
+            .map_or(0, |budget| budget.requests)
+    }
+
+    /// The number of requests that can still be made on `date` within
+    /// [`DAILY_REQUEST_LIMIT`]
+    pub fn remaining_requests(&self, date: NaiveDate) -> u32 {
+        DAILY_REQUEST_LIMIT.saturating_sub(self.requests_on(date))
+    }
+}
+
+/// Receives the energy downloaded by [`Client::backfill`], i.e. to store it in
+/// a database. A closure taking the site id, time unit and energy is a sink
+pub trait BackfillSink {
+    /// store the `energy` of `site_id` per `time_unit`. An error stops the
+    /// backfill
+    fn write(
+        &mut self,
+        site_id: u32,
+        time_unit: TimeUnit,
+        energy: &TimeSeries<Energy>,
+    ) -> Result<(), SolarApiError>;
+}
+
+impl<F> BackfillSink for F
+where
+    F: FnMut(u32, TimeUnit, &TimeSeries<Energy>) -> Result<(), SolarApiError>,
+{
+    fn write(
+        &mut self,
+        site_id: u32,
+        time_unit: TimeUnit,
+        energy: &TimeSeries<Energy>,
+    ) -> Result<(), SolarApiError> {
+        self(site_id, time_unit, energy)
+    }
+}
+
+/// The result of [`Client::backfill`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BackfillReport {
+    /// the number of requests made
+    pub requests: u32,
+    /// the number of values written to the sink
+    pub values: usize,
+    /// false when the backfill stopped because the request budget of today
+    /// was used. Call [`Client::backfill`] again tomorrow to continue
+    pub complete: bool,
+}
+
+/// The periods to request to backfill the days from `from` until `to` per
+/// `time_unit`, split into periods the API allows. The buckets until the one
+/// starting at `last_seen` were fetched already and are skipped
+pub fn backfill_periods(
+    from: NaiveDate,
+    to: NaiveDate,
+    last_seen: Option<NaiveDateTime>,
+    time_unit: TimeUnit,
+) -> Vec<DataPeriod> {
+    let start = last_seen.map_or(from, |last_seen| {
+        time_unit.bucket_end(last_seen).date().max(from)
+    });
+    DataPeriod::between(start, to)
+        .map(|period| period.split_for(time_unit))
+        .unwrap_or_default()
+}
+
+impl Client {
+    /// The energy of `site_id` per `time_unit` that is new since the bucket
+    /// starting at `last_seen`, until `until`, i.e. the current time of the
+    /// site. Only the days from `last_seen` are requested, split into periods
+    /// the API allows. Store the timestamp of the last returned value as
+    /// `last_seen` for the next sync, see [`new_values`] for the values that are
+    /// returned
+    pub fn sync_energy(
+        &self,
+        site_id: u32,
+        last_seen: NaiveDateTime,
+        until: NaiveDateTime,
+        time_unit: TimeUnit,
+    ) -> Result<TimeSeries<Energy>, SolarApiError> {
+        let Some(period) = sync_period(last_seen, until, time_unit) else {
+            debug!("No new energy for {site_id} since {last_seen}");
+            return Ok(TimeSeries::new(time_unit, "Wh", vec![]));
+        };
+        let energy = self
+            .energy_chunked(site_id, period, time_unit)?
+            .to_series()?;
+        Ok(new_values(&energy, last_seen, until))
+    }
+
+    /// Like [`Client::sync_energy`], but continues from the last seen
+    /// timestamp in `state`, or `since` when this site was not synced yet. The
+    /// timestamp of the last returned value is stored in `state`, and every
+    /// request is recorded in `state` when it is made, so failed requests
+    /// count as well
+    pub fn sync_energy_with_state(
+        &self,
+        state: &mut SyncState,
+        site_id: u32,
+        since: NaiveDateTime,
+        until: NaiveDateTime,
+        time_unit: TimeUnit,
+    ) -> Result<TimeSeries<Energy>, SolarApiError> {
+        let endpoint = SyncState::energy_endpoint(time_unit);
+        let last_seen = state.last_seen(site_id, &endpoint).unwrap_or(since);
+        let periods = sync_period(last_seen, until, time_unit)
+            .map_or(Vec::new(), |period| period.split_for(time_unit));
+        let mut fetched: Option<GeneratedEnergy> = None;
+        for period in periods {
+            self.take_token();
+            state.record_requests(chrono::Local::now().date_naive(), 1);
+            let chunk = self.energy(site_id, period, time_unit)?;
+            match &mut fetched {
+                Some(fetched) => fetched.append(chunk)?,
+                None => fetched = Some(chunk),
+            }
+        }
+        let energy = match fetched {
+            Some(fetched) => new_values(&fetched.to_series()?, last_seen, until),
+            None => {
+                debug!("No new energy for {site_id} since {last_seen}");
+                TimeSeries::new(time_unit, "Wh", vec![])
+            }
+        };
+        if let Some(last) = energy.values.last() {
+            state.set_last_seen(site_id, &endpoint, last.date);
+        }
+        Ok(energy)
+    }
+
+    /// Downloads the energy of `site_id` from `from` until `to` for every time
+    /// unit of `resolutions` and writes it to `sink` per period the API
+    /// allows. The whole data period of the site is used when `from` or `to`
+    /// is None. After every period the last written timestamp is stored in
+    /// `state`, shared with [`Client::sync_energy_with_state`], so save
+    /// `state` after this returns, also on errors, and call this again to
+    /// resume. Every request waits for the rate limit of the client and is
+    /// recorded in `state` when it is made, so failed requests count as well.
+    /// The backfill stops when the [`DAILY_REQUEST_LIMIT`] of today is used,
+    /// see [`BackfillReport::complete`]
+    pub fn backfill(
+        &self,
+        state: &mut SyncState,
+        site_id: u32,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        resolutions: &[TimeUnit],
+        sink: &mut impl BackfillSink,
+    ) -> Result<BackfillReport, SolarApiError> {
+        let now = chrono::Local::now().naive_local();
+        let today = now.date();
+        let mut report = BackfillReport::default();
+        let (from, to) = match (from, to) {
+            (Some(from), Some(to)) => (from, to),
+            _ => {
+                self.take_token();
+                state.record_requests(today, 1);
+                report.requests += 1;
+                let period = self.data_period(site_id)?;
+                match (from.or(period.start_date), to.or(period.end_date)) {
+                    (Some(from), Some(to)) => (from, to),
+                    // the site has no data to backfill yet
+                    _ => {
+                        report.complete = true;
+                        return Ok(report);
+                    }
+                }
+            }
+        };
+
+        for time_unit in resolutions {
+            let endpoint = SyncState::energy_endpoint(*time_unit);
+            let last_seen = state.last_seen(site_id, &endpoint);
+            for period in backfill_periods(from, to, last_seen, *time_unit) {
+                if state.remaining_requests(today) == 0 {
+                    debug!("Request budget of {today} used, stopping backfill of {site_id}");
+                    return Ok(report);
+                }
+                let (start_date, end_date) = period.bounds()?;
+                debug!(
+                    "Backfilling {site_id} from {} until {} per {}",
+                    start_date,
+                    end_date,
+                    time_unit.to_param()
+                );
+                self.take_token();
+                state.record_requests(today, 1);
+                report.requests += 1;
+                let energy = self.energy(site_id, period, *time_unit)?.to_series()?;
+
+                let end = end_date
+                    .succ_opt()
+                    .map_or(now, |next| next.and_time(chrono::NaiveTime::MIN))
+                    .min(now);
+                let last_seen = state.last_seen(site_id, &endpoint);
+                let values = new_values(&energy, last_seen.unwrap_or(NaiveDateTime::MIN), end);
+                sink.write(site_id, *time_unit, &values)?;
+                report.values += values.len();
+
+                // old periods without values at the end will not get them anymore
+                let last = if end_date < today {
+                    Some(time_unit.bucket_start(end - chrono::Duration::seconds(1)))
+                } else {
+                    values.values.last().map(|v| v.date)
+                };
+                if let Some(last) = last {
+                    state.set_last_seen(site_id, &endpoint, last);
+                }
+            }
+        }
+        report.complete = true;
+        Ok(report)
+    }
+}
+
+#[test]
+fn test_sync_energy() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+
+    let period = sync_period(
+        date("2023-06-01 23:45:00"),
+        date("2023-06-03 10:20:00"),
+        TimeUnit::QuarterOfAnHour,
+    )
+    .unwrap();
+    assert_eq!(Some("2023-06-02"), period.formatted_start_date().as_deref());
+    assert_eq!(Some("2023-06-03"), period.formatted_end_date().as_deref());
+    // the bucket after last seen has not ended yet
+    assert!(sync_period(
+        date("2023-06-03 10:00:00"),
+        date("2023-06-03 10:14:00"),
+        TimeUnit::QuarterOfAnHour
+    )
+    .is_none());
+
+    let values = ["09:30:00", "09:45:00", "10:00:00", "10:15:00", "10:30:00"]
+        .iter()
+        .zip([Some(100.0), Some(200.0), None, Some(300.0), Some(400.0)])
+        .map(|(time, wh)| TimeSeriesValue {
+            date: date(&format!("2023-06-03 {time}")),
+            value: wh.map(watt_hours),
+        })
+        .collect();
+    let energy = TimeSeries::new(TimeUnit::QuarterOfAnHour, "Wh", values);
+    let new = new_values(
+        &energy,
+        date("2023-06-03 09:30:00"),
+        date("2023-06-03 10:40:00"),
+    );
+    // 10:30 is still counting
+    assert_eq!(3, new.len());
+    assert_eq!(date("2023-06-03 10:15:00"), new.values[2].date);
+
+    // the missing value at 10:00 may still arrive
+    let new = new_values(
+        &energy,
+        date("2023-06-03 09:30:00"),
+        date("2023-06-03 10:20:00"),
+    );
+    assert_eq!(1, new.len());
+}
+
+#[test]
+fn test_sync_state() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let day = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let endpoint = SyncState::energy_endpoint(TimeUnit::QuarterOfAnHour);
+
+    let mut state = SyncState::new();
+    assert_eq!(None, state.last_seen(1234, &endpoint));
+    state.set_last_seen(1234, &endpoint, date("2023-06-01 10:00:00"));
+    state.set_last_seen(1234, &endpoint, date("2023-06-01 10:15:00"));
+    state.set_last_seen(5678, &endpoint, date("2023-06-01 09:00:00"));
+    assert_eq!(
+        Some(date("2023-06-01 10:15:00")),
+        state.last_seen(1234, &endpoint)
+    );
+    assert_eq!(None, state.last_seen(1234, "energy:DAY"));
+
+    state.record_requests(day("2023-06-01"), 2);
+    state.record_requests(day("2023-06-01"), 3);
+    assert_eq!(5, state.requests_on(day("2023-06-01")));
+    assert_eq!(295, state.remaining_requests(day("2023-06-01")));
+    state.record_requests(day("2023-06-02"), 1);
+    assert_eq!(0, state.requests_on(day("2023-06-01")));
+
+    let path = std::env::temp_dir().join(format!("solar-api-sync-{}.json", std::process::id()));
+    state.save(&path).unwrap();
+    assert_eq!(state, SyncState::load(&path).unwrap());
+    std::fs::write(&path, "{").unwrap();
+    assert!(matches!(
+        SyncState::load(&path),
+        Err(SolarApiError::InvalidSyncState { .. })
+    ));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(SyncState::new(), SyncState::load(&path).unwrap());
+}
+
+#[test]
+fn test_backfill_periods() {
+    let day = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let (from, to) = (day("2021-03-15"), day("2023-06-10"));
+
+    let periods = backfill_periods(from, to, None, TimeUnit::Day);
+    assert_eq!(3, periods.len());
+    assert_eq!(Some(from), periods[0].start_date);
+    assert_eq!(Some(to), periods[2].end_date);
+    assert_eq!(
+        27,
+        backfill_periods(from, to, None, TimeUnit::QuarterOfAnHour).len()
+    );
+    assert_eq!(1, backfill_periods(from, to, None, TimeUnit::Month).len());
+
+    // resume after the last seen quarter of 2023-05-31
+    let last_seen = day("2023-05-31").and_hms_opt(23, 45, 0);
+    let periods = backfill_periods(from, to, last_seen, TimeUnit::QuarterOfAnHour);
+    assert_eq!(1, periods.len());
+    assert_eq!(Some(day("2023-06-01")), periods[0].start_date);
+    // done
+    let last_seen = to.and_hms_opt(23, 45, 0);
+    assert!(backfill_periods(from, to, last_seen, TimeUnit::QuarterOfAnHour).is_empty());
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_sync_energy_records_failed_requests() {
+    use crate::fake_server::{FakeServer, FakeSite, Scenario};
+
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let server = FakeServer::start(
+        Scenario::new("KEY")
+            .with_site(FakeSite::new(1234, 5.0))
+            .with_daily_limit(1)
+            .with_now(date("2023-06-01 12:00:00")),
+    )
+    .unwrap();
+    let client = server.client();
+    let mut state = SyncState::default();
+
+    // the hours since March are three requests, the second is over the limit
+    let result = client.sync_energy_with_state(
+        &mut state,
+        1234,
+        date("2023-03-10 00:00:00"),
+        date("2023-05-20 00:00:00"),
+        TimeUnit::Hour,
+    );
+    assert!(result.is_err());
+    assert_eq!(2, server.requests().len());
+    assert_eq!(2, state.requests_on(chrono::Local::now().date_naive()));
+    assert_eq!(
+        None,
+        state.last_seen(1234, &SyncState::energy_endpoint(TimeUnit::Hour))
+    );
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_backfill_records_failed_requests() {
+    use crate::fake_server::{FakeServer, FakeSite, Scenario};
+
+    let day = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let now = day("2023-06-01").and_hms_opt(12, 0, 0).unwrap();
+    let today = chrono::Local::now().date_naive();
+    let server = FakeServer::start(
+        Scenario::new("KEY")
+            .with_site(FakeSite::new(1234, 5.0))
+            .with_daily_limit(1)
+            .with_now(now),
+    )
+    .unwrap();
+    let client = server.client();
+    let mut state = SyncState::default();
+    let mut sink = |_: u32, _: TimeUnit, _: &TimeSeries<Energy>| Ok(());
+
+    // the days since 2021 are three requests, the second is over the limit
+    let result = client.backfill(
+        &mut state,
+        1234,
+        Some(day("2021-03-15")),
+        Some(day("2023-05-31")),
+        &[TimeUnit::Day],
+        &mut sink,
+    );
+    assert!(result.is_err());
+    assert_eq!(2, server.requests().len());
+    assert_eq!(2, state.requests_on(today));
+
+    // the data period is requested without `from` and `to` and fails as well
+    let result = client.backfill(&mut state, 1234, None, None, &[TimeUnit::Day], &mut sink);
+    assert!(result.is_err());
+    assert_eq!(3, server.requests().len());
+    assert_eq!(3, state.requests_on(today));
+}
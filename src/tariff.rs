@@ -0,0 +1,163 @@
+//! Prices of energy to calculate the revenue of exported energy or the savings
+//! of self consumed energy, instead of the `revenue` returned by the API that
+//! is often missing and cannot handle dynamic tariffs
+
+use crate::{
+    series::{TimeSeries, TimeSeriesValue},
+    units::{Energy, EnergyUnit},
+};
+#[cfg(test)]
+use crate::{site::TimeUnit, units::watt_hours};
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+
+/// A period of a time-of-use tariff in which the same price applies
+#[derive(Debug, Clone, PartialEq)]
+pub struct TariffPeriod {
+    /// the days on which this period applies. Empty means every day
+    pub weekdays: Vec<Weekday>,
+    /// the start time of the period (inclusive)
+    pub start: NaiveTime,
+    /// the end time of the period (exclusive). An end before `start` means the
+    /// period continues after midnight, i.e. 23:00 until 07:00
+    pub end: NaiveTime,
+    /// the price per kWh
+    pub price: f64,
+}
+
+impl TariffPeriod {
+    // true when `datetime` falls in this period
+    fn contains(&self, datetime: NaiveDateTime) -> bool {
+        let time = datetime.time();
+        let (in_time, weekday) = if self.start <= self.end {
+            (time >= self.start && time < self.end, datetime.weekday())
+        } else if time >= self.start {
+            (true, datetime.weekday())
+        } else {
+            // after midnight the period belongs to the day it started
+            (time < self.end, datetime.weekday().pred())
+        };
+        in_time && (self.weekdays.is_empty() || self.weekdays.contains(&weekday))
+    }
+}
+
+/// The price of energy. Prices are per kWh in a currency of your choice
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tariff {
+    /// the same price at all times
+    Flat(f64),
+    /// a price per period of the day, i.e. peak and off-peak. The first period
+    /// that contains a timestamp determines the price, `default` is used when
+    /// none does
+    TimeOfUse {
+        periods: Vec<TariffPeriod>,
+        default: f64,
+    },
+    /// a price per interval, i.e. the hourly prices of a dynamic tariff. A
+    /// price applies from its timestamp until the timestamp of the next price
+    PerInterval(TimeSeries<f64>),
+}
+
+impl Tariff {
+    /// The price per kWh at `datetime`, or None when the price is not known,
+    /// i.e. before the first price of [`Tariff::PerInterval`]
+    pub fn price_at(&self, datetime: NaiveDateTime) -> Option<f64> {
+        match self {
+            Tariff::Flat(price) => Some(*price),
+            Tariff::TimeOfUse { periods, default } => Some(
+                periods
+                    .iter()
+                    .find(|period| period.contains(datetime))
+                    .map_or(*default, |period| period.price),
+            ),
+            Tariff::PerInterval(prices) => prices
+                .iter()
+                .take_while(|price| price.date <= datetime)
+                .last()
+                .and_then(|price| price.value),
+        }
+    }
+
+    /// Prices every value of `energy` using the price at its timestamp, i.e.
+    /// the revenue of exported energy. Use a resolution of `energy` that
+    /// matches the tariff, as a value gets the price of the start of its
+    /// interval. A value is None when the energy or price is not known
+    pub fn price(&self, energy: &TimeSeries<Energy>) -> TimeSeries<f64> {
+        let values = energy
+            .iter()
+            .map(|v| TimeSeriesValue {
+                date: v.date,
+                value: v
+                    .value
+                    .zip(self.price_at(v.date))
+                    .map(|(energy, price)| EnergyUnit::KilowattHour.value_of(energy) * price),
+            })
+            .collect();
+        TimeSeries::new(energy.time_unit, "", values)
+    }
+
+    /// The total price of `energy`, see [`Tariff::price`]. Values without a
+    /// price are skipped
+    pub fn total(&self, energy: &TimeSeries<Energy>) -> f64 {
+        self.price(energy).total()
+    }
+}
+
+#[test]
+fn test_tariffs() {
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    // 2023-06-02 is a Friday
+    let energy = TimeSeries::new(
+        TimeUnit::Hour,
+        "Wh",
+        vec![
+            TimeSeriesValue {
+                date: date("2023-06-02 12:00:00"),
+                value: Some(watt_hours(2000.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-06-02 23:00:00"),
+                value: Some(watt_hours(1000.0)),
+            },
+            TimeSeriesValue {
+                date: date("2023-06-03 01:00:00"),
+                value: Some(watt_hours(1000.0)),
+            },
+        ],
+    );
+
+    assert_eq!(1.0, Tariff::Flat(0.25).total(&energy));
+
+    let time_of_use = Tariff::TimeOfUse {
+        periods: vec![TariffPeriod {
+            weekdays: vec![Weekday::Fri],
+            start: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+            price: 0.10,
+        }],
+        default: 0.30,
+    };
+    let priced = time_of_use.price(&energy);
+    assert_eq!(Some(0.6), priced.values[0].value);
+    assert_eq!(Some(0.1), priced.values[1].value);
+    // saturday 01:00 belongs to the friday night period
+    assert_eq!(Some(0.1), priced.values[2].value);
+
+    let dynamic = Tariff::PerInterval(TimeSeries::new(
+        TimeUnit::Hour,
+        "EUR/kWh",
+        vec![
+            TimeSeriesValue {
+                date: date("2023-06-02 13:00:00"),
+                value: Some(0.5),
+            },
+            TimeSeriesValue {
+                date: date("2023-06-03 00:00:00"),
+                value: Some(-0.1),
+            },
+        ],
+    ));
+    let priced = dynamic.price(&energy);
+    assert_eq!(None, priced.values[0].value);
+    assert_eq!(Some(0.5), priced.values[1].value);
+    assert_eq!(Some(-0.1), priced.values[2].value);
+}
@@ -0,0 +1,122 @@
+//! Conversions of the timestamps and dates returned by the API to the types of
+//! the [time](https://docs.rs/time) crate. Only available with the `time`
+//! feature. Like the chrono types used by this lib, a [`PrimitiveDateTime`] is
+//! in the local time of the site. Use [`to_offset_date_time`] or
+//! [`TimeSeriesValue::offset_date_time`] to attach the time zone of the site
+
+use crate::{
+    series::TimeSeriesValue, site::Overview, timezone::localize, DataPeriod, SolarApiError,
+};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Offset, TimeZone, Timelike};
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+fn out_of_range(e: time::error::ComponentRange) -> SolarApiError {
+    SolarApiError::TimeOutOfRange(e.to_string())
+}
+
+/// Convert a chrono `date` to a [`Date`]
+pub fn to_date(date: NaiveDate) -> Result<Date, SolarApiError> {
+    let month = Month::try_from(date.month() as u8).map_err(out_of_range)?;
+    Date::from_calendar_date(date.year(), month, date.day() as u8).map_err(out_of_range)
+}
+
+/// Convert a [`Date`] to a chrono date
+pub fn from_date(date: Date) -> Result<NaiveDate, SolarApiError> {
+    NaiveDate::from_ymd_opt(date.year(), date.month() as u32, date.day() as u32)
+        .ok_or_else(|| SolarApiError::TimeOutOfRange(date.to_string()))
+}
+
+/// Convert a chrono `datetime` without time zone to a [`PrimitiveDateTime`]
+pub fn to_primitive_date_time(datetime: NaiveDateTime) -> Result<PrimitiveDateTime, SolarApiError> {
+    let time = Time::from_hms_nano(
+        datetime.hour() as u8,
+        datetime.minute() as u8,
+        datetime.second() as u8,
+        datetime.nanosecond(),
+    )
+    .map_err(out_of_range)?;
+    Ok(PrimitiveDateTime::new(to_date(datetime.date())?, time))
+}
+
+/// Convert a chrono `datetime` with time zone to an [`OffsetDateTime`] with
+/// the same UTC offset
+pub fn to_offset_date_time<Tz: TimeZone>(
+    datetime: &DateTime<Tz>,
+) -> Result<OffsetDateTime, SolarApiError> {
+    let offset = UtcOffset::from_whole_seconds(datetime.offset().fix().local_minus_utc())
+        .map_err(out_of_range)?;
+    Ok(to_primitive_date_time(datetime.naive_local())?.assume_offset(offset))
+}
+
+impl<Q> TimeSeriesValue<Q> {
+    /// The timestamp of this value in the local time of the site
+    pub fn primitive_date_time(&self) -> Result<PrimitiveDateTime, SolarApiError> {
+        to_primitive_date_time(self.date)
+    }
+
+    /// The timestamp of this value, given the time zone `tz` of the site. See
+    /// [`localize`] for the handling of daylight saving time transitions
+    pub fn offset_date_time<Tz: TimeZone>(&self, tz: &Tz) -> Result<OffsetDateTime, SolarApiError> {
+        to_offset_date_time(&localize(self.date, tz)?)
+    }
+}
+
+impl Overview {
+    /// The `last_updated_time`, given the time zone `tz` of the site. None
+    /// when the site has not communicated yet
+    pub fn last_updated_offset_date_time<Tz: TimeZone>(
+        &self,
+        tz: &Tz,
+    ) -> Result<Option<OffsetDateTime>, SolarApiError> {
+        self.last_updated_with_timezone(tz)?
+            .map(|last_updated| to_offset_date_time(&last_updated))
+            .transpose()
+    }
+}
+
+impl DataPeriod {
+    /// The period from `start_date` until `end_date` given as [`Date`], both
+    /// inclusive. See [`DataPeriod::between`]
+    pub fn between_dates(start_date: Date, end_date: Date) -> Result<Self, SolarApiError> {
+        DataPeriod::between(from_date(start_date)?, from_date(end_date)?)
+    }
+
+    /// The start and end date of this period as [`Date`], see
+    /// [`DataPeriod::bounds`]
+    pub fn dates(&self) -> Result<(Date, Date), SolarApiError> {
+        let (start_date, end_date) = self.bounds()?;
+        Ok((to_date(start_date)?, to_date(end_date)?))
+    }
+}
+
+#[test]
+fn test_time_conversions() {
+    let datetime =
+        NaiveDateTime::parse_from_str("2023-11-09 12:15:30", "%Y-%m-%d %H:%M:%S").unwrap();
+    let value = crate::GeneratedPowerValue {
+        date: datetime,
+        value: None,
+    };
+
+    let primitive = value.primitive_date_time().unwrap();
+    assert_eq!("2023-11-09 12:15:30.0", primitive.to_string());
+
+    let tz = chrono::FixedOffset::east_opt(3600).unwrap();
+    let offset = value.offset_date_time(&tz).unwrap();
+    assert_eq!(3600, offset.offset().whole_seconds());
+    assert_eq!(
+        datetime.and_utc().timestamp() - 3600,
+        offset.unix_timestamp()
+    );
+
+    let period = DataPeriod::between_dates(
+        Date::from_calendar_date(2023, Month::January, 1).unwrap(),
+        Date::from_calendar_date(2023, Month::March, 31).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(Some("2023-03-31".into()), period.formatted_end_date());
+    assert_eq!(
+        Date::from_calendar_date(2023, Month::January, 1).unwrap(),
+        period.dates().unwrap().0
+    );
+}
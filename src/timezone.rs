@@ -0,0 +1,298 @@
+//! Helpers to attach a time zone to the timestamps returned by the API. The API
+//! returns timestamps in the local time of the site, see [`crate::Location`]
+
+use crate::{
+    series::TimeSeriesValue,
+    site::{Overview, NEXT_UPDATE_BACKOFF_IN_S, REFRESH_TIME_IN_M},
+    SolarApiError,
+};
+use chrono::{DateTime, LocalResult, NaiveDateTime, TimeZone, Utc};
+
+/// Attach the time zone `tz` to a `datetime` in the local time of the site.
+/// Ambiguous timestamps, i.e. when the clock is turned back, resolve to the
+/// earliest instant. Timestamps that do not exist in `tz`, i.e. when the clock
+/// is turned forward, return [`SolarApiError::InvalidLocalTime`]
+pub fn localize<Tz: TimeZone>(
+    datetime: NaiveDateTime,
+    tz: &Tz,
+) -> Result<DateTime<Tz>, SolarApiError> {
+    match tz.from_local_datetime(&datetime) {
+        LocalResult::Single(localized) => Ok(localized),
+        LocalResult::Ambiguous(earliest, _) => Ok(earliest),
+        LocalResult::None => Err(SolarApiError::InvalidLocalTime(datetime)),
+    }
+}
+
+impl Overview {
+    /// The `last_updated_time` in the time zone `tz` of the site. None when
+    /// the site has not communicated yet
+    pub fn last_updated_with_timezone<Tz: TimeZone>(
+        &self,
+        tz: &Tz,
+    ) -> Result<Option<DateTime<Tz>>, SolarApiError> {
+        self.last_updated_time
+            .map(|last_updated| localize(last_updated, tz))
+            .transpose()
+    }
+
+    /// The `last_updated_time` in UTC, given the time zone `tz` of the site.
+    /// None when the site has not communicated yet
+    pub fn last_updated_to_utc<Tz: TimeZone>(
+        &self,
+        tz: &Tz,
+    ) -> Result<Option<DateTime<Utc>>, SolarApiError> {
+        Ok(self
+            .last_updated_with_timezone(tz)?
+            .map(|last_updated| last_updated.with_timezone(&Utc)))
+    }
+
+    /// Like [`Overview::estimated_next_update`], but interprets `last_updated_time`
+    /// in the time zone `tz` of the site. The duration is calculated from the
+    /// current UTC time, so it is also correct when this machine is in a
+    /// different time zone than the site. None when the site has not
+    /// communicated yet
+    pub fn estimated_next_update_in<Tz: TimeZone>(
+        &self,
+        tz: &Tz,
+    ) -> Result<Option<(DateTime<Tz>, chrono::Duration)>, SolarApiError> {
+        // add 10s extra time
+        self.estimated_next_update_in_with_margin(
+            tz,
+            chrono::Duration::seconds(NEXT_UPDATE_BACKOFF_IN_S[0]),
+        )
+    }
+
+    /// Like [`Overview::estimated_next_update_in`], but uses `margin` as extra
+    /// time after the 15 minutes between updates instead of 10 seconds
+    pub fn estimated_next_update_in_with_margin<Tz: TimeZone>(
+        &self,
+        tz: &Tz,
+        margin: chrono::Duration,
+    ) -> Result<Option<(DateTime<Tz>, chrono::Duration)>, SolarApiError> {
+        let Some(last_updated) = self.last_updated_with_timezone(tz)? else {
+            return Ok(None);
+        };
+        let next = last_updated + chrono::Duration::minutes(REFRESH_TIME_IN_M) + margin;
+        let delta = next.clone().signed_duration_since(Utc::now());
+        Ok(Some((next, delta)))
+    }
+
+    /// Like [`Overview::next_update_schedule`], but interprets `last_updated_time`
+    /// in the time zone `tz` of the site
+    pub fn next_update_schedule_in<Tz: TimeZone>(
+        &self,
+        tz: &Tz,
+    ) -> Result<Vec<DateTime<Tz>>, SolarApiError> {
+        let Some(last_updated) = self.last_updated_with_timezone(tz)? else {
+            return Ok(Vec::new());
+        };
+        Ok(NEXT_UPDATE_BACKOFF_IN_S
+            .iter()
+            .map(|backoff| {
+                last_updated.clone()
+                    + chrono::Duration::minutes(REFRESH_TIME_IN_M)
+                    + chrono::Duration::seconds(*backoff)
+            })
+            .collect())
+    }
+
+    /// The first timestamp of [`Overview::next_update_schedule_in`] that is
+    /// still in the future and the time to wait until then. Returns `None` when
+    /// the whole schedule has passed, i.e. the site has not sent new data, or
+    /// when the site has not communicated yet.
+    /// Unlike [`Overview::estimated_next_update_in`] the duration is never negative
+    pub fn next_attempt_in<Tz: TimeZone>(
+        &self,
+        tz: &Tz,
+    ) -> Result<Option<(DateTime<Tz>, std::time::Duration)>, SolarApiError> {
+        let now = Utc::now();
+        Ok(self
+            .next_update_schedule_in(tz)?
+            .into_iter()
+            .find_map(|next| {
+                let wait = next.clone().signed_duration_since(now).to_std().ok()?;
+                Some((next, wait))
+            }))
+    }
+}
+
+impl<Q> TimeSeriesValue<Q> {
+    /// The timestamp of this value in the time zone `tz` of the site
+    pub fn with_timezone<Tz: TimeZone>(&self, tz: &Tz) -> Result<DateTime<Tz>, SolarApiError> {
+        localize(self.date, tz)
+    }
+
+    /// The timestamp of this value in UTC, given the time zone `tz` of the site
+    pub fn to_utc<Tz: TimeZone>(&self, tz: &Tz) -> Result<DateTime<Utc>, SolarApiError> {
+        Ok(self.with_timezone(tz)?.with_timezone(&Utc))
+    }
+}
+
+#[cfg(feature = "chrono-tz")]
+impl crate::Location {
+    /// The time zone of the site, parsed from `time_zone`, i.e. `Europe/Amsterdam`
+    pub fn tz(&self) -> Result<chrono_tz::Tz, SolarApiError> {
+        self.time_zone
+            .parse()
+            .map_err(|_| SolarApiError::UnknownTimeZone(self.time_zone.clone()))
+    }
+}
+
+#[cfg(feature = "chrono-tz")]
+impl crate::Site {
+    /// Attach the time zone of this site to a `datetime` returned by the API.
+    /// See [`localize`] for the handling of daylight saving time transitions
+    pub fn localize(
+        &self,
+        datetime: NaiveDateTime,
+    ) -> Result<DateTime<chrono_tz::Tz>, SolarApiError> {
+        localize(datetime, &self.location.tz()?)
+    }
+
+    /// Convert a `datetime` returned by the API for this site to UTC
+    pub fn to_utc(&self, datetime: NaiveDateTime) -> Result<DateTime<Utc>, SolarApiError> {
+        Ok(self.localize(datetime)?.with_timezone(&Utc))
+    }
+}
+
+#[test]
+fn test_localize_fixed_offset() {
+    let tz = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+    let datetime =
+        NaiveDateTime::parse_from_str("2023-06-01 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+
+    let localized = localize(datetime, &tz).unwrap();
+    assert_eq!(
+        "2023-06-01 10:00:00 UTC",
+        localized.with_timezone(&Utc).to_string()
+    );
+}
+
+#[test]
+fn test_values_to_utc() {
+    let tz = chrono::FixedOffset::east_opt(3600).unwrap();
+    let date = NaiveDateTime::parse_from_str("2023-11-09 12:15:00", "%Y-%m-%d %H:%M:%S").unwrap();
+
+    let energy = crate::GeneratedEnergyValue { date, value: None };
+    assert_eq!(
+        "2023-11-09 11:15:00 UTC",
+        energy.to_utc(&tz).unwrap().to_string()
+    );
+    let power = crate::GeneratedPowerValue { date, value: None };
+    assert_eq!(
+        "2023-11-09 12:15:00 +01:00",
+        power.with_timezone(&tz).unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_estimated_next_update_in() {
+    let tz = chrono::FixedOffset::west_opt(5 * 3600).unwrap();
+    let last_updated = Utc::now().with_timezone(&tz).naive_local();
+    let overview = Overview {
+        last_updated_time: Some(last_updated),
+        ..serde_json::from_str::<crate::site::OverviewReply>(
+            r#"{"overview":{
+                "lastUpdateTime":"2023-11-09 10:28:56",
+                "lifeTimeData":{"energy":1.0},
+                "lastYearData":{"energy":1.0},
+                "lastMonthData":{"energy":1.0},
+                "lastDayData":{"energy":1.0},
+                "currentPower":{"power":1.0},
+                "measuredBy":"INVERTER"}}"#,
+        )
+        .unwrap()
+        .overview
+    };
+
+    assert_eq!(
+        last_updated,
+        overview
+            .last_updated_to_utc(&tz)
+            .unwrap()
+            .unwrap()
+            .naive_utc()
+            - chrono::Duration::hours(5)
+    );
+
+    let (_, delta) = overview.estimated_next_update_in(&tz).unwrap().unwrap();
+    assert!(delta <= chrono::Duration::seconds(REFRESH_TIME_IN_M * 60 + 10));
+    assert!(delta > chrono::Duration::seconds(REFRESH_TIME_IN_M * 60));
+}
+
+#[cfg(feature = "chrono-tz")]
+#[test]
+fn test_localize_daylight_saving() {
+    let tz = chrono_tz::Europe::Amsterdam;
+    let parse = |s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+
+    // clock turned forward from 02:00 to 03:00
+    assert!(matches!(
+        localize(parse("2023-03-26 02:30:00"), &tz),
+        Err(SolarApiError::InvalidLocalTime(_))
+    ));
+    // clock turned back from 03:00 to 02:00
+    let ambiguous = localize(parse("2023-10-29 02:30:00"), &tz).unwrap();
+    assert_eq!(
+        "2023-10-29 00:30:00 UTC",
+        ambiguous.with_timezone(&Utc).to_string()
+    );
+}
+
+#[test]
+fn test_next_update_schedule() {
+    let tz = chrono::FixedOffset::east_opt(3600).unwrap();
+    let overview = serde_json::from_str::<crate::site::OverviewReply>(
+        r#"{"overview":{
+            "lastUpdateTime":"2023-11-09 10:28:56",
+            "lifeTimeData":{"energy":1.0},
+            "lastYearData":{"energy":1.0},
+            "lastMonthData":{"energy":1.0},
+            "lastDayData":{"energy":1.0},
+            "currentPower":{"power":1.0},
+            "measuredBy":"INVERTER"}}"#,
+    )
+    .unwrap()
+    .overview;
+
+    let schedule: Vec<String> = overview
+        .next_update_schedule()
+        .iter()
+        .map(|next| next.to_string())
+        .collect();
+    assert_eq!(
+        vec![
+            "2023-11-09 10:44:06",
+            "2023-11-09 10:44:56",
+            "2023-11-09 10:48:56"
+        ],
+        schedule
+    );
+    assert_eq!(
+        "2023-11-09 10:45:56",
+        overview
+            .estimated_next_update_with_margin(chrono::Duration::minutes(2))
+            .unwrap()
+            .0
+            .to_string()
+    );
+    assert_eq!(
+        "2023-11-09 09:48:56 UTC",
+        overview.next_update_schedule_in(&tz).unwrap()[2]
+            .with_timezone(&Utc)
+            .to_string()
+    );
+    // the schedule of 2023 has long passed
+    assert!(overview.next_attempt_in(&tz).unwrap().is_none());
+
+    let overview = Overview {
+        last_updated_time: Some(
+            Utc::now().with_timezone(&tz).naive_local()
+                - chrono::Duration::minutes(REFRESH_TIME_IN_M),
+        ),
+        ..overview
+    };
+    let (next, wait) = overview.next_attempt_in(&tz).unwrap().unwrap();
+    assert!(wait <= std::time::Duration::from_secs(10));
+    assert!(next > Utc::now().with_timezone(&tz));
+}
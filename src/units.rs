@@ -0,0 +1,308 @@
+//! Units of measurement used by this lib. With the default `uom` feature,
+//! [`Energy`] and [`Power`] are quantities of the [uom](https://docs.rs/uom)
+//! crate. Without it, they are plain `f64` values in watt-hour and watt. Use the
+//! functions of this module, like [`watt_hours`] and [`as_watt_hours`], or
+//! [`EnergyUnit`] and [`PowerUnit`] to work with both representations
+
+use crate::SolarApiError;
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "uom")]
+pub use uom::si::f64::{Energy, Power};
+
+/// Energy in watt-hour
+#[cfg(not(feature = "uom"))]
+pub type Energy = f64;
+
+/// Power in watt
+#[cfg(not(feature = "uom"))]
+pub type Power = f64;
+
+/// Create an [`Energy`] from a `value` in watt-hour
+#[cfg(feature = "uom")]
+pub fn watt_hours(value: f64) -> Energy {
+    Energy::new::<uom::si::energy::watt_hour>(value)
+}
+
+/// Create an [`Energy`] from a `value` in watt-hour
+#[cfg(not(feature = "uom"))]
+pub fn watt_hours(value: f64) -> Energy {
+    value
+}
+
+/// The value of `energy` in watt-hour
+#[cfg(feature = "uom")]
+pub fn as_watt_hours(energy: Energy) -> f64 {
+    energy.get::<uom::si::energy::watt_hour>()
+}
+
+/// The value of `energy` in watt-hour
+#[cfg(not(feature = "uom"))]
+pub fn as_watt_hours(energy: Energy) -> f64 {
+    energy
+}
+
+/// Create a [`Power`] from a `value` in watt
+#[cfg(feature = "uom")]
+pub fn watts(value: f64) -> Power {
+    Power::new::<uom::si::power::watt>(value)
+}
+
+/// Create a [`Power`] from a `value` in watt
+#[cfg(not(feature = "uom"))]
+pub fn watts(value: f64) -> Power {
+    value
+}
+
+/// The value of `power` in watt
+#[cfg(feature = "uom")]
+pub fn as_watts(power: Power) -> f64 {
+    power.get::<uom::si::power::watt>()
+}
+
+/// The value of `power` in watt
+#[cfg(not(feature = "uom"))]
+pub fn as_watts(power: Power) -> f64 {
+    power
+}
+
+/// A quantity that can be converted to a number in the base unit of this lib,
+/// i.e. to write it to another format: watt-hour for [`Energy`], watt for
+/// [`Power`] and the value itself for `f64`, like percentages
+pub trait Quantity: Copy {
+    /// the value in the base unit
+    fn base_value(self) -> f64;
+}
+
+#[cfg(feature = "uom")]
+impl Quantity for Energy {
+    fn base_value(self) -> f64 {
+        as_watt_hours(self)
+    }
+}
+
+#[cfg(feature = "uom")]
+impl Quantity for Power {
+    fn base_value(self) -> f64 {
+        as_watts(self)
+    }
+}
+
+// without uom, energy and power are f64 values in their base unit already
+impl Quantity for f64 {
+    fn base_value(self) -> f64 {
+        self
+    }
+}
+
+/// The [`Energy`] produced by a constant `power` during `duration`
+pub fn energy_of(power: Power, duration: chrono::Duration) -> Energy {
+    let hours = duration.num_milliseconds() as f64 / 3_600_000.0;
+    watt_hours(as_watts(power) * hours)
+}
+
+/// A value with the unit string as returned by the API. An escape hatch for
+/// units this lib does not support yet, so values can still be logged or
+/// converted by the user without losing data
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawQuantity {
+    /// the value as returned by the API
+    pub value: f64,
+    /// the unit of measurement as returned by the API, i.e. `kWh`
+    pub unit: String,
+}
+
+impl RawQuantity {
+    /// Create a quantity from a `value` in `unit`
+    pub fn raw(value: f64, unit: &str) -> Self {
+        RawQuantity {
+            value,
+            unit: unit.to_string(),
+        }
+    }
+
+    /// Convert to [`Energy`]. Returns [`SolarApiError::UnsupportedUnit`] when
+    /// `unit` is not an [`EnergyUnit`]
+    pub fn energy(&self) -> Result<Energy, SolarApiError> {
+        Ok(self.unit.parse::<EnergyUnit>()?.energy(self.value))
+    }
+
+    /// Convert to [`Power`]. Returns [`SolarApiError::UnsupportedUnit`] when
+    /// `unit` is not a [`PowerUnit`]
+    pub fn power(&self) -> Result<Power, SolarApiError> {
+        Ok(self.unit.parse::<PowerUnit>()?.power(self.value))
+    }
+}
+
+impl fmt::Display for RawQuantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.value, self.unit)
+    }
+}
+
+/// Units of energy used by the API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EnergyUnit {
+    WattHour,
+    KilowattHour,
+    MegawattHour,
+    GigawattHour,
+}
+
+impl EnergyUnit {
+    /// the symbol the API uses for this unit, i.e. `kWh`
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            EnergyUnit::WattHour => "Wh",
+            EnergyUnit::KilowattHour => "kWh",
+            EnergyUnit::MegawattHour => "MWh",
+            EnergyUnit::GigawattHour => "GWh",
+        }
+    }
+
+    /// the number of watt-hour in one of this unit
+    pub fn watt_hours(&self) -> f64 {
+        match self {
+            EnergyUnit::WattHour => 1.0,
+            EnergyUnit::KilowattHour => 1e3,
+            EnergyUnit::MegawattHour => 1e6,
+            EnergyUnit::GigawattHour => 1e9,
+        }
+    }
+
+    /// Create an [`Energy`] from a `value` in this unit
+    pub fn energy(&self, value: f64) -> Energy {
+        watt_hours(value * self.watt_hours())
+    }
+
+    /// The value of `energy` in this unit
+    pub fn value_of(&self, energy: Energy) -> f64 {
+        as_watt_hours(energy) / self.watt_hours()
+    }
+}
+
+impl fmt::Display for EnergyUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+impl FromStr for EnergyUnit {
+    type Err = SolarApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Wh" => Ok(EnergyUnit::WattHour),
+            "kWh" => Ok(EnergyUnit::KilowattHour),
+            "MWh" => Ok(EnergyUnit::MegawattHour),
+            "GWh" => Ok(EnergyUnit::GigawattHour),
+            _ => Err(SolarApiError::UnsupportedUnit(s.to_string())),
+        }
+    }
+}
+
+/// A unit of measurement of the API, which converts the values of a reply to
+/// [`QuantityUnit::Quantity`]
+pub trait QuantityUnit: Copy + FromStr<Err = SolarApiError> {
+    /// the quantity that is measured in this unit, i.e. [`Energy`]
+    type Quantity;
+
+    /// Create a quantity from a `value` in this unit
+    fn quantity(&self, value: f64) -> Self::Quantity;
+}
+
+impl QuantityUnit for EnergyUnit {
+    type Quantity = Energy;
+
+    fn quantity(&self, value: f64) -> Energy {
+        self.energy(value)
+    }
+}
+
+/// Units of power used by the API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerUnit {
+    Watt,
+    Kilowatt,
+    Megawatt,
+    Gigawatt,
+}
+
+impl PowerUnit {
+    /// the symbol the API uses for this unit, i.e. `kW`
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            PowerUnit::Watt => "W",
+            PowerUnit::Kilowatt => "kW",
+            PowerUnit::Megawatt => "MW",
+            PowerUnit::Gigawatt => "GW",
+        }
+    }
+
+    /// the number of watt in one of this unit
+    pub fn watts(&self) -> f64 {
+        match self {
+            PowerUnit::Watt => 1.0,
+            PowerUnit::Kilowatt => 1e3,
+            PowerUnit::Megawatt => 1e6,
+            PowerUnit::Gigawatt => 1e9,
+        }
+    }
+
+    /// Create a [`Power`] from a `value` in this unit
+    pub fn power(&self, value: f64) -> Power {
+        watts(value * self.watts())
+    }
+
+    /// The value of `power` in this unit
+    pub fn value_of(&self, power: Power) -> f64 {
+        as_watts(power) / self.watts()
+    }
+}
+
+impl fmt::Display for PowerUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+impl FromStr for PowerUnit {
+    type Err = SolarApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "W" => Ok(PowerUnit::Watt),
+            "kW" => Ok(PowerUnit::Kilowatt),
+            "MW" => Ok(PowerUnit::Megawatt),
+            "GW" => Ok(PowerUnit::Gigawatt),
+            _ => Err(SolarApiError::UnsupportedUnit(s.to_string())),
+        }
+    }
+}
+
+impl QuantityUnit for PowerUnit {
+    type Quantity = Power;
+
+    fn quantity(&self, value: f64) -> Power {
+        self.power(value)
+    }
+}
+
+#[test]
+fn test_units() {
+    assert_eq!(watt_hours(2500.0), EnergyUnit::KilowattHour.energy(2.5));
+    assert_eq!(2.5, EnergyUnit::KilowattHour.value_of(watt_hours(2500.0)));
+    assert_eq!(watts(1_500_000.0), PowerUnit::Megawatt.power(1.5));
+    assert_eq!(Ok(PowerUnit::Kilowatt), "kW".parse().map_err(|_| ()));
+    assert!("J".parse::<EnergyUnit>().is_err());
+    assert_eq!(
+        250.0,
+        as_watt_hours(energy_of(watts(1000.0), chrono::Duration::minutes(15)))
+    );
+
+    let raw = RawQuantity::raw(1.5, "kWh");
+    assert_eq!("1.5 kWh", raw.to_string());
+    assert_eq!(watt_hours(1500.0), raw.energy().unwrap());
+    assert!(raw.power().is_err());
+    assert!(RawQuantity::raw(2.0, "kvarh").energy().is_err());
+}
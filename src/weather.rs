@@ -0,0 +1,219 @@
+//! Weather data of a site, like the irradiation and temperature per interval,
+//! to normalize the production of a site. This lib does not fetch weather data
+//! itself: implement [`WeatherProvider`] for the weather service of your
+//! choice or use [`CsvWeather`] with exported data
+//!
+//! ```
+//! # use solar_api::weather::{CsvWeather, WeatherProvider};
+//! # use solar_api::chrono::NaiveDate;
+//! let weather: CsvWeather = "date,irradiation,temperature
+//! 2023-06-01 12:00:00,0.8,21.5
+//! 2023-06-01 13:00:00,0.7,"
+//!     .parse()?;
+//! let start = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+//! let observations = weather.weather(start, start + chrono::Duration::days(1))?;
+//! assert_eq!(2, observations.len());
+//! assert_eq!(None, observations[1].temperature);
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+#[cfg(test)]
+use crate::units::{as_watt_hours, watts};
+use crate::{
+    analytics::{peak_power_kw, IrradiationSource},
+    series::{Aggregation, TimeSeries, TimeSeriesValue},
+    site::TimeUnit,
+    units::{Energy, EnergyUnit, Power},
+    SolarApiError,
+};
+use chrono::NaiveDateTime;
+use std::str::FromStr;
+
+// the temperature in °C at which the peak power of modules is rated
+const STC_TEMPERATURE: f64 = 25.0;
+
+/// The weather of a site during an interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weather {
+    /// the start of the interval in the local time of the site
+    pub date: NaiveDateTime,
+    /// the irradiation in the plane of the modules in kWh/m² during the
+    /// interval
+    pub irradiation: Option<f64>,
+    /// the mean temperature in °C during the interval
+    pub temperature: Option<f64>,
+}
+
+/// A source of weather data of a site, i.e. a weather service or an on-site
+/// sensor. Every provider is an [`IrradiationSource`], so it can be used for
+/// [`performance_ratio`](crate::analytics::performance_ratio)
+pub trait WeatherProvider {
+    /// the weather of the intervals that start from `start` until `end`,
+    /// ordered by date
+    fn weather(
+        &self,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Vec<Weather>, SolarApiError>;
+}
+
+/// The irradiation of a period is the sum of the irradiation of the intervals
+/// in the period
+impl<W: WeatherProvider> IrradiationSource for W {
+    fn irradiation(
+        &self,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Option<f64>, SolarApiError> {
+        Ok(self
+            .weather(start, end)?
+            .iter()
+            .filter_map(|weather| weather.irradiation)
+            .reduce(|sum, irradiation| sum + irradiation))
+    }
+}
+
+/// Weather data read from CSV with a header line. The header names the
+/// columns: `date` with timestamps formatted as `2023-06-01 12:00:00`,
+/// `irradiation` in kWh/m² and `temperature` in °C. Only `date` is required
+/// and an empty cell is a missing value
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CsvWeather {
+    weather: Vec<Weather>,
+}
+
+impl FromStr for CsvWeather {
+    type Err = SolarApiError;
+
+    fn from_str(csv: &str) -> Result<Self, Self::Err> {
+        let invalid = |line: usize, message: String| SolarApiError::InvalidWeatherData {
+            line: line + 1,
+            message,
+        };
+        let mut lines = csv.lines().enumerate();
+        let header: Vec<&str> = lines
+            .next()
+            .map(|(_, header)| header.split(',').map(str::trim).collect())
+            .unwrap_or_default();
+        let column = |name: &str| header.iter().position(|column| *column == name);
+        let date = column("date").ok_or_else(|| invalid(0, "no date column".to_string()))?;
+        let (irradiation, temperature) = (column("irradiation"), column("temperature"));
+
+        let mut weather = Vec::new();
+        for (line, text) in lines.filter(|(_, text)| !text.trim().is_empty()) {
+            let cells: Vec<&str> = text.split(',').map(str::trim).collect();
+            let value = |column: Option<usize>| -> Result<Option<f64>, SolarApiError> {
+                match column.and_then(|column| cells.get(column)) {
+                    Some(cell) if !cell.is_empty() => cell
+                        .parse()
+                        .map(Some)
+                        .map_err(|_| invalid(line, format!("invalid number {cell}"))),
+                    _ => Ok(None),
+                }
+            };
+            let cell = cells.get(date).copied().unwrap_or_default();
+            weather.push(Weather {
+                date: NaiveDateTime::parse_from_str(cell, "%Y-%m-%d %H:%M:%S")
+                    .map_err(|_| invalid(line, format!("invalid date {cell}")))?,
+                irradiation: value(irradiation)?,
+                temperature: value(temperature)?,
+            });
+        }
+        weather.sort_by_key(|weather| weather.date);
+        Ok(CsvWeather { weather })
+    }
+}
+
+impl WeatherProvider for CsvWeather {
+    fn weather(
+        &self,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Vec<Weather>, SolarApiError> {
+        Ok(self
+            .weather
+            .iter()
+            .filter(|weather| weather.date >= start && weather.date < end)
+            .copied()
+            .collect())
+    }
+}
+
+/// The energy that a site with `peak_power` is expected to produce from
+/// `start` until `end` per bucket of `time_unit`, given the weather of
+/// `provider`. The energy of an interval is the peak power times the
+/// irradiation in kWh/m² times `performance_ratio`, corrected by
+/// `temperature_coefficient` per °C that the temperature differs from 25 °C,
+/// i.e. -0.004 for crystalline modules. Intervals without temperature are not
+/// corrected and intervals without irradiation have no expected energy
+pub fn expected_energy(
+    provider: &impl WeatherProvider,
+    peak_power: Power,
+    performance_ratio: f64,
+    temperature_coefficient: f64,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    time_unit: TimeUnit,
+) -> Result<TimeSeries<Energy>, SolarApiError> {
+    let kwp = peak_power_kw(peak_power)?;
+    let values = provider
+        .weather(start, end)?
+        .into_iter()
+        .map(|weather| {
+            let correction = weather.temperature.map_or(1.0, |temperature| {
+                1.0 + temperature_coefficient * (temperature - STC_TEMPERATURE)
+            });
+            TimeSeriesValue {
+                date: weather.date,
+                value: weather.irradiation.map(|irradiation| {
+                    EnergyUnit::KilowattHour
+                        .energy(kwp * irradiation * performance_ratio * correction)
+                }),
+            }
+        })
+        .collect();
+    Ok(TimeSeries::new(time_unit, "Wh", values).resample(time_unit, Aggregation::Sum))
+}
+
+#[test]
+fn test_csv_weather() {
+    let csv = "date, temperature, irradiation
+2023-06-01 12:00:00, 35.0, 0.8
+2023-06-01 13:00:00, , 0.5
+
+2023-06-02 12:00:00, 15.0,
+";
+    let weather: CsvWeather = csv.parse().unwrap();
+    let date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let (start, end) = (date("2023-06-01 00:00:00"), date("2023-06-03 00:00:00"));
+
+    assert_eq!(
+        Some(1.3),
+        weather
+            .irradiation(start, date("2023-06-02 00:00:00"))
+            .unwrap()
+    );
+    let expected = expected_energy(
+        &weather,
+        watts(5000.0),
+        0.8,
+        -0.004,
+        start,
+        end,
+        TimeUnit::Day,
+    )
+    .unwrap();
+    assert_eq!(2, expected.len());
+    // 5 kWp * 0.8 * (0.8 kWh/m² * 0.96 + 0.5 kWh/m²)
+    assert!((as_watt_hours(expected.values[0].value.unwrap()) - 5072.0).abs() < 1e-6);
+    assert_eq!(None, expected.values[1].value);
+
+    assert!(matches!(
+        "date\n2023-06-01".parse::<CsvWeather>(),
+        Err(SolarApiError::InvalidWeatherData { line: 2, .. })
+    ));
+    assert!(matches!(
+        "time,irradiation".parse::<CsvWeather>(),
+        Err(SolarApiError::InvalidWeatherData { line: 1, .. })
+    ));
+}
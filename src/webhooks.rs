@@ -0,0 +1,588 @@
+//! POSTs notifications to webhooks when a site has new data or when a rule
+//! fires, i.e. for alerts with ntfy, Slack or Discord, see [`WebhookNotifier`].
+//!
+//! The rules fire once when their condition starts to hold and again only
+//! after the condition stopped holding:
+//! * [`Rule::ProductionBelow`]: the current power of the site is below a
+//!   threshold during the day
+//! * [`Rule::InverterOffline`]: the site reported no new data for a while
+//! * [`Rule::BatteryFull`]: the state of charge of a battery reached a
+//!   percentage
+//!
+//! With [`PayloadFormat::Json`], the payload is an object with the `event`,
+//! the `siteId`, the `timestamp` in the local time of the site, a `message`
+//! and the values of the event, i.e.
+//! `{"event":"production_below","siteId":1234,"timestamp":"2023-06-01 12:20:00","message":"...","powerW":50.0,"thresholdW":100.0}`.
+//!
+//! ```no_run
+//! # use solar_api::webhooks::{PayloadFormat, Rule, WebhookNotifier};
+//! # use solar_api::Client;
+//! # use solar_api::chrono::{Duration, NaiveTime};
+//! let client = Client::new("API_KEY");
+//! let mut notifier = WebhookNotifier::new()
+//!     .with_webhook("https://ntfy.sh/my-solar-site", PayloadFormat::Text)
+//!     .with_new_data(false)
+//!     .with_rule(Rule::ProductionBelow {
+//!         threshold: solar_api::units::watts(100.0),
+//!         from: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+//!         until: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+//!     })
+//!     .with_rule(Rule::InverterOffline {
+//!         after: Duration::hours(1),
+//!     });
+//! // notifies on every new overview until the API key is rejected
+//! notifier.notify_updates(1234, client.poller(1234))?;
+//! # Ok::<(), solar_api::SolarApiError>(())
+//! ```
+
+use crate::{
+    site::Overview,
+    storage::StorageData,
+    units::{as_watt_hours, as_watts, Energy, Power},
+    SolarApiError,
+};
+#[cfg(test)]
+use crate::{
+    storage::Battery,
+    units::{watt_hours, watts},
+};
+use chrono::{Duration, Local, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use log::{debug, warn};
+use reqwest::header::CONTENT_TYPE;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+/// A condition of a site to notify about
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    /// The current power of an overview is below `threshold` between `from`
+    /// and `until` in the local time of the site
+    ProductionBelow {
+        threshold: Power,
+        from: NaiveTime,
+        until: NaiveTime,
+    },
+    /// The last update of an overview is more than `after` ago. As a
+    /// [`Poller`](crate::poller::Poller) only yields new data, check this rule
+    /// with overviews requested on a schedule, i.e. with
+    /// [`WebhookNotifier::notify_overview`]
+    InverterOffline { after: Duration },
+    /// The last state of charge of a battery is at least `percentage`
+    BatteryFull { percentage: f64 },
+}
+
+/// An event that is sent to the webhooks
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebhookEvent {
+    /// The site has a new overview
+    NewData {
+        site_id: u32,
+        timestamp: NaiveDateTime,
+        power: Power,
+        energy_today: Energy,
+    },
+    /// [`Rule::ProductionBelow`] fired
+    ProductionBelow {
+        site_id: u32,
+        timestamp: NaiveDateTime,
+        power: Power,
+        threshold: Power,
+    },
+    /// [`Rule::InverterOffline`] fired, `timestamp` is the last update
+    InverterOffline {
+        site_id: u32,
+        timestamp: NaiveDateTime,
+        offline: Duration,
+    },
+    /// [`Rule::BatteryFull`] fired
+    BatteryFull {
+        site_id: u32,
+        timestamp: NaiveDateTime,
+        battery: String,
+        state_of_charge: f64,
+    },
+}
+
+/// The format of the payload that is posted to a webhook
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PayloadFormat {
+    /// the event as JSON object
+    #[default]
+    Json,
+    /// the message as plain text, i.e. for ntfy
+    Text,
+    /// the message as `{"text":...}` for Slack incoming webhooks
+    Slack,
+    /// the message as `{"content":...}` for Discord webhooks
+    Discord,
+}
+
+// a webhook to post events to
+#[derive(Debug, Clone, PartialEq)]
+struct Webhook {
+    url: String,
+    format: PayloadFormat,
+}
+
+/// Posts [`WebhookEvent`]s of overviews and storage data to webhooks. The API
+/// returns timestamps in the local time of the site, so set the time zone of
+/// the site with [`WebhookNotifier::with_timezone`] when it differs from the
+/// local time zone
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier<Tz: TimeZone = Local> {
+    webhooks: Vec<Webhook>,
+    rules: Vec<Rule>,
+    new_data: bool,
+    tz: Tz,
+    http: reqwest::blocking::Client,
+    // the rules that fired and still hold, per site, rule and battery
+    firing: HashSet<(u32, usize, String)>,
+}
+
+impl WebhookNotifier<Local> {
+    /// Create a notifier without webhooks and rules that notifies about new
+    /// data
+    pub fn new() -> Self {
+        WebhookNotifier {
+            webhooks: Vec::new(),
+            rules: Vec::new(),
+            new_data: true,
+            tz: Local,
+            http: reqwest::blocking::Client::new(),
+            firing: HashSet::new(),
+        }
+    }
+}
+
+impl Default for WebhookNotifier<Local> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Tz: TimeZone> WebhookNotifier<Tz> {
+    /// Use `tz` as the time zone of the site, i.e. from
+    /// [`Location::tz`](crate::Location)
+    pub fn with_timezone<Tz2: TimeZone>(self, tz: Tz2) -> WebhookNotifier<Tz2> {
+        WebhookNotifier {
+            webhooks: self.webhooks,
+            rules: self.rules,
+            new_data: self.new_data,
+            tz,
+            http: self.http,
+            firing: self.firing,
+        }
+    }
+
+    /// Post events to `url` with payloads in `format`
+    pub fn with_webhook(mut self, url: &str, format: PayloadFormat) -> Self {
+        self.webhooks.push(Webhook {
+            url: url.to_string(),
+            format,
+        });
+        self
+    }
+
+    /// Notify when `rule` fires
+    pub fn with_rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Set whether to notify about every new overview, true by default
+    pub fn with_new_data(mut self, new_data: bool) -> Self {
+        self.new_data = new_data;
+        self
+    }
+
+    /// The events of `overview` of `site_id` at `now` in the local time of the
+    /// site. Rules that fired before and still hold have no event, and a site
+    /// that has not communicated yet has no events at all
+    pub fn overview_events(
+        &mut self,
+        site_id: u32,
+        overview: &Overview,
+        now: NaiveDateTime,
+    ) -> Vec<WebhookEvent> {
+        let Some(timestamp) = overview.last_updated_time else {
+            return Vec::new();
+        };
+        let power = overview.current_power.power;
+        let mut events = Vec::new();
+        if self.new_data {
+            events.push(WebhookEvent::NewData {
+                site_id,
+                timestamp,
+                power,
+                energy_today: overview.last_day_data.energy,
+            });
+        }
+        for (index, rule) in self.rules.clone().into_iter().enumerate() {
+            let event = match rule {
+                Rule::ProductionBelow {
+                    threshold,
+                    from,
+                    until,
+                } => {
+                    let time = timestamp.time();
+                    (time >= from && time < until && as_watts(power) < as_watts(threshold))
+                        .then_some(WebhookEvent::ProductionBelow {
+                            site_id,
+                            timestamp,
+                            power,
+                            threshold,
+                        })
+                }
+                Rule::InverterOffline { after } => {
+                    (now - timestamp > after).then_some(WebhookEvent::InverterOffline {
+                        site_id,
+                        timestamp,
+                        offline: now - timestamp,
+                    })
+                }
+                Rule::BatteryFull { .. } => continue,
+            };
+            events.extend(self.fire((site_id, index, String::new()), event));
+        }
+        events
+    }
+
+    /// The events of the batteries of `storage` of `site_id`. Rules that fired
+    /// before and still hold have no event
+    pub fn storage_events(&mut self, site_id: u32, storage: &StorageData) -> Vec<WebhookEvent> {
+        let mut events = Vec::new();
+        for (index, rule) in self.rules.clone().into_iter().enumerate() {
+            let Rule::BatteryFull { percentage } = rule else {
+                continue;
+            };
+            for battery in &storage.batteries {
+                let last = battery
+                    .sorted_telemetries()
+                    .into_iter()
+                    .rev()
+                    .find_map(|t| t.battery_percentage_state.map(|soc| (t.timestamp, soc)));
+                let event = last.filter(|(_, soc)| *soc >= percentage).map(
+                    |(timestamp, state_of_charge)| WebhookEvent::BatteryFull {
+                        site_id,
+                        timestamp,
+                        battery: battery.serial_number.clone(),
+                        state_of_charge,
+                    },
+                );
+                let key = (site_id, index, battery.serial_number.clone());
+                events.extend(self.fire(key, event));
+            }
+        }
+        events
+    }
+
+    /// Post the events of `overview` of `site_id` to every webhook
+    pub fn notify_overview(
+        &mut self,
+        site_id: u32,
+        overview: &Overview,
+    ) -> Result<(), SolarApiError> {
+        let now = Utc::now().with_timezone(&self.tz).naive_local();
+        let events = self.overview_events(site_id, overview, now);
+        self.send_all(&events)
+    }
+
+    /// Post the events of `storage` of `site_id` to every webhook
+    pub fn notify_storage(
+        &mut self,
+        site_id: u32,
+        storage: &StorageData,
+    ) -> Result<(), SolarApiError> {
+        let events = self.storage_events(site_id, storage);
+        self.send_all(&events)
+    }
+
+    /// Post the events of every overview of `updates`, i.e. of a
+    /// [`Poller`](crate::poller::Poller), until the updates end. Errors of the
+    /// updates and of the webhooks are logged and skipped, except
+    /// [`SolarApiError::ForbiddenError`] of an update, which is returned as the
+    /// API will keep rejecting the requests
+    pub fn notify_updates(
+        &mut self,
+        site_id: u32,
+        updates: impl IntoIterator<Item = Result<Overview, SolarApiError>>,
+    ) -> Result<(), SolarApiError> {
+        for update in updates {
+            match update {
+                Ok(overview) => {
+                    if let Err(e) = self.notify_overview(site_id, &overview) {
+                        warn!("Could not notify webhooks of site {site_id}: {e}");
+                    }
+                }
+                Err(e @ SolarApiError::ForbiddenError(_)) => return Err(e),
+                Err(e) => warn!("Could not get overview of site {site_id}: {e}"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Post `event` to every webhook. All webhooks are tried, the first error
+    /// is returned
+    pub fn send(&self, event: &WebhookEvent) -> Result<(), SolarApiError> {
+        let mut result = Ok(());
+        for webhook in &self.webhooks {
+            let (content_type, body) = event.payload(webhook.format);
+            debug!("Post {body} to {}", webhook.url);
+            let sent = self
+                .http
+                .post(&webhook.url)
+                .header(CONTENT_TYPE, content_type)
+                .body(body)
+                .send()
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = sent {
+                warn!("Could not post to webhook {}: {e}", webhook.url);
+                if result.is_ok() {
+                    result = Err(e.into());
+                }
+            }
+        }
+        result
+    }
+
+    fn send_all(&self, events: &[WebhookEvent]) -> Result<(), SolarApiError> {
+        events.iter().try_for_each(|event| self.send(event))
+    }
+
+    // `event` when the rule of `key` starts firing with it, None when the rule
+    // fired before or does not hold
+    fn fire(
+        &mut self,
+        key: (u32, usize, String),
+        event: Option<WebhookEvent>,
+    ) -> Option<WebhookEvent> {
+        match event {
+            Some(event) => self.firing.insert(key).then_some(event),
+            None => {
+                self.firing.remove(&key);
+                None
+            }
+        }
+    }
+}
+
+impl WebhookEvent {
+    /// The name of the event in the JSON payload, i.e. `production_below`
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::NewData { .. } => "new_data",
+            WebhookEvent::ProductionBelow { .. } => "production_below",
+            WebhookEvent::InverterOffline { .. } => "inverter_offline",
+            WebhookEvent::BatteryFull { .. } => "battery_full",
+        }
+    }
+
+    /// A message for people, i.e. `Site 1234 produces 50 W, below 100 W`
+    pub fn message(&self) -> String {
+        match self {
+            WebhookEvent::NewData {
+                site_id,
+                power,
+                energy_today,
+                ..
+            } => format!(
+                "Site {site_id} produces {} W, {} Wh today",
+                as_watts(*power),
+                as_watt_hours(*energy_today)
+            ),
+            WebhookEvent::ProductionBelow {
+                site_id,
+                power,
+                threshold,
+                ..
+            } => format!(
+                "Site {site_id} produces {} W, below {} W",
+                as_watts(*power),
+                as_watts(*threshold)
+            ),
+            WebhookEvent::InverterOffline {
+                site_id, offline, ..
+            } => format!(
+                "Site {site_id} reported no data for {} minutes",
+                offline.num_minutes()
+            ),
+            WebhookEvent::BatteryFull {
+                site_id,
+                battery,
+                state_of_charge,
+                ..
+            } => format!("Battery {battery} of site {site_id} is at {state_of_charge}%"),
+        }
+    }
+
+    /// The event as JSON object
+    pub fn to_json(&self) -> Value {
+        let (site_id, timestamp, values) = match self {
+            WebhookEvent::NewData {
+                site_id,
+                timestamp,
+                power,
+                energy_today,
+            } => (
+                site_id,
+                timestamp,
+                json!({ "powerW": as_watts(*power), "energyTodayWh": as_watt_hours(*energy_today) }),
+            ),
+            WebhookEvent::ProductionBelow {
+                site_id,
+                timestamp,
+                power,
+                threshold,
+            } => (
+                site_id,
+                timestamp,
+                json!({ "powerW": as_watts(*power), "thresholdW": as_watts(*threshold) }),
+            ),
+            WebhookEvent::InverterOffline {
+                site_id,
+                timestamp,
+                offline,
+            } => (
+                site_id,
+                timestamp,
+                json!({ "offlineMinutes": offline.num_minutes() }),
+            ),
+            WebhookEvent::BatteryFull {
+                site_id,
+                timestamp,
+                battery,
+                state_of_charge,
+            } => (
+                site_id,
+                timestamp,
+                json!({ "battery": battery, "stateOfCharge": state_of_charge }),
+            ),
+        };
+        let mut payload = json!({
+            "event": self.name(),
+            "siteId": site_id,
+            "timestamp": timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+            "message": self.message(),
+        });
+        if let (Value::Object(payload), Value::Object(values)) = (&mut payload, values) {
+            payload.extend(values);
+        }
+        payload
+    }
+
+    // the content type and body of the payload in `format`
+    fn payload(&self, format: PayloadFormat) -> (&'static str, String) {
+        match format {
+            PayloadFormat::Json => ("application/json", self.to_json().to_string()),
+            PayloadFormat::Text => ("text/plain; charset=utf-8", self.message()),
+            PayloadFormat::Slack => (
+                "application/json",
+                json!({ "text": self.message() }).to_string(),
+            ),
+            PayloadFormat::Discord => (
+                "application/json",
+                json!({ "content": self.message() }).to_string(),
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_overview_events() {
+    let overview: Overview = serde_json::from_str(
+        r#"{"lastUpdateTime":"2023-06-01 12:20:00",
+            "lifeTimeData":{"energy":1000000.0},"lastYearData":{"energy":500000.0},
+            "lastMonthData":{"energy":20000.0},"lastDayData":{"energy":2000.0},
+            "currentPower":{"power":50.0},"measuredBy":"INVERTER"}"#,
+    )
+    .unwrap();
+    let time = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let mut notifier = WebhookNotifier::new()
+        .with_rule(Rule::ProductionBelow {
+            threshold: watts(100.0),
+            from: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+            until: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+        })
+        .with_rule(Rule::InverterOffline {
+            after: Duration::hours(1),
+        });
+
+    let events = notifier.overview_events(1234, &overview, time("2023-06-01 12:25:00"));
+    assert_eq!(
+        vec![
+            WebhookEvent::NewData {
+                site_id: 1234,
+                timestamp: time("2023-06-01 12:20:00"),
+                power: watts(50.0),
+                energy_today: watt_hours(2000.0),
+            },
+            WebhookEvent::ProductionBelow {
+                site_id: 1234,
+                timestamp: time("2023-06-01 12:20:00"),
+                power: watts(50.0),
+                threshold: watts(100.0),
+            }
+        ],
+        events
+    );
+    assert_eq!(
+        serde_json::json!({
+            "event": "production_below",
+            "siteId": 1234,
+            "timestamp": "2023-06-01 12:20:00",
+            "message": "Site 1234 produces 50 W, below 100 W",
+            "powerW": 50.0,
+            "thresholdW": 100.0
+        }),
+        events[1].to_json()
+    );
+    assert_eq!(
+        (
+            "application/json",
+            r#"{"content":"Site 1234 produces 50 W, below 100 W"}"#.to_string()
+        ),
+        events[1].payload(PayloadFormat::Discord)
+    );
+
+    // the rule fired already, the offline rule fires now
+    let mut notifier = notifier.with_new_data(false);
+    assert_eq!(
+        vec![WebhookEvent::InverterOffline {
+            site_id: 1234,
+            timestamp: time("2023-06-01 12:20:00"),
+            offline: Duration::minutes(100),
+        }],
+        notifier.overview_events(1234, &overview, time("2023-06-01 14:00:00"))
+    );
+    assert!(notifier
+        .overview_events(1234, &overview, time("2023-06-01 14:15:00"))
+        .is_empty());
+}
+
+#[test]
+fn test_storage_events() {
+    let battery: Battery = serde_json::from_str(
+        r#"{"nameplate":10000.0,"serialNumber":"BAT-1","modelNumber":"RESU10H","telemetryCount":2,
+            "telemetries":[
+                {"timeStamp":"2023-06-01 12:00:00","power":500.0,"batteryState":3,"lifeTimeEnergyDischarged":0,"lifeTimeEnergyCharged":0,"batteryPercentageState":98.0},
+                {"timeStamp":"2023-06-01 12:05:00","power":0.0,"batteryState":4,"lifeTimeEnergyDischarged":0,"lifeTimeEnergyCharged":0,"batteryPercentageState":100.0}
+            ]}"#,
+    )
+    .unwrap();
+    let mut storage = StorageData {
+        battery_count: 1,
+        batteries: vec![battery],
+    };
+    let mut notifier = WebhookNotifier::new().with_rule(Rule::BatteryFull { percentage: 100.0 });
+
+    let events = notifier.storage_events(1234, &storage);
+    assert_eq!(1, events.len());
+    assert_eq!("Battery BAT-1 of site 1234 is at 100%", events[0].message());
+    assert!(notifier.storage_events(1234, &storage).is_empty());
+
+    // the rule fires again after the battery was below the percentage
+    let full = storage.batteries[0].telemetries.pop().unwrap();
+    assert!(notifier.storage_events(1234, &storage).is_empty());
+    storage.batteries[0].telemetries.push(full);
+    assert_eq!(1, notifier.storage_events(1234, &storage).len());
+}